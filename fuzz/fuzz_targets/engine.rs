@@ -0,0 +1,12 @@
+//! Random transaction sequences through the standard rules, checking the invariants after each.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use transakt::policy::DisputeFundsAction;
+use transakt::transaction::Transaction;
+use transakt::Transakt;
+
+fuzz_target!(|transactions: Vec<Transaction>| {
+    let builder = Transakt::builder().disputes_exceeding_funds(DisputeFundsAction::HoldAvailable);
+    transakt::fuzz::run(builder, &transactions);
+});
@@ -0,0 +1,52 @@
+//! Random transaction sequences under random choices of the standard policy.
+#![no_main]
+
+use libfuzzer_sys::arbitrary::{self, Arbitrary, Unstructured};
+use libfuzzer_sys::fuzz_target;
+use transakt::policy::{DisputeFundsAction, LockedAction, UnknownClientAction};
+use transakt::transaction::Transaction;
+use transakt::Transakt;
+
+const LOCKED: [LockedAction; 4] = [
+    LockedAction::Apply,
+    LockedAction::Reject,
+    LockedAction::Hold,
+    LockedAction::Review,
+];
+
+#[derive(Debug)]
+struct Input {
+    deposit: LockedAction,
+    withdrawal: LockedAction,
+    unknown_client: UnknownClientAction,
+    // Allowing negative funds breaks the invariants on purpose
+    dispute_exceeding_funds: DisputeFundsAction,
+    transactions: Vec<Transaction>,
+}
+
+impl<'a> Arbitrary<'a> for Input {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Input {
+            deposit: *u.choose(&LOCKED)?,
+            withdrawal: *u.choose(&LOCKED)?,
+            unknown_client: *u.choose(&[
+                UnknownClientAction::Create,
+                UnknownClientAction::Reject,
+                UnknownClientAction::Flag,
+            ])?,
+            dispute_exceeding_funds: *u.choose(&[
+                DisputeFundsAction::HoldAvailable,
+                DisputeFundsAction::Reject,
+            ])?,
+            transactions: u.arbitrary()?,
+        })
+    }
+}
+
+fuzz_target!(|input: Input| {
+    let builder = Transakt::builder()
+        .locked_accounts(input.deposit, input.withdrawal)
+        .unknown_clients(input.unknown_client)
+        .disputes_exceeding_funds(input.dispute_exceeding_funds);
+    transakt::fuzz::run(builder, &input.transactions);
+});
@@ -1,4 +1,5 @@
 use crate::currency::Currency;
+use crate::snapshot::AccountState;
 use crate::transaction::ClientId;
 use crate::Error;
 use serde::ser::{Error as SerdeError, SerializeStruct};
@@ -10,6 +11,18 @@ pub struct Account {
     available: Currency,
     held: Currency,
     locked: bool,
+    /// When the client last deposited, withdrew or reactivated the account, in seconds since the
+    /// Unix epoch. Only kept if the engine detects dormant accounts or keeps account metadata.
+    last_activity: Option<u64>,
+    /// When the account was opened, in seconds since the Unix epoch. Only kept if the engine keeps
+    /// account metadata, see [`crate::metadata`].
+    created_at: Option<u64>,
+    /// Disputes opened on deposits of the account.
+    disputes: u32,
+    chargebacks: u32,
+    /// What disputes could not hold for lack of available funds, see
+    /// [`DisputeFundsAction::HoldAvailable`](crate::policy::DisputeFundsAction::HoldAvailable).
+    shortfall: Currency,
 }
 
 impl Serialize for Account {
@@ -35,9 +48,33 @@ impl Account {
             available: Currency::default(),
             held: Currency::default(),
             locked: false,
+            last_activity: None,
+            created_at: None,
+            disputes: 0,
+            chargebacks: 0,
+            shortfall: Currency::default(),
         }
     }
 
+    /// Recreates an account from persisted state.
+    pub(crate) fn restore(state: AccountState) -> Account {
+        Self {
+            client: state.client,
+            available: state.available,
+            held: state.held,
+            locked: state.locked,
+            last_activity: state.last_activity,
+            created_at: state.created_at,
+            disputes: state.disputes,
+            chargebacks: state.chargebacks,
+            shortfall: state.shortfall,
+        }
+    }
+
+    pub fn client(&self) -> ClientId {
+        self.client
+    }
+
     pub fn available(&self) -> &Currency {
         &self.available
     }
@@ -54,33 +91,83 @@ impl Account {
         self.locked = true;
     }
 
+    pub fn unlock(&mut self) {
+        self.locked = false;
+    }
+
     pub fn is_locked(&self) -> bool {
         self.locked
     }
 
+    pub fn last_activity(&self) -> Option<u64> {
+        self.last_activity
+    }
+
+    pub(crate) fn touch(&mut self, at: u64) {
+        self.last_activity = Some(at);
+    }
+
+    pub fn created_at(&self) -> Option<u64> {
+        self.created_at
+    }
+
+    pub(crate) fn set_created_at(&mut self, at: u64) {
+        self.created_at = Some(at);
+    }
+
+    pub fn dispute_count(&self) -> u32 {
+        self.disputes
+    }
+
+    pub fn chargeback_count(&self) -> u32 {
+        self.chargebacks
+    }
+
+    pub(crate) fn count_dispute(&mut self) {
+        self.disputes = self.disputes.saturating_add(1);
+    }
+
+    pub(crate) fn count_chargeback(&mut self) {
+        self.chargebacks = self.chargebacks.saturating_add(1);
+    }
+
+    /// What open disputes could not hold, and chargebacks could not take back, for lack of
+    /// available funds: what the client still owes.
+    pub fn shortfall(&self) -> Currency {
+        self.shortfall
+    }
+
+    pub(crate) fn add_shortfall(&mut self, amount: Currency) -> Result<(), Error> {
+        self.shortfall = self.shortfall.checked_add(amount).ok_or(Error::Overflow)?;
+        Ok(())
+    }
+
+    /// Drops the shortfall of a dispute that was resolved.
+    pub(crate) fn settle_shortfall(&mut self, amount: Currency) -> Result<(), Error> {
+        self.shortfall = self.shortfall.checked_sub(amount).ok_or(Error::Overflow)?;
+        Ok(())
+    }
+
+    /// Whether a locked account accepts deposits is up to the engine's
+    /// [`ProcessingPolicy`](crate::policy::ProcessingPolicy), so it is not checked here.
     pub fn deposit(&mut self, amount: Currency) -> Result<(), Error> {
-        if !self.is_locked() {
-            let sum = self.available.checked_add(amount).ok_or(Error::Overflow)?;
-            self.available = sum;
-            Ok(())
-        } else {
-            Err(Error::AccountLocked)
-        }
+        let sum = self.available.checked_add(amount).ok_or(Error::Overflow)?;
+        self.available = sum;
+        Ok(())
     }
 
+    /// Like [`Account::deposit`], locks are not checked here.
     pub fn withdraw(&mut self, amount: Currency) -> Result<(), Error> {
-        if !self.is_locked() {
-            let diff = self.available.checked_sub(amount).ok_or(Error::Overflow)?;
-            if diff.is_negative() {
-                return Err(Error::InsufficientFunds);
-            }
-            self.available = diff;
-            Ok(())
-        } else {
-            Err(Error::AccountLocked)
+        let diff = self.available.checked_sub(amount).ok_or(Error::Overflow)?;
+        if diff.is_negative() {
+            return Err(Error::InsufficientFunds);
         }
+        self.available = diff;
+        Ok(())
     }
 
+    /// Removes the held funds. Doesn't lock the account, see
+    /// [`ProcessingPolicy::lock_on_chargeback`](crate::policy::ProcessingPolicy::lock_on_chargeback).
     pub fn chargeback(&mut self, amount: Currency) -> Result<(), Error> {
         let diff = self.held.checked_sub(amount).ok_or(Error::Overflow)?;
         if diff.is_negative() {
@@ -88,7 +175,6 @@ impl Account {
             return Err(Error::InsufficientHeldFunds);
         }
         self.held = diff;
-        self.lock();
         Ok(())
     }
 
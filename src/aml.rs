@@ -0,0 +1,206 @@
+//! Anti-money-laundering monitoring of applied transactions. Unlike [rules](crate::rules), it
+//! never rejects anything: it reports suspicious activity for someone to investigate.
+//!
+//! Structuring, or smurfing, is splitting a large amount into deposits just below a reporting
+//! threshold. It is configured in the `[aml.structuring]` section:
+//!
+//! ```toml
+//! [aml.structuring]
+//! threshold = "10000"
+//! # Deposits from 9000 up to, not including, 10000 count as just below
+//! margin = "1000"
+//! count = 3
+//! # Within this many applied transactions
+//! window = 1000
+//! ```
+
+use crate::currency::Currency;
+use crate::event::RiskEvent;
+use crate::transaction::{ClientId, Transaction, TransactionId};
+use crate::{Error, Transakt};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AmlConfig {
+    pub structuring: Option<StructuringRule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StructuringRule {
+    /// The amount deposits are kept below.
+    pub threshold: Currency,
+    /// How far below the threshold a deposit still counts.
+    pub margin: Currency,
+    /// How many such deposits of a client make it suspicious.
+    pub count: u32,
+    /// The deposits have to fall within this many applied transactions.
+    pub window: u64,
+}
+
+impl StructuringRule {
+    fn just_below(&self, amount: Currency) -> bool {
+        let floor = self
+            .threshold
+            .checked_sub(self.margin)
+            .unwrap_or(self.threshold);
+        amount >= floor && amount < self.threshold
+    }
+}
+
+/// An entry of the suspicious activity report.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SuspiciousActivity {
+    pub client: ClientId,
+    /// What was detected, `structuring`.
+    pub kind: &'static str,
+    pub reason: String,
+    /// The transactions that add up to the activity, oldest first.
+    pub transactions: Vec<TransactionId>,
+    pub total: Currency,
+}
+
+/// The rules, the recent deposits they are evaluated on, and what was reported so far. Like the
+/// rule state, it is not part of snapshots.
+#[derive(Debug)]
+pub(crate) struct AmlMonitor {
+    structuring: Option<StructuringRule>,
+    /// Deposits just below the threshold, with the count of applied transactions when they were.
+    recent: HashMap<ClientId, VecDeque<(u64, TransactionId, Currency)>>,
+    report: Vec<SuspiciousActivity>,
+}
+
+impl AmlMonitor {
+    pub(crate) fn new(config: AmlConfig) -> Self {
+        Self {
+            structuring: config.structuring,
+            recent: HashMap::new(),
+            report: vec![],
+        }
+    }
+
+    pub(crate) fn report(&self) -> &[SuspiciousActivity] {
+        &self.report
+    }
+
+    /// Looks for structuring after the deposit was applied as the `applied`th transaction.
+    fn check_structuring(
+        &mut self,
+        applied: u64,
+        client: ClientId,
+        tx: TransactionId,
+        amount: Currency,
+    ) -> Option<SuspiciousActivity> {
+        let rule = self.structuring.as_ref()?;
+        if !rule.just_below(amount) {
+            return None;
+        }
+        let recent = self.recent.entry(client).or_default();
+        recent.push_back((applied, tx, amount));
+        while recent
+            .front()
+            .is_some_and(|(at, _, _)| applied - at >= rule.window)
+        {
+            recent.pop_front();
+        }
+        if (recent.len() as u64) < u64::from(rule.count) {
+            return None;
+        }
+        // Each deposit is only reported once
+        let deposits: Vec<_> = recent.drain(..).collect();
+        let total = deposits
+            .iter()
+            .try_fold(Currency::default(), |total, (_, _, amount)| {
+                total.checked_add(*amount)
+            })?;
+        Some(SuspiciousActivity {
+            client,
+            kind: "structuring",
+            reason: format!(
+                "{} deposits just below {} within {} transactions",
+                deposits.len(),
+                rule.threshold,
+                rule.window
+            ),
+            transactions: deposits.into_iter().map(|(_, tx, _)| tx).collect(),
+            total,
+        })
+    }
+}
+
+/// Writes report entries as JSON lines.
+pub fn write_report<W: Write>(report: &[SuspiciousActivity], mut writer: W) -> Result<(), Error> {
+    for activity in report {
+        serde_json::to_writer(&mut writer, activity).map_err(|err| Error::Io(err.into()))?;
+        writer.write_all(b"\n").map_err(Error::Io)?;
+    }
+    writer.flush().map_err(Error::Io)
+}
+
+impl Transakt {
+    /// Monitors the transaction after it was applied, reporting suspicious activity.
+    pub(crate) fn record_aml(&mut self, transaction: &Transaction) {
+        let monitor = match self.aml.as_mut() {
+            Some(monitor) => monitor,
+            None => return,
+        };
+        let activity = match *transaction {
+            Transaction::Deposit {
+                client, tx, amount, ..
+            } => monitor.check_structuring(self.applied, client, tx, amount),
+            _ => None,
+        };
+        if let Some(activity) = activity {
+            tracing::debug!(reason = %activity.reason, "suspicious activity");
+            monitor.report.push(activity.clone());
+            self.emit(RiskEvent::SuspiciousActivity {
+                client: activity.client,
+                kind: activity.kind.to_string(),
+                transactions: activity.transactions,
+                total: activity.total,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::currency::Currency;
+    use crate::transaction::{ClientId, TransactionId};
+    use crate::Transakt;
+
+    #[test]
+    fn structuring() {
+        let config = Config::parse(
+            "[aml.structuring]\n\
+             threshold = \"10000\"\n\
+             margin = \"1000\"\n\
+             count = 3\n\
+             window = 4\n",
+        )
+        .unwrap();
+        let mut transakt = Transakt::builder().aml(config.aml).build();
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,9500\n\
+                     deposit,1,2,100\n\
+                     deposit,2,3,9000\n\
+                     deposit,1,4,100\n\
+                     deposit,1,5,9900\n\
+                     deposit,1,6,9000\n\
+                     deposit,1,7,10000\n\
+                     deposit,1,8,9200\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        // Tx 1 is out of the window by the time of tx 5
+        let report = transakt.suspicious_activity();
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].client, ClientId::new(1));
+        let transactions = [5, 6, 8].iter().copied().map(TransactionId::new);
+        assert_eq!(report[0].transactions, transactions.collect::<Vec<_>>());
+        assert_eq!(report[0].total, Currency::new(28100, 0).unwrap());
+        assert_eq!(transakt.get_accounts_map().len(), 2);
+    }
+}
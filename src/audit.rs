@@ -0,0 +1,410 @@
+//! Append-only record of every decision the engine made: each transaction, whether it was
+//! applied or rejected and why, and the balances of its client before and after.
+//!
+//! Unlike the [journal](crate::journal), which only holds what is needed to rebuild the state,
+//! the audit log explains it.
+//!
+//! Records are chained: each holds the hash of the record before it and its own hash, SHA-256 over
+//! the record as written with the previous hash in it, so changing, inserting or removing a record
+//! breaks the chain from there on, see [`verify_audit`]. Removing the last records can only be
+//! told by comparing the head of the chain with one kept elsewhere.
+
+use crate::hex;
+use crate::redact;
+use crate::snapshot::AccountState;
+use crate::transaction::{ClientId, Transaction};
+use crate::Error;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Decision {
+    Applied,
+    Rejected,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Number of transactions applied to the ledger, including this one if it was applied.
+    pub seq: u64,
+    /// In seconds since the Unix epoch.
+    pub recorded_at: u64,
+    pub transaction: Transaction,
+    pub decision: Decision,
+    /// Error code of a rejection, see [`crate::explain`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// What rejected the transaction: a `validator`, the configured `rules`, the `denylist`,
+    /// `screening`, the `kyc` limits,
+    /// `dormancy`, the `signature` check, or the `engine` itself, following its processing policy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rejected_by: Option<String>,
+    /// Who made the change: the operator of a
+    /// [`Transakt::unlock_account`](crate::Transakt::unlock_account) call, or the
+    /// [key](crate::signature) that signed the row.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operator: Option<String>,
+    /// The client's account, if it had one.
+    pub before: Option<AccountState>,
+    pub after: Option<AccountState>,
+    /// Hash of the record before, [`GENESIS_HASH`] for the first one. Set when written.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prev_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+}
+
+/// What the first record of an audit log is chained to.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+impl AuditRecord {
+    pub(crate) fn new(
+        seq: u64,
+        transaction: Transaction,
+        res: &Result<(), Error>,
+        before: Option<AccountState>,
+        after: Option<AccountState>,
+    ) -> Self {
+        let recorded_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let (decision, code, reason, rejected_by) = match res {
+            Ok(()) => (Decision::Applied, None, None, None),
+            Err(err) => {
+                let rejected_by = match err {
+                    Error::Rejected(_) => "validator",
+                    Error::RuleViolation(_) => "rules",
+                    Error::Denied(_) => "denylist",
+                    Error::ScreeningHit(_) => "screening",
+                    Error::LimitExceeded(_) => "kyc",
+                    Error::Dormant(_) => "dormancy",
+                    Error::InvalidSignature(_) => "signature",
+                    _ => "engine",
+                };
+                (
+                    Decision::Rejected,
+                    Some(err.code().to_string()),
+                    Some(format!("{:?}", err)),
+                    Some(rejected_by.to_string()),
+                )
+            }
+        };
+        Self {
+            seq,
+            recorded_at,
+            transaction,
+            decision,
+            code,
+            reason,
+            rejected_by,
+            operator: None,
+            before,
+            after,
+            prev_hash: None,
+            hash: None,
+        }
+    }
+}
+
+/// The hash of a record as written, without its own hash.
+fn chain_hash(record: &Value) -> String {
+    let bytes = serde_json::to_vec(record).expect("JSON values are always serializable");
+    hex::encode(&Sha256::digest(&bytes))
+}
+
+/// Writes audit records as JSON lines.
+pub struct AuditWriter {
+    out: BufWriter<Box<dyn Write + Send>>,
+    /// Hash of the last record written.
+    head: String,
+}
+
+impl AuditWriter {
+    /// Starts a new chain, for a writer at the start of an audit log.
+    pub fn new<W: Write + Send + 'static>(writer: W) -> Self {
+        Self {
+            out: BufWriter::new(Box::new(writer)),
+            head: GENESIS_HASH.to_string(),
+        }
+    }
+
+    /// Opens an audit log for appending, creating it if needed. New records are chained to the
+    /// last one already in the log.
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)
+            .map_err(Error::Io)?;
+        let mut last = None;
+        for line in BufReader::new(&file).lines() {
+            let line = line.map_err(Error::Io)?;
+            if !line.trim().is_empty() {
+                last = Some(line);
+            }
+        }
+        let head = match last {
+            Some(line) => serde_json::from_str::<Value>(&line)
+                .ok()
+                .and_then(|record| record.get("hash")?.as_str().map(str::to_string))
+                .ok_or_else(|| {
+                    Error::InvalidSnapshot(format!(
+                        "the last record of {} has no hash to chain to",
+                        path.display()
+                    ))
+                })?,
+            None => GENESIS_HASH.to_string(),
+        };
+        Ok(Self {
+            head,
+            ..Self::new(file)
+        })
+    }
+
+    /// Writes the record chained to the one before. Client ids and amounts are redacted if
+    /// configured, see [`crate::redact`]; the hash covers the record as written.
+    pub fn append(&mut self, record: &AuditRecord) -> Result<(), Error> {
+        let mut value = serde_json::to_value(record).map_err(|err| Error::Io(err.into()))?;
+        let redaction = redact::current();
+        if !redaction.is_off() {
+            redaction.json(&mut value);
+        }
+        if let Some(fields) = value.as_object_mut() {
+            fields.remove("hash");
+        }
+        value["prev_hash"] = Value::from(self.head.as_str());
+        let hash = chain_hash(&value);
+        value["hash"] = Value::from(hash.as_str());
+        serde_json::to_writer(&mut self.out, &value).map_err(|err| Error::Io(err.into()))?;
+        self.out.write_all(b"\n").map_err(Error::Io)?;
+        self.head = hash;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.out.flush().map_err(Error::Io)
+    }
+}
+
+/// Reads audit records, one per line.
+pub fn read_audit<R: BufRead>(reader: R) -> impl Iterator<Item = Result<AuditRecord, Error>> {
+    reader
+        .lines()
+        .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|line| {
+            let line = line.map_err(Error::Io)?;
+            serde_json::from_str(&line).map_err(|_| Error::TransactionParseError)
+        })
+}
+
+/// An audit log whose chain holds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifiedAudit {
+    pub records: u64,
+    /// Hash of the last record, [`GENESIS_HASH`] for an empty log.
+    pub head: String,
+}
+
+/// Checks the hash chain of an audit log, failing with the first line that was changed, inserted
+/// or doesn't follow the line before.
+pub fn verify_audit<R: BufRead>(reader: R) -> Result<VerifiedAudit, Error> {
+    let mut verified = VerifiedAudit {
+        records: 0,
+        head: GENESIS_HASH.to_string(),
+    };
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.map_err(Error::Io)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let broken = |reason: &str| {
+            Error::InvalidSnapshot(format!("audit log line {}: {}", index + 1, reason))
+        };
+        let mut record: Value =
+            serde_json::from_str(&line).map_err(|_| broken("not an audit record"))?;
+        let fields = record
+            .as_object_mut()
+            .ok_or_else(|| broken("not an audit record"))?;
+        let hash = match fields.remove("hash") {
+            Some(Value::String(hash)) => hash,
+            _ => return Err(broken("no hash")),
+        };
+        if fields.get("prev_hash").and_then(Value::as_str) != Some(verified.head.as_str()) {
+            return Err(broken("not chained to the record before"));
+        }
+        if chain_hash(&record) != hash {
+            return Err(broken("modified after it was written"));
+        }
+        verified.records += 1;
+        verified.head = hash;
+    }
+    Ok(verified)
+}
+
+/// Which audit records to look at. Unset fields match everything.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AuditQuery {
+    pub client: Option<ClientId>,
+    pub decision: Option<Decision>,
+    /// Error code, ignoring case.
+    pub code: Option<String>,
+}
+
+impl AuditQuery {
+    pub fn matches(&self, record: &AuditRecord) -> bool {
+        self.client
+            .is_none_or(|client| record.transaction.client() == client)
+            && self
+                .decision
+                .is_none_or(|decision| record.decision == decision)
+            && self.code.as_ref().is_none_or(|code| {
+                record
+                    .code
+                    .as_ref()
+                    .is_some_and(|recorded| recorded.eq_ignore_ascii_case(code))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_audit, verify_audit, AuditQuery, AuditWriter, Decision, GENESIS_HASH};
+    use crate::transaction::ClientId;
+    use crate::{Error, Transakt};
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn audit_log() {
+        let buffer = SharedBuffer::default();
+        let mut transakt = Transakt::builder()
+            .audit_log(AuditWriter::new(buffer.clone()))
+            .build();
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,2.0\n\
+                     withdrawal,1,2,5.0\n\
+                     deposit,2,3,1.0\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+
+        let log = buffer.0.lock().unwrap().clone();
+        let records: Vec<_> = read_audit(log.as_slice()).map(Result::unwrap).collect();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].before, None);
+        assert_eq!(
+            records[0].after.as_ref().unwrap().available.to_string(),
+            "2.0000"
+        );
+
+        let query = AuditQuery {
+            client: Some(ClientId::new(1)),
+            decision: Some(Decision::Rejected),
+            code: Some("e_funds".to_string()),
+        };
+        let rejected: Vec<_> = records.iter().filter(|r| query.matches(r)).collect();
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].seq, 1);
+        assert_eq!(rejected[0].rejected_by.as_deref(), Some("engine"));
+        assert_eq!(rejected[0].before, rejected[0].after);
+    }
+
+    #[test]
+    fn unlock_account() {
+        let buffer = SharedBuffer::default();
+        let mut transakt = Transakt::builder()
+            .audit_log(AuditWriter::new(buffer.clone()))
+            .build();
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,2.0\n\
+                     dispute,1,1,\n\
+                     chargeback,1,1,\n\
+                     freeze,2,2,\n\
+                     unlock,2,3,\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        assert!(transakt.get_accounts_map()[&ClientId::new(1)].is_locked());
+        assert!(!transakt.get_accounts_map()[&ClientId::new(2)].is_locked());
+
+        transakt.unlock_account(ClientId::new(1), "alice").unwrap();
+        assert!(!transakt.get_accounts_map()[&ClientId::new(1)].is_locked());
+        assert!(matches!(
+            transakt.unlock_account(ClientId::new(1), "alice"),
+            Err(Error::InvalidTransaction)
+        ));
+        transakt.flush_journal().unwrap();
+
+        let log = buffer.0.lock().unwrap().clone();
+        let records: Vec<_> = read_audit(log.as_slice()).map(Result::unwrap).collect();
+        assert_eq!(records[4].transaction.kind(), "unfreeze");
+        assert_eq!(records[4].operator, None);
+        let unlocked = &records[5];
+        assert_eq!(unlocked.decision, Decision::Applied);
+        assert_eq!(unlocked.operator.as_deref(), Some("alice"));
+        assert!(unlocked.before.as_ref().unwrap().locked);
+        assert!(!unlocked.after.as_ref().unwrap().locked);
+        // A refused unlock is audited with its operator too
+        assert_eq!(records[6].decision, Decision::Rejected);
+        assert_eq!(records[6].operator.as_deref(), Some("alice"));
+    }
+
+    #[test]
+    fn hash_chain() {
+        let dir = std::env::temp_dir().join(format!("transakt-audit-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("audit.jsonl");
+        let _ = std::fs::remove_file(&path);
+        // Two runs appending to the same log make one chain
+        for input in ["deposit,1,1,2.0\nwithdrawal,1,2,5.0\n", "deposit,2,3,1.0\n"] {
+            let mut transakt = Transakt::builder()
+                .audit_log(AuditWriter::open(&path).unwrap())
+                .build();
+            let input = format!("type,client,tx,amount\n{}", input);
+            transakt.execute_csv(input.as_bytes()).unwrap();
+            transakt.flush_journal().unwrap();
+        }
+        let log = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let verified = verify_audit(log.as_bytes()).unwrap();
+        assert_eq!(verified.records, 3);
+        let records: Vec<_> = read_audit(log.as_bytes()).map(Result::unwrap).collect();
+        assert_eq!(records[0].prev_hash.as_deref(), Some(GENESIS_HASH));
+        assert_eq!(records[2].prev_hash, records[1].hash);
+        assert_eq!(records[2].hash.as_ref(), Some(&verified.head));
+
+        let broken_at = |log: &str| match verify_audit(log.as_bytes()) {
+            Err(Error::InvalidSnapshot(reason)) => reason,
+            res => panic!("verified a tampered log: {:?}", res),
+        };
+        let tampered = log.replacen("\"2.0000\"", "\"20.0000\"", 1);
+        assert_eq!(
+            broken_at(&tampered),
+            "audit log line 1: modified after it was written"
+        );
+        let lines: Vec<&str> = log.lines().collect();
+        let removed = format!("{}\n{}\n", lines[0], lines[2]);
+        assert_eq!(
+            broken_at(&removed),
+            "audit log line 2: not chained to the record before"
+        );
+    }
+}
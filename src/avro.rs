@@ -0,0 +1,911 @@
+//! Avro object container files of transactions and of the account report, for the Kafka side of
+//! things. Amounts are `decimal` bytes with a scale of 4, client ids `int`, transaction ids and
+//! times `long`. Files are written uncompressed.
+//!
+//! Files are read uncompressed or with the `deflate` or `snappy` codec. Their schema needs
+//! records with `type`, `client` and `tx` fields, in any order and among any others; `amount`,
+//! `signature` and `timestamp` are optional like in [`TRANSACTION_SCHEMA`]. Types are resolved as
+//! far as transactions need: `int` or `long` ids, a `string` or `enum` type, and amounts as
+//! `decimal` of any scale, truncated to four decimals like in CSV, or as `string`.
+//!
+//! Transactions are numbered from 1 in the order they are in the file, which is what rejections
+//! report as their line.
+
+use crate::currency::Currency;
+use crate::transaction::{ClientId, TimedRow, TransactionId, TransactionRow, TransactionType};
+use crate::{Error, Transakt};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io::{BufReader, Cursor, ErrorKind, Read, Write};
+use std::str::FromStr;
+
+pub const TRANSACTION_SCHEMA: &str = r#"{"type": "record", "name": "TransactionRow", "namespace": "transakt", "fields": [
+    {"name": "type", "type": "string"},
+    {"name": "client", "type": "int"},
+    {"name": "tx", "type": "long"},
+    {"name": "amount", "type": ["null", {"type": "bytes", "logicalType": "decimal", "precision": 18, "scale": 4}], "default": null},
+    {"name": "signature", "type": ["null", "string"], "default": null},
+    {"name": "timestamp", "type": ["null", "long"], "default": null}
+]}"#;
+
+pub const ACCOUNT_SCHEMA: &str = r#"{"type": "record", "name": "Account", "namespace": "transakt", "fields": [
+    {"name": "client", "type": "int"},
+    {"name": "available", "type": {"type": "bytes", "logicalType": "decimal", "precision": 18, "scale": 4}},
+    {"name": "held", "type": {"type": "bytes", "logicalType": "decimal", "precision": 18, "scale": 4}},
+    {"name": "total", "type": {"type": "bytes", "logicalType": "decimal", "precision": 18, "scale": 4}},
+    {"name": "locked", "type": "boolean"}
+]}"#;
+
+const MAGIC: &[u8] = b"Obj\x01";
+/// Decimal digits of amounts, as in [`Currency`].
+const SCALE: u32 = 4;
+/// Records per block of a written file.
+const BLOCK_RECORDS: u64 = 4096;
+
+impl Transakt {
+    /// Writes the `client,available,held,total,locked` columns of the account report as an Avro
+    /// file, ordered by client. Fails if the total of an account overflows.
+    pub fn write_accounts_avro<W: Write>(&self, writer: W) -> Result<(), Error> {
+        let accounts = self.get_accounts();
+        let mut file = ContainerWriter::new(writer, ACCOUNT_SCHEMA, Codec::Null)?;
+        for account in accounts.iter() {
+            let total = account.total().ok_or_else(|| {
+                Error::Io(std::io::Error::other(format!(
+                    "total of client {} overflows",
+                    account.client()
+                )))
+            })?;
+            file.record(|out| {
+                write_long(out, i64::from(account.client().id()));
+                write_decimal(out, *account.available());
+                write_decimal(out, *account.held());
+                write_decimal(out, total);
+                out.push(u8::from(account.is_locked()));
+            })?;
+        }
+        file.finish()
+    }
+
+    /// Like [`Transakt::load_csv_from`], for an Avro file of transactions.
+    pub fn load_avro_from<R: Read>(&mut self, source: &str, reader: R) -> Result<(), Error> {
+        self.load_from(source, |transakt| {
+            let read = transakt.read_stats();
+            let rows = transakt.in_row_range(read_timed_avro(crate::profile::TimedReader {
+                inner: reader,
+                stats: read.clone(),
+            }));
+            transakt.execute_rows(rows, read)
+        })
+    }
+}
+
+/// Writes transactions with their signatures and event times as an Avro file. Returns how many
+/// were written.
+pub fn write_transactions_avro<W, I>(rows: I, writer: W) -> Result<u64, Error>
+where
+    W: Write,
+    I: IntoIterator<Item = TimedRow>,
+{
+    write_transactions(rows, writer, Codec::Null)
+}
+
+fn write_transactions<W, I>(rows: I, writer: W, codec: Codec) -> Result<u64, Error>
+where
+    W: Write,
+    I: IntoIterator<Item = TimedRow>,
+{
+    let mut file = ContainerWriter::new(writer, TRANSACTION_SCHEMA, codec)?;
+    let mut written = 0;
+    for (transaction, signature, event_time) in rows {
+        file.record(|out| {
+            write_bytes(out, transaction.kind().as_bytes());
+            write_long(out, i64::from(transaction.client().id()));
+            write_long(out, i64::from(transaction.tx().id()));
+            write_optional(out, transaction.amount(), write_decimal);
+            write_optional(out, signature, |out, signature| {
+                write_bytes(out, signature.as_bytes())
+            });
+            write_optional(out, event_time, |out, time| write_long(out, time as i64));
+        })?;
+        written += 1;
+    }
+    file.finish()?;
+    Ok(written)
+}
+
+/// Reads transactions from an Avro file, in order, with their numbers. Anything malformed ends
+/// the input with [`Error::TransactionParseError`].
+pub fn read_timed_avro<R: Read>(reader: R) -> impl Iterator<Item = (u64, Result<TimedRow, Error>)> {
+    let mut rows = AvroRows {
+        decoder: Decoder(BufReader::new(reader)),
+        header: None,
+        block: Decoder(Cursor::new(vec![])),
+        remaining: 0,
+        done: false,
+    };
+    (1..).zip(std::iter::from_fn(move || rows.next_row()))
+}
+
+struct ContainerWriter<W> {
+    out: W,
+    codec: Codec,
+    sync: [u8; 16],
+    block: Vec<u8>,
+    count: u64,
+}
+
+impl<W: Write> ContainerWriter<W> {
+    fn new(mut out: W, schema: &str, codec: Codec) -> Result<Self, Error> {
+        // Any 16 bytes unlikely to show up in the data do, these don't change between runs
+        let mut sync = [0; 16];
+        sync.copy_from_slice(&Sha256::digest(schema.as_bytes())[..16]);
+        let mut header = MAGIC.to_vec();
+        write_long(&mut header, 2);
+        write_bytes(&mut header, b"avro.schema");
+        write_bytes(&mut header, schema.as_bytes());
+        write_bytes(&mut header, b"avro.codec");
+        write_bytes(&mut header, codec.name());
+        write_long(&mut header, 0);
+        header.extend_from_slice(&sync);
+        out.write_all(&header).map_err(Error::Io)?;
+        Ok(Self {
+            out,
+            codec,
+            sync,
+            block: vec![],
+            count: 0,
+        })
+    }
+
+    fn record<F: FnOnce(&mut Vec<u8>)>(&mut self, encode: F) -> Result<(), Error> {
+        encode(&mut self.block);
+        self.count += 1;
+        if self.count == BLOCK_RECORDS {
+            self.write_block()?;
+        }
+        Ok(())
+    }
+
+    fn write_block(&mut self) -> Result<(), Error> {
+        if self.count == 0 {
+            return Ok(());
+        }
+        let block = self.codec.compress(&self.block)?;
+        let mut sizes = vec![];
+        write_long(&mut sizes, self.count as i64);
+        write_long(&mut sizes, block.len() as i64);
+        self.out.write_all(&sizes).map_err(Error::Io)?;
+        self.out.write_all(&block).map_err(Error::Io)?;
+        self.out.write_all(&self.sync).map_err(Error::Io)?;
+        self.block.clear();
+        self.count = 0;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(), Error> {
+        self.write_block()?;
+        self.out.flush().map_err(Error::Io)
+    }
+}
+
+/// Zigzag varint, for both `int` and `long`.
+fn write_long(out: &mut Vec<u8>, value: i64) {
+    let mut zigzag = ((value << 1) ^ (value >> 63)) as u64;
+    while zigzag >= 0x80 {
+        out.push(zigzag as u8 | 0x80);
+        zigzag >>= 7;
+    }
+    out.push(zigzag as u8);
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_long(out, bytes.len() as i64);
+    out.extend_from_slice(bytes);
+}
+
+/// The scaled amount as the shortest big-endian two's complement.
+fn write_decimal(out: &mut Vec<u8>, amount: Currency) {
+    let bytes = amount.scaled().to_be_bytes();
+    let sign_only =
+        |byte: u8, next: u8| (byte == 0 && next < 0x80) || (byte == 0xff && next >= 0x80);
+    let start = (0..7)
+        .take_while(|&i| sign_only(bytes[i], bytes[i + 1]))
+        .count();
+    write_bytes(out, &bytes[start..]);
+}
+
+/// A union of `null` and the type `write` writes.
+fn write_optional<T, F: FnOnce(&mut Vec<u8>, T)>(out: &mut Vec<u8>, value: Option<T>, write: F) {
+    match value {
+        Some(value) => {
+            write_long(out, 1);
+            write(out, value);
+        }
+        None => write_long(out, 0),
+    }
+}
+
+struct Decoder<R>(R);
+
+impl<R: Read> Decoder<R> {
+    /// The next byte, `None` at the end of the input.
+    fn try_byte(&mut self) -> Result<Option<u8>, Error> {
+        let mut byte = [0];
+        loop {
+            match self.0.read(&mut byte) {
+                Ok(0) => return Ok(None),
+                Ok(_) => return Ok(Some(byte[0])),
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(Error::Io(err)),
+            }
+        }
+    }
+
+    fn byte(&mut self) -> Result<u8, Error> {
+        self.try_byte()?.ok_or(Error::TransactionParseError)
+    }
+
+    /// The next `long`, `None` at the end of the input.
+    fn try_long(&mut self) -> Result<Option<i64>, Error> {
+        let first = match self.try_byte()? {
+            Some(byte) => byte,
+            None => return Ok(None),
+        };
+        let mut zigzag = u64::from(first & 0x7f);
+        let mut byte = first;
+        let mut shift = 7;
+        while byte & 0x80 != 0 {
+            if shift > 63 {
+                return Err(Error::TransactionParseError);
+            }
+            byte = self.byte()?;
+            zigzag |= u64::from(byte & 0x7f) << shift;
+            shift += 7;
+        }
+        Ok(Some((zigzag >> 1) as i64 ^ -((zigzag & 1) as i64)))
+    }
+
+    fn long(&mut self) -> Result<i64, Error> {
+        self.try_long()?.ok_or(Error::TransactionParseError)
+    }
+
+    fn bytes(&mut self) -> Result<Vec<u8>, Error> {
+        let len = u64::try_from(self.long()?).map_err(|_| Error::TransactionParseError)?;
+        self.exactly(len)
+    }
+
+    fn exactly(&mut self, len: u64) -> Result<Vec<u8>, Error> {
+        let mut bytes = vec![];
+        // Grows with what is actually there, whatever the length says
+        (&mut self.0)
+            .take(len)
+            .read_to_end(&mut bytes)
+            .map_err(Error::Io)?;
+        if bytes.len() as u64 != len {
+            return Err(Error::TransactionParseError);
+        }
+        Ok(bytes)
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        String::from_utf8(self.bytes()?).map_err(|_| Error::TransactionParseError)
+    }
+
+    /// Big-endian two's complement of a decimal of `scale` digits, truncated to four.
+    fn decimal(bytes: &[u8], scale: u32) -> Result<Currency, Error> {
+        let fill = match bytes.first() {
+            Some(&first) if first >= 0x80 => 0xff,
+            Some(_) => 0,
+            None => return Err(Error::TransactionParseError),
+        };
+        // Longer than a long only with bytes of sign
+        let extra = bytes.len().saturating_sub(8);
+        if bytes[..extra].iter().any(|&byte| byte != fill) {
+            return Err(Error::TransactionParseError);
+        }
+        let mut be = [fill; 8];
+        be[8 - (bytes.len() - extra)..].copy_from_slice(&bytes[extra..]);
+        let scaled = i64::from_be_bytes(be);
+        let scaled = match scale.checked_sub(SCALE) {
+            Some(excess) => 10i64.checked_pow(excess).map_or(0, |div| scaled / div),
+            None => 10i64
+                .checked_pow(SCALE - scale)
+                .and_then(|mul| scaled.checked_mul(mul))
+                .ok_or(Error::TransactionParseError)?,
+        };
+        Ok(Currency::from_scaled(scaled))
+    }
+
+    /// Reads the header of a file, checking it holds transactions.
+    fn header(&mut self) -> Result<Header, Error> {
+        for &expected in MAGIC {
+            if self.byte()? != expected {
+                return Err(Error::TransactionParseError);
+            }
+        }
+        let mut metadata = HashMap::new();
+        loop {
+            let mut count = self.long()?;
+            if count == 0 {
+                break;
+            }
+            if count < 0 {
+                // Followed by the size of the block
+                count = count.checked_neg().ok_or(Error::TransactionParseError)?;
+                self.long()?;
+            }
+            for _ in 0..count {
+                let key = self.string()?;
+                metadata.insert(key, self.bytes()?);
+            }
+        }
+        let codec = match metadata.get("avro.codec").map(Vec::as_slice) {
+            None | Some(b"null") => Codec::Null,
+            Some(b"deflate") => Codec::Deflate,
+            Some(b"snappy") => Codec::Snappy,
+            Some(_) => return Err(Error::TransactionParseError),
+        };
+        let schema = metadata
+            .get("avro.schema")
+            .and_then(|schema| serde_json::from_slice::<Value>(schema).ok())
+            .ok_or(Error::TransactionParseError)?;
+        let fields = match Schema::parse(&schema, &mut HashMap::new())? {
+            Schema::Record(fields) => fields,
+            _ => return Err(Error::TransactionParseError),
+        };
+        for required in ["type", "client", "tx"] {
+            if !fields.iter().any(|(name, _)| name == required) {
+                return Err(Error::TransactionParseError);
+            }
+        }
+        Ok(Header {
+            codec,
+            fields,
+            sync: self.sync()?,
+        })
+    }
+
+    fn sync(&mut self) -> Result<[u8; 16], Error> {
+        let mut sync = [0; 16];
+        for byte in sync.iter_mut() {
+            *byte = self.byte()?;
+        }
+        Ok(sync)
+    }
+
+    /// A value of the schema, if it is one transactions use.
+    fn datum(&mut self, schema: &Schema) -> Result<Datum, Error> {
+        Ok(match schema {
+            Schema::Null => Datum::Null,
+            Schema::Boolean => {
+                self.byte()?;
+                Datum::Other
+            }
+            Schema::Long => Datum::Long(self.long()?),
+            Schema::Float => {
+                self.exactly(4)?;
+                Datum::Other
+            }
+            Schema::Double => {
+                self.exactly(8)?;
+                Datum::Other
+            }
+            Schema::Bytes => {
+                self.bytes()?;
+                Datum::Other
+            }
+            Schema::String => Datum::String(self.string()?),
+            Schema::Decimal { size, scale } => {
+                let bytes = match size {
+                    Some(size) => self.exactly(*size)?,
+                    None => self.bytes()?,
+                };
+                Datum::Decimal(Self::decimal(&bytes, *scale)?)
+            }
+            Schema::Fixed(size) => {
+                self.exactly(*size)?;
+                Datum::Other
+            }
+            Schema::Enum(symbols) => {
+                let index = usize::try_from(self.long()?).ok();
+                let symbol = index.and_then(|index| symbols.get(index));
+                Datum::String(symbol.ok_or(Error::TransactionParseError)?.clone())
+            }
+            Schema::Array(items) => {
+                self.blocks(|decoder| decoder.datum(items).map(drop))?;
+                Datum::Other
+            }
+            Schema::Map(values) => {
+                self.blocks(|decoder| {
+                    decoder.bytes()?;
+                    decoder.datum(values).map(drop)
+                })?;
+                Datum::Other
+            }
+            Schema::Union(branches) => {
+                let index = usize::try_from(self.long()?).ok();
+                let branch = index.and_then(|index| branches.get(index));
+                self.datum(branch.ok_or(Error::TransactionParseError)?)?
+            }
+            Schema::Record(fields) => {
+                for (_, field) in fields {
+                    self.datum(field)?;
+                }
+                Datum::Other
+            }
+        })
+    }
+
+    /// The items of an array or the entries of a map, in blocks ending with an empty one.
+    fn blocks<F: FnMut(&mut Self) -> Result<(), Error>>(
+        &mut self,
+        mut item: F,
+    ) -> Result<(), Error> {
+        loop {
+            let mut count = self.long()?;
+            if count == 0 {
+                return Ok(());
+            }
+            if count < 0 {
+                // Followed by the size of the block
+                count = count.checked_neg().ok_or(Error::TransactionParseError)?;
+                self.long()?;
+            }
+            for _ in 0..count {
+                item(self)?;
+            }
+        }
+    }
+
+    /// A record of the file's schema as a transaction, its fields found by name.
+    fn transaction(&mut self, fields: &[(String, Schema)]) -> Result<TimedRow, Error> {
+        let mut values = HashMap::new();
+        for (name, schema) in fields {
+            let datum = self.datum(schema)?;
+            values.insert(name.as_str(), datum);
+        }
+        let mut take = |name: &str| values.remove(name).unwrap_or(Datum::Null);
+        let tx_type = match take("type") {
+            Datum::String(kind) => TransactionType::from_str(&kind)?,
+            _ => return Err(Error::TransactionParseError),
+        };
+        let client = match take("client") {
+            Datum::Long(client) => u16::try_from(client).ok(),
+            _ => None,
+        };
+        let tx = match take("tx") {
+            Datum::Long(tx) => u32::try_from(tx).ok(),
+            _ => None,
+        };
+        let (Some(client), Some(tx)) = (client, tx) else {
+            return Err(Error::TransactionParseError);
+        };
+        let amount = match take("amount") {
+            Datum::Null => None,
+            Datum::Decimal(amount) => Some(amount),
+            Datum::String(amount) => {
+                Some(Currency::from_str(&amount).map_err(|_| Error::TransactionParseError)?)
+            }
+            _ => return Err(Error::TransactionParseError),
+        };
+        let signature = match take("signature") {
+            Datum::Null => None,
+            Datum::String(signature) => Some(signature),
+            _ => return Err(Error::TransactionParseError),
+        };
+        let event_time = match take("timestamp") {
+            Datum::Null => None,
+            Datum::Long(time) => {
+                Some(u64::try_from(time).map_err(|_| Error::TransactionParseError)?)
+            }
+            _ => return Err(Error::TransactionParseError),
+        };
+        let row = TransactionRow::new(
+            tx_type,
+            ClientId::new(client),
+            TransactionId::new(tx),
+            amount,
+        );
+        let (transaction, _) = row.into_signed()?;
+        let signature = signature.filter(|signature| !signature.is_empty());
+        Ok((transaction, signature, event_time))
+    }
+}
+
+/// How the blocks of a file are compressed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Codec {
+    Null,
+    /// Raw deflate, without zlib header.
+    Deflate,
+    /// Snappy, each block followed by the big-endian CRC-32 of its uncompressed data.
+    Snappy,
+}
+
+impl Codec {
+    fn name(self) -> &'static [u8] {
+        match self {
+            Codec::Null => b"null",
+            Codec::Deflate => b"deflate",
+            Codec::Snappy => b"snappy",
+        }
+    }
+
+    fn compress(self, block: &[u8]) -> Result<Vec<u8>, Error> {
+        match self {
+            Codec::Null => Ok(block.to_vec()),
+            Codec::Deflate => {
+                let mut encoder =
+                    flate2::write::DeflateEncoder::new(vec![], flate2::Compression::default());
+                encoder.write_all(block).map_err(Error::Io)?;
+                encoder.finish().map_err(Error::Io)
+            }
+            Codec::Snappy => {
+                let mut compressed = snap::raw::Encoder::new()
+                    .compress_vec(block)
+                    .map_err(|err| Error::Io(std::io::Error::other(err)))?;
+                compressed.extend_from_slice(&crc32(block).to_be_bytes());
+                Ok(compressed)
+            }
+        }
+    }
+
+    fn decompress(self, block: Vec<u8>) -> Result<Vec<u8>, Error> {
+        match self {
+            Codec::Null => Ok(block),
+            Codec::Deflate => {
+                let mut data = vec![];
+                flate2::read::DeflateDecoder::new(&block[..])
+                    .read_to_end(&mut data)
+                    .map_err(|_| Error::TransactionParseError)?;
+                Ok(data)
+            }
+            Codec::Snappy => {
+                let split = block
+                    .len()
+                    .checked_sub(4)
+                    .ok_or(Error::TransactionParseError)?;
+                let (compressed, crc) = block.split_at(split);
+                let data = snap::raw::Decoder::new()
+                    .decompress_vec(compressed)
+                    .map_err(|_| Error::TransactionParseError)?;
+                if crc32(&data).to_be_bytes() != crc {
+                    return Err(Error::TransactionParseError);
+                }
+                Ok(data)
+            }
+        }
+    }
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = flate2::Crc::new();
+    crc.update(data);
+    crc.sum()
+}
+
+/// What the header of a file says about its blocks.
+struct Header {
+    codec: Codec,
+    /// The fields of the records, in order.
+    fields: Vec<(String, Schema)>,
+    sync: [u8; 16],
+}
+
+/// A schema, with `int` read as `long` and logical types other than `decimal` as their type.
+#[derive(Debug, Clone)]
+enum Schema {
+    Null,
+    Boolean,
+    Long,
+    Float,
+    Double,
+    Bytes,
+    String,
+    /// `bytes`, or `fixed` of a size, holding a decimal.
+    Decimal {
+        size: Option<u64>,
+        scale: u32,
+    },
+    Fixed(u64),
+    Enum(Vec<String>),
+    Array(Box<Schema>),
+    Map(Box<Schema>),
+    Union(Vec<Schema>),
+    Record(Vec<(String, Schema)>),
+}
+
+impl Schema {
+    /// Parses a schema, with the named types defined so far.
+    fn parse(json: &Value, named: &mut HashMap<String, Schema>) -> Result<Schema, Error> {
+        let invalid = || Error::TransactionParseError;
+        let object = match json {
+            Value::String(name) => {
+                return match name.as_str() {
+                    "null" => Ok(Schema::Null),
+                    "boolean" => Ok(Schema::Boolean),
+                    "int" | "long" => Ok(Schema::Long),
+                    "float" => Ok(Schema::Float),
+                    "double" => Ok(Schema::Double),
+                    "bytes" => Ok(Schema::Bytes),
+                    "string" => Ok(Schema::String),
+                    name => named.get(name).cloned().ok_or_else(invalid),
+                }
+            }
+            Value::Array(branches) => {
+                let branches = branches.iter().map(|branch| Schema::parse(branch, named));
+                return branches.collect::<Result<_, _>>().map(Schema::Union);
+            }
+            Value::Object(object) => object,
+            _ => return Err(invalid()),
+        };
+        let size = || object["size"].as_u64().ok_or_else(invalid);
+        let schema = match object.get("type").ok_or_else(invalid)? {
+            Value::String(kind) if kind == "record" || kind == "error" => {
+                let fields = object["fields"].as_array().ok_or_else(invalid)?;
+                let fields = fields.iter().map(|field| {
+                    let name = field["name"].as_str().ok_or_else(invalid)?;
+                    Ok((name.to_string(), Schema::parse(&field["type"], named)?))
+                });
+                Schema::Record(fields.collect::<Result<_, Error>>()?)
+            }
+            Value::String(kind) if kind == "enum" => {
+                let symbols = object["symbols"].as_array().ok_or_else(invalid)?;
+                let symbols = symbols
+                    .iter()
+                    .map(|symbol| symbol.as_str().map(str::to_string));
+                Schema::Enum(symbols.collect::<Option<_>>().ok_or_else(invalid)?)
+            }
+            Value::String(kind) if kind == "array" => {
+                Schema::Array(Box::new(Schema::parse(&object["items"], named)?))
+            }
+            Value::String(kind) if kind == "map" => {
+                Schema::Map(Box::new(Schema::parse(&object["values"], named)?))
+            }
+            Value::String(kind) if kind == "fixed" => match object.get("logicalType") {
+                Some(logical) if logical == "decimal" => Schema::Decimal {
+                    size: Some(size()?),
+                    scale: decimal_scale(object)?,
+                },
+                _ => Schema::Fixed(size()?),
+            },
+            Value::String(kind) if kind == "bytes" && object["logicalType"] == "decimal" => {
+                Schema::Decimal {
+                    size: None,
+                    scale: decimal_scale(object)?,
+                }
+            }
+            kind => Schema::parse(kind, named)?,
+        };
+        if let Some(name) = object.get("name").and_then(Value::as_str) {
+            if let Some(namespace) = object.get("namespace").and_then(Value::as_str) {
+                named.insert(format!("{}.{}", namespace, name), schema.clone());
+            }
+            named.insert(name.to_string(), schema.clone());
+        }
+        Ok(schema)
+    }
+}
+
+fn decimal_scale(object: &serde_json::Map<String, Value>) -> Result<u32, Error> {
+    match object.get("scale") {
+        None => Ok(0),
+        Some(scale) => scale
+            .as_u64()
+            .and_then(|scale| u32::try_from(scale).ok())
+            .ok_or(Error::TransactionParseError),
+    }
+}
+
+/// A value of a record, as far as transactions need.
+enum Datum {
+    Null,
+    Long(i64),
+    String(String),
+    Decimal(Currency),
+    /// Of a type no field of a transaction has.
+    Other,
+}
+
+struct AvroRows<R> {
+    decoder: Decoder<R>,
+    /// Read before the first block.
+    header: Option<Header>,
+    /// The rest of the current block, uncompressed.
+    block: Decoder<Cursor<Vec<u8>>>,
+    /// Transactions left in the current block.
+    remaining: u64,
+    done: bool,
+}
+
+impl<R: Read> AvroRows<R> {
+    fn next_row(&mut self) -> Option<Result<TimedRow, Error>> {
+        if self.done {
+            return None;
+        }
+        let row = self.advance().transpose();
+        if !matches!(row, Some(Ok(_))) {
+            self.done = true;
+        }
+        row
+    }
+
+    fn advance(&mut self) -> Result<Option<TimedRow>, Error> {
+        let header = match &mut self.header {
+            Some(header) => header,
+            header => header.insert(self.decoder.header()?),
+        };
+        while self.remaining == 0 {
+            let count = match self.decoder.try_long()? {
+                Some(count) => count,
+                None => return Ok(None),
+            };
+            let size = u64::try_from(self.decoder.long()?);
+            let block = self
+                .decoder
+                .exactly(size.map_err(|_| Error::TransactionParseError)?)?;
+            if self.decoder.sync()? != header.sync {
+                return Err(Error::TransactionParseError);
+            }
+            self.block = Decoder(Cursor::new(header.codec.decompress(block)?));
+            self.remaining = u64::try_from(count).map_err(|_| Error::TransactionParseError)?;
+        }
+        let row = self.block.transaction(&header.fields)?;
+        self.remaining -= 1;
+        Ok(Some(row))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_timed_avro, write_transactions_avro, Codec, ContainerWriter, Decoder};
+    use super::{write_bytes, write_long};
+    use crate::currency::Currency;
+    use crate::transaction::read_timed_csv;
+    use crate::{Error, Transakt};
+
+    #[test]
+    fn avro_round_trip() {
+        let input = "type,client,tx,amount,signature,timestamp\n\
+                     deposit,1,1,10.5,,100\n\
+                     withdrawal,1,2,0.25,abc,\n\
+                     deposit,2,3,1.0,,\n\
+                     dispute,2,3,,,\n";
+        let rows: Vec<_> = read_timed_csv(input.as_bytes())
+            .map(Result::unwrap)
+            .collect();
+        let mut file = vec![];
+        assert_eq!(write_transactions_avro(rows.clone(), &mut file).unwrap(), 4);
+        let read: Vec<_> = read_timed_avro(&file[..])
+            .map(|(_, row)| row.unwrap())
+            .collect();
+        assert_eq!(read, rows);
+
+        let mut transakt = Transakt::default();
+        transakt.load_avro_from("input", &file[..]).unwrap();
+        let mut csv = Transakt::default();
+        csv.load_csv_from("input", input.as_bytes()).unwrap();
+        let (mut accounts, mut expected) = (vec![], vec![]);
+        transakt.write_accounts_avro(&mut accounts).unwrap();
+        csv.write_accounts_avro(&mut expected).unwrap();
+        assert_eq!(accounts, expected);
+
+        // Negative amounts keep their sign
+        let mut decimals = vec![];
+        for amount in [-1, -128, 127, 128, i64::MIN] {
+            super::write_decimal(&mut decimals, Currency::from_scaled(amount));
+        }
+        let mut decoder = Decoder(&decimals[..]);
+        for amount in [-1, -128, 127, 128, i64::MIN] {
+            let bytes = decoder.bytes().unwrap();
+            assert_eq!(
+                Decoder::<&[u8]>::decimal(&bytes, 4).unwrap().scaled(),
+                amount
+            );
+        }
+
+        // Files of something else aren't taken for transactions
+        let mut accounts_file = vec![];
+        ContainerWriter::new(&mut accounts_file, super::ACCOUNT_SCHEMA, Codec::Null)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let mut rows = read_timed_avro(&accounts_file[..]);
+        assert!(matches!(
+            rows.next(),
+            Some((1, Err(Error::TransactionParseError)))
+        ));
+        assert!(rows.next().is_none());
+        assert!(read_timed_avro(&file[..file.len() - 1]).any(|(_, row)| row.is_err()));
+    }
+
+    #[test]
+    fn avro_codecs() {
+        let input = "type,client,tx,amount,signature,timestamp\n\
+                     deposit,1,1,10.5,,100\n\
+                     withdrawal,1,2,0.25,abc,\n";
+        let rows: Vec<_> = read_timed_csv(input.as_bytes())
+            .map(Result::unwrap)
+            .collect();
+        for codec in [Codec::Deflate, Codec::Snappy] {
+            // More than a block, so blocks follow each other
+            let more = rows
+                .iter()
+                .cloned()
+                .cycle()
+                .take(super::BLOCK_RECORDS as usize + 2);
+            let mut file = vec![];
+            super::write_transactions(more.clone(), &mut file, codec).unwrap();
+            let read: Vec<_> = read_timed_avro(&file[..])
+                .map(|(_, row)| row.unwrap())
+                .collect();
+            assert_eq!(read, more.collect::<Vec<_>>());
+
+            // A corrupt block ends the input
+            let last = file.len() - 17;
+            file[last] ^= 0xff;
+            assert!(read_timed_avro(&file[..]).any(|(_, row)| row.is_err()));
+        }
+    }
+
+    #[test]
+    fn avro_fields_by_name() {
+        // Reordered, with fields of other types in between, an enum for the type, amounts of
+        // another scale and no signature
+        let schema = r#"{"type": "record", "name": "Payment", "fields": [
+            {"name": "tx", "type": "int"},
+            {"name": "meta", "type": {"type": "map", "values": ["null", "double", "boolean"]}},
+            {"name": "amount", "type": ["null", {"type": "fixed", "name": "Money", "size": 8, "logicalType": "decimal", "precision": 18, "scale": 6}]},
+            {"name": "type", "type": {"type": "enum", "name": "Kind", "symbols": ["deposit", "withdrawal"]}},
+            {"name": "tags", "type": {"type": "array", "items": "string"}},
+            {"name": "client", "type": "long"},
+            {"name": "timestamp", "type": ["null", {"type": "long", "logicalType": "timestamp-millis"}]}
+        ]}"#;
+        let mut file = ContainerWriter::new(vec![], schema, Codec::Deflate).unwrap();
+        for (tx, kind, amount) in [(1, 0, 10_500_000i64), (2, 1, 250_099)] {
+            file.record(|out| {
+                write_long(out, tx);
+                write_long(out, 1);
+                write_bytes(out, b"source");
+                write_long(out, 1);
+                out.extend_from_slice(&1.5f64.to_le_bytes());
+                write_long(out, 0);
+                write_long(out, 1);
+                out.extend_from_slice(&amount.to_be_bytes());
+                write_long(out, kind);
+                write_long(out, -1);
+                write_long(out, 2);
+                write_bytes(out, b"a");
+                write_long(out, 0);
+                write_long(out, 7);
+                write_long(out, 0);
+            })
+            .unwrap();
+        }
+        file.write_block().unwrap();
+        let read: Vec<_> = read_timed_avro(&file.out[..])
+            .map(|(_, row)| row.unwrap())
+            .collect();
+        let expected = "type,client,tx,amount\n\
+                        deposit,7,1,10.5\n\
+                        withdrawal,7,2,0.25\n";
+        let expected: Vec<_> = read_timed_csv(expected.as_bytes())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(read, expected);
+
+        // Without a client there is no transaction
+        let schema = schema.replace(r#""name": "client""#, r#""name": "customer""#);
+        let mut missing = vec![];
+        ContainerWriter::new(&mut missing, &schema, Codec::Null)
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert!(matches!(
+            read_timed_avro(&missing[..]).next(),
+            Some((1, Err(Error::TransactionParseError)))
+        ));
+    }
+}
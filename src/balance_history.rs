@@ -0,0 +1,155 @@
+//! Balances of clients at past instants, to answer "what was the balance at 5pm" without
+//! replaying the journal. With [`TransaktBuilder::balance_history`], or `balance_history = true`
+//! in the `[engine]` section of the configuration, the balances every applied transaction left
+//! behind are kept with the time it was applied, which [`Transakt::balance_as_of`] searches.
+//!
+//! Times are taken from the clock of the machine processing the input, in seconds since the Unix
+//! epoch. The history is not part of snapshots, so it starts over after a restore; before that,
+//! `transakt history CLIENT --journal FILE --as-of TIME` finds the balance in the journal.
+//!
+//! The history also answers queries along the time transactions happened, see
+//! [`crate::bitemporal`].
+
+use crate::bitemporal::Timestamps;
+use crate::currency::Currency;
+use crate::dormancy::now;
+use crate::transaction::ClientId;
+use crate::{Transakt, TransaktBuilder};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// A client's balances from a point in time until the next transaction of the client.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct BalanceAt {
+    /// When the transaction that left these balances was applied.
+    pub at: u64,
+    /// When the transaction happened, see [`crate::bitemporal`].
+    pub event_time: u64,
+    pub available: Currency,
+    pub held: Currency,
+}
+
+/// The balances of a client after each of their transactions, in the order they were applied.
+#[derive(Debug, Default)]
+pub(crate) struct ClientHistory {
+    /// Available and held before the first transaction kept.
+    pub(crate) start: (Currency, Currency),
+    pub(crate) balances: Vec<BalanceAt>,
+}
+
+pub(crate) type BalanceHistory = HashMap<ClientId, ClientHistory>;
+
+impl TransaktBuilder {
+    /// Keep the balances of every client after each transaction, see [`Transakt::balance_as_of`].
+    pub fn balance_history(mut self) -> Self {
+        self.balance_history = true;
+        self
+    }
+}
+
+impl Transakt {
+    /// The client's balances at `at`, in seconds since the Unix epoch: those left by the last
+    /// transaction of the client applied at or before then. `None` without balance history, or
+    /// if the client had no transaction yet at that time.
+    pub fn balance_as_of(&self, client: ClientId, at: u64) -> Option<BalanceAt> {
+        let history = &self.balance_history.as_ref()?.get(&client)?.balances;
+        let after = history.partition_point(|balance| balance.at <= at);
+        after.checked_sub(1).map(|last| history[last])
+    }
+
+    /// Available and held of the client if balance history is kept, to pass to
+    /// [`Transakt::record_balance`] once the transaction was executed.
+    pub(crate) fn balance_before(&self, client: ClientId) -> Option<(Currency, Currency)> {
+        self.balance_history.as_ref()?;
+        Some(
+            self.accounts
+                .get(&client)
+                .map(|account| (*account.available(), *account.held()))
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Records the balances the transaction of the client just left.
+    pub(crate) fn record_balance(
+        &mut self,
+        client: ClientId,
+        before: Option<(Currency, Currency)>,
+        times: Timestamps,
+    ) {
+        let (history, account, before) = match (
+            self.balance_history.as_mut(),
+            self.accounts.get(&client),
+            before,
+        ) {
+            (Some(history), Some(account), Some(before)) => (history, account, before),
+            _ => return,
+        };
+        let history = history.entry(client).or_insert_with(|| ClientHistory {
+            start: before,
+            balances: vec![],
+        });
+        let mut at = times.recorded_at.unwrap_or_else(now);
+        // The clock can go back, keep the history ordered
+        if let Some(last) = history.balances.last() {
+            at = at.max(last.at);
+        }
+        history.balances.push(BalanceAt {
+            at,
+            event_time: times.event_time.unwrap_or(at),
+            available: *account.available(),
+            held: *account.held(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::currency::Currency;
+    use crate::transaction::ClientId;
+    use crate::Transakt;
+    use std::str::FromStr;
+
+    #[test]
+    fn balance_as_of() {
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,10.0\n\
+                     withdrawal,1,2,4.0\n\
+                     dispute,1,1,\n";
+        let client = ClientId::new(1);
+        let mut transakt = Transakt::default();
+        transakt.load_csv_from("input", input.as_bytes()).unwrap();
+        assert_eq!(transakt.balance_as_of(client, u64::MAX), None);
+
+        let mut transakt = Transakt::builder().balance_history().build();
+        transakt.load_csv_from("input", input.as_bytes()).unwrap();
+        let amount = |amount: &str| Currency::from_str(amount).unwrap();
+        let latest = transakt.balance_as_of(client, u64::MAX).unwrap();
+        assert_eq!(
+            (latest.available, latest.held),
+            (amount("-4"), amount("10"))
+        );
+        assert_eq!(latest.event_time, latest.at);
+        assert_eq!(transakt.balance_as_of(client, 0), None);
+        assert_eq!(transakt.balance_as_of(ClientId::new(2), u64::MAX), None);
+
+        // Spread the transactions over time
+        let history = transakt
+            .balance_history
+            .as_mut()
+            .unwrap()
+            .get_mut(&client)
+            .unwrap();
+        assert_eq!(history.balances.len(), 3);
+        for (balance, at) in history.balances.iter_mut().zip([100, 200, 300]) {
+            balance.at = at;
+        }
+        assert_eq!(transakt.balance_as_of(client, 99), None);
+        let balance = transakt.balance_as_of(client, 250).unwrap();
+        assert_eq!(balance.at, 200);
+        assert_eq!(
+            (balance.available, balance.held),
+            (amount("6"), amount("0"))
+        );
+        assert_eq!(transakt.balance_as_of(client, 300).unwrap().at, 300);
+    }
+}
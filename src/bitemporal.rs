@@ -0,0 +1,166 @@
+//! Two times for every applied transaction: when it happened, its event time, and when it was
+//! processed. Rows give their event time in an optional `timestamp` column, in seconds since the
+//! Unix epoch; rows without one happened when they were processed. The journal keeps both, so a
+//! replay rebuilds the same history.
+//!
+//! With [balance history](crate::balance_history) kept, balances can be queried along either
+//! axis, or both: [`Transakt::balance_bitemporal`] answers "what did we believe the balance at
+//! event time E was, at processing time K", which tells a late-arriving correction apart from what
+//! was reported before it came in. Along event time, balances add up the changes of the
+//! transactions that happened by then, in whatever order they were processed.
+
+use crate::currency::Currency;
+use crate::transaction::{ClientId, Transaction};
+use crate::{Error, Transakt};
+use serde::Serialize;
+
+/// When a transaction happened and was processed, in seconds since the Unix epoch.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Timestamps {
+    /// When it happened, the time it was processed if unknown.
+    pub event_time: Option<u64>,
+    /// When it was processed, the clock if unknown. Replays take it from the journal.
+    pub recorded_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Balance {
+    pub available: Currency,
+    pub held: Currency,
+}
+
+impl Transakt {
+    /// Executes a transaction that happened at `event_time`, like
+    /// [`Transakt::execute_signed`] otherwise.
+    pub fn execute_at(
+        &mut self,
+        transaction: Transaction,
+        signature: Option<&str>,
+        event_time: Option<u64>,
+    ) -> Result<(), Error> {
+        let times = Timestamps {
+            event_time,
+            recorded_at: None,
+        };
+        self.execute_timed(transaction, signature, times)
+    }
+
+    /// The client's balances at event time `event_time` as known at processing time `known_at`:
+    /// the changes of the client's transactions that happened by the first and were processed by
+    /// the second. `None` without balance history, for a client without transactions since it
+    /// was kept, or if the sum overflows.
+    pub fn balance_bitemporal(
+        &self,
+        client: ClientId,
+        event_time: u64,
+        known_at: u64,
+    ) -> Option<Balance> {
+        let history = self.balance_history.as_ref()?.get(&client)?;
+        let (mut available, mut held) = history.start;
+        let mut previous = history.start;
+        for balance in history
+            .balances
+            .iter()
+            .take_while(|balance| balance.at <= known_at)
+        {
+            if balance.event_time <= event_time {
+                available = available.checked_add(balance.available.checked_sub(previous.0)?)?;
+                held = held.checked_add(balance.held.checked_sub(previous.1)?)?;
+            }
+            previous = (balance.available, balance.held);
+        }
+        Some(Balance { available, held })
+    }
+
+    /// The client's balances at event time `event_time`, with everything processed so far.
+    pub fn balance_at_event_time(&self, client: ClientId, event_time: u64) -> Option<Balance> {
+        self.balance_bitemporal(client, event_time, u64::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Balance;
+    use crate::currency::Currency;
+    use crate::journal::{read_journal, JournalWriter, ReplayLimit};
+    use crate::testing::SharedBuffer;
+    use crate::transaction::ClientId;
+    use crate::Transakt;
+    use std::str::FromStr;
+
+    #[test]
+    fn bitemporal_balances() {
+        // The deposit of 200 comes in late, the last one has no event time
+        let input = "type,client,tx,amount,timestamp\n\
+                     deposit,1,1,10.0,100\n\
+                     withdrawal,1,2,3.0,300\n\
+                     deposit,1,3,5.0,200\n\
+                     deposit,1,4,1.0,\n";
+        let client = ClientId::new(1);
+        let journal = SharedBuffer::default();
+        let mut transakt = Transakt::builder()
+            .balance_history()
+            .journal(JournalWriter::new(journal.clone()))
+            .build();
+        transakt.load_csv_from("input", input.as_bytes()).unwrap();
+        let available = |transakt: &Transakt, event_time| {
+            transakt
+                .balance_at_event_time(client, event_time)
+                .map(|balance| balance.available.to_string())
+        };
+        assert_eq!(available(&transakt, 99).as_deref(), Some("0.0000"));
+        assert_eq!(available(&transakt, 150).as_deref(), Some("10.0000"));
+        assert_eq!(available(&transakt, 250).as_deref(), Some("15.0000"));
+        assert_eq!(available(&transakt, 300).as_deref(), Some("12.0000"));
+        assert_eq!(available(&transakt, u64::MAX).as_deref(), Some("13.0000"));
+        assert_eq!(transakt.balance_at_event_time(ClientId::new(2), 0), None);
+
+        // The journal keeps both times
+        let entries: Vec<_> = read_journal(&journal.contents()[..])
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(entries[2].event_time, Some(200));
+        assert_eq!(entries[3].event_time, None);
+        let mut replayed = Transakt::builder().balance_history().build();
+        replayed
+            .replay(&journal.contents()[..], &ReplayLimit::default())
+            .unwrap();
+        assert_eq!(available(&replayed, 250).as_deref(), Some("15.0000"));
+        let recorded = |transakt: &Transakt| {
+            transakt.balance_history.as_ref().unwrap()[&client]
+                .balances
+                .iter()
+                .map(|balance| balance.at)
+                .collect::<Vec<_>>()
+        };
+        let times: Vec<u64> = entries.iter().map(|entry| entry.recorded_at).collect();
+        assert_eq!(recorded(&replayed), times);
+
+        // What was believed before the late deposit was processed
+        let history = transakt
+            .balance_history
+            .as_mut()
+            .unwrap()
+            .get_mut(&client)
+            .unwrap();
+        for (balance, at) in history.balances.iter_mut().zip([1000, 2000, 3000, 4000]) {
+            balance.at = at;
+        }
+        let balance = |available: &str| Balance {
+            available: Currency::from_str(available).unwrap(),
+            held: Currency::default(),
+        };
+        assert_eq!(
+            transakt.balance_bitemporal(client, 250, 2500),
+            Some(balance("10"))
+        );
+        assert_eq!(
+            transakt.balance_bitemporal(client, 250, 3000),
+            Some(balance("15"))
+        );
+        assert_eq!(
+            transakt.balance_bitemporal(client, u64::MAX, 0),
+            Some(balance("0"))
+        );
+    }
+}
@@ -0,0 +1,105 @@
+//! Counters of what each client's transactions did, for downstream analytics. They are always
+//! kept, and added to the account report with [`TransaktBuilder::report_client_stats`] or
+//! `client_stats = true` in the `[output]` section of the configuration.
+//!
+//! Like the risk signals, the counters are not part of snapshots, so they start over after a
+//! restore.
+
+use crate::transaction::{ClientId, Transaction};
+use crate::{Error, Transakt, TransaktBuilder};
+use serde::Serialize;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ClientStats {
+    pub deposits: u64,
+    pub withdrawals: u64,
+    pub disputes_opened: u64,
+    pub disputes_resolved: u64,
+    pub chargebacks: u64,
+    /// Transactions of the client rejected, for any reason.
+    pub rejected: u64,
+}
+
+impl TransaktBuilder {
+    /// Add the [`ClientStats`] of every client to the account report.
+    pub fn report_client_stats(mut self) -> Self {
+        self.report_client_stats = true;
+        self
+    }
+}
+
+impl Transakt {
+    /// Counters of the client's transactions so far, all 0 for a client never seen.
+    pub fn client_stats(&self, client: ClientId) -> ClientStats {
+        self.client_stats.get(&client).copied().unwrap_or_default()
+    }
+
+    /// Counts the transaction in the stats of its client.
+    pub(crate) fn record_client_stats(
+        &mut self,
+        transaction: &Transaction,
+        res: Result<(), &Error>,
+    ) {
+        let stats = self.client_stats.entry(transaction.client()).or_default();
+        match (transaction, res) {
+            (_, Err(_)) => stats.rejected += 1,
+            (Transaction::Deposit { .. }, Ok(())) => stats.deposits += 1,
+            (Transaction::Withdrawal { .. }, Ok(())) => stats.withdrawals += 1,
+            (Transaction::Dispute { .. }, Ok(())) => stats.disputes_opened += 1,
+            (Transaction::Resolve { .. }, Ok(())) => stats.disputes_resolved += 1,
+            (Transaction::Chargeback { .. }, Ok(())) => stats.chargebacks += 1,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ClientStats;
+    use crate::transaction::ClientId;
+    use crate::Transakt;
+
+    #[test]
+    fn client_stats() {
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,10.0\n\
+                     deposit,1,2,5.0\n\
+                     withdrawal,1,3,2.0\n\
+                     withdrawal,1,4,100.0\n\
+                     dispute,1,1,\n\
+                     resolve,1,1,\n\
+                     dispute,1,2,\n\
+                     chargeback,1,2,\n\
+                     deposit,1,5,1.0\n\
+                     deposit,2,6,3.0\n";
+        let mut transakt = Transakt::builder().report_client_stats().build();
+        transakt.load_csv_from("input", input.as_bytes()).unwrap();
+        assert_eq!(
+            transakt.client_stats(ClientId::new(1)),
+            ClientStats {
+                deposits: 2,
+                withdrawals: 1,
+                disputes_opened: 2,
+                disputes_resolved: 1,
+                chargebacks: 1,
+                // The withdrawal over the balance and the deposit on the locked account
+                rejected: 2,
+            }
+        );
+        assert_eq!(transakt.client_stats(ClientId::new(2)).deposits, 1);
+        assert_eq!(
+            transakt.client_stats(ClientId::new(3)),
+            ClientStats::default()
+        );
+
+        let mut out = vec![];
+        transakt.write_accounts_csv(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "client,available,held,total,locked,deposits,withdrawals,disputes_opened,\
+             disputes_resolved,chargebacks,rejected\n\
+             1,8.0000,0.0000,8.0000,true,2,1,2,1,1,2\n\
+             2,3.0000,0.0000,3.0000,false,1,0,0,0,0,0\n"
+        );
+    }
+}
@@ -0,0 +1,83 @@
+//! Compressed inputs, decompressed while they are read. Gzip and zstd are told apart from plain
+//! input by their magic bytes, whatever the file is named, so `transactions.csv.gz` and a gzip
+//! stream piped to stdin both work. Built with the `compression` feature,
+//! [`Transakt::read_from_csv`](crate::Transakt::read_from_csv) decompresses the files it reads.
+//!
+//! Concatenated gzip members are read as one input; of a zstd input, only the first frame is.
+
+use crate::Error;
+use std::io::{BufRead, Read};
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// The compression of an input starting with `header`, `None` for plain input.
+    pub fn detect(header: &[u8]) -> Option<Self> {
+        if header.starts_with(GZIP_MAGIC) {
+            Some(Self::Gzip)
+        } else if header.starts_with(ZSTD_MAGIC) {
+            Some(Self::Zstd)
+        } else {
+            None
+        }
+    }
+}
+
+/// The input decompressed if it is compressed, as is otherwise.
+pub fn decompressed<'a, R: BufRead + 'a>(mut reader: R) -> Result<Box<dyn Read + 'a>, Error> {
+    // A short first read could miss the magic, but files and pipes give at least 4 bytes
+    let compression = Compression::detect(reader.fill_buf().map_err(Error::Io)?);
+    Ok(match compression {
+        Some(Compression::Gzip) => Box::new(flate2::bufread::MultiGzDecoder::new(reader)),
+        Some(Compression::Zstd) => Box::new(
+            ruzstd::decoding::StreamingDecoder::new(reader)
+                .map_err(|err| Error::Io(std::io::Error::other(err)))?,
+        ),
+        None => Box::new(reader),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decompressed, Compression};
+    use flate2::write::GzEncoder;
+    use ruzstd::encoding::{compress_to_vec, CompressionLevel};
+    use std::io::{Read, Write};
+
+    #[test]
+    fn decompress() {
+        let input = "type,client,tx,amount\ndeposit,1,1,2.0\n";
+        let read = |bytes: &[u8]| {
+            let mut out = String::new();
+            decompressed(bytes)
+                .unwrap()
+                .read_to_string(&mut out)
+                .unwrap();
+            out
+        };
+
+        // Two gzip members, as from appending to a .gz file
+        let mut gzip = vec![];
+        for part in ["type,client,tx,amount\n", "deposit,1,1,2.0\n"] {
+            let mut encoder = GzEncoder::new(vec![], flate2::Compression::default());
+            encoder.write_all(part.as_bytes()).unwrap();
+            gzip.extend(encoder.finish().unwrap());
+        }
+        assert_eq!(Compression::detect(&gzip), Some(Compression::Gzip));
+        assert_eq!(read(&gzip), input);
+
+        let zstd = compress_to_vec(input.as_bytes(), CompressionLevel::Fastest);
+        assert_eq!(Compression::detect(&zstd), Some(Compression::Zstd));
+        assert_eq!(read(&zstd), input);
+
+        assert_eq!(Compression::detect(input.as_bytes()), None);
+        assert_eq!(read(input.as_bytes()), input);
+    }
+}
@@ -0,0 +1,346 @@
+//! Settings loaded from a TOML file, with `TRANSAKT_*` environment variables taking precedence.
+//!
+//! ```toml
+//! [engine]
+//! large_transaction_threshold = "10000.00"
+//! # Sanity cap on every deposit and withdrawal
+//! max_amount = "1000000000"
+//! # `create`, `reject` or `flag` withdrawals and disputes of clients without an account
+//! unknown_clients = "reject"
+//! # `allow_negative`, `hold_available` or `reject` disputes of deposits already withdrawn
+//! disputes_exceeding_funds = "hold_available"
+//! # `apply`, `reject` or `review` disputes, resolves and chargebacks on locked accounts
+//! locked_disputes = "reject"
+//! # Unlock accounts when a chargeback of theirs is reversed
+//! unlock_on_chargeback_reversal = true
+//! # Keep balances after every transaction for as-of queries
+//! balance_history = true
+//!
+//! [storage]
+//! journal = "/var/lib/transakt/journal.jsonl"
+//! # `json` or `binary`, smaller and quicker to replay
+//! journal_format = "json"
+//! state_in = "/var/lib/transakt/yesterday.json"
+//! state_out = "/var/lib/transakt/today.json"
+//! audit_log = "/var/lib/transakt/audit.jsonl"
+//! sar_report = "/var/lib/transakt/sar.jsonl"
+//!
+//! [output]
+//! log_format = "json"
+//! progress = false
+//! redaction = "hash"
+//! redaction_key = "..."
+//! client_stats = true
+//! account_metadata = true
+//!
+//! [rules]
+//! blocked_clients = [13]
+//!
+//! [denylist]
+//! clients = [42]
+//!
+//! [signatures]
+//! required = true
+//!
+//! [kyc]
+//! default_tier = "basic"
+//!
+//! [kyc.limits.basic]
+//! max_balance = "1000"
+//!
+//! [dormancy]
+//! after_days = 365
+//!
+//! [risk]
+//! chargeback = 50.0
+//!
+//! [aml.structuring]
+//! threshold = "10000"
+//! margin = "1000"
+//! count = 3
+//! window = 1000
+//!
+//! [csv]
+//! delimiter = ";"
+//! decimal_separator = ","
+//!
+//! [ingest]
+//! backpressure = "shed"
+//! max_transactions_per_second = 5000
+//!
+//! [fixed_width]
+//! type = { start = 0, len = 3 }
+//! client = { start = 3, len = 5 }
+//! tx = { start = 8, len = 10 }
+//! amount = { start = 18, len = 12 }
+//!
+//! [webhook]
+//! url = "https://risk.example.com/hooks/transakt"
+//! secret = "..."
+//! max_retries = 5
+//! ```
+
+use crate::aml::AmlConfig;
+use crate::currency::Currency;
+use crate::denylist::Denylist;
+use crate::dormancy::DormancyConfig;
+use crate::fixed_width::FixedWidthLayout;
+use crate::ingest::IngestConfig;
+use crate::journal::JournalFormat;
+use crate::kyc::KycConfig;
+use crate::policy::{DisputeFundsAction, LockedAction, UnknownClientAction};
+use crate::redact::RedactionMode;
+use crate::risk::RiskWeights;
+use crate::rules::RuleSet;
+use crate::signature::SignatureConfig;
+use crate::transaction::CsvOptions;
+use crate::Error;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub engine: EngineConfig,
+    pub storage: StorageConfig,
+    pub output: OutputConfig,
+    /// See [`crate::rules`].
+    pub rules: RuleSet,
+    /// See [`crate::denylist`].
+    pub denylist: Denylist,
+    /// See [`crate::signature`].
+    pub signatures: SignatureConfig,
+    /// See [`crate::aml`].
+    pub aml: AmlConfig,
+    /// See [`crate::kyc`].
+    pub kyc: KycConfig,
+    /// Dormant accounts are detected if set, see [`crate::dormancy`].
+    pub dormancy: Option<DormancyConfig>,
+    /// Risk scoring is on if set, see [`crate::risk`].
+    pub risk: Option<RiskWeights>,
+    /// Only used when built with the `webhook` feature.
+    pub webhook: Option<WebhookSettings>,
+    /// Layout of fixed-width inputs, see [`crate::fixed_width`].
+    pub fixed_width: Option<FixedWidthLayout>,
+    /// See [`CsvOptions`].
+    pub csv: CsvOptions,
+    /// See [`crate::ingest`].
+    pub ingest: IngestConfig,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EngineConfig {
+    /// Deposits and withdrawals of at least this amount raise a risk event.
+    pub large_transaction_threshold: Option<Currency>,
+    /// Deposits and withdrawals over this amount are rejected with `E_AMOUNT_CAP`.
+    pub max_amount: Option<Currency>,
+    /// See [`UnknownClientAction`], `create` if not set.
+    pub unknown_clients: Option<UnknownClientAction>,
+    /// See [`DisputeFundsAction`], `allow_negative` if not set.
+    pub disputes_exceeding_funds: Option<DisputeFundsAction>,
+    /// See [`DefaultPolicy::locked_dispute`](crate::policy::DefaultPolicy::locked_dispute),
+    /// `apply` if not set.
+    pub locked_disputes: Option<LockedAction>,
+    /// Unlock accounts when a chargeback of theirs is reversed.
+    pub unlock_on_chargeback_reversal: bool,
+    /// See [`crate::balance_history`].
+    pub balance_history: bool,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    /// Journal every applied transaction is appended to.
+    pub journal: Option<PathBuf>,
+    /// Format of new journals, see [`crate::journal`]. Replay reads either.
+    pub journal_format: JournalFormat,
+    /// Snapshot the state is restored from before processing.
+    pub state_in: Option<PathBuf>,
+    /// Snapshot the final state is saved to.
+    pub state_out: Option<PathBuf>,
+    /// Audit log every decision is appended to.
+    pub audit_log: Option<PathBuf>,
+    /// Suspicious activity report written after processing, see [`crate::aml`].
+    pub sar_report: Option<PathBuf>,
+    /// Key snapshots and the journal are encrypted with, as hex. Needs the `encryption` feature.
+    pub state_key: Option<String>,
+    /// Id of the state key, `default` if not set.
+    pub state_key_id: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    /// `text` or `json`.
+    pub log_format: Option<String>,
+    /// Set to `false` to never show a progress bar.
+    pub progress: Option<bool>,
+    /// Redaction of client ids and amounts in diagnostics, see [`crate::redact`].
+    pub redaction: RedactionMode,
+    /// Secret for `hash` redaction.
+    pub redaction_key: Option<String>,
+    /// Adds the counters of [`crate::client_stats`] to the account report.
+    pub client_stats: bool,
+    /// Times accounts and adds their metadata to the account report, see [`crate::metadata`].
+    pub account_metadata: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookSettings {
+    pub url: String,
+    pub secret: Option<String>,
+    pub max_retries: Option<u32>,
+    pub retry_backoff_ms: Option<u64>,
+    pub timeout_secs: Option<u64>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Config, Error> {
+        let text = std::fs::read_to_string(path).map_err(Error::Io)?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Config, Error> {
+        toml::from_str(text).map_err(|err| Error::InvalidConfig(err.to_string()))
+    }
+
+    /// Overrides settings with the `TRANSAKT_*` environment variables that are set.
+    pub fn apply_env(&mut self) -> Result<(), Error> {
+        self.apply_vars(|name| std::env::var(name).ok())
+    }
+
+    fn apply_vars<F: Fn(&str) -> Option<String>>(&mut self, var: F) -> Result<(), Error> {
+        if let Some(threshold) = var("TRANSAKT_LARGE_TRANSACTION_THRESHOLD") {
+            let threshold = Currency::from_str(&threshold).map_err(|_| {
+                Error::InvalidConfig(format!(
+                    "TRANSAKT_LARGE_TRANSACTION_THRESHOLD: invalid amount `{}`",
+                    threshold
+                ))
+            })?;
+            self.engine.large_transaction_threshold = Some(threshold);
+        }
+        if let Some(cap) = var("TRANSAKT_MAX_AMOUNT") {
+            let cap = Currency::from_str(&cap).map_err(|_| {
+                Error::InvalidConfig(format!("TRANSAKT_MAX_AMOUNT: invalid amount `{}`", cap))
+            })?;
+            self.engine.max_amount = Some(cap);
+        }
+        if let Some(journal) = var("TRANSAKT_JOURNAL") {
+            self.storage.journal = Some(journal.into());
+        }
+        if let Some(state_in) = var("TRANSAKT_STATE_IN") {
+            self.storage.state_in = Some(state_in.into());
+        }
+        if let Some(state_out) = var("TRANSAKT_STATE_OUT") {
+            self.storage.state_out = Some(state_out.into());
+        }
+        if let Some(audit_log) = var("TRANSAKT_AUDIT_LOG") {
+            self.storage.audit_log = Some(audit_log.into());
+        }
+        if let Some(sar_report) = var("TRANSAKT_SAR_REPORT") {
+            self.storage.sar_report = Some(sar_report.into());
+        }
+        if let Some(state_key) = var("TRANSAKT_STATE_KEY") {
+            self.storage.state_key = Some(state_key);
+        }
+        if let Some(log_format) = var("TRANSAKT_LOG_FORMAT") {
+            self.output.log_format = Some(log_format);
+        }
+        if let Some(progress) = var("TRANSAKT_PROGRESS") {
+            let progress = bool::from_str(&progress).map_err(|_| {
+                Error::InvalidConfig(format!(
+                    "TRANSAKT_PROGRESS: expected `true` or `false`, found `{}`",
+                    progress
+                ))
+            })?;
+            self.output.progress = Some(progress);
+        }
+        if let Some(redaction) = var("TRANSAKT_REDACTION") {
+            self.output.redaction = match redaction.as_str() {
+                "off" => RedactionMode::Off,
+                "mask" => RedactionMode::Mask,
+                "hash" => RedactionMode::Hash,
+                _ => {
+                    return Err(Error::InvalidConfig(format!(
+                        "TRANSAKT_REDACTION: expected `off`, `mask` or `hash`, found `{}`",
+                        redaction
+                    )))
+                }
+            };
+        }
+        if let Some(key) = var("TRANSAKT_REDACTION_KEY") {
+            self.output.redaction_key = Some(key);
+        }
+        if let Some(url) = var("TRANSAKT_WEBHOOK_URL") {
+            match self.webhook.as_mut() {
+                Some(webhook) => webhook.url = url,
+                None => {
+                    self.webhook = Some(WebhookSettings {
+                        url,
+                        secret: None,
+                        max_retries: None,
+                        retry_backoff_ms: None,
+                        timeout_secs: None,
+                    })
+                }
+            }
+        }
+        if let Some(secret) = var("TRANSAKT_WEBHOOK_SECRET") {
+            if let Some(webhook) = self.webhook.as_mut() {
+                webhook.secret = Some(secret);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Config;
+    use crate::currency::Currency;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::str::FromStr;
+
+    #[test]
+    fn config_env_overrides() {
+        let mut config = Config::parse(
+            "[engine]\n\
+             large_transaction_threshold = \"100\"\n\
+             [storage]\n\
+             journal = \"journal.jsonl\"\n\
+             [webhook]\n\
+             url = \"http://localhost/hook\"\n\
+             max_retries = 1\n",
+        )
+        .unwrap();
+        assert_eq!(config.webhook.as_ref().unwrap().max_retries, Some(1));
+
+        let vars: HashMap<&str, &str> = vec![
+            ("TRANSAKT_LARGE_TRANSACTION_THRESHOLD", "250.5"),
+            ("TRANSAKT_WEBHOOK_SECRET", "s3cret"),
+        ]
+        .into_iter()
+        .collect();
+        config
+            .apply_vars(|name| vars.get(name).map(|v| v.to_string()))
+            .unwrap();
+        assert_eq!(
+            config.engine.large_transaction_threshold,
+            Currency::from_str("250.5").ok()
+        );
+        assert_eq!(config.storage.journal, Some(PathBuf::from("journal.jsonl")));
+        let webhook = config.webhook.unwrap();
+        assert_eq!(webhook.url, "http://localhost/hook");
+        assert_eq!(webhook.secret.as_deref(), Some("s3cret"));
+    }
+
+    #[test]
+    fn config_rejects_unknown_settings() {
+        assert!(Config::parse("[engine]\nthreshold = \"1\"\n").is_err());
+        assert!(Config::parse("[output]\nprogress = \"yes\"\n").is_err());
+    }
+}
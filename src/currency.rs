@@ -1,6 +1,7 @@
+use crate::redact;
 use serde::de::Error;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use std::fmt::{Display, Formatter};
+use std::fmt::{Debug, Display, Formatter};
 use std::str::FromStr;
 
 #[derive(Debug, PartialEq)]
@@ -18,7 +19,7 @@ pub enum CurrencyFormatError {
 /// The upper bound is not specified, but assuming that u64 should be sufficient.
 /// In any real system, this would need be more generic, to allow for multiple currencies to exist
 /// without implementing a separate structure for each one.
-#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[derive(Default, Eq, PartialEq, Ord, PartialOrd, Copy, Clone)]
 pub struct Currency {
     /// Holds the value as a single integer, without decimals.
     /// holding currency like this is that it's easier to add and multiply without dealing with
@@ -28,12 +29,6 @@ pub struct Currency {
     amount: i64,
 }
 
-impl Default for Currency {
-    fn default() -> Self {
-        Self { amount: 0 }
-    }
-}
-
 impl Currency {
     /// How much is one unit in the decimal representation.
     /// Examples:
@@ -77,12 +72,45 @@ impl Currency {
     pub fn is_negative(&self) -> bool {
         self.amount.is_negative()
     }
+
+    /// The amount in units of the last decimal digit, as stored.
+    pub fn scaled(self) -> i64 {
+        self.amount
+    }
+
+    /// The amount of [`Currency::scaled`] units of the last decimal digit.
+    pub fn from_scaled(amount: i64) -> Self {
+        Self { amount }
+    }
+}
+
+/// Mostly amounts up to 100, which accounts can afford, now and then any amount at all.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Currency {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let amount = if u.ratio(1, 16)? {
+            u.arbitrary()?
+        } else {
+            u.int_in_range(0..=100 * Self::UNIT_IN_DECIMALS)?
+        };
+        Ok(Self { amount })
+    }
 }
 
 impl FromStr for Currency {
     type Err = CurrencyFormatError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Parsed without the sign, so the decimals of amounts above -1 keep it too
+        if let Some(abs) = s.strip_prefix('-') {
+            if abs.starts_with(['-', '+']) {
+                return Err(CurrencyFormatError::InvalidRepresentation);
+            }
+            let abs = Currency::from_str(abs)?;
+            return Ok(Currency {
+                amount: -abs.amount,
+            });
+        }
         let fields: Vec<&str> = s.split('.').collect();
         match fields.as_slice() {
             [units] => {
@@ -95,7 +123,7 @@ impl FromStr for Currency {
                 let units = units
                     .parse()
                     .map_err(|_| CurrencyFormatError::InvalidRepresentation)?;
-                let mut decimals: String = if decimals.len() > 0 {
+                let mut decimals: String = if !decimals.is_empty() {
                     decimals.chars().collect()
                 } else {
                     "0".to_string()
@@ -104,7 +132,7 @@ impl FromStr for Currency {
                 // can be eluded, but are important. Simply parsing 0001 and 1 will get us the same
                 // result, but we want 0.1 to be 1000 times larger than 0.0001.
                 // To deal with this, first ensure that all the characters are digits
-                if !decimals.chars().all(|c| c.is_digit(10)) {
+                if !decimals.chars().all(|c| c.is_ascii_digit()) {
                     return Err(CurrencyFormatError::InvalidRepresentation);
                 }
                 // Then, cut the digits that are not significant.
@@ -126,9 +154,24 @@ impl FromStr for Currency {
 
 impl Display for Currency {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let units = self.amount / Self::UNIT_IN_DECIMALS;
-        let decimals = self.amount % Self::UNIT_IN_DECIMALS;
-        write!(f, "{}.{:04}", units, decimals)
+        let sign = if self.amount.is_negative() { "-" } else { "" };
+        let abs = self.amount.unsigned_abs();
+        let unit = Self::UNIT_IN_DECIMALS as u64;
+        write!(f, "{}{}.{:04}", sign, abs / unit, abs % unit)
+    }
+}
+
+/// Redacted if configured, see [`crate::redact`].
+impl Debug for Currency {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let redaction = redact::current();
+        if redaction.is_off() {
+            f.debug_struct("Currency")
+                .field("amount", &self.amount)
+                .finish()
+        } else {
+            write!(f, "Currency({})", redaction.amount(*self))
+        }
     }
 }
 
@@ -138,7 +181,9 @@ impl<'de> Deserialize<'de> for Currency {
         D: Deserializer<'de>,
     {
         let s = String::deserialize(deserializer)?;
-        Currency::from_str(&s).map_err(|err| D::Error::custom(format!("{:?}", err)))
+        Currency::from_str(&s).map_err(|_| {
+            D::Error::custom(format!("invalid amount `{}`", redact::unparsed_amount(&s)))
+        })
     }
 }
 
@@ -242,6 +287,21 @@ mod tests {
         assert_eq!(format!("{}", Currency::new(0, 1000).unwrap()), "0.1000");
     }
 
+    #[test]
+    fn test_negative() {
+        for (scaled, text) in [(-5000, "-0.5000"), (-15001, "-1.5001"), (-20000, "-2.0000")] {
+            let amount = Currency::from_scaled(scaled);
+            assert_eq!(amount.to_string(), text);
+            assert_eq!(Currency::from_str(text).unwrap(), amount);
+        }
+        assert_eq!(
+            Currency::from_str("-0.5").unwrap(),
+            Currency::from_scaled(-5000)
+        );
+        Currency::from_str("--1").unwrap_err();
+        Currency::from_str("-+1").unwrap_err();
+    }
+
     #[test]
     fn test_add() {
         let am1 = Currency::new(1, 0).unwrap();
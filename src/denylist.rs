@@ -0,0 +1,123 @@
+//! Clients whose transactions are refused outright, like sanctioned or fraud-confirmed
+//! customers. Loaded from the `[denylist]` section of the configuration, and changeable while the
+//! engine runs through [`Transakt::denylist_mut`](crate::Transakt::denylist_mut).
+//!
+//! ```toml
+//! [denylist]
+//! clients = [13, 42]
+//!
+//! # Customers known by an identifier of another system, with their client id
+//! [denylist.aliases]
+//! "crm:ACME-7731" = 7
+//! ```
+
+use crate::event::RiskEvent;
+use crate::transaction::{ClientId, Transaction};
+use crate::{Error, Transakt};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Denylist {
+    pub clients: BTreeSet<ClientId>,
+    /// External identifiers, mapped to the client they stand for.
+    pub aliases: BTreeMap<String, ClientId>,
+}
+
+impl Denylist {
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty() && self.aliases.is_empty()
+    }
+
+    pub fn deny(&mut self, client: ClientId) {
+        self.clients.insert(client);
+    }
+
+    pub fn deny_alias(&mut self, alias: &str, client: ClientId) {
+        self.aliases.insert(alias.to_string(), client);
+    }
+
+    /// Lifts the denial of a client, whether it was listed directly or by alias.
+    pub fn allow(&mut self, client: ClientId) {
+        self.clients.remove(&client);
+        self.aliases.retain(|_, denied| *denied != client);
+    }
+
+    pub fn is_denied(&self, client: ClientId) -> bool {
+        self.clients.contains(&client) || self.aliases.values().any(|denied| *denied == client)
+    }
+
+    /// The aliases a client is denied under.
+    pub fn aliases_of(&self, client: ClientId) -> Vec<&str> {
+        self.aliases
+            .iter()
+            .filter(|(_, denied)| **denied == client)
+            .map(|(alias, _)| alias.as_str())
+            .collect()
+    }
+}
+
+impl Transakt {
+    /// Rejects the transaction if its client is denied, reporting it.
+    pub(crate) fn check_denylist(&mut self, transaction: &Transaction) -> Result<(), Error> {
+        let client = transaction.client();
+        if !self.denylist.is_denied(client) {
+            return Ok(());
+        }
+        let aliases = self
+            .denylist
+            .aliases_of(client)
+            .into_iter()
+            .map(String::from)
+            .collect();
+        self.emit(RiskEvent::DeniedClient {
+            client,
+            tx: transaction.tx(),
+            aliases,
+        });
+        let err = Error::Denied(client);
+        tracing::debug!(code = err.code(), "client denied");
+        Err(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::transaction::ClientId;
+    use crate::Transakt;
+
+    #[test]
+    fn denylist() {
+        let config = Config::parse(
+            "[denylist]\n\
+             clients = [2]\n\
+             [denylist.aliases]\n\
+             \"crm:ACME-7731\" = 3\n",
+        )
+        .unwrap();
+        let mut transakt = Transakt::builder().denylist(config.denylist).build();
+        transakt.denylist_mut().deny(ClientId::new(4));
+        transakt.denylist_mut().allow(ClientId::new(2));
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,1.0\n\
+                     deposit,2,2,1.0\n\
+                     deposit,3,3,1.0\n\
+                     deposit,4,4,1.0\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        let mut clients: Vec<u16> = transakt
+            .get_accounts_map()
+            .keys()
+            .map(|client| client.id())
+            .collect();
+        clients.sort();
+        assert_eq!(clients, vec![1, 2]);
+        let denied = transakt.rejections().groups().next().unwrap();
+        assert_eq!((denied.0, denied.1.count), ("E_DENIED", 2));
+        assert_eq!(
+            transakt.denylist_mut().aliases_of(ClientId::new(3)),
+            vec!["crm:ACME-7731"]
+        );
+    }
+}
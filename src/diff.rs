@@ -0,0 +1,249 @@
+//! Compares two sets of account balances, from account reports or snapshots, or checks balances
+//! against expected values.
+
+use crate::currency::Currency;
+use crate::snapshot::{AccountState, Snapshot};
+use crate::transaction::ClientId;
+use crate::Error;
+use csv::Trim;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+/// One line of the account report, as printed by the command line tool.
+#[derive(Debug, Deserialize)]
+struct AccountRow {
+    client: ClientId,
+    available: Currency,
+    held: Currency,
+    locked: bool,
+}
+
+/// Reads accounts from the CSV account report.
+pub fn read_accounts_csv<R: Read>(reader: R) -> Result<Vec<AccountState>, Error> {
+    let mut csv = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .trim(Trim::All)
+        .from_reader(reader);
+    let mut accounts = vec![];
+    for row in csv.deserialize() {
+        let row: AccountRow = row.map_err(|_| Error::TransactionParseError)?;
+        accounts.push(AccountState {
+            client: row.client,
+            available: row.available,
+            held: row.held,
+            locked: row.locked,
+            last_activity: None,
+            created_at: None,
+            disputes: 0,
+            chargebacks: 0,
+            shortfall: Currency::default(),
+        });
+    }
+    Ok(accounts)
+}
+
+/// Reads accounts from either a snapshot or an account report, depending on what the file holds.
+pub fn load_accounts(path: &Path) -> Result<Vec<AccountState>, Error> {
+    let file = std::fs::File::open(path).map_err(Error::Io)?;
+    let mut reader = BufReader::new(file);
+    let is_json = reader
+        .fill_buf()
+        .map_err(Error::Io)?
+        .iter()
+        .find(|byte| !byte.is_ascii_whitespace())
+        == Some(&b'{');
+    if is_json {
+        Ok(Snapshot::read(reader)?.accounts)
+    } else {
+        read_accounts_csv(reader)
+    }
+}
+
+/// A client whose account differs between the two sides. A side is `None` if the client is
+/// missing there.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountDiff {
+    pub client: ClientId,
+    pub left: Option<AccountState>,
+    pub right: Option<AccountState>,
+}
+
+impl Display for AccountDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (left, right) = match (&self.left, &self.right) {
+            (Some(left), Some(right)) => (left, right),
+            (Some(_), None) => return write!(f, "client {}: only in left", self.client),
+            (None, _) => return write!(f, "client {}: only in right", self.client),
+        };
+        let total = |account: &AccountState| account.available.checked_add(account.held);
+        let mut changes = vec![];
+        if left.available != right.available {
+            changes.push(format!(
+                "available {} -> {}",
+                left.available, right.available
+            ));
+        }
+        if left.held != right.held {
+            changes.push(format!("held {} -> {}", left.held, right.held));
+        }
+        if let (Some(l), Some(r)) = (total(left), total(right)) {
+            if l != r {
+                changes.push(format!("total {} -> {}", l, r));
+            }
+        }
+        if left.locked != right.locked {
+            changes.push(format!("locked {} -> {}", left.locked, right.locked));
+        }
+        write!(f, "client {}: {}", self.client, changes.join(", "))
+    }
+}
+
+/// Returns the clients whose accounts differ, ordered by client.
+pub fn diff_accounts(left: &[AccountState], right: &[AccountState]) -> Vec<AccountDiff> {
+    let mut clients: BTreeMap<ClientId, (Option<&AccountState>, Option<&AccountState>)> =
+        BTreeMap::new();
+    for account in left {
+        clients.entry(account.client).or_default().0 = Some(account);
+    }
+    for account in right {
+        clients.entry(account.client).or_default().1 = Some(account);
+    }
+    clients
+        .into_iter()
+        // Account reports have no metadata, so only balances are compared
+        .filter(|(_, (left, right))| match (left, right) {
+            (Some(left), Some(right)) => {
+                (left.available, left.held, left.locked)
+                    != (right.available, right.held, right.locked)
+            }
+            _ => true,
+        })
+        .map(|(client, (left, right))| AccountDiff {
+            client,
+            left: left.cloned(),
+            right: right.cloned(),
+        })
+        .collect()
+}
+
+/// Expected balances of one client. Only the values given are checked.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Expectation {
+    pub client: ClientId,
+    pub available: Option<Currency>,
+    pub held: Option<Currency>,
+    pub total: Option<Currency>,
+    pub locked: Option<bool>,
+}
+
+/// Reads expectations from a CSV with a `client` column and any of `available`, `held`, `total`
+/// and `locked`. An account report works as is.
+pub fn read_expectations_csv<R: Read>(reader: R) -> Result<Vec<Expectation>, Error> {
+    let mut csv = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .trim(Trim::All)
+        .from_reader(reader);
+    csv.deserialize()
+        .map(|row| row.map_err(|_| Error::TransactionParseError))
+        .collect()
+}
+
+/// Describes every expectation the accounts don't meet, in the order the expectations are given.
+/// Clients without an expectation are not checked.
+pub fn check_expectations(accounts: &[AccountState], expected: &[Expectation]) -> Vec<String> {
+    let mut mismatches = vec![];
+    for expectation in expected {
+        let client = expectation.client;
+        let account = match accounts.iter().find(|account| account.client == client) {
+            Some(account) => account,
+            None => {
+                mismatches.push(format!("client {}: no account", client));
+                continue;
+            }
+        };
+        let mut wrong = vec![];
+        let mut check = |name: &str, expected: Option<String>, actual: String| {
+            if let Some(expected) = expected {
+                if expected != actual {
+                    wrong.push(format!("{} {}, expected {}", name, actual, expected));
+                }
+            }
+        };
+        let total = account
+            .available
+            .checked_add(account.held)
+            .map_or("overflow".to_string(), |total| total.to_string());
+        let show = |amount: Option<Currency>| amount.map(|amount| amount.to_string());
+        check(
+            "available",
+            show(expectation.available),
+            account.available.to_string(),
+        );
+        check("held", show(expectation.held), account.held.to_string());
+        check("total", show(expectation.total), total);
+        check(
+            "locked",
+            expectation.locked.map(|locked| locked.to_string()),
+            account.locked.to_string(),
+        );
+        if !wrong.is_empty() {
+            mismatches.push(format!("client {}: {}", client, wrong.join(", ")));
+        }
+    }
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_expectations, diff_accounts, read_accounts_csv, read_expectations_csv};
+    use crate::transaction::ClientId;
+
+    #[test]
+    fn diff() {
+        let left = "client,available,held,total,locked\n\
+                    1,1.0000,0.0000,1.0000,false\n\
+                    2,2.0000,0.0000,2.0000,false\n\
+                    3,3.0000,0.0000,3.0000,false\n";
+        let right = "client,available,held,total,locked\n\
+                     3,3.0000,0.0000,3.0000,false\n\
+                     2,0.0000,2.0000,2.0000,true\n\
+                     4,4.0000,0.0000,4.0000,false\n";
+        let left = read_accounts_csv(left.as_bytes()).unwrap();
+        let right = read_accounts_csv(right.as_bytes()).unwrap();
+        let diffs = diff_accounts(&left, &right);
+        let clients: Vec<ClientId> = diffs.iter().map(|diff| diff.client).collect();
+        assert_eq!(
+            clients,
+            vec![ClientId::new(1), ClientId::new(2), ClientId::new(4)]
+        );
+        assert_eq!(diffs[0].to_string(), "client 1: only in left");
+        assert_eq!(
+            diffs[1].to_string(),
+            "client 2: available 2.0000 -> 0.0000, held 0.0000 -> 2.0000, locked false -> true"
+        );
+        assert_eq!(diffs[2].to_string(), "client 4: only in right");
+    }
+
+    #[test]
+    fn expectations() {
+        let accounts = "client,available,held,total,locked\n\
+                        1,1.0000,0.0000,1.0000,false\n\
+                        2,0.0000,2.0000,2.0000,true\n";
+        let expected = "client,total,locked\n\
+                        1,1.0,\n\
+                        2,3,false\n\
+                        3,1,\n";
+        let accounts = read_accounts_csv(accounts.as_bytes()).unwrap();
+        let expected = read_expectations_csv(expected.as_bytes()).unwrap();
+        assert_eq!(
+            check_expectations(&accounts, &expected),
+            vec![
+                "client 2: total 2.0000, expected 3.0000, locked true, expected false",
+                "client 3: no account",
+            ]
+        );
+    }
+}
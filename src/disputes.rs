@@ -0,0 +1,89 @@
+//! Deposits and transfers under an open dispute, for the risk team to see the funds on hold. `transakt report
+//! disputes SNAPSHOT` lists those of a saved state.
+
+use crate::currency::Currency;
+use crate::transaction::{ClientId, Transaction, TransactionId};
+use crate::Transakt;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct OpenDispute {
+    /// The client holding the funds, the receiving one for transfers.
+    pub client: ClientId,
+    pub tx: TransactionId,
+    /// The amount of the disputed deposit or transfer.
+    pub amount: Currency,
+    /// What the dispute holds, less than the amount if only part of the deposit was disputed or
+    /// if it was partly withdrawn, see
+    /// [`DisputeFundsAction::HoldAvailable`](crate::policy::DisputeFundsAction::HoldAvailable).
+    pub held: Currency,
+}
+
+impl Transakt {
+    /// The deposits and transfers currently disputed, ordered by client, then transaction id.
+    pub fn open_disputes(&self) -> Vec<OpenDispute> {
+        let mut disputes: Vec<OpenDispute> = self
+            .transactions
+            .values()
+            .filter_map(|transaction| match *transaction {
+                Transaction::Deposit {
+                    client,
+                    tx,
+                    amount,
+                    disputed: true,
+                }
+                | Transaction::Transfer {
+                    to: client,
+                    tx,
+                    amount,
+                    disputed: true,
+                    ..
+                } => Some(OpenDispute {
+                    client,
+                    tx,
+                    amount,
+                    held: self.partial_holds.get(&tx).copied().unwrap_or(amount),
+                }),
+                _ => None,
+            })
+            .collect();
+        disputes.sort_by_key(|dispute| (dispute.client, dispute.tx));
+        disputes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OpenDispute;
+    use crate::currency::Currency;
+    use crate::policy::DisputeFundsAction;
+    use crate::transaction::{ClientId, TransactionId};
+    use crate::Transakt;
+
+    #[test]
+    fn open_disputes() {
+        let mut transakt = Transakt::builder()
+            .disputes_exceeding_funds(DisputeFundsAction::HoldAvailable)
+            .build();
+        let input = "type,client,tx,amount\n\
+                     deposit,2,1,4.0\n\
+                     deposit,1,2,3.0\n\
+                     deposit,1,3,1.0\n\
+                     withdrawal,2,4,3.0\n\
+                     dispute,2,1,\n\
+                     dispute,1,3,\n\
+                     dispute,1,2,\n\
+                     resolve,1,3,\n";
+        transakt.load_csv_from("input", input.as_bytes()).unwrap();
+        let dispute = |client, tx, amount, held| OpenDispute {
+            client: ClientId::new(client),
+            tx: TransactionId::new(tx),
+            amount: Currency::new(amount, 0).unwrap(),
+            held: Currency::new(held, 0).unwrap(),
+        };
+        assert_eq!(
+            transakt.open_disputes(),
+            vec![dispute(1, 2, 3, 3), dispute(2, 1, 4, 1)]
+        );
+    }
+}
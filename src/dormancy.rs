@@ -0,0 +1,149 @@
+//! Accounts without activity for a while, configured in the `[dormancy]` section:
+//!
+//! ```toml
+//! [dormancy]
+//! after_days = 365
+//! # Reject withdrawals from dormant accounts until a `reactivate` row for the client
+//! require_reactivation = true
+//! ```
+//!
+//! Inputs have no timestamps, so activity is timed by the clock of the machine processing them.
+//! Deposits and withdrawals are activity, as is a `reactivate` row; with `require_reactivation`
+//! only the latter wakes up a dormant account. Accounts are only timed while dormancy is
+//! configured or [account metadata](crate::metadata) is kept, and the time of their last activity
+//! is kept in snapshots: an account without one isn't dormant.
+
+use crate::event::RiskEvent;
+use crate::transaction::{ClientId, Transaction};
+use crate::{Error, Transakt};
+use serde::Deserialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DormancyConfig {
+    pub after_days: u64,
+    #[serde(default)]
+    pub require_reactivation: bool,
+}
+
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+impl Transakt {
+    /// Whether the client's account is dormant, if dormancy is configured and it has an account.
+    pub fn is_dormant(&self, client: ClientId) -> Option<bool> {
+        let dormancy = self.dormancy.as_ref()?;
+        let account = self.accounts.get(&client)?;
+        let idle = account
+            .last_activity()
+            .map_or(0, |at| now().saturating_sub(at));
+        Some(idle >= dormancy.after_days * 86400)
+    }
+
+    /// Reports a transaction on a dormant account, rejecting withdrawals if the account has to be
+    /// reactivated first.
+    pub(crate) fn check_dormancy(&mut self, transaction: &Transaction) -> Result<(), Error> {
+        let client = transaction.client();
+        if self.is_dormant(client) != Some(true) {
+            return Ok(());
+        }
+        let require_reactivation = self
+            .dormancy
+            .as_ref()
+            .is_some_and(|dormancy| dormancy.require_reactivation);
+        let last_activity = self
+            .accounts
+            .get(&client)
+            .and_then(|account| account.last_activity());
+        self.emit(RiskEvent::AccountDormant {
+            client,
+            tx: transaction.tx(),
+            last_activity,
+        });
+        match transaction {
+            Transaction::Withdrawal { .. } if require_reactivation => {
+                let err = Error::Dormant(client);
+                tracing::debug!(code = err.code(), "dormant account");
+                Err(err)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Times the activity of the client after the transaction was applied.
+    pub(crate) fn record_activity(&mut self, transaction: &Transaction) {
+        let require_reactivation = match &self.dormancy {
+            Some(dormancy) => dormancy.require_reactivation,
+            None if self.account_metadata => false,
+            None => return,
+        };
+        let active = match transaction {
+            Transaction::Reactivate { .. } => true,
+            Transaction::Deposit { .. } | Transaction::Withdrawal { .. } => {
+                !require_reactivation || self.is_dormant(transaction.client()) != Some(true)
+            }
+            _ => false,
+        };
+        if let (true, Some(account)) = (active, self.accounts.get_mut(&transaction.client())) {
+            account.touch(now());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::snapshot::Snapshot;
+    use crate::transaction::ClientId;
+    use crate::Transakt;
+
+    #[test]
+    fn dormant_accounts() {
+        let config = Config::parse(
+            "[dormancy]\n\
+             after_days = 30\n\
+             require_reactivation = true\n",
+        )
+        .unwrap();
+        let mut transakt = Transakt::builder()
+            .dormancy(config.dormancy.unwrap())
+            .build();
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,10.0\n\
+                     deposit,2,2,10.0\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        let (first, second) = (ClientId::new(1), ClientId::new(2));
+        assert_eq!(transakt.is_dormant(first), Some(false));
+
+        // Client 1 was last active in 1970
+        let mut snapshot = transakt.snapshot();
+        snapshot.accounts[0].last_activity = Some(0);
+        let json = serde_json::to_string(&snapshot).unwrap();
+        transakt
+            .restore(Snapshot::read(json.as_bytes()).unwrap())
+            .unwrap();
+        assert_eq!(transakt.is_dormant(first), Some(true));
+        assert_eq!(transakt.is_dormant(second), Some(false));
+        assert_eq!(transakt.is_dormant(ClientId::new(3)), None);
+
+        let input = "type,client,tx,amount\n\
+                     deposit,1,3,5.0\n\
+                     withdrawal,1,4,5.0\n\
+                     reactivate,1,5,\n\
+                     withdrawal,1,6,5.0\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        let rejected: Vec<(&str, Vec<u64>)> = transakt
+            .rejections()
+            .groups()
+            .map(|(code, group)| (code, group.lines.clone()))
+            .collect();
+        assert_eq!(rejected, vec![("E_DORMANT", vec![3])]);
+        assert_eq!(transakt.is_dormant(first), Some(false));
+        let account = &transakt.get_accounts_map()[&first];
+        assert_eq!(account.available().to_string(), "10.0000");
+    }
+}
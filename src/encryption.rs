@@ -0,0 +1,280 @@
+//! Encryption of persisted state at rest, built with the `encryption` feature. Snapshots and
+//! journal entries are sealed with AES-256-GCM under a key from a [`KeyProvider`]: a key given
+//! in the configuration, or one fetched from a KMS by an implementation of the trait.
+//!
+//! ```toml
+//! [storage]
+//! # 32 bytes as hex, or the TRANSAKT_STATE_KEY environment variable
+//! state_key = "..."
+//! # Recorded in the header, so keys can be rotated
+//! state_key_id = "2024-01"
+//! ```
+//!
+//! Sealed data starts with a header of a magic string, the format version, the key id and the
+//! nonce. The header is authenticated along with the data, so a snapshot whose header or content
+//! was tampered with, or that was sealed with another key, is rejected on load. A snapshot that
+//! isn't sealed is rejected too when a key is configured.
+//!
+//! Journals stay line oriented: each entry is sealed on its own and written as a line of hex.
+
+use crate::hex;
+use crate::journal::JournalWriter;
+use crate::snapshot::Snapshot;
+use crate::Error;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use std::convert::TryFrom;
+use std::fmt::{Debug, Formatter};
+use std::io::BufRead;
+use std::path::Path;
+use std::sync::Arc;
+
+const MAGIC: &[u8] = b"TRANSAKT-SEALED";
+const FORMAT_VERSION: u8 = 1;
+const NONCE_LEN: usize = 12;
+
+/// Where the keys come from, the hook for a KMS.
+pub trait KeyProvider: Send + Sync {
+    /// Id of the key new state is sealed with.
+    fn current_key_id(&self) -> String;
+
+    /// The 256-bit key with the id, as recorded in the header of sealed data.
+    fn key(&self, key_id: &str) -> Result<[u8; 32], Error>;
+}
+
+/// A single key given by the user.
+pub struct StaticKey {
+    id: String,
+    key: [u8; 32],
+}
+
+impl StaticKey {
+    pub fn new(id: &str, key: [u8; 32]) -> Self {
+        Self {
+            id: id.to_string(),
+            key,
+        }
+    }
+
+    /// Parses the key from 64 hex digits.
+    pub fn from_hex(id: &str, digits: &str) -> Result<Self, Error> {
+        let bytes = hex::decode(digits.trim())
+            .filter(|bytes| bytes.len() == 32)
+            .ok_or_else(|| Error::InvalidConfig("state key must be 64 hex digits".to_string()))?;
+        let mut key = [0; 32];
+        key.copy_from_slice(&bytes);
+        Ok(Self::new(id, key))
+    }
+}
+
+impl KeyProvider for StaticKey {
+    fn current_key_id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn key(&self, key_id: &str) -> Result<[u8; 32], Error> {
+        if key_id == self.id {
+            Ok(self.key)
+        } else {
+            Err(Error::InvalidSnapshot(format!("unknown key `{}`", key_id)))
+        }
+    }
+}
+
+/// Seals and opens persisted state with the keys of a provider.
+#[derive(Clone)]
+pub struct StateCipher {
+    keys: Arc<dyn KeyProvider>,
+}
+
+impl Debug for StateCipher {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StateCipher")
+            .field("key_id", &self.keys.current_key_id())
+            .finish()
+    }
+}
+
+impl StateCipher {
+    pub fn new<P: KeyProvider + 'static>(keys: P) -> Self {
+        Self {
+            keys: Arc::new(keys),
+        }
+    }
+
+    /// Whether the data starts like sealed data.
+    pub fn is_sealed(data: &[u8]) -> bool {
+        data.starts_with(MAGIC)
+    }
+
+    pub fn seal(&self, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        let key_id = self.keys.current_key_id();
+        let key_len = u8::try_from(key_id.len())
+            .map_err(|_| Error::InvalidConfig("state key id is too long".to_string()))?;
+        let key = self.keys.key(&key_id)?;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let mut sealed = MAGIC.to_vec();
+        sealed.push(FORMAT_VERSION);
+        sealed.push(key_len);
+        sealed.extend_from_slice(key_id.as_bytes());
+        sealed.extend_from_slice(&nonce);
+        let payload = Payload {
+            msg: plaintext,
+            aad: &sealed,
+        };
+        let ciphertext = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+            .encrypt(&nonce, payload)
+            .map_err(|_| Error::InvalidConfig("encryption failed".to_string()))?;
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    /// Opens sealed data, failing if it isn't sealed, or was tampered with.
+    pub fn open(&self, sealed: &[u8]) -> Result<Vec<u8>, Error> {
+        let invalid = |reason: &str| Error::InvalidSnapshot(reason.to_string());
+        let rest = sealed
+            .strip_prefix(MAGIC)
+            .ok_or_else(|| invalid("not encrypted"))?;
+        let (version, key_len, rest) = match rest {
+            [version, key_len, rest @ ..] => (*version, usize::from(*key_len), rest),
+            _ => return Err(invalid("truncated header")),
+        };
+        if version != FORMAT_VERSION {
+            return Err(Error::InvalidSnapshot(format!(
+                "unsupported encryption version {}",
+                version
+            )));
+        }
+        if rest.len() < key_len + NONCE_LEN {
+            return Err(invalid("truncated header"));
+        }
+        let key_id =
+            std::str::from_utf8(&rest[..key_len]).map_err(|_| invalid("invalid key id"))?;
+        let key = self.keys.key(key_id)?;
+        let header_len = sealed.len() - rest.len() + key_len + NONCE_LEN;
+        let (header, ciphertext) = sealed.split_at(header_len);
+        let nonce = Nonce::from_slice(&header[header_len - NONCE_LEN..]);
+        let payload = Payload {
+            msg: ciphertext,
+            aad: header,
+        };
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+            .decrypt(nonce, payload)
+            .map_err(|_| invalid("authentication failed, wrong key or tampered with"))
+    }
+}
+
+impl Snapshot {
+    pub fn load_encrypted(path: &Path, cipher: &StateCipher) -> Result<Snapshot, Error> {
+        let sealed = std::fs::read(path).map_err(Error::Io)?;
+        Self::read(&cipher.open(&sealed)?[..])
+    }
+
+    pub fn save_encrypted(&self, path: &Path, cipher: &StateCipher) -> Result<(), Error> {
+        let mut plaintext = vec![];
+        self.write(&mut plaintext)?;
+        let sealed = cipher.seal(&plaintext)?;
+        crate::snapshot::save_atomically(path, |writer| {
+            std::io::Write::write_all(writer, &sealed).map_err(Error::Io)
+        })
+    }
+}
+
+impl JournalWriter {
+    /// Seals every entry written from now on.
+    pub fn encrypted(mut self, cipher: StateCipher) -> Self {
+        self.cipher = Some(cipher);
+        self
+    }
+}
+
+/// Opens every line of an encrypted journal, returning the plain journal to replay.
+pub fn decrypt_journal<R: BufRead>(reader: R, cipher: &StateCipher) -> Result<Vec<u8>, Error> {
+    let mut journal = vec![];
+    for (i, line) in reader.lines().enumerate() {
+        let line = line.map_err(Error::Io)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = hex::decode(line.trim())
+            .ok_or_else(|| Error::InvalidSnapshot("not hex".to_string()))
+            .and_then(|sealed| cipher.open(&sealed))
+            .map_err(|err| match err {
+                Error::InvalidSnapshot(reason) => {
+                    Error::InvalidSnapshot(format!("journal line {}: {}", i + 1, reason))
+                }
+                err => err,
+            })?;
+        journal.extend_from_slice(&entry);
+        journal.push(b'\n');
+    }
+    Ok(journal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decrypt_journal, StateCipher, StaticKey};
+    use crate::journal::JournalWriter;
+    use crate::snapshot::Snapshot;
+    use crate::{Error, Transakt};
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn sealed_state() {
+        let cipher = StateCipher::new(StaticKey::new("k1", [7; 32]));
+        let journal = Shared::default();
+        let mut transakt = Transakt::builder()
+            .journal(JournalWriter::new(journal.clone()).encrypted(cipher.clone()))
+            .build();
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,10.0\n\
+                     withdrawal,1,2,4.0\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        transakt.flush_journal().unwrap();
+
+        let dir = std::env::temp_dir().join(format!("transakt-sealed-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("state.json");
+        transakt.snapshot().save_encrypted(&path, &cipher).unwrap();
+        let sealed = std::fs::read(&path).unwrap();
+        assert!(StateCipher::is_sealed(&sealed));
+        assert!(!String::from_utf8_lossy(&sealed).contains("\"accounts\""));
+        let snapshot = Snapshot::load_encrypted(&path, &cipher).unwrap();
+        assert_eq!(snapshot, transakt.snapshot());
+
+        // Tampering with the header or the content, or another key, fails authentication
+        for at in [20, sealed.len() - 1].iter().copied() {
+            let mut tampered = sealed.clone();
+            tampered[at] ^= 1;
+            assert!(matches!(
+                cipher.open(&tampered),
+                Err(Error::InvalidSnapshot(_))
+            ));
+        }
+        let other = StateCipher::new(StaticKey::new("k1", [8; 32]));
+        assert!(other.open(&sealed).is_err());
+        assert!(cipher.open(b"{\"version\":1}").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let sealed_journal = journal.0.lock().unwrap().clone();
+        assert!(!String::from_utf8_lossy(&sealed_journal).contains("deposit"));
+        let plain = decrypt_journal(&sealed_journal[..], &cipher).unwrap();
+        let mut replayed = Transakt::default();
+        replayed.replay(&plain[..], &Default::default()).unwrap();
+        assert_eq!(replayed.snapshot().accounts, transakt.snapshot().accounts);
+    }
+}
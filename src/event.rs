@@ -0,0 +1,90 @@
+use crate::currency::Currency;
+use crate::transaction::{ClientId, TransactionId};
+use serde::Serialize;
+
+/// Something that happened while processing transactions which downstream risk systems may want
+/// to react to.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum RiskEvent {
+    AccountLocked {
+        client: ClientId,
+    },
+    Chargeback {
+        client: ClientId,
+        tx: TransactionId,
+        amount: Currency,
+    },
+    /// A deposit or withdrawal at or above the configured threshold was applied.
+    LargeTransaction {
+        client: ClientId,
+        tx: TransactionId,
+        amount: Currency,
+    },
+    /// A transaction of a client on the denylist was rejected.
+    DeniedClient {
+        client: ClientId,
+        tx: TransactionId,
+        /// Aliases the client is denied under, if any.
+        aliases: Vec<String>,
+    },
+    /// A client or transaction was flagged by screening, and rejected if `blocked`.
+    ScreeningHit {
+        client: ClientId,
+        tx: TransactionId,
+        reason: String,
+        blocked: bool,
+    },
+    /// Applied transactions of a client look like money laundering, see [`crate::aml`].
+    SuspiciousActivity {
+        client: ClientId,
+        kind: String,
+        transactions: Vec<TransactionId>,
+        total: Currency,
+    },
+    /// A client's chargebacks per deposit went over the `[rules]` limit.
+    ChargebackRatioExceeded {
+        client: ClientId,
+        ratio: f64,
+        /// Whether the account was locked for it; it may have been locked already.
+        locked: bool,
+    },
+    /// A deposit or withdrawal was rejected for going over a limit of the client's KYC tier.
+    LimitExceeded {
+        client: ClientId,
+        tx: TransactionId,
+        tier: String,
+        /// `max_balance` or `daily_withdrawal`.
+        limit: String,
+    },
+    /// A transaction came in for an account without activity for longer than configured.
+    AccountDormant {
+        client: ClientId,
+        tx: TransactionId,
+        /// In seconds since the Unix epoch.
+        last_activity: Option<u64>,
+    },
+    /// A dispute came in for a deposit larger than the available funds, see
+    /// [`crate::policy::DisputeFundsAction`].
+    DisputeExceedsFunds {
+        client: ClientId,
+        tx: TransactionId,
+        /// What the dispute was for, the deposit or part of it.
+        amount: Currency,
+        available: Currency,
+        /// How much of the deposit was held, zero if the dispute was rejected.
+        held: Currency,
+    },
+    /// A transaction other than a deposit came in for a client without an account, see
+    /// [`crate::policy::UnknownClientAction`].
+    UnknownClient {
+        client: ClientId,
+        tx: TransactionId,
+        kind: String,
+    },
+    /// Processing of an input stopped early because of an unrecoverable error.
+    BatchRejected {
+        source: String,
+        reason: String,
+    },
+}
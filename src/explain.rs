@@ -0,0 +1,328 @@
+//! Documentation for the stable error codes reported in logs, see [`Error::code`].
+
+use crate::Error;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Explanation {
+    pub code: &'static str,
+    pub summary: &'static str,
+    pub causes: &'static [&'static str],
+    /// What the engine does with the row, and with the rest of the input.
+    pub handling: &'static str,
+}
+
+const STOPS_INPUT: &str =
+    "Processing of the whole input stops, transactions already executed stay applied.";
+const ROW_REJECTED: &str =
+    "The row is rejected and leaves no trace in the ledger, processing continues with the next row.";
+
+pub const EXPLANATIONS: &[Explanation] = &[
+    Explanation {
+        code: "E_PARSE",
+        summary: "A row, or a journal entry, could not be read as a transaction.",
+        causes: &[
+            "unknown transaction type",
+            "client or tx id that is not a number, or out of range",
+            "amount with more than 4 decimals or invalid characters",
+            "amount missing on a deposit or withdrawal, or given on any other type",
+            "wrong number of columns",
+        ],
+        handling: STOPS_INPUT,
+    },
+    Explanation {
+        code: "E_HELD_FUNDS",
+        summary: "Releasing or charging back a dispute would take more than the account holds.",
+        causes: &["an inconsistent ledger, for example a snapshot edited by hand"],
+        handling: STOPS_INPUT,
+    },
+    Explanation {
+        code: "E_IO",
+        summary: "A file could not be read or written.",
+        causes: &[
+            "missing file or wrong path",
+            "missing permissions",
+            "full disk",
+            "stdout closed early, like when piping into `head`",
+        ],
+        handling: STOPS_INPUT,
+    },
+    Explanation {
+        code: "E_SNAPSHOT",
+        summary: "A snapshot could not be loaded.",
+        causes: &[
+            "the file is not a snapshot, or is truncated",
+            "the snapshot was written by an incompatible version",
+            "a stored transaction belongs to a client without an account",
+            "an audit log was modified after it was written",
+        ],
+        handling: "Nothing is processed.",
+    },
+    Explanation {
+        code: "E_CONFIG",
+        summary: "The configuration file or a TRANSAKT_* environment variable is invalid.",
+        causes: &[
+            "misspelled or unknown setting",
+            "value of the wrong type, like a number where an amount string is expected",
+        ],
+        handling: "Nothing is processed.",
+    },
+    Explanation {
+        code: "E_SCENARIO",
+        summary: "A scenario file has a line that is neither a transaction row nor an expectation.",
+        causes: &[
+            "a row with a missing or extra field",
+            "a misspelled key in an `expect` line, or one without a `client`",
+        ],
+        handling: "The scenario is not run.",
+    },
+    Explanation {
+        code: "E_INVARIANT",
+        summary: "With --verify, a transaction left the ledger inconsistent.",
+        causes: &[
+            "a dispute holding more than the account has left, after a withdrawal",
+            "a bug in the engine or in a custom processing policy or validator",
+        ],
+        handling:
+            "Processing of the whole input stops right after the transaction, which stays applied.",
+    },
+    Explanation {
+        code: "E_DUP_TX",
+        summary: "A deposit or withdrawal reuses the tx id of an earlier one.",
+        causes: &[
+            "the same file, or overlapping files, processed twice",
+            "an upstream system reusing ids",
+        ],
+        handling: ROW_REJECTED,
+    },
+    Explanation {
+        code: "E_OVERFLOW",
+        summary: "A balance would go beyond what can be represented.",
+        causes: &["an amount with an unrealistic number of digits"],
+        handling: ROW_REJECTED,
+    },
+    Explanation {
+        code: "E_LOCKED",
+        summary: "A deposit or withdrawal targets a locked account.",
+        causes: &["the account was locked by an earlier chargeback"],
+        handling: ROW_REJECTED,
+    },
+    Explanation {
+        code: "E_FUNDS",
+        summary: "A withdrawal is larger than the available balance.",
+        causes: &[
+            "the client really lacks the funds",
+            "funds are held by an open dispute",
+            "rows out of order, with the withdrawal before the deposit",
+        ],
+        handling: ROW_REJECTED,
+    },
+    Explanation {
+        code: "E_INVALID_TX",
+        summary: "The transaction is well formed but not allowed in the current state.",
+        causes: &[
+            "negative deposit or withdrawal amount",
+            "dispute of a transaction that is already disputed",
+            "resolve or chargeback of a transaction that is not disputed",
+            "freeze of a locked account, or unfreeze of an unlocked one",
+        ],
+        handling: ROW_REJECTED,
+    },
+    Explanation {
+        code: "E_REVIEW",
+        summary: "A transaction on a locked account was set aside for manual review.",
+        causes: &["the processing policy queues this kind of transaction on locked accounts"],
+        handling: "The row is not executed for now; the application embedding the engine decides \
+                   what happens to it after the review.",
+    },
+    Explanation {
+        code: "E_RULE",
+        summary: "The transaction breaks one of the rules in the `[rules]` configuration.",
+        causes: &[
+            "a transaction of a blocked client",
+            "an amount over the maximum for its type",
+            "a dispute outside of the dispute window",
+        ],
+        handling: ROW_REJECTED,
+    },
+    Explanation {
+        code: "E_REJECTED",
+        summary: "A validator registered by the application rejected the transaction.",
+        causes: &["a fraud or business check of the application embedding the engine"],
+        handling: ROW_REJECTED,
+    },
+    Explanation {
+        code: "E_DENIED",
+        summary: "The client is on the denylist.",
+        causes: &[
+            "the client or one of its aliases is in the `[denylist]` configuration",
+            "the application embedding the engine denied the client while processing",
+        ],
+        handling: "The row is skipped and a `denied_client` risk event is raised; processing \
+                   continues with the next row.",
+    },
+    Explanation {
+        code: "E_SCREENING",
+        summary: "Sanctions or KYC screening blocked the client or the transaction.",
+        causes: &[
+            "the client is on the watchlist of the screening provider",
+            "the provider blocked a large deposit or withdrawal",
+        ],
+        handling: "The row is skipped and a `screening_hit` risk event is raised; processing \
+                   continues with the next row. The client's result is cached, so its later \
+                   transactions are blocked too until it is screened again.",
+    },
+    Explanation {
+        code: "E_LIMIT",
+        summary: "The transaction goes over a limit of the client's KYC tier.",
+        causes: &[
+            "a deposit taking the account over the maximum balance of the tier",
+            "a withdrawal over what the tier allows per day",
+        ],
+        handling: "The row is skipped and a `limit_exceeded` risk event is raised; processing \
+                   continues with the next row.",
+    },
+    Explanation {
+        code: "E_DORMANT",
+        summary: "A withdrawal from a dormant account that has to be reactivated first.",
+        causes: &[
+            "no deposit or withdrawal on the account for longer than the `[dormancy]` period",
+        ],
+        handling: "The row is skipped and processing continues with the next row. A `reactivate` \
+                   row for the client lets later withdrawals through.",
+    },
+    Explanation {
+        code: "E_SIGNATURE",
+        summary: "A row whose signature doesn't verify against the configured keys.",
+        causes: &[
+            "a row without a `signature` while `[signatures]` requires one",
+            "a row changed after it was signed, or signed with a key that isn't configured",
+            "a signature that isn't 128 hex digits",
+        ],
+        handling: "The row is skipped and processing continues with the next row. Sign the \
+                   canonical row, `type,client,tx,amount` with four decimals, see \
+                   `src/signature.rs`.",
+    },
+    Explanation {
+        code: "E_UNKNOWN_CLIENT",
+        summary: "A transaction other than a deposit for a client without an account.",
+        causes: &[
+            "a withdrawal, dispute, freeze or reactivation before the client's first deposit",
+            "a mistyped client id",
+        ],
+        handling: "The row is skipped and processing continues with the next row, instead of \
+                   creating an empty account. Rejected only with `unknown_clients = \"reject\"` \
+                   in the `[engine]` section.",
+    },
+    Explanation {
+        code: "E_DISPUTE_FUNDS",
+        summary: "A dispute of a deposit larger than the funds available on the account.",
+        causes: &["the disputed deposit was already withdrawn, in part or in full"],
+        handling: "The row is skipped and processing continues with the next row. Rejected only \
+                   with `disputes_exceeding_funds = \"reject\"` in the `[engine]` section.",
+    },
+    Explanation {
+        code: "E_AMOUNT_CAP",
+        summary: "A deposit or withdrawal of more than the `max_amount` of the `[engine]` section.",
+        causes: &[
+            "a fat-fingered amount, like one with extra zeros",
+            "a corrupted feed, or a value in another unit like cents",
+        ],
+        handling: "The row is skipped and processing continues with the next row. The cap is \
+                   meant to be far above any real transaction, so check the source.",
+    },
+];
+
+/// Looks up an error code, ignoring case.
+pub fn explain(code: &str) -> Option<&'static Explanation> {
+    EXPLANATIONS
+        .iter()
+        .find(|explanation| explanation.code.eq_ignore_ascii_case(code))
+}
+
+impl Error {
+    /// Stable identifier of the kind of error, documented by [`explain`].
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::TransactionParseError => "E_PARSE",
+            Error::InsufficientHeldFunds => "E_HELD_FUNDS",
+            Error::Io(_) => "E_IO",
+            Error::InvalidSnapshot(_) => "E_SNAPSHOT",
+            Error::InvalidConfig(_) => "E_CONFIG",
+            Error::InvariantViolation(_) => "E_INVARIANT",
+            Error::InvalidScenario(_) => "E_SCENARIO",
+            Error::DuplicateTransaction(_) => "E_DUP_TX",
+            Error::Overflow => "E_OVERFLOW",
+            Error::AccountLocked => "E_LOCKED",
+            Error::InsufficientFunds => "E_FUNDS",
+            Error::InvalidTransaction => "E_INVALID_TX",
+            Error::PendingReview => "E_REVIEW",
+            Error::RuleViolation(_) => "E_RULE",
+            Error::Rejected(_) => "E_REJECTED",
+            Error::Denied(_) => "E_DENIED",
+            Error::ScreeningHit(_) => "E_SCREENING",
+            Error::LimitExceeded(_) => "E_LIMIT",
+            Error::Dormant(_) => "E_DORMANT",
+            Error::InvalidSignature(_) => "E_SIGNATURE",
+            Error::UnknownClient(_) => "E_UNKNOWN_CLIENT",
+            Error::DisputeExceedsFunds(_) => "E_DISPUTE_FUNDS",
+            Error::AmountOverCap(_) => "E_AMOUNT_CAP",
+        }
+    }
+}
+
+impl Display for Explanation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}: {}", self.code, self.summary)?;
+        writeln!(f)?;
+        writeln!(f, "Common causes:")?;
+        for cause in self.causes {
+            writeln!(f, "  - {}", cause)?;
+        }
+        writeln!(f)?;
+        write!(f, "Handling: {}", self.handling)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{explain, EXPLANATIONS};
+    use crate::currency::Currency;
+    use crate::transaction::{ClientId, TransactionId};
+    use crate::Error;
+
+    #[test]
+    fn every_code_explained() {
+        let errors = vec![
+            Error::TransactionParseError,
+            Error::InsufficientHeldFunds,
+            Error::Io(std::io::ErrorKind::NotFound.into()),
+            Error::InvalidSnapshot(String::new()),
+            Error::InvalidConfig(String::new()),
+            Error::InvariantViolation(String::new()),
+            Error::InvalidScenario(String::new()),
+            Error::DuplicateTransaction(TransactionId::new(1)),
+            Error::Overflow,
+            Error::AccountLocked,
+            Error::InsufficientFunds,
+            Error::InvalidTransaction,
+            Error::PendingReview,
+            Error::RuleViolation(String::new()),
+            Error::Rejected(String::new()),
+            Error::Denied(ClientId::new(1)),
+            Error::ScreeningHit(String::new()),
+            Error::LimitExceeded(String::new()),
+            Error::Dormant(ClientId::new(1)),
+            Error::InvalidSignature(String::new()),
+            Error::UnknownClient(ClientId::new(1)),
+            Error::DisputeExceedsFunds(TransactionId::new(1)),
+            Error::AmountOverCap(Currency::default()),
+        ];
+        assert_eq!(errors.len(), EXPLANATIONS.len());
+        for err in errors {
+            assert_eq!(explain(err.code()).unwrap().code, err.code());
+        }
+        assert_eq!(explain("e_dup_tx").unwrap().code, "E_DUP_TX");
+        assert!(explain("E_NOPE").is_none());
+    }
+}
@@ -0,0 +1,169 @@
+//! Fault injection for tests of recovery, built with the `fault-injection` feature. A
+//! [`FaultyWriter`] wraps the writer of a [`JournalWriter`](crate::journal::JournalWriter), or one
+//! a [`Snapshot`](crate::snapshot::Snapshot) is written to, and fails the way storage does:
+//!
+//! - the N-th write fails with an error,
+//! - every K-th write is interrupted, a transient error that is meant to be retried,
+//! - after some bytes the process "crashes": the write in progress is cut short and every later
+//!   write and flush fails, leaving a torn tail like a crash in the middle of a flush would.
+//!
+//! Faults are counted over write attempts, so they hit the same place for the same sequence of
+//! writes.
+
+use std::convert::TryFrom;
+use std::io::{Error, ErrorKind, Write};
+use std::sync::{Arc, Mutex};
+
+/// Which faults to inject, none by default.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Faults {
+    fail_write: Option<u64>,
+    interrupt_every: Option<u64>,
+    crash_after: Option<u64>,
+}
+
+impl Faults {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fail the N-th write, counting from 1.
+    pub fn fail_write(mut self, n: u64) -> Self {
+        self.fail_write = Some(n);
+        self
+    }
+
+    /// Interrupt every K-th write with [`ErrorKind::Interrupted`].
+    pub fn interrupt_every(mut self, k: u64) -> Self {
+        self.interrupt_every = Some(k.max(1));
+        self
+    }
+
+    /// Crash once this many bytes have been written.
+    pub fn crash_after(mut self, bytes: u64) -> Self {
+        self.crash_after = Some(bytes);
+        self
+    }
+}
+
+/// What a [`FaultyWriter`] went through, shared with the test through [`FaultyWriter::log`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FaultLog {
+    /// Write attempts, including the failed ones.
+    pub writes: u64,
+    /// Bytes that reached the wrapped writer.
+    pub written: u64,
+    pub failed: u64,
+    pub interrupted: u64,
+    pub crashed: bool,
+}
+
+pub struct FaultyWriter<W> {
+    inner: W,
+    faults: Faults,
+    log: Arc<Mutex<FaultLog>>,
+}
+
+impl<W: Write> FaultyWriter<W> {
+    pub fn new(inner: W, faults: Faults) -> Self {
+        Self {
+            inner,
+            faults,
+            log: Arc::default(),
+        }
+    }
+
+    /// A handle on the log, which stays readable after the writer moved into a journal.
+    pub fn log(&self) -> Arc<Mutex<FaultLog>> {
+        self.log.clone()
+    }
+}
+
+fn crashed() -> Error {
+    Error::new(ErrorKind::BrokenPipe, "injected crash")
+}
+
+impl<W: Write> Write for FaultyWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut log = self.log.lock().unwrap();
+        if log.crashed {
+            return Err(crashed());
+        }
+        log.writes += 1;
+        let writes = log.writes;
+        if self.faults.fail_write == Some(writes) {
+            log.failed += 1;
+            return Err(Error::other(format!(
+                "injected failure of write {}",
+                writes
+            )));
+        }
+        if let Some(k) = self.faults.interrupt_every {
+            if writes.is_multiple_of(k) {
+                log.interrupted += 1;
+                return Err(Error::new(ErrorKind::Interrupted, "injected interruption"));
+            }
+        }
+        let mut len = buf.len();
+        if let Some(limit) = self.faults.crash_after {
+            let left = usize::try_from(limit.saturating_sub(log.written)).unwrap_or(usize::MAX);
+            if left < len {
+                log.crashed = true;
+                if left == 0 {
+                    return Err(crashed());
+                }
+                len = left;
+            }
+        }
+        let written = self.inner.write(&buf[..len])?;
+        log.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if self.log.lock().unwrap().crashed {
+            return Err(crashed());
+        }
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FaultLog, Faults, FaultyWriter};
+    use std::io::{ErrorKind, Write};
+
+    #[test]
+    fn faults() {
+        let mut writer = FaultyWriter::new(vec![], Faults::new().interrupt_every(2));
+        // `write_all` retries interrupted writes
+        writer.write_all(b"ab").unwrap();
+        writer.write_all(b"cd").unwrap();
+        assert_eq!(writer.inner, b"abcd");
+        assert_eq!(writer.log().lock().unwrap().interrupted, 1);
+
+        let mut writer = FaultyWriter::new(vec![], Faults::new().fail_write(2));
+        writer.write_all(b"ab").unwrap();
+        assert_eq!(writer.write(b"cd").unwrap_err().kind(), ErrorKind::Other);
+        writer.write_all(b"ef").unwrap();
+        assert_eq!(writer.inner, b"abef");
+
+        let mut writer = FaultyWriter::new(vec![], Faults::new().crash_after(3));
+        writer.write_all(b"ab").unwrap();
+        assert!(writer.write_all(b"cd").is_err());
+        assert!(writer.flush().is_err());
+        assert!(writer.write_all(b"ef").is_err());
+        assert_eq!(writer.inner, b"abc");
+        let log = writer.log().lock().unwrap().clone();
+        assert_eq!(
+            log,
+            FaultLog {
+                writes: 2,
+                written: 3,
+                failed: 0,
+                interrupted: 0,
+                crashed: true,
+            }
+        );
+    }
+}
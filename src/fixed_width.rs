@@ -0,0 +1,214 @@
+//! Fixed-width records, as legacy bank systems export them: each field of a transaction at the
+//! same byte offsets on every line. The [`FixedWidthLayout`] says where, and is read from the
+//! `[fixed_width]` section of the configuration:
+//!
+//! ```toml
+//! [fixed_width]
+//! type = { start = 0, len = 3 }
+//! client = { start = 3, len = 5 }
+//! tx = { start = 8, len = 10 }
+//! amount = { start = 18, len = 12 }
+//! implied_decimals = 2
+//! skip_lines = 1
+//!
+//! [fixed_width.types]
+//! DEP = "deposit"
+//! WDR = "withdrawal"
+//! ```
+//!
+//! Fields are trimmed of spaces, and an empty amount is no amount. Lines shorter than the layout
+//! leave the fields past their end empty. Blank lines are skipped, and like a malformed CSV row, a
+//! malformed record ends the input with [`Error::TransactionParseError`].
+
+use crate::currency::Currency;
+use crate::transaction::{ClientId, TimedRow, TransactionId, TransactionRow, TransactionType};
+use crate::{Error, Transakt};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+use std::str::FromStr;
+
+/// Byte range of a field in a record.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Field {
+    /// Offset from the start of the line, from 0.
+    pub start: usize,
+    pub len: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FixedWidthLayout {
+    #[serde(rename = "type")]
+    pub tx_type: Field,
+    pub client: Field,
+    pub tx: Field,
+    /// Records without amounts if not set.
+    pub amount: Option<Field>,
+    /// The codes of the file for transaction types. The type field holds type names if empty.
+    #[serde(default)]
+    pub types: HashMap<String, TransactionType>,
+    /// Digits after the decimal point of amounts written without one, as in `000000012345`
+    /// for 123.45 with 2.
+    #[serde(default)]
+    pub implied_decimals: u32,
+    /// Header lines before the first record.
+    #[serde(default)]
+    pub skip_lines: usize,
+}
+
+impl FixedWidthLayout {
+    fn field<'a>(&self, line: &'a [u8], field: Field) -> Result<&'a str, Error> {
+        let start = field.start.min(line.len());
+        let end = field.start.saturating_add(field.len).min(line.len());
+        std::str::from_utf8(&line[start..end])
+            .map(str::trim)
+            .map_err(|_| Error::TransactionParseError)
+    }
+
+    fn parse(&self, line: &[u8]) -> Result<TimedRow, Error> {
+        let kind = self.field(line, self.tx_type)?;
+        let tx_type = if self.types.is_empty() {
+            TransactionType::from_str(&kind.to_lowercase())?
+        } else {
+            *self.types.get(kind).ok_or(Error::TransactionParseError)?
+        };
+        let client = self.field(line, self.client)?;
+        let tx = self.field(line, self.tx)?;
+        let amount = match self.amount {
+            Some(field) => self.amount(self.field(line, field)?)?,
+            None => None,
+        };
+        TransactionRow::new(
+            tx_type,
+            ClientId::new(client.parse().map_err(|_| Error::TransactionParseError)?),
+            TransactionId::new(tx.parse().map_err(|_| Error::TransactionParseError)?),
+            amount,
+        )
+        .into_timed()
+    }
+
+    fn amount(&self, text: &str) -> Result<Option<Currency>, Error> {
+        if text.is_empty() {
+            return Ok(None);
+        }
+        let implied = self.implied_decimals as usize;
+        let text = if implied > 0 && !text.contains('.') {
+            let (sign, digits) = match text.strip_prefix('-') {
+                Some(digits) => ("-", digits),
+                None => ("", text),
+            };
+            let digits = format!("{:0>width$}", digits, width = implied + 1);
+            let (units, decimals) = digits.split_at(digits.len() - implied);
+            format!("{}{}.{}", sign, units, decimals)
+        } else {
+            text.to_string()
+        };
+        Currency::from_str(&text)
+            .map(Some)
+            .map_err(|_| Error::TransactionParseError)
+    }
+}
+
+/// Reads transactions from fixed-width records, in order, with their line numbers.
+pub fn read_timed_fixed_width<'a, R: Read + 'a>(
+    reader: R,
+    layout: &'a FixedWidthLayout,
+) -> impl Iterator<Item = (u64, Result<TimedRow, Error>)> + 'a {
+    (1..)
+        .zip(BufReader::new(reader).split(b'\n'))
+        .skip(layout.skip_lines)
+        .map(|(number, line)| {
+            let line = line.map(|mut line| {
+                if line.last() == Some(&b'\r') {
+                    line.pop();
+                }
+                line
+            });
+            (number, line)
+        })
+        .filter(|(_, line)| {
+            !matches!(line, Ok(line) if line.iter().all(|byte| byte.is_ascii_whitespace()))
+        })
+        .map(move |(number, line)| {
+            let row = line
+                .map_err(Error::Io)
+                .and_then(|line| layout.parse(&line));
+            (number, row)
+        })
+}
+
+impl Transakt {
+    /// Like [`Transakt::load_csv_from`], for fixed-width records laid out as `layout` says.
+    pub fn load_fixed_width_from<R: Read>(
+        &mut self,
+        source: &str,
+        reader: R,
+        layout: &FixedWidthLayout,
+    ) -> Result<(), Error> {
+        self.load_from(source, |transakt| {
+            let read = transakt.read_stats();
+            let records = read_timed_fixed_width(
+                crate::profile::TimedReader {
+                    inner: reader,
+                    stats: read.clone(),
+                },
+                layout,
+            );
+            let rows = transakt.in_row_range(records);
+            transakt.execute_rows(rows, read)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::currency::Currency;
+    use crate::transaction::ClientId;
+    use crate::{Error, Transakt};
+    use std::str::FromStr;
+
+    #[test]
+    fn load_fixed_width() {
+        let config = Config::parse(
+            "[fixed_width]\n\
+             type = { start = 0, len = 3 }\n\
+             client = { start = 3, len = 5 }\n\
+             tx = { start = 8, len = 6 }\n\
+             amount = { start = 15, len = 10 }\n\
+             implied_decimals = 2\n\
+             skip_lines = 1\n\
+             [fixed_width.types]\n\
+             DEP = \"deposit\"\n\
+             WDR = \"withdrawal\"\n\
+             DSP = \"dispute\"\n",
+        )
+        .unwrap();
+        let layout = config.fixed_width.unwrap();
+        let input = "HEADER 2024-01-31\n\
+                     DEP    1     1 0000001050\r\n\
+                     \n\
+                     WDR    1     2      1.25\n\
+                     DEP    2     3 5\n\
+                     DSP    1     1\n";
+        let mut transakt = Transakt::default();
+        transakt
+            .load_fixed_width_from("input", input.as_bytes(), &layout)
+            .unwrap();
+        let accounts = transakt.get_accounts_map();
+        let account = &accounts[&ClientId::new(1)];
+        assert_eq!(account.held(), &Currency::from_str("10.5").unwrap());
+        assert_eq!(
+            accounts[&ClientId::new(2)].available(),
+            &Currency::from_str("0.05").unwrap()
+        );
+
+        let unknown = "\nXXX    1     1 0000001050\n";
+        assert!(matches!(
+            Transakt::default().load_fixed_width_from("input", unknown.as_bytes(), &layout),
+            Err(Error::TransactionParseError)
+        ));
+    }
+}
@@ -0,0 +1,72 @@
+//! Fuzzing the engine, built with the `arbitrary` feature. [`Transaction`], [`Currency`] and the
+//! ids implement [`arbitrary::Arbitrary`], biased towards few clients and ids so that random
+//! sequences dispute, resolve and charge back each other's deposits. [`run`] feeds a sequence
+//! through an engine with [verification](crate::verify) on and panics at the first broken
+//! invariant, which is what the targets in `fuzz/` do:
+//!
+//! ```text
+//! cargo +nightly fuzz run engine
+//! ```
+//!
+//! To fuzz a policy of your own, write a target that passes a builder with it to [`run`].
+//!
+//! [`Currency`]: crate::currency::Currency
+
+use crate::transaction::Transaction;
+use crate::{Transakt, TransaktBuilder};
+
+/// Executes the transactions with verification on, panicking at the first fatal error, like an
+/// [`Error::InvariantViolation`](crate::Error::InvariantViolation). Rejections are expected.
+///
+/// Note that the default [`DisputeFundsAction::AllowNegative`] holds more than accounts have,
+/// which verification reports, so targets pick another action.
+///
+/// [`DisputeFundsAction::AllowNegative`]: crate::policy::DisputeFundsAction::AllowNegative
+pub fn run(builder: TransaktBuilder, transactions: &[Transaction]) -> Transakt {
+    let mut transakt = builder.verify().build();
+    for (i, transaction) in transactions.iter().enumerate() {
+        if let Err(err) = transakt.execute_transaction(*transaction) {
+            assert!(
+                !err.is_fatal(),
+                "transaction {}, {:?}: {:?}",
+                i,
+                transaction,
+                err
+            );
+        }
+    }
+    transakt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::run;
+    use crate::policy::DisputeFundsAction;
+    use crate::transaction::Transaction;
+    use crate::Transakt;
+    use arbitrary::{Arbitrary, Unstructured};
+
+    #[test]
+    fn random_sequences() {
+        // A fixed xorshift stream stands in for the fuzzer's input
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let bytes: Vec<u8> = (0..1 << 16)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        for chunk in bytes.chunks(512) {
+            let mut u = Unstructured::new(chunk);
+            let mut transactions = vec![];
+            while !u.is_empty() {
+                transactions.push(Transaction::arbitrary(&mut u).unwrap());
+            }
+            let builder =
+                Transakt::builder().disputes_exceeding_funds(DisputeFundsAction::HoldAvailable);
+            run(builder, &transactions);
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! Approval tests against golden files. Every `*.csv` input in a directory is processed and its
+//! account report, ordered by client, is compared with the committed `*.golden` file of the same
+//! name. When a change of behavior is intended, the golden files are rewritten with `update` and
+//! the new outputs reviewed in the diff of the commit.
+//!
+//! ```text
+//! tests/golden/disputes.csv      input
+//! tests/golden/disputes.golden   expected account report
+//! ```
+//!
+//! `transakt golden DIR [--update]` runs a directory with the engine of the configuration.
+
+use crate::{Error, Transakt, TransaktBuilder};
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+const INPUT_EXTENSION: &str = "csv";
+const GOLDEN_EXTENSION: &str = "golden";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GoldenStatus {
+    Matches,
+    /// Lines only in the golden file, prefixed with `-`, and only in the output, with `+`.
+    Differs(Vec<String>),
+    /// There is no golden file yet.
+    Missing,
+    /// The golden file was written or rewritten with the output.
+    Updated,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GoldenResult {
+    pub input: PathBuf,
+    pub golden: PathBuf,
+    pub status: GoldenStatus,
+}
+
+impl GoldenResult {
+    pub fn is_ok(&self) -> bool {
+        matches!(self.status, GoldenStatus::Matches | GoldenStatus::Updated)
+    }
+}
+
+impl Display for GoldenResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = self.input.display();
+        match &self.status {
+            GoldenStatus::Matches => write!(f, "ok {}", name),
+            GoldenStatus::Updated => write!(f, "updated {}", self.golden.display()),
+            GoldenStatus::Missing => write!(f, "missing {}", self.golden.display()),
+            GoldenStatus::Differs(lines) => {
+                write!(f, "differs {}", name)?;
+                for line in lines {
+                    write!(f, "\n    {}", line)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The output compared with golden files: the account report, ordered by client.
+pub fn golden_output(transakt: &Transakt) -> Result<String, Error> {
+    let mut out = vec![];
+    transakt.write_accounts_csv(&mut out)?;
+    String::from_utf8(out).map_err(|err| Error::Io(std::io::Error::other(err)))
+}
+
+/// Lines only in `expected`, then lines only in `actual`, each in their order. Reports are
+/// ordered by client, so this shows the accounts that changed.
+fn diff_lines(expected: &str, actual: &str) -> Vec<String> {
+    let removed = expected
+        .lines()
+        .filter(|line| !actual.lines().any(|other| other == *line))
+        .map(|line| format!("-{}", line));
+    let added = actual
+        .lines()
+        .filter(|line| !expected.lines().any(|other| other == *line))
+        .map(|line| format!("+{}", line));
+    let lines: Vec<String> = removed.chain(added).collect();
+    if lines.is_empty() {
+        // Same lines, in another order or with other line endings
+        vec![
+            "-(golden file)".to_string(),
+            "+(same lines, laid out differently)".to_string(),
+        ]
+    } else {
+        lines
+    }
+}
+
+/// Processes every input of the directory on an engine from `builder`, comparing the outputs with
+/// the golden files, or writing them if `update` is set. Results are ordered by input name.
+pub fn check_golden<F>(dir: &Path, builder: F, update: bool) -> Result<Vec<GoldenResult>, Error>
+where
+    F: Fn() -> TransaktBuilder,
+{
+    let mut inputs = vec![];
+    for entry in std::fs::read_dir(dir).map_err(Error::Io)? {
+        let path = entry.map_err(Error::Io)?.path();
+        if path.extension().is_some_and(|ext| ext == INPUT_EXTENSION) {
+            inputs.push(path);
+        }
+    }
+    inputs.sort();
+    let mut results = vec![];
+    for input in inputs {
+        let mut transakt = builder().build();
+        transakt.load_csv(&input)?;
+        let output = golden_output(&transakt)?;
+        let golden = input.with_extension(GOLDEN_EXTENSION);
+        let expected = match std::fs::read_to_string(&golden) {
+            Ok(expected) => Some(expected),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+            Err(err) => return Err(Error::Io(err)),
+        };
+        let status = match expected {
+            Some(expected) if expected == output => GoldenStatus::Matches,
+            _ if update => {
+                std::fs::write(&golden, &output).map_err(Error::Io)?;
+                GoldenStatus::Updated
+            }
+            Some(expected) => GoldenStatus::Differs(diff_lines(&expected, &output)),
+            None => GoldenStatus::Missing,
+        };
+        results.push(GoldenResult {
+            input,
+            golden,
+            status,
+        });
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_golden, GoldenStatus};
+    use crate::Transakt;
+
+    #[test]
+    fn golden_files() {
+        let dir = std::env::temp_dir().join(format!("transakt-golden-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = "type,client,tx,amount\n\
+                     deposit,2,1,5.0\n\
+                     deposit,1,2,2.0\n\
+                     withdrawal,1,3,0.5\n";
+        std::fs::write(dir.join("a.csv"), input).unwrap();
+        std::fs::write(dir.join("notes.txt"), "not an input").unwrap();
+
+        let results = check_golden(&dir, Transakt::builder, false).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].status, GoldenStatus::Missing);
+        assert!(!results[0].is_ok());
+
+        let results = check_golden(&dir, Transakt::builder, true).unwrap();
+        assert_eq!(results[0].status, GoldenStatus::Updated);
+        let golden = std::fs::read_to_string(dir.join("a.golden")).unwrap();
+        assert_eq!(
+            golden,
+            "client,available,held,total,locked\n\
+             1,1.5000,0.0000,1.5000,false\n\
+             2,5.0000,0.0000,5.0000,false\n"
+        );
+        let results = check_golden(&dir, Transakt::builder, false).unwrap();
+        assert_eq!(results[0].status, GoldenStatus::Matches);
+
+        std::fs::write(dir.join("a.csv"), format!("{}dispute,2,1,\n", input)).unwrap();
+        let results = check_golden(&dir, Transakt::builder, false).unwrap();
+        assert_eq!(
+            results[0].status,
+            GoldenStatus::Differs(vec![
+                "-2,5.0000,0.0000,5.0000,false".to_string(),
+                "+2,0.0000,5.0000,5.0000,false".to_string(),
+            ])
+        );
+        assert!(results[0]
+            .to_string()
+            .ends_with("\n    -2,5.0000,0.0000,5.0000,false\n    +2,0.0000,5.0000,5.0000,false"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
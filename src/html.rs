@@ -0,0 +1,120 @@
+//! The account report as a single HTML page, with the [summary](crate::summary) of the run above
+//! the balances, for emailing to stakeholders. The page has its styles inline and nothing to load,
+//! so it displays the same as an attachment.
+
+use crate::currency::Currency;
+use crate::{Error, Transakt};
+use std::io::Write;
+
+const STYLE: &str = "body{font-family:sans-serif;margin:2em;color:#222}\
+table{border-collapse:collapse;margin-bottom:2em}\
+th,td{border:1px solid #ccc;padding:.3em .8em}\
+th{background:#f0f0f0;text-align:left}\
+td.amount{text-align:right;font-family:monospace}\
+tr.locked{background:#fde8e8}";
+
+impl Transakt {
+    /// Writes the account report and the run summary as an HTML page, accounts ordered by client.
+    /// Locked accounts are highlighted.
+    pub fn write_accounts_html<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        self.write_page(&mut writer).map_err(Error::Io)?;
+        writer.flush().map_err(Error::Io)
+    }
+
+    fn write_page<W: Write>(&self, out: &mut W) -> std::io::Result<()> {
+        let amount = |amount: Option<Currency>| {
+            amount.map_or("overflow".to_string(), |amount| amount.to_string())
+        };
+        let summary = self.summary();
+        writeln!(out, "<!DOCTYPE html>")?;
+        writeln!(out, "<html><head><meta charset=\"utf-8\">")?;
+        writeln!(out, "<title>Account balances</title>")?;
+        writeln!(out, "<style>{}</style>", STYLE)?;
+        writeln!(out, "</head><body>")?;
+        writeln!(out, "<h1>Account balances</h1>")?;
+        writeln!(out, "<h2>Summary</h2>")?;
+        writeln!(out, "<table>")?;
+        let mut row = |name: &str, value: String| {
+            writeln!(
+                out,
+                "<tr><th>{}</th><td class=\"amount\">{}</td></tr>",
+                escape(name),
+                escape(&value)
+            )
+        };
+        row("Transactions processed", summary.processed.to_string())?;
+        row("Applied", summary.accepted.to_string())?;
+        row("Rejected", summary.rejected_total().to_string())?;
+        for (code, count) in summary.rejected.iter() {
+            row(&format!("Rejected with {}", code), count.to_string())?;
+        }
+        row("Accounts", summary.accounts.to_string())?;
+        row("Available", amount(summary.available))?;
+        row("Held", amount(summary.held))?;
+        writeln!(out, "</table>")?;
+
+        let accounts = self.get_accounts();
+        writeln!(out, "<h2>Accounts</h2>")?;
+        writeln!(out, "<table>")?;
+        writeln!(
+            out,
+            "<tr><th>Client</th><th>Available</th><th>Held</th><th>Total</th><th>Locked</th></tr>"
+        )?;
+        for account in accounts.iter() {
+            let locked = account.is_locked();
+            writeln!(
+                out,
+                "<tr{}><td>{}</td><td class=\"amount\">{}</td><td class=\"amount\">{}</td>\
+                 <td class=\"amount\">{}</td><td>{}</td></tr>",
+                if locked { " class=\"locked\"" } else { "" },
+                account.client(),
+                account.available(),
+                account.held(),
+                amount(account.total()),
+                if locked { "yes" } else { "no" }
+            )?;
+        }
+        writeln!(out, "</table>")?;
+        writeln!(out, "</body></html>")
+    }
+}
+
+/// Text as HTML, with the characters that have a meaning in markup escaped.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Transakt;
+
+    #[test]
+    fn html_report() {
+        let input = "type,client,tx,amount\n\
+                     deposit,2,1,2.0\n\
+                     deposit,1,2,1.0\n\
+                     dispute,1,2,\n\
+                     chargeback,1,2,\n\
+                     withdrawal,2,3,5.0\n";
+        let transakt = Transakt::read_from_reader(input.as_bytes()).unwrap();
+        let mut out = vec![];
+        transakt.write_accounts_html(&mut out).unwrap();
+        let html = String::from_utf8(out).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<tr><th>Rejected with E_FUNDS</th><td class=\"amount\">1</td></tr>"));
+        let client_1 = html.find("<tr class=\"locked\"><td>1</td>").unwrap();
+        let client_2 = html.find("<tr><td>2</td>").unwrap();
+        assert!(client_1 < client_2);
+        assert!(html.trim_end().ends_with("</html>"));
+    }
+}
@@ -0,0 +1,421 @@
+//! Streaming ingest, where reading the input and executing transactions happen on separate
+//! threads connected by a bounded queue. Rows are read like those of any other CSV input, with
+//! the CSV options, row range and rejection tracking of the engine.
+//!
+//! [`Transakt::serve`](crate::Transakt::serve) ingests the same way. Both are configured in the
+//! `[ingest]` section:
+//!
+//! ```toml
+//! [ingest]
+//! queue_capacity = 1024
+//! # `block`, `shed` or `spill` rows that don't fit in the queue
+//! backpressure = "spill"
+//! spill_file = "/var/lib/transakt/spill.jsonl"
+//! max_transactions_per_second = 5000
+//! ```
+
+use crate::transaction::TimedRow;
+use crate::{Error, Transakt};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A row of the input with its line number, as read, and parsed.
+pub(crate) type Row = (u64, Option<String>, Result<TimedRow, Error>);
+
+/// What the reader does when the queue towards the engine is full.
+#[derive(Debug, Clone)]
+pub enum Backpressure {
+    /// Wait for the engine to catch up. Nothing is lost, the input is just read more slowly.
+    Block,
+    /// Drop rows that don't fit in the queue.
+    Shed,
+    /// Once the queue fills up, write the rest of the input to this file, which is executed after
+    /// the queue drains. Rows are still executed in input order.
+    Spill(PathBuf),
+}
+
+#[derive(Debug, Clone)]
+pub struct IngestOptions {
+    pub queue_capacity: usize,
+    pub backpressure: Backpressure,
+    /// Upper bound on how many transactions are executed per second.
+    pub max_transactions_per_second: Option<u32>,
+}
+
+impl Default for IngestOptions {
+    fn default() -> Self {
+        Self {
+            queue_capacity: 1024,
+            backpressure: Backpressure::Block,
+            max_transactions_per_second: None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackpressureMode {
+    #[default]
+    Block,
+    Shed,
+    Spill,
+}
+
+/// The `[ingest]` section of the configuration.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IngestConfig {
+    pub queue_capacity: Option<usize>,
+    pub backpressure: BackpressureMode,
+    /// Where rows are spilled, required with `backpressure = "spill"`.
+    pub spill_file: Option<PathBuf>,
+    pub max_transactions_per_second: Option<u32>,
+}
+
+impl IngestConfig {
+    pub fn options(&self) -> Result<IngestOptions, Error> {
+        let backpressure = match (self.backpressure, &self.spill_file) {
+            (BackpressureMode::Block, _) => Backpressure::Block,
+            (BackpressureMode::Shed, _) => Backpressure::Shed,
+            (BackpressureMode::Spill, Some(path)) => Backpressure::Spill(path.clone()),
+            (BackpressureMode::Spill, None) => {
+                return Err(Error::InvalidConfig(
+                    "ingest: `backpressure = \"spill\"` needs a `spill_file`".to_string(),
+                ))
+            }
+        };
+        let defaults = IngestOptions::default();
+        Ok(IngestOptions {
+            queue_capacity: self.queue_capacity.unwrap_or(defaults.queue_capacity),
+            backpressure,
+            max_transactions_per_second: self.max_transactions_per_second,
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct IngestStats {
+    /// Rows handed to the engine, whether or not the transaction was accepted.
+    pub executed: u64,
+    pub shed: u64,
+    pub spilled: u64,
+}
+
+/// A row written to the spill file. Rows that could not be parsed have no transaction.
+#[derive(Serialize, Deserialize)]
+struct SpilledRow {
+    line: u64,
+    raw: Option<String>,
+    row: Option<TimedRow>,
+}
+
+pub(crate) struct Throttle {
+    per_second: u32,
+    start: Instant,
+    count: u64,
+}
+
+impl Throttle {
+    fn new(per_second: u32) -> Self {
+        Self {
+            per_second,
+            start: Instant::now(),
+            count: 0,
+        }
+    }
+
+    fn wait(&mut self) {
+        let due = self.start
+            + Duration::from_secs_f64(self.count as f64 / f64::from(self.per_second.max(1)));
+        let now = Instant::now();
+        if due > now {
+            thread::sleep(due - now);
+        }
+        self.count += 1;
+    }
+}
+
+/// Called between rows every `every`, whether rows came in or not.
+pub(crate) struct Tick<'a> {
+    pub(crate) every: Duration,
+    pub(crate) run: &'a mut dyn FnMut(&mut Transakt) -> Result<(), Error>,
+}
+
+/// Where the engine side of an ingest stands.
+pub(crate) struct Ingest {
+    throttle: Option<Throttle>,
+    stats: IngestStats,
+}
+
+impl Ingest {
+    pub(crate) fn new(options: &IngestOptions) -> Self {
+        Self {
+            throttle: options.max_transactions_per_second.map(Throttle::new),
+            stats: IngestStats::default(),
+        }
+    }
+}
+
+impl Transakt {
+    /// Executes the transactions in a CSV stream, reading ahead on a separate thread.
+    pub fn ingest_csv<R: Read + Send>(
+        &mut self,
+        reader: R,
+        options: &IngestOptions,
+    ) -> Result<IngestStats, Error> {
+        let rows = self.csv_rows(reader);
+        let (sender, receiver) = mpsc::sync_channel(options.queue_capacity);
+        let mut ingest = Ingest::new(options);
+        let produced = thread::scope(|scope| {
+            let producer = scope.spawn(|| produce(rows, sender, &options.backpressure));
+            self.consume(receiver, &mut ingest, None)?;
+            producer.join().expect("ingest reader panicked")
+        })?;
+        self.finish_ingest(ingest, produced, options)
+    }
+
+    /// Executes the rows of the queue until the reader is done, running `tick` on time. The queue
+    /// is dropped on an error, which stops the reader.
+    pub(crate) fn consume(
+        &mut self,
+        receiver: Receiver<Row>,
+        ingest: &mut Ingest,
+        mut tick: Option<Tick<'_>>,
+    ) -> Result<(), Error> {
+        let mut due = tick.as_ref().map(|tick| Instant::now() + tick.every);
+        loop {
+            let row = match due {
+                Some(due) => {
+                    let timeout = due.saturating_duration_since(Instant::now());
+                    match receiver.recv_timeout(timeout) {
+                        Ok(row) => Some(row),
+                        Err(RecvTimeoutError::Timeout) => None,
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+                None => match receiver.recv() {
+                    Ok(row) => Some(row),
+                    Err(_) => break,
+                },
+            };
+            if let Some((line, raw, row)) = row {
+                self.execute_throttled(line, raw, row, ingest)?;
+            }
+            if let (Some(at), Some(tick)) = (due, tick.as_mut()) {
+                if Instant::now() >= at {
+                    (tick.run)(self)?;
+                    due = Some(Instant::now() + tick.every);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Executes what was spilled, once the queue drained, and returns the stats.
+    pub(crate) fn finish_ingest(
+        &mut self,
+        mut ingest: Ingest,
+        (shed, spilled): (u64, u64),
+        options: &IngestOptions,
+    ) -> Result<IngestStats, Error> {
+        ingest.stats.shed = shed;
+        ingest.stats.spilled = spilled;
+        if let (Backpressure::Spill(path), true) = (&options.backpressure, spilled > 0) {
+            self.execute_spill(path, &mut ingest)?;
+        }
+        self.flush_journal()?;
+        Ok(ingest.stats)
+    }
+
+    fn execute_spill(&mut self, path: &Path, ingest: &mut Ingest) -> Result<(), Error> {
+        let file = std::fs::File::open(path).map_err(Error::Io)?;
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(Error::Io)?;
+            let spilled: SpilledRow =
+                serde_json::from_str(&line).map_err(|err| Error::Io(err.into()))?;
+            let row = spilled.row.ok_or(Error::TransactionParseError);
+            self.execute_throttled(spilled.line, spilled.raw, row, ingest)?;
+        }
+        std::fs::remove_file(path).map_err(Error::Io)
+    }
+
+    fn execute_throttled(
+        &mut self,
+        line: u64,
+        raw: Option<String>,
+        row: Result<TimedRow, Error>,
+        ingest: &mut Ingest,
+    ) -> Result<(), Error> {
+        if let Some(throttle) = ingest.throttle.as_mut() {
+            throttle.wait();
+        }
+        ingest.stats.executed += 1;
+        self.execute_row(line, raw, row)
+    }
+}
+
+/// Reads the rows and feeds the queue. Returns how many rows were shed and spilled.
+pub(crate) fn produce<I: Iterator<Item = Row>>(
+    rows: I,
+    sender: SyncSender<Row>,
+    backpressure: &Backpressure,
+) -> Result<(u64, u64), Error> {
+    let mut spill: Option<BufWriter<std::fs::File>> = None;
+    let (mut shed, mut spilled) = (0, 0);
+    for (line, raw, row) in rows {
+        // Errors are never dropped, the engine has to see them to stop processing.
+        let malformed = row.is_err();
+        if let Some(writer) = spill.as_mut() {
+            write_spilled(writer, line, raw, row)?;
+            spilled += 1;
+            if malformed {
+                break;
+            }
+            continue;
+        }
+        if malformed {
+            let _ = sender.send((line, raw, row));
+            break;
+        }
+        let sent = match backpressure {
+            Backpressure::Block => sender
+                .send((line, raw, row))
+                .map_err(|err| TrySendError::Disconnected(err.0)),
+            _ => sender.try_send((line, raw, row)),
+        };
+        match (sent, backpressure) {
+            (Ok(()), _) => {}
+            // The engine stopped because of a fatal error
+            (Err(TrySendError::Disconnected(_)), _) | (Err(_), Backpressure::Block) => break,
+            (Err(TrySendError::Full(_)), Backpressure::Shed) => {
+                tracing::warn!(line, "queue full, row shed");
+                shed += 1;
+            }
+            (Err(TrySendError::Full((line, raw, row))), Backpressure::Spill(path)) => {
+                tracing::warn!(path = %path.display(), "queue full, spilling to disk");
+                let file = std::fs::File::create(path).map_err(Error::Io)?;
+                let writer = spill.insert(BufWriter::new(file));
+                write_spilled(writer, line, raw, row)?;
+                spilled += 1;
+            }
+        }
+    }
+    if let Some(mut writer) = spill {
+        writer.flush().map_err(Error::Io)?;
+    }
+    Ok((shed, spilled))
+}
+
+fn write_spilled<W: Write>(
+    writer: &mut W,
+    line: u64,
+    raw: Option<String>,
+    row: Result<TimedRow, Error>,
+) -> Result<(), Error> {
+    let spilled = SpilledRow {
+        line,
+        raw,
+        row: row.ok(),
+    };
+    serde_json::to_writer(&mut *writer, &spilled).map_err(|err| Error::Io(err.into()))?;
+    writer.write_all(b"\n").map_err(Error::Io)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Backpressure, IngestOptions};
+    use crate::currency::Currency;
+    use crate::transaction::{ClientId, CsvOptions};
+    use crate::Transakt;
+    use std::str::FromStr;
+
+    fn input(deposits: u32) -> String {
+        let mut csv = "type,client,tx,amount\n".to_string();
+        for tx in 1..=deposits {
+            csv.push_str(&format!("deposit,1,{},1.0\n", tx));
+        }
+        csv
+    }
+
+    #[test]
+    fn ingest_block() {
+        let mut transakt = Transakt::default();
+        let options = IngestOptions {
+            queue_capacity: 1,
+            ..IngestOptions::default()
+        };
+        let stats = transakt
+            .ingest_csv(input(100).as_bytes(), &options)
+            .unwrap();
+        assert_eq!(stats.executed, 100);
+        let account = transakt.get_accounts_map().get(&ClientId::new(1)).unwrap();
+        assert_eq!(account.available(), &Currency::from_str("100").unwrap());
+    }
+
+    #[test]
+    fn ingest_csv_options() {
+        let options = CsvOptions {
+            delimiter: ';',
+            decimal_separator: ',',
+            ..CsvOptions::default()
+        };
+        let mut transakt = Transakt::builder()
+            .csv_options(options)
+            .row_range(1, None)
+            .rejected_rows()
+            .build();
+        let input = "type;client;tx;amount\n\
+                     deposit;1;1;5,0\n\
+                     deposit;1;2;1,5\n\
+                     withdrawal;1;3;9,0\n";
+        let stats = transakt
+            .ingest_csv(input.as_bytes(), &IngestOptions::default())
+            .unwrap();
+        assert_eq!(stats.executed, 2);
+        let account = transakt.get_accounts_map().get(&ClientId::new(1)).unwrap();
+        assert_eq!(account.available(), &Currency::from_str("1.5").unwrap());
+        let rejected = transakt.rejected_rows();
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].line, 4);
+        assert_eq!(rejected[0].row.as_deref(), Some("withdrawal;1;3;9,0"));
+    }
+
+    #[test]
+    fn ingest_shed() {
+        let mut transakt = Transakt::default();
+        let options = IngestOptions {
+            queue_capacity: 1,
+            backpressure: Backpressure::Shed,
+            max_transactions_per_second: Some(1000),
+        };
+        let stats = transakt
+            .ingest_csv(input(100).as_bytes(), &options)
+            .unwrap();
+        assert!(stats.shed > 0);
+        assert_eq!(stats.executed + stats.shed, 100);
+    }
+
+    #[test]
+    fn ingest_spill() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("transakt-spill-{}.csv", std::process::id()));
+        let mut transakt = Transakt::default();
+        let options = IngestOptions {
+            queue_capacity: 1,
+            backpressure: Backpressure::Spill(path.clone()),
+            max_transactions_per_second: Some(1000),
+        };
+        let stats = transakt
+            .ingest_csv(input(100).as_bytes(), &options)
+            .unwrap();
+        assert!(stats.spilled > 0);
+        assert_eq!(stats.executed, 100);
+        assert!(!path.exists());
+        let account = transakt.get_accounts_map().get(&ClientId::new(1)).unwrap();
+        assert_eq!(account.available(), &Currency::from_str("100").unwrap());
+    }
+}
@@ -0,0 +1,285 @@
+//! ISO 20022 XML messages, for banks that send payments and statements that way:
+//!
+//! - `pain.001` customer credit transfer initiations: each `CdtTrfTxInf` is a withdrawal from the
+//!   debtor account of its `PmtInf`, with the `EndToEndId` as transaction id.
+//! - `camt.053` bank to customer statements: each booked `Ntry` of a `Stmt` is a deposit to the
+//!   statement account if it is a credit (`CRDT`), a withdrawal if a debit (`DBIT`), with the
+//!   `NtryRef` as transaction id. Pending and informational entries are skipped.
+//!
+//! Accounts are identified by their `Othr/Id`, which must be a client id; the engine has a single
+//! currency, so the `Ccy` of amounts is not looked at. Reversed entries and anything else that
+//! doesn't fit, including XML with a DTD, end the input with [`Error::TransactionParseError`].
+//! Transactions are reported at the line their element starts on.
+
+use crate::currency::Currency;
+use crate::transaction::{ClientId, TimedRow, TransactionId, TransactionRow, TransactionType};
+use crate::{Error, Transakt};
+use roxmltree::{Document, Node};
+use std::io::Read;
+use std::str::FromStr;
+
+/// An element of a parsed document.
+#[derive(Clone, Copy)]
+struct Element<'a, 'input>(Node<'a, 'input>);
+
+impl<'a, 'input> Element<'a, 'input> {
+    /// The child elements of this name, without the namespace prefix.
+    fn children(self, name: &'a str) -> impl Iterator<Item = Element<'a, 'input>> + 'a {
+        self.0
+            .children()
+            .filter(move |child| child.tag_name().name() == name)
+            .map(Element)
+    }
+
+    /// The first element down the path of names.
+    fn find(self, path: &[&str]) -> Option<Element<'a, 'input>> {
+        path.iter().try_fold(self, |element, name| {
+            element
+                .0
+                .children()
+                .find(|child| child.tag_name().name() == *name)
+                .map(Element)
+        })
+    }
+
+    /// The text of the first element down the path, trimmed.
+    fn text(self, path: &[&str]) -> Result<String, Error> {
+        let element = self.find(path).ok_or(Error::TransactionParseError)?;
+        let text: String = element
+            .0
+            .children()
+            .filter(Node::is_text)
+            .filter_map(|child| child.text())
+            .collect();
+        Ok(text.trim().to_string())
+    }
+
+    /// The line the element starts on.
+    fn line(self) -> u64 {
+        let position = self.0.document().text_pos_at(self.0.range().start);
+        u64::from(position.row)
+    }
+}
+
+/// Reads transactions from a `pain.001` or `camt.053` document, in order, with their line numbers.
+pub fn read_timed_iso20022<R: Read>(
+    mut reader: R,
+) -> impl Iterator<Item = (u64, Result<TimedRow, Error>)> {
+    let mut document = String::new();
+    let rows = match reader.read_to_string(&mut document) {
+        // Documents with a DTD are refused, rather than expanding its entities
+        Ok(_) => Document::parse(&document).map_or_else(
+            |_| vec![(1, Err(Error::TransactionParseError))],
+            |document| rows(Element(document.root_element())),
+        ),
+        Err(err) if err.kind() == std::io::ErrorKind::InvalidData => {
+            vec![(1, Err(Error::TransactionParseError))]
+        }
+        Err(err) => vec![(1, Err(Error::Io(err)))],
+    };
+    rows.into_iter()
+}
+
+/// The transactions of a document, up to the first that can't be read.
+fn rows(document: Element) -> Vec<(u64, Result<TimedRow, Error>)> {
+    let mut rows = vec![];
+    let mut push = |line: u64, row: Result<Option<TransactionRow>, Error>| {
+        let row = row.and_then(|row| row.map(TransactionRow::into_timed).transpose());
+        match row {
+            Ok(None) => true,
+            Ok(Some(row)) => {
+                rows.push((line, Ok(row)));
+                true
+            }
+            Err(err) => {
+                rows.push((line, Err(err)));
+                false
+            }
+        }
+    };
+    if let Some(initiation) = document.find(&["CstmrCdtTrfInitn"]) {
+        'payments: for payment in initiation.children("PmtInf") {
+            let debtor = client(payment, &["DbtrAcct", "Id", "Othr", "Id"]).ok();
+            for transfer in payment.children("CdtTrfTxInf") {
+                let row = debtor
+                    .ok_or(Error::TransactionParseError)
+                    .and_then(|debtor| credit_transfer(debtor, transfer));
+                if !push(transfer.line(), row.map(Some)) {
+                    break 'payments;
+                }
+            }
+        }
+    } else if let Some(statement) = document.find(&["BkToCstmrStmt"]) {
+        'statements: for statement in statement.children("Stmt") {
+            let account = client(statement, &["Acct", "Id", "Othr", "Id"]).ok();
+            for entry in statement.children("Ntry") {
+                let row = account
+                    .ok_or(Error::TransactionParseError)
+                    .and_then(|account| statement_entry(account, entry));
+                if !push(entry.line(), row) {
+                    break 'statements;
+                }
+            }
+        }
+    } else {
+        rows.push((document.line(), Err(Error::TransactionParseError)));
+    }
+    rows
+}
+
+fn client(element: Element, path: &[&str]) -> Result<ClientId, Error> {
+    let id = element.text(path)?;
+    id.parse()
+        .map(ClientId::new)
+        .map_err(|_| Error::TransactionParseError)
+}
+
+fn transaction_id(id: &str) -> Result<TransactionId, Error> {
+    id.parse()
+        .map(TransactionId::new)
+        .map_err(|_| Error::TransactionParseError)
+}
+
+fn amount(text: &str) -> Result<Currency, Error> {
+    Currency::from_str(text).map_err(|_| Error::TransactionParseError)
+}
+
+fn credit_transfer(debtor: ClientId, transfer: Element) -> Result<TransactionRow, Error> {
+    Ok(TransactionRow::new(
+        TransactionType::Withdrawal,
+        debtor,
+        transaction_id(&transfer.text(&["PmtId", "EndToEndId"])?)?,
+        Some(amount(&transfer.text(&["Amt", "InstdAmt"])?)?),
+    ))
+}
+
+/// The transaction of a statement entry, `None` if it isn't booked.
+fn statement_entry(account: ClientId, entry: Element) -> Result<Option<TransactionRow>, Error> {
+    // A code of its own since version 8, the text of the element before
+    let status = entry
+        .text(&["Sts", "Cd"])
+        .or_else(|_| entry.text(&["Sts"]))?;
+    if status != "BOOK" {
+        return Ok(None);
+    }
+    if entry
+        .text(&["RvslInd"])
+        .is_ok_and(|reversal| reversal == "true")
+    {
+        return Err(Error::TransactionParseError);
+    }
+    let tx_type = match entry.text(&["CdtDbtInd"])?.as_str() {
+        "CRDT" => TransactionType::Deposit,
+        "DBIT" => TransactionType::Withdrawal,
+        _ => return Err(Error::TransactionParseError),
+    };
+    Ok(Some(TransactionRow::new(
+        tx_type,
+        account,
+        transaction_id(&entry.text(&["NtryRef"])?)?,
+        Some(amount(&entry.text(&["Amt"])?)?),
+    )))
+}
+
+impl Transakt {
+    /// Like [`Transakt::load_csv_from`], for an ISO 20022 `pain.001` or `camt.053` document.
+    pub fn load_iso20022_from<R: Read>(&mut self, source: &str, reader: R) -> Result<(), Error> {
+        self.load_from(source, |transakt| {
+            let read = transakt.read_stats();
+            let rows = transakt.in_row_range(read_timed_iso20022(crate::profile::TimedReader {
+                inner: reader,
+                stats: read.clone(),
+            }));
+            transakt.execute_rows(rows, read)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Element;
+    use crate::currency::Currency;
+    use crate::transaction::ClientId;
+    use crate::{Error, Transakt};
+    use std::str::FromStr;
+
+    const STATEMENT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.08">
+  <BkToCstmrStmt>
+    <!-- Header left out -->
+    <Stmt>
+      <Acct><Id><Othr><Id>7</Id></Othr></Id></Acct>
+      <Ntry>
+        <NtryRef>1</NtryRef>
+        <Amt Ccy="EUR">100.50</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+        <Sts><Cd>BOOK</Cd></Sts>
+      </Ntry>
+      <Ntry>
+        <NtryRef>2</NtryRef>
+        <Amt Ccy="EUR">1000</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <Sts><Cd>PDNG</Cd></Sts>
+      </Ntry>
+      <Ntry>
+        <NtryRef>3</NtryRef>
+        <Amt Ccy="EUR">200</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <Sts>BOOK</Sts>
+      </Ntry>
+    </Stmt>
+  </BkToCstmrStmt>
+</Document>
+"#;
+
+    const INITIATION: &str = r#"<Document xmlns:p="urn:iso:std:iso:20022:tech:xsd:pain.001.001.09">
+<p:CstmrCdtTrfInitn>
+  <p:PmtInf>
+    <p:DbtrAcct><p:Id><p:Othr><p:Id>7</p:Id></p:Othr></p:Id></p:DbtrAcct>
+    <p:CdtTrfTxInf>
+      <p:PmtId><p:EndToEndId>10</p:EndToEndId></p:PmtId>
+      <p:Amt><p:InstdAmt Ccy="EUR">0.50</p:InstdAmt></p:Amt>
+      <p:RmtInf><p:Ustrd>Invoice &lt;42&gt; &amp; co<![CDATA[ <raw> ]]></p:Ustrd></p:RmtInf>
+    </p:CdtTrfTxInf>
+  </p:PmtInf>
+</p:CstmrCdtTrfInitn>
+</Document>"#;
+
+    #[test]
+    fn load_iso20022() {
+        let mut transakt = Transakt::default();
+        transakt
+            .load_iso20022_from("statement", STATEMENT.as_bytes())
+            .unwrap();
+        transakt
+            .load_iso20022_from("initiation", INITIATION.as_bytes())
+            .unwrap();
+        let account = &transakt.get_accounts_map()[&ClientId::new(7)];
+        assert_eq!(account.available(), &Currency::from_str("100").unwrap());
+        // The booked debit exceeds what was there, reported at the line of its entry
+        let rejected: Vec<u64> = transakt
+            .rejections()
+            .groups()
+            .flat_map(|(_, group)| group.lines.clone())
+            .collect();
+        assert_eq!(rejected, [19]);
+
+        let document = roxmltree::Document::parse(INITIATION).unwrap();
+        let remittance = Element(document.root_element()).text(&[
+            "CstmrCdtTrfInitn",
+            "PmtInf",
+            "CdtTrfTxInf",
+            "RmtInf",
+            "Ustrd",
+        ]);
+        assert_eq!(remittance.unwrap(), "Invoice <42> & co <raw>");
+
+        let entity = "<!DOCTYPE d [<!ENTITY e \"e\">]><Document>&e;</Document>";
+        for malformed in [entity, "<Document><Stmt></Document>", "<Other/>"] {
+            assert!(matches!(
+                Transakt::default().load_iso20022_from("input", malformed.as_bytes()),
+                Err(Error::TransactionParseError)
+            ));
+        }
+    }
+}
@@ -0,0 +1,273 @@
+//! ISO 8583 (1987) financial messages, for replaying card-network feeds. Messages are framed by
+//! a 2-byte big-endian length, with a binary bitmap and ASCII data elements. Only the subset a
+//! ledger needs is read:
+//!
+//! - `0200` financial requests are deposits if the transaction type of the processing code
+//!   (field 3) is `21`, and withdrawals if it is `00` (purchase) or `01` (cash).
+//! - `0420` reversal advices are chargebacks of the transaction in the original data elements
+//!   (field 90). Like any chargeback, they only go through if that deposit is disputed.
+//!
+//! The client is the account identification 1 (field 102), the transaction id the system trace
+//! audit number (field 11), and the amount (field 4) is in minor units with two decimals. Any
+//! other message, or a field this module doesn't know the length of, ends the input with
+//! [`Error::TransactionParseError`]. Messages are numbered from 1, which is what rejections
+//! report as their line.
+
+use crate::currency::Currency;
+use crate::transaction::{ClientId, TimedRow, TransactionId, TransactionRow, TransactionType};
+use crate::{Error, Transakt};
+use std::io::{BufReader, ErrorKind, Read};
+
+/// How the length of a data element is given.
+#[derive(Debug, Clone, Copy)]
+enum Length {
+    Fixed(usize),
+    /// Prefixed with its length in 2 digits.
+    LlVar,
+    /// Prefixed with its length in 3 digits.
+    LllVar,
+}
+
+/// The length of each data element, from 2 to 128.
+fn length(field: usize) -> Length {
+    use Length::*;
+    match field {
+        2 | 32..=35 | 44 | 45 | 99..=103 => LlVar,
+        36 | 46..=48 | 54..=63 | 104..=127 => LllVar,
+        3 | 11 | 12 | 38 | 73 => Fixed(6),
+        4..=6 | 37 | 82..=85 => Fixed(12),
+        7 | 74..=81 => Fixed(10),
+        8..=10 | 41 | 52 | 64 | 96 | 128 => Fixed(8),
+        13..=18 | 71 | 72 => Fixed(4),
+        19..=24 | 40 | 49..=51 | 68..=70 => Fixed(3),
+        25 | 26 | 39 | 67 | 92 => Fixed(2),
+        27 | 65 | 66 | 91 => Fixed(1),
+        28..=31 => Fixed(9),
+        42 => Fixed(15),
+        43 => Fixed(40),
+        53 | 86..=89 => Fixed(16),
+        90 | 95 => Fixed(42),
+        93 => Fixed(5),
+        94 => Fixed(7),
+        97 => Fixed(17),
+        98 => Fixed(25),
+        _ => unreachable!("data elements go from 2 to 128"),
+    }
+}
+
+/// Reads transactions from ISO 8583 messages, in order, with their numbers.
+pub fn read_timed_iso8583<R: Read>(
+    reader: R,
+) -> impl Iterator<Item = (u64, Result<TimedRow, Error>)> {
+    let mut reader = BufReader::new(reader);
+    let mut done = false;
+    let messages = std::iter::from_fn(move || {
+        if done {
+            return None;
+        }
+        let row = next_message(&mut reader)
+            .and_then(|message| message.map(|message| parse(&message)).transpose())
+            .transpose();
+        done = !matches!(row, Some(Ok(_)));
+        row
+    });
+    (1..).zip(messages)
+}
+
+/// The next message, `None` at the end of the input.
+fn next_message<R: Read>(reader: &mut R) -> Result<Option<Vec<u8>>, Error> {
+    let mut prefix = [0; 2];
+    let mut filled = 0;
+    while filled < prefix.len() {
+        match reader.read(&mut prefix[filled..]) {
+            Ok(0) if filled == 0 => return Ok(None),
+            Ok(0) => return Err(Error::TransactionParseError),
+            Ok(read) => filled += read,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(Error::Io(err)),
+        }
+    }
+    let mut message = vec![0; usize::from(u16::from_be_bytes(prefix))];
+    reader
+        .read_exact(&mut message)
+        .map_err(|err| match err.kind() {
+            ErrorKind::UnexpectedEof => Error::TransactionParseError,
+            _ => Error::Io(err),
+        })?;
+    Ok(Some(message))
+}
+
+struct Message<'a> {
+    rest: &'a [u8],
+}
+
+impl<'a> Message<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.rest.len() < len {
+            return Err(Error::TransactionParseError);
+        }
+        let (taken, rest) = self.rest.split_at(len);
+        self.rest = rest;
+        Ok(taken)
+    }
+
+    fn number(&mut self, digits: usize) -> Result<usize, Error> {
+        number(self.take(digits)?)
+    }
+
+    fn field(&mut self, field: usize) -> Result<&'a [u8], Error> {
+        let len = match length(field) {
+            Length::Fixed(len) => len,
+            Length::LlVar => self.number(2)?,
+            Length::LllVar => self.number(3)?,
+        };
+        self.take(len)
+    }
+}
+
+fn number<T: std::str::FromStr>(digits: &[u8]) -> Result<T, Error> {
+    std::str::from_utf8(digits)
+        .ok()
+        .filter(|digits| !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()))
+        .and_then(|digits| digits.parse().ok())
+        .ok_or(Error::TransactionParseError)
+}
+
+fn parse(message: &[u8]) -> Result<TimedRow, Error> {
+    let mut message = Message { rest: message };
+    let mti = message.take(4)?;
+    let mut bitmap = message.take(8)?.to_vec();
+    if bitmap[0] & 0x80 != 0 {
+        bitmap.extend_from_slice(message.take(8)?);
+    }
+    let mut fields: [Option<&[u8]>; 129] = [None; 129];
+    for (field, value) in fields
+        .iter_mut()
+        .enumerate()
+        .take(bitmap.len() * 8 + 1)
+        .skip(2)
+    {
+        let bit = field - 1;
+        if bitmap[bit / 8] & (0x80 >> (bit % 8)) != 0 {
+            *value = Some(message.field(field)?);
+        }
+    }
+    let field = |field: usize| fields[field].ok_or(Error::TransactionParseError);
+    let client = ClientId::new(number(field(102)?)?);
+    let row = match mti {
+        b"0200" => {
+            let tx_type = match &field(3)?[..2] {
+                b"21" => TransactionType::Deposit,
+                b"00" | b"01" => TransactionType::Withdrawal,
+                _ => return Err(Error::TransactionParseError),
+            };
+            let minor: i64 = number(field(4)?)?;
+            TransactionRow::new(
+                tx_type,
+                client,
+                TransactionId::new(number(field(11)?)?),
+                Some(Currency::from_scaled(minor * 100)),
+            )
+        }
+        // The original message type comes first, then its trace number
+        b"0420" => TransactionRow::new(
+            TransactionType::Chargeback,
+            client,
+            TransactionId::new(number(&field(90)?[4..10])?),
+            None,
+        ),
+        _ => return Err(Error::TransactionParseError),
+    };
+    row.into_timed()
+}
+
+impl Transakt {
+    /// Like [`Transakt::load_csv_from`], for a feed of ISO 8583 messages.
+    pub fn load_iso8583_from<R: Read>(&mut self, source: &str, reader: R) -> Result<(), Error> {
+        self.load_from(source, |transakt| {
+            let read = transakt.read_stats();
+            let rows = transakt.in_row_range(read_timed_iso8583(crate::profile::TimedReader {
+                inner: reader,
+                stats: read.clone(),
+            }));
+            transakt.execute_rows(rows, read)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read_timed_iso8583;
+    use crate::currency::Currency;
+    use crate::transaction::ClientId;
+    use crate::{Error, Transakt};
+    use std::str::FromStr;
+
+    /// A framed message with the given data elements, in order.
+    fn message(mti: &str, fields: &[(usize, &str)]) -> Vec<u8> {
+        let mut bitmap = [0u8; 16];
+        let mut data = vec![];
+        for &(field, value) in fields {
+            let bit = field - 1;
+            bitmap[bit / 8] |= 0x80 >> (bit % 8);
+            if field == 102 {
+                data.extend(format!("{:02}", value.len()).into_bytes());
+            }
+            data.extend(value.as_bytes());
+        }
+        let secondary = fields.iter().any(|&(field, _)| field > 64);
+        if secondary {
+            bitmap[0] |= 0x80;
+        }
+        let mut body = mti.as_bytes().to_vec();
+        body.extend_from_slice(&bitmap[..if secondary { 16 } else { 8 }]);
+        body.extend(data);
+        let mut framed = (body.len() as u16).to_be_bytes().to_vec();
+        framed.extend(body);
+        framed
+    }
+
+    #[test]
+    fn load_iso8583() {
+        let mut feed = vec![];
+        let deposit = [
+            (3, "210000"),
+            (4, "000000001050"),
+            (11, "000001"),
+            (102, "7"),
+        ];
+        feed.extend(message("0200", &deposit));
+        // A purchase, with the time and terminal it came from
+        let purchase = [
+            (3, "000000"),
+            (4, "000000000025"),
+            (7, "0131120000"),
+            (11, "000002"),
+            (41, "TERM0001"),
+            (102, "7"),
+        ];
+        feed.extend(message("0200", &purchase));
+        let reversal = [(90, &format!("0200000001{:032}", 0)[..]), (102, "7")];
+        feed.extend(message("0420", &reversal));
+
+        let mut transakt = Transakt::default();
+        transakt.load_iso8583_from("feed", &feed[..]).unwrap();
+        let account = &transakt.get_accounts_map()[&ClientId::new(7)];
+        assert_eq!(account.available(), &Currency::from_str("10.25").unwrap());
+        // The deposit isn't disputed
+        let rejected: Vec<u64> = transakt
+            .rejections()
+            .groups()
+            .flat_map(|(_, group)| group.lines.clone())
+            .collect();
+        assert_eq!(rejected, [3]);
+
+        let balance_inquiry = message("0200", &[(3, "310000"), (11, "000003"), (102, "7")]);
+        assert!(matches!(
+            Transakt::default().load_iso8583_from("feed", &balance_inquiry[..]),
+            Err(Error::TransactionParseError)
+        ));
+        let truncated = &feed[..feed.len() - 1];
+        assert!(read_timed_iso8583(truncated).any(|(_, row)| row.is_err()));
+    }
+}
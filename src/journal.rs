@@ -0,0 +1,805 @@
+//! Append-only log of every transaction applied to the ledger, in order. Replaying it on an empty
+//! engine, or on top of a snapshot, rebuilds the state.
+//!
+//! Journals are JSON lines, or with [`JournalFormat::Binary`] a compact binary encoding, a fraction
+//! of the size and quicker to read: a `TXJ` magic and a version byte, then each entry prefixed
+//! with its length, with integers as LEB128 varints and amounts zigzag encoded. [`read_journal`] reads
+//! either, telling them apart by the magic.
+
+use crate::bitemporal::Timestamps;
+use crate::currency::Currency;
+use crate::snapshot::AccountState;
+use crate::transaction::{ClientId, Transaction, TransactionId};
+use crate::{Error, Transakt};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::io::{BufRead, BufWriter, ErrorKind, Read, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Position of the transaction in the ledger history, starting at 1.
+    pub seq: u64,
+    /// When the transaction was applied, in seconds since the Unix epoch.
+    pub recorded_at: u64,
+    pub transaction: Transaction,
+    /// Signature of the row the transaction came from, see [`crate::signature`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    /// When the transaction happened, if its row said, see [`crate::bitemporal`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_time: Option<u64>,
+}
+
+const BINARY_MAGIC: &[u8] = b"TXJ";
+const BINARY_VERSION: u8 = 1;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JournalFormat {
+    /// A JSON object per line.
+    #[default]
+    Json,
+    /// Length-prefixed binary entries, see the [module docs](self).
+    Binary,
+}
+
+/// Writes journal entries as JSON lines, or in the binary format.
+pub struct JournalWriter {
+    out: BufWriter<Box<dyn Write + Send>>,
+    format: JournalFormat,
+    /// Whether the magic and version of a binary journal are still to be written.
+    header_pending: bool,
+    #[cfg(feature = "encryption")]
+    pub(crate) cipher: Option<crate::encryption::StateCipher>,
+}
+
+impl JournalWriter {
+    pub fn new<W: Write + Send + 'static>(writer: W) -> Self {
+        Self {
+            out: BufWriter::new(Box::new(writer)),
+            format: JournalFormat::Json,
+            header_pending: false,
+            #[cfg(feature = "encryption")]
+            cipher: None,
+        }
+    }
+
+    /// Writes a binary journal, starting with its header.
+    pub fn binary<W: Write + Send + 'static>(writer: W) -> Self {
+        Self {
+            format: JournalFormat::Binary,
+            header_pending: true,
+            ..Self::new(writer)
+        }
+    }
+
+    /// Opens a journal file for appending, creating it if needed.
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(Error::Io)?;
+        Ok(Self::new(file))
+    }
+
+    /// Opens a binary journal file for appending, creating it if needed. Fails if the file
+    /// already holds a journal of another format.
+    pub fn open_binary(path: &Path) -> Result<Self, Error> {
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)
+            .map_err(Error::Io)?;
+        let mut header = vec![];
+        (&mut file)
+            .take(BINARY_MAGIC.len() as u64 + 1)
+            .read_to_end(&mut header)
+            .map_err(Error::Io)?;
+        if !header.is_empty() {
+            check_header(&header)?;
+        }
+        Ok(Self {
+            header_pending: header.is_empty(),
+            ..Self::binary(file)
+        })
+    }
+
+    pub fn append(
+        &mut self,
+        seq: u64,
+        transaction: &Transaction,
+        signature: Option<&str>,
+        event_time: Option<u64>,
+    ) -> Result<(), Error> {
+        let recorded_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let entry = JournalEntry {
+            seq,
+            recorded_at,
+            transaction: *transaction,
+            signature: signature.map(str::to_string),
+            event_time,
+        };
+        if self.format == JournalFormat::Binary {
+            #[cfg(feature = "encryption")]
+            if self.cipher.is_some() {
+                return Err(Error::InvalidConfig(
+                    "binary journals can't be encrypted".to_string(),
+                ));
+            }
+            if self.header_pending {
+                self.out.write_all(BINARY_MAGIC).map_err(Error::Io)?;
+                self.out.write_all(&[BINARY_VERSION]).map_err(Error::Io)?;
+                self.header_pending = false;
+            }
+            return self.out.write_all(&encode(&entry)).map_err(Error::Io);
+        }
+        #[cfg(feature = "encryption")]
+        if let Some(cipher) = &self.cipher {
+            let entry = serde_json::to_vec(&entry).map_err(|err| Error::Io(err.into()))?;
+            let sealed = crate::hex::encode(&cipher.seal(&entry)?);
+            return writeln!(self.out, "{}", sealed).map_err(Error::Io);
+        }
+        serde_json::to_writer(&mut self.out, &entry).map_err(|err| Error::Io(err.into()))?;
+        self.out.write_all(b"\n").map_err(Error::Io)
+    }
+
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.out.flush().map_err(Error::Io)
+    }
+}
+
+/// Writes entries as a binary journal. Returns how many were written.
+pub fn write_journal<W, I>(entries: I, writer: W) -> Result<u64, Error>
+where
+    W: Write,
+    I: IntoIterator<Item = JournalEntry>,
+{
+    let mut out = BufWriter::new(writer);
+    out.write_all(BINARY_MAGIC).map_err(Error::Io)?;
+    out.write_all(&[BINARY_VERSION]).map_err(Error::Io)?;
+    let mut written = 0;
+    for entry in entries {
+        out.write_all(&encode(&entry)).map_err(Error::Io)?;
+        written += 1;
+    }
+    out.flush().map_err(Error::Io)?;
+    Ok(written)
+}
+
+/// Reads journal entries, one per line of a JSON journal or one per frame of a binary one.
+pub fn read_journal<R: BufRead>(
+    mut reader: R,
+) -> impl Iterator<Item = Result<JournalEntry, Error>> {
+    // An error here comes back from the first read of the entries
+    let binary = reader
+        .fill_buf()
+        .is_ok_and(|buf| buf.starts_with(BINARY_MAGIC));
+    let mut entries = if binary {
+        Entries::Binary {
+            reader,
+            header: false,
+            done: false,
+        }
+    } else {
+        Entries::Json(reader.lines())
+    };
+    std::iter::from_fn(move || entries.next())
+}
+
+enum Entries<R> {
+    Json(std::io::Lines<R>),
+    Binary {
+        reader: R,
+        /// Whether the header has been read.
+        header: bool,
+        /// Set at the end of the input or at a torn frame, after which nothing can be read.
+        done: bool,
+    },
+}
+
+impl<R: BufRead> Entries<R> {
+    fn next(&mut self) -> Option<Result<JournalEntry, Error>> {
+        match self {
+            Entries::Json(lines) => {
+                let line =
+                    lines.find(|line| !matches!(line, Ok(line) if line.trim().is_empty()))?;
+                Some(line.map_err(Error::Io).and_then(|line| {
+                    serde_json::from_str(&line).map_err(|_| Error::TransactionParseError)
+                }))
+            }
+            Entries::Binary {
+                reader,
+                header,
+                done,
+            } => {
+                if *done {
+                    return None;
+                }
+                if !*header {
+                    *header = true;
+                    // The magic and the version
+                    let mut bytes = [0; 4];
+                    if let Err(err) = read_frame_bytes(reader, &mut bytes) {
+                        *done = true;
+                        return Some(Err(err));
+                    }
+                    if let Err(err) = check_header(&bytes) {
+                        *done = true;
+                        return Some(Err(err));
+                    }
+                }
+                let frame = next_frame(reader).transpose()?;
+                // Only a frame cut short ends the entries, one that is all there but doesn't
+                // decode is corrupt and the next ones are still read
+                *done = frame.is_err();
+                Some(frame.and_then(|frame| decode(&frame)))
+            }
+        }
+    }
+}
+
+fn check_header(header: &[u8]) -> Result<(), Error> {
+    match header {
+        [magic @ .., version] if magic == BINARY_MAGIC => match *version {
+            BINARY_VERSION => Ok(()),
+            version => Err(Error::InvalidSnapshot(format!(
+                "unsupported journal version {}",
+                version
+            ))),
+        },
+        _ => Err(Error::InvalidSnapshot("not a binary journal".to_string())),
+    }
+}
+
+/// Fills `bytes`, failing with [`Error::TransactionParseError`] if the input ends first.
+fn read_frame_bytes<R: Read>(reader: &mut R, bytes: &mut [u8]) -> Result<(), Error> {
+    reader.read_exact(bytes).map_err(|err| match err.kind() {
+        ErrorKind::UnexpectedEof => Error::TransactionParseError,
+        _ => Error::Io(err),
+    })
+}
+
+/// The next entry, still encoded, `None` at the end of the input.
+fn next_frame<R: BufRead>(reader: &mut R) -> Result<Option<Vec<u8>>, Error> {
+    if reader.fill_buf().map_err(Error::Io)?.is_empty() {
+        return Ok(None);
+    }
+    let mut len = 0u64;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0];
+        read_frame_bytes(reader, &mut byte)?;
+        len |= u64::from(byte[0] & 0x7f) << shift;
+        if byte[0] < 0x80 {
+            let mut frame = vec![];
+            reader
+                .take(len)
+                .read_to_end(&mut frame)
+                .map_err(Error::Io)?;
+            if frame.len() as u64 != len {
+                return Err(Error::TransactionParseError);
+            }
+            return Ok(Some(frame));
+        }
+    }
+    Err(Error::TransactionParseError)
+}
+
+const KIND_MASK: u8 = 0x07;
+const DISPUTED: u8 = 0x08;
+const HAS_SIGNATURE: u8 = 0x10;
+const HAS_EVENT_TIME: u8 = 0x20;
+/// The fourth bit of the kind, for the kinds added once the first three bits were taken.
+const KIND_HIGH: u8 = 0x40;
+/// A dispute of part of the deposit, with the amount of the part.
+const PARTIAL: u8 = 0x80;
+
+/// An entry as a frame of a binary journal: its length, then the sequence number, the recording
+/// time, a tag byte of the kind of transaction and flags, the client, the transaction id, the
+/// amount, the receiving client of a transfer, the transaction a reversal undoes, the signature
+/// and the event time, each only there if the transaction has it.
+fn encode(entry: &JournalEntry) -> Vec<u8> {
+    let transaction = &entry.transaction;
+    let kind = match transaction {
+        Transaction::Deposit { .. } => 0,
+        Transaction::Withdrawal { .. } => 1,
+        Transaction::Dispute { .. } => 2,
+        Transaction::Resolve { .. } => 3,
+        Transaction::Chargeback { .. } => 4,
+        Transaction::Freeze { .. } => 5,
+        Transaction::Unfreeze { .. } => 6,
+        Transaction::Reactivate { .. } => 7,
+        Transaction::Transfer { .. } => 8,
+        Transaction::Reversal { .. } => 9,
+        Transaction::ChargebackReversal { .. } => 10,
+    };
+    let mut tag = (kind & KIND_MASK) | ((kind << 3) & KIND_HIGH);
+    if matches!(
+        transaction,
+        Transaction::Deposit { disputed: true, .. } | Transaction::Transfer { disputed: true, .. }
+    ) {
+        tag |= DISPUTED;
+    }
+    if matches!(
+        transaction,
+        Transaction::Dispute {
+            amount: Some(_),
+            ..
+        }
+    ) {
+        tag |= PARTIAL;
+    }
+    if entry.signature.is_some() {
+        tag |= HAS_SIGNATURE;
+    }
+    if entry.event_time.is_some() {
+        tag |= HAS_EVENT_TIME;
+    }
+    let mut body = vec![];
+    write_varint(&mut body, entry.seq);
+    write_varint(&mut body, entry.recorded_at);
+    body.push(tag);
+    write_varint(&mut body, u64::from(transaction.client().id()));
+    write_varint(&mut body, u64::from(transaction.tx().id()));
+    if let Some(amount) = transaction.amount() {
+        let scaled = amount.scaled();
+        write_varint(&mut body, ((scaled << 1) ^ (scaled >> 63)) as u64);
+    }
+    if let Some(to) = transaction.counterparty() {
+        write_varint(&mut body, u64::from(to.id()));
+    }
+    if let Transaction::Reversal { ref_tx, .. } = transaction {
+        write_varint(&mut body, u64::from(ref_tx.id()));
+    }
+    if let Some(signature) = &entry.signature {
+        write_varint(&mut body, signature.len() as u64);
+        body.extend_from_slice(signature.as_bytes());
+    }
+    if let Some(event_time) = entry.event_time {
+        write_varint(&mut body, event_time);
+    }
+    let mut frame = Vec::with_capacity(body.len() + 1);
+    write_varint(&mut frame, body.len() as u64);
+    frame.extend(body);
+    frame
+}
+
+fn decode(frame: &[u8]) -> Result<JournalEntry, Error> {
+    let mut rest = frame;
+    let seq = read_varint(&mut rest)?;
+    let recorded_at = read_varint(&mut rest)?;
+    let (&tag, tail) = rest.split_first().ok_or(Error::TransactionParseError)?;
+    rest = tail;
+    let client =
+        u16::try_from(read_varint(&mut rest)?).map_err(|_| Error::TransactionParseError)?;
+    let tx = u32::try_from(read_varint(&mut rest)?).map_err(|_| Error::TransactionParseError)?;
+    let (client, tx) = (ClientId::new(client), TransactionId::new(tx));
+    let mut amount = || {
+        let zigzag = read_varint(&mut rest)?;
+        Ok::<_, Error>(Currency::from_scaled(
+            (zigzag >> 1) as i64 ^ -((zigzag & 1) as i64),
+        ))
+    };
+    let kind = (tag & KIND_MASK) | ((tag & KIND_HIGH) >> 3);
+    let transaction = match kind {
+        0 => Transaction::Deposit {
+            client,
+            tx,
+            amount: amount()?,
+            disputed: tag & DISPUTED != 0,
+        },
+        1 => Transaction::Withdrawal {
+            client,
+            tx,
+            amount: amount()?,
+        },
+        2 => Transaction::Dispute {
+            client,
+            tx,
+            amount: if tag & PARTIAL != 0 {
+                Some(amount()?)
+            } else {
+                None
+            },
+        },
+        3 => Transaction::Resolve { client, tx },
+        4 => Transaction::Chargeback { client, tx },
+        5 => Transaction::Freeze { client, tx },
+        6 => Transaction::Unfreeze { client, tx },
+        7 => Transaction::Reactivate { client, tx },
+        8 => {
+            let amount = amount()?;
+            let to =
+                u16::try_from(read_varint(&mut rest)?).map_err(|_| Error::TransactionParseError)?;
+            Transaction::Transfer {
+                from: client,
+                to: ClientId::new(to),
+                tx,
+                amount,
+                disputed: tag & DISPUTED != 0,
+            }
+        }
+        9 => {
+            let ref_tx =
+                u32::try_from(read_varint(&mut rest)?).map_err(|_| Error::TransactionParseError)?;
+            Transaction::Reversal {
+                client,
+                tx,
+                ref_tx: TransactionId::new(ref_tx),
+            }
+        }
+        10 => Transaction::ChargebackReversal { client, tx },
+        _ => return Err(Error::TransactionParseError),
+    };
+    let signature = if tag & HAS_SIGNATURE != 0 {
+        let len =
+            usize::try_from(read_varint(&mut rest)?).map_err(|_| Error::TransactionParseError)?;
+        if rest.len() < len {
+            return Err(Error::TransactionParseError);
+        }
+        let (signature, tail) = rest.split_at(len);
+        rest = tail;
+        let signature =
+            String::from_utf8(signature.to_vec()).map_err(|_| Error::TransactionParseError)?;
+        Some(signature)
+    } else {
+        None
+    };
+    let event_time = if tag & HAS_EVENT_TIME != 0 {
+        Some(read_varint(&mut rest)?)
+    } else {
+        None
+    };
+    if !rest.is_empty() {
+        return Err(Error::TransactionParseError);
+    }
+    Ok(JournalEntry {
+        seq,
+        recorded_at,
+        transaction,
+        signature,
+        event_time,
+    })
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Result<u64, Error> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes.split_first().ok_or(Error::TransactionParseError)?;
+        *bytes = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte < 0x80 {
+            return Ok(value);
+        }
+    }
+    Err(Error::TransactionParseError)
+}
+
+/// Where to stop replaying. Both bounds are inclusive.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ReplayLimit {
+    pub until_seq: Option<u64>,
+    /// Seconds since the Unix epoch.
+    pub until_time: Option<u64>,
+}
+
+impl ReplayLimit {
+    fn includes(&self, entry: &JournalEntry) -> bool {
+        self.until_seq.is_none_or(|seq| entry.seq <= seq)
+            && self.until_time.is_none_or(|time| entry.recorded_at <= time)
+    }
+}
+
+/// A journaled transaction of one client, with the balances right after it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub seq: u64,
+    pub recorded_at: u64,
+    pub transaction: Transaction,
+    pub account: AccountState,
+}
+
+impl Transakt {
+    /// Re-executes journal entries in order, stopping at the limit. Entries the ledger already
+    /// contains, as when replaying on top of a snapshot, are skipped. A last entry that doesn't
+    /// parse is taken for one torn by a crash while it was written, and ignored. Returns how many
+    /// entries were replayed.
+    pub fn replay<R: BufRead>(&mut self, reader: R, limit: &ReplayLimit) -> Result<u64, Error> {
+        self.replay_entries(reader, limit, |_, _| {})
+    }
+
+    /// Replays the journal like [`Transakt::replay`], collecting the transactions of one client,
+    /// transfers to them included, along with the balances each of them left behind.
+    pub fn client_history<R: BufRead>(
+        &mut self,
+        reader: R,
+        client: ClientId,
+    ) -> Result<Vec<HistoryEntry>, Error> {
+        let mut history = vec![];
+        self.replay_entries(reader, &ReplayLimit::default(), |transakt, entry| {
+            let transaction = &entry.transaction;
+            if transaction.client() != client && transaction.counterparty() != Some(client) {
+                return;
+            }
+            if let Some(account) = transakt.accounts.get(&client) {
+                history.push(HistoryEntry {
+                    seq: entry.seq,
+                    recorded_at: entry.recorded_at,
+                    transaction: entry.transaction,
+                    account: AccountState::from(account),
+                });
+            }
+        })?;
+        Ok(history)
+    }
+
+    fn replay_entries<R, F>(
+        &mut self,
+        reader: R,
+        limit: &ReplayLimit,
+        mut replayed: F,
+    ) -> Result<u64, Error>
+    where
+        R: BufRead,
+        F: FnMut(&Transakt, &JournalEntry),
+    {
+        let mut count = 0;
+        let mut entries = read_journal(reader).peekable();
+        while let Some(entry) = entries.next() {
+            let entry = match entry {
+                Err(Error::TransactionParseError) if entries.peek().is_none() => {
+                    tracing::warn!(after = count, "ignoring torn last journal entry");
+                    break;
+                }
+                entry => entry?,
+            };
+            if entry.seq <= self.applied {
+                continue;
+            }
+            if !limit.includes(&entry) {
+                break;
+            }
+            let times = Timestamps {
+                event_time: entry.event_time,
+                recorded_at: Some(entry.recorded_at),
+            };
+            let res = match entry.transaction {
+                // Unlocks by an operator are not signed rows
+                Transaction::Unfreeze { client, tx } if tx == TransactionId::new(0) => {
+                    self.unlock_as(client, None)
+                }
+                transaction => self.execute_timed(transaction, entry.signature.as_deref(), times),
+            };
+            if let Err(err) = res {
+                // Only applied transactions are journaled, so they should apply again
+                tracing::error!(seq = entry.seq, error = ?err, "journal entry did not replay");
+                if err.is_fatal() {
+                    return Err(err);
+                }
+            }
+            replayed(self, &entry);
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_journal, write_journal, JournalWriter, ReplayLimit};
+    use crate::currency::Currency;
+    use crate::signature::{sign, PublicKey, SignatureConfig};
+    use crate::transaction::{ClientId, Transaction, TransactionId};
+    use crate::Transakt;
+    use ed25519_dalek::SigningKey;
+    use std::str::FromStr;
+    use std::sync::{Arc, Mutex};
+
+    /// Lets the test look at what the engine wrote to its journal.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn journal_replay() {
+        let buffer = SharedBuffer::default();
+        let mut transakt = Transakt::builder()
+            .journal(JournalWriter::new(buffer.clone()))
+            .build();
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,2.0\n\
+                     withdrawal,1,2,5.0\n\
+                     deposit,2,3,1.5\n\
+                     dispute,1,1,\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        let journal = buffer.0.lock().unwrap().clone();
+
+        // The failed withdrawal is not journaled
+        let entries: Vec<u64> = read_journal(journal.as_slice())
+            .map(|entry| entry.unwrap().seq)
+            .collect();
+        assert_eq!(entries, vec![1, 2, 3]);
+
+        let mut replayed = Transakt::default();
+        let count = replayed
+            .replay(journal.as_slice(), &ReplayLimit::default())
+            .unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(replayed.snapshot().digest(), transakt.snapshot().digest());
+
+        let mut partial = Transakt::default();
+        let limit = ReplayLimit {
+            until_seq: Some(2),
+            until_time: None,
+        };
+        assert_eq!(partial.replay(journal.as_slice(), &limit).unwrap(), 2);
+        assert_eq!(partial.snapshot().applied, 2);
+        // Replaying the rest continues where the state left off
+        let count = partial
+            .replay(journal.as_slice(), &ReplayLimit::default())
+            .unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(partial.snapshot().digest(), transakt.snapshot().digest());
+
+        // A last entry torn by a crash is ignored, one in the middle is not
+        let torn = &journal[..journal.len() - 10];
+        assert_eq!(
+            Transakt::default()
+                .replay(torn, &ReplayLimit::default())
+                .unwrap(),
+            2
+        );
+        let mut corrupt = torn.to_vec();
+        corrupt.extend_from_slice(b"\n");
+        corrupt.extend_from_slice(&journal);
+        assert!(Transakt::default()
+            .replay(corrupt.as_slice(), &ReplayLimit::default())
+            .is_err());
+    }
+
+    #[test]
+    fn unlock_with_signatures_required() {
+        let originator = SigningKey::from_bytes(&[1; 32]);
+        let mut config = SignatureConfig {
+            required: true,
+            ..Default::default()
+        };
+        let key = PublicKey::from(originator.verifying_key());
+        config.keys.insert("payments".to_string(), key);
+        let buffer = SharedBuffer::default();
+        let mut transakt = Transakt::builder()
+            .signatures(config.clone())
+            .journal(JournalWriter::new(buffer.clone()))
+            .build();
+        let freeze = Transaction::Freeze {
+            client: ClientId::new(1),
+            tx: TransactionId::new(1),
+        };
+        transakt
+            .execute_signed(freeze, Some(&sign(&freeze, &originator)))
+            .unwrap();
+
+        // The operator's unlock is not a row, so it needs no signature
+        transakt.unlock_account(ClientId::new(1), "alice").unwrap();
+        assert!(!transakt.get_accounts_map()[&ClientId::new(1)].is_locked());
+        assert_eq!(transakt.snapshot().applied, 2);
+        assert_eq!(
+            transakt.snapshot().last_applied,
+            Some(TransactionId::new(1))
+        );
+        transakt.flush_journal().unwrap();
+
+        // Nor does it when replayed
+        let journal = buffer.0.lock().unwrap().clone();
+        let mut replayed = Transakt::builder().signatures(config).build();
+        let count = replayed
+            .replay(journal.as_slice(), &ReplayLimit::default())
+            .unwrap();
+        assert_eq!(count, 2);
+        assert!(!replayed.get_accounts_map()[&ClientId::new(1)].is_locked());
+        assert_eq!(replayed.snapshot().digest(), transakt.snapshot().digest());
+    }
+
+    #[test]
+    fn journal_client_history() {
+        let buffer = SharedBuffer::default();
+        let mut transakt = Transakt::builder()
+            .journal(JournalWriter::new(buffer.clone()))
+            .build();
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,2.0\n\
+                     deposit,2,2,1.5\n\
+                     withdrawal,1,3,0.5\n\
+                     dispute,1,1,\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        let journal = buffer.0.lock().unwrap().clone();
+
+        let history = Transakt::default()
+            .client_history(journal.as_slice(), ClientId::new(1))
+            .unwrap();
+        let seqs: Vec<u64> = history.iter().map(|entry| entry.seq).collect();
+        assert_eq!(seqs, vec![1, 3, 4]);
+        let last = &history[2].account;
+        // 2.0 - 0.5 withdrawn - 2.0 held
+        let half = Currency::from_str("0.5").unwrap();
+        assert_eq!(last.available.checked_add(half), Some(Currency::default()));
+        assert_eq!(last.held, Currency::from_str("2").unwrap());
+    }
+
+    #[test]
+    fn binary_journal() {
+        let buffer = SharedBuffer::default();
+        let mut transakt = Transakt::builder()
+            .journal(JournalWriter::binary(buffer.clone()))
+            .build();
+        let input = "type,client,tx,amount,to,signature,timestamp\n\
+                     deposit,1,1,2.0,,,1700000000\n\
+                     withdrawal,1,2,0.5,,,\n\
+                     deposit,2,3,1.5,,,\n\
+                     transfer,2,5,0.5,1,,\n\
+                     dispute,2,5,0.25,,,\n\
+                     dispute,1,1,,,,\n\
+                     chargeback,1,1,,,,\n\
+                     chargeback_reversal,1,1,,,,\n\
+                     freeze,2,4,,,,\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        transakt.flush_journal().unwrap();
+        let journal = buffer.0.lock().unwrap().clone();
+
+        let mut replayed = Transakt::default();
+        let count = replayed
+            .replay(journal.as_slice(), &ReplayLimit::default())
+            .unwrap();
+        assert_eq!(count, 9);
+        assert_eq!(replayed.snapshot().digest(), transakt.snapshot().digest());
+        let torn = &journal[..journal.len() - 1];
+        let count = Transakt::default()
+            .replay(torn, &ReplayLimit::default())
+            .unwrap();
+        assert_eq!(count, 8);
+
+        // JSON journals convert to the same entries
+        let mut entries: Vec<_> = read_journal(journal.as_slice())
+            .map(Result::unwrap)
+            .collect();
+        entries[0].signature = Some("abc".to_string());
+        let mut json = vec![];
+        for entry in entries.iter() {
+            serde_json::to_writer(&mut json, entry).unwrap();
+            json.push(b'\n');
+        }
+        let converted: Vec<_> = read_journal(json.as_slice()).map(Result::unwrap).collect();
+        let mut binary = vec![];
+        assert_eq!(write_journal(converted, &mut binary).unwrap(), 9);
+        let read: Vec<_> = read_journal(binary.as_slice())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(read, entries);
+        assert!(binary.len() * 3 < json.len());
+
+        binary[3] = 2;
+        assert!(read_journal(binary.as_slice()).next().unwrap().is_err());
+    }
+}
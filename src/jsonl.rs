@@ -0,0 +1,101 @@
+//! Transactions as JSON Lines, one object per line with the fields of the CSV columns:
+//!
+//! ```text
+//! {"type": "deposit", "client": 1, "tx": 1, "amount": "1.5"}
+//! {"type": "deposit", "client": 1, "tx": 3, "amount": "1"}
+//! ```
+//!
+//! Amounts can be strings or numbers, and are cut to four decimals either way. Blank
+//! lines are skipped, and like a malformed CSV row, a malformed line ends the input with
+//! [`Error::TransactionParseError`].
+
+use crate::transaction::{TimedRow, TransactionRow};
+use crate::{Error, Transakt};
+use serde::Deserialize;
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+/// Reads transactions from JSON Lines input, in order, with their line numbers.
+pub fn read_timed_jsonl<R: Read>(
+    reader: R,
+) -> impl Iterator<Item = (u64, Result<TimedRow, Error>)> {
+    (1..)
+        .zip(BufReader::new(reader).lines())
+        .filter(|(_, line)| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|(number, line)| {
+            (
+                number,
+                line.map_err(Error::Io).and_then(|line| parse(&line)),
+            )
+        })
+}
+
+fn parse(line: &str) -> Result<TimedRow, Error> {
+    let mut row: Value = serde_json::from_str(line).map_err(|_| Error::TransactionParseError)?;
+    // Parsed from the text of the number, not a float
+    if let Some(amount) = row.get_mut("amount") {
+        if let Value::Number(number) = amount {
+            *amount = Value::String(number.to_string());
+        }
+    }
+    TransactionRow::deserialize(row)
+        .map_err(|_| Error::TransactionParseError)?
+        .into_timed()
+}
+
+impl Transakt {
+    pub fn read_from_jsonl(filepath: &Path) -> Result<Transakt, Error> {
+        let mut transakt = Self::default();
+        let file = std::fs::File::open(filepath).map_err(Error::Io)?;
+        transakt.load_jsonl_from(&filepath.display().to_string(), file)?;
+        Ok(transakt)
+    }
+
+    /// Like [`Transakt::load_csv_from`], for JSON Lines input.
+    pub fn load_jsonl_from<R: Read>(&mut self, source: &str, reader: R) -> Result<(), Error> {
+        self.load_from(source, |transakt| {
+            let read = transakt.read_stats();
+            let rows = transakt.in_row_range(read_timed_jsonl(crate::profile::TimedReader {
+                inner: reader,
+                stats: read.clone(),
+            }));
+            transakt.execute_rows(rows, read)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::currency::Currency;
+    use crate::transaction::ClientId;
+    use crate::{Error, Transakt};
+    use std::str::FromStr;
+
+    #[test]
+    fn load_jsonl() {
+        let input = r#"{"type": "deposit", "client": 1, "tx": 1, "amount": "10.5"}
+
+{"type": "withdrawal", "client": 1, "tx": 2, "amount": 0.25, "timestamp": 100}
+{"type": "deposit", "client": 1, "tx": 3, "amount": "1"}
+{"type": "withdrawal", "client": 2, "tx": 4, "amount": 1.0}
+"#;
+        let mut transakt = Transakt::default();
+        transakt.load_jsonl_from("input", input.as_bytes()).unwrap();
+        let account = &transakt.get_accounts_map()[&ClientId::new(1)];
+        assert_eq!(account.available(), &Currency::from_str("11.25").unwrap());
+        // Line numbers count the blank line
+        let rejected: Vec<u64> = transakt
+            .rejections()
+            .groups()
+            .flat_map(|(_, group)| group.lines.clone())
+            .collect();
+        assert_eq!(rejected, [5]);
+
+        let malformed = "{\"type\": \"deposit\", \"client\": 1, \"amount\": 1}\n";
+        assert!(matches!(
+            Transakt::default().load_jsonl_from("input", malformed.as_bytes()),
+            Err(Error::TransactionParseError)
+        ));
+    }
+}
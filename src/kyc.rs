@@ -0,0 +1,247 @@
+//! KYC tiers of clients, and the limits of each tier, configured in the `[kyc]` section:
+//!
+//! ```toml
+//! [kyc]
+//! # Sidecar CSV with a `client,tier` row per client
+//! tiers_file = "/var/lib/transakt/kyc.csv"
+//! # Tier of clients missing from it
+//! default_tier = "basic"
+//!
+//! [kyc.limits.basic]
+//! max_balance = "1000"
+//! daily_withdrawal = "250"
+//!
+//! [kyc.limits.verified]
+//! max_balance = "100000"
+//! ```
+//!
+//! Tiers can also be set while processing with [`Transakt::set_kyc_tier`], e.g. from a KYC
+//! service. A client without a tier, or of a tier without limits, is not limited. Days are UTC
+//! days of the clock of the machine processing the transactions, not of the transactions.
+
+use crate::currency::Currency;
+use crate::event::RiskEvent;
+use crate::transaction::{ClientId, Transaction};
+use crate::{Error, Transakt};
+use csv::Trim;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KycConfig {
+    pub tiers_file: Option<PathBuf>,
+    pub default_tier: Option<String>,
+    pub limits: HashMap<String, TierLimits>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TierLimits {
+    /// Most an account can hold, counting held funds.
+    pub max_balance: Option<Currency>,
+    /// Most that can be withdrawn from an account per day.
+    pub daily_withdrawal: Option<Currency>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TierRow {
+    client: ClientId,
+    tier: String,
+}
+
+/// Reads the tier of each client from CSV with `client` and `tier` columns.
+pub fn read_tiers_csv<R: Read>(reader: R) -> Result<HashMap<ClientId, String>, Error> {
+    let mut csv = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .trim(Trim::All)
+        .from_reader(reader);
+    let mut tiers = HashMap::new();
+    for (i, row) in csv.deserialize().enumerate() {
+        let row: TierRow =
+            row.map_err(|err| Error::InvalidConfig(format!("KYC tiers, line {}: {}", i + 2, err)))?;
+        tiers.insert(row.client, row.tier);
+    }
+    Ok(tiers)
+}
+
+pub fn load_tiers(path: &Path) -> Result<HashMap<ClientId, String>, Error> {
+    let file = std::fs::File::open(path).map_err(Error::Io)?;
+    read_tiers_csv(file)
+}
+
+/// The limits, the tier of each client, and what they withdrew today.
+#[derive(Debug, Default)]
+pub(crate) struct KycLimits {
+    config: KycConfig,
+    tiers: HashMap<ClientId, String>,
+    /// Day number since the Unix epoch and the amount withdrawn on it.
+    withdrawn: HashMap<ClientId, (u64, Currency)>,
+}
+
+impl KycLimits {
+    pub(crate) fn new(config: KycConfig, tiers: HashMap<ClientId, String>) -> Self {
+        Self {
+            config,
+            tiers,
+            withdrawn: HashMap::new(),
+        }
+    }
+
+    /// Replaces the limits and the default tier, keeping the tiers of clients and what they
+    /// withdrew today.
+    pub(crate) fn set_config(&mut self, config: KycConfig) {
+        self.config = config;
+    }
+
+    fn tier(&self, client: ClientId) -> Option<&str> {
+        self.tiers
+            .get(&client)
+            .or(self.config.default_tier.as_ref())
+            .map(String::as_str)
+    }
+
+    fn withdrawn_today(&self, client: ClientId) -> Currency {
+        match self.withdrawn.get(&client) {
+            Some((day, amount)) if *day == today() => *amount,
+            _ => Currency::default(),
+        }
+    }
+}
+
+fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() / 86400)
+}
+
+impl Transakt {
+    /// Sets the KYC tier of a client, replacing the one it had. Does nothing unless built with
+    /// [`TransaktBuilder::kyc`](crate::TransaktBuilder::kyc).
+    pub fn set_kyc_tier(&mut self, client: ClientId, tier: &str) {
+        if let Some(kyc) = self.kyc.as_mut() {
+            kyc.tiers.insert(client, tier.to_string());
+        }
+    }
+
+    /// The KYC tier of a client, if it has one.
+    pub fn kyc_tier(&self, client: ClientId) -> Option<&str> {
+        self.kyc.as_ref()?.tier(client)
+    }
+
+    /// Rejects a deposit or withdrawal that would go over the limits of the client's tier.
+    pub(crate) fn check_kyc_limits(&mut self, transaction: &Transaction) -> Result<(), Error> {
+        let kyc = match self.kyc.as_ref() {
+            Some(kyc) => kyc,
+            None => return Ok(()),
+        };
+        let client = transaction.client();
+        let (tier, limits) = match kyc.tier(client) {
+            Some(tier) => match kyc.config.limits.get(tier) {
+                Some(limits) => (tier, limits),
+                None => return Ok(()),
+            },
+            None => return Ok(()),
+        };
+        let exceeded = match *transaction {
+            Transaction::Deposit { amount, .. } => {
+                let total = self
+                    .accounts
+                    .get(&client)
+                    .and_then(|account| account.total())
+                    .unwrap_or_default();
+                match (limits.max_balance, total.checked_add(amount)) {
+                    (Some(max), Some(after)) if after > max => Some(("max_balance", max)),
+                    _ => None,
+                }
+            }
+            Transaction::Withdrawal { amount, .. } => {
+                let withdrawn = kyc.withdrawn_today(client).checked_add(amount);
+                match (limits.daily_withdrawal, withdrawn) {
+                    (Some(max), Some(withdrawn)) if withdrawn > max => {
+                        Some(("daily_withdrawal", max))
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        let (limit, max) = match exceeded {
+            Some(exceeded) => exceeded,
+            None => return Ok(()),
+        };
+        let tier = tier.to_string();
+        let err = Error::LimitExceeded(format!("{} of tier {} is {}", limit, tier, max));
+        self.emit(RiskEvent::LimitExceeded {
+            client,
+            tx: transaction.tx(),
+            tier,
+            limit: limit.to_string(),
+        });
+        tracing::debug!(code = err.code(), error = ?err, "limit exceeded");
+        Err(err)
+    }
+
+    /// Counts an applied withdrawal towards the daily limit.
+    pub(crate) fn record_kyc(&mut self, transaction: &Transaction) {
+        let (kyc, client, amount) = match (self.kyc.as_mut(), transaction) {
+            (Some(kyc), Transaction::Withdrawal { client, amount, .. }) => (kyc, *client, *amount),
+            _ => return,
+        };
+        let today = today();
+        let withdrawn = kyc.withdrawn_today(client);
+        // Only overflows for tiers without a daily limit, where the count doesn't matter
+        let withdrawn = withdrawn.checked_add(amount).unwrap_or(withdrawn);
+        kyc.withdrawn.insert(client, (today, withdrawn));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read_tiers_csv;
+    use crate::config::Config;
+    use crate::currency::Currency;
+    use crate::transaction::ClientId;
+    use crate::Transakt;
+
+    #[test]
+    fn tier_limits() {
+        let config = Config::parse(
+            "[kyc]\n\
+             default_tier = \"basic\"\n\
+             [kyc.limits.basic]\n\
+             max_balance = \"100\"\n\
+             daily_withdrawal = \"30\"\n\
+             [kyc.limits.verified]\n\
+             max_balance = \"1000\"\n",
+        )
+        .unwrap();
+        let tiers = read_tiers_csv("client,tier\n2, verified\n".as_bytes()).unwrap();
+        let mut transakt = Transakt::builder().kyc(config.kyc, tiers).build();
+        transakt.set_kyc_tier(ClientId::new(3), "unlimited");
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,80.0\n\
+                     deposit,1,2,30.0\n\
+                     withdrawal,1,3,20.0\n\
+                     withdrawal,1,4,20.0\n\
+                     withdrawal,1,5,10.0\n\
+                     deposit,2,6,500.0\n\
+                     withdrawal,2,7,100.0\n\
+                     deposit,3,8,5000.0\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        let accounts = transakt.get_accounts_map();
+        let balance = |client| *accounts[&ClientId::new(client)].available();
+        assert_eq!(balance(1), Currency::new(50, 0).unwrap());
+        assert_eq!(balance(2), Currency::new(400, 0).unwrap());
+        assert_eq!(balance(3), Currency::new(5000, 0).unwrap());
+        let limited = transakt.rejections().groups().next().unwrap();
+        assert_eq!(
+            (limited.0, limited.1.lines.clone()),
+            ("E_LIMIT", vec![3, 5])
+        );
+        assert_eq!(transakt.kyc_tier(ClientId::new(1)), Some("basic"));
+    }
+}
@@ -1,20 +1,132 @@
 pub mod account;
+pub mod aml;
+pub mod audit;
+#[cfg(feature = "avro")]
+pub mod avro;
+pub mod balance_history;
+pub mod bitemporal;
+pub mod client_stats;
+#[cfg(feature = "compression")]
+pub mod compression;
+pub mod config;
 pub mod currency;
+pub mod denylist;
+pub mod diff;
+pub mod disputes;
+pub mod dormancy;
+#[cfg(feature = "encryption")]
+pub mod encryption;
+pub mod event;
+pub mod explain;
+#[cfg(any(test, feature = "fault-injection"))]
+pub mod fault;
+pub mod fixed_width;
+#[cfg(feature = "arbitrary")]
+pub mod fuzz;
+pub mod golden;
+mod hex;
+#[cfg(feature = "report-html")]
+pub mod html;
+pub mod ingest;
+#[cfg(feature = "iso20022")]
+pub mod iso20022;
+#[cfg(feature = "iso8583")]
+pub mod iso8583;
+pub mod journal;
+pub mod jsonl;
+pub mod kyc;
+pub mod metadata;
+pub mod minimize;
+#[cfg(feature = "node")]
+pub mod node;
+pub mod notification;
+pub mod observer;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+pub mod policy;
+pub mod profile;
+pub mod redact;
+pub mod rejections;
+pub mod reload;
+pub mod replica;
+pub mod risk;
+pub mod rules;
+pub mod scenario;
+pub mod screening;
+pub mod search;
+pub mod serve;
+pub mod shadow;
+pub mod signature;
+pub mod snapshot;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod statements;
+pub mod stats;
+pub mod summary;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 pub mod transaction;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod validate;
+pub mod validator;
+pub mod verify;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "webhook")]
+pub mod webhook;
+#[cfg(feature = "xlsx")]
+pub mod xlsx;
 
-use crate::transaction::{ClientId, Transaction, TransactionId, TransactionRow};
+use crate::transaction::{ClientId, CsvOptions, TimedRow, Transaction, TransactionId};
 
 use crate::account::Account;
-use csv::Trim;
-use std::collections::HashMap;
-use std::convert::TryInto;
-use std::path::Path;
+use crate::aml::{AmlConfig, AmlMonitor, SuspiciousActivity};
+use crate::audit::{AuditRecord, AuditWriter};
+use crate::balance_history::BalanceHistory;
+use crate::bitemporal::Timestamps;
+use crate::client_stats::ClientStats;
+use crate::currency::Currency;
+use crate::denylist::Denylist;
+use crate::dormancy::DormancyConfig;
+use crate::event::RiskEvent;
+use crate::journal::JournalWriter;
+use crate::kyc::{KycConfig, KycLimits};
+use crate::notification::NotificationSink;
+use crate::observer::Observer;
+use crate::policy::{
+    DefaultPolicy, DisputeFundsAction, LockedAction, ProcessingPolicy, UnknownClientAction,
+};
+use crate::profile::{PhaseStats, Profile, Sample, TimedReader};
+use crate::rejections::{RejectedRow, RejectionSummary};
+use crate::risk::{RiskScoring, RiskWeights};
+use crate::rules::{RuleEngine, RuleSet};
+use crate::screening::{Screener, ScreeningProvider};
+use crate::search::TransactionIndex;
+use crate::signature::SignatureConfig;
+use crate::snapshot::AccountState;
+use crate::summary::RunCounts;
+use crate::validator::TransactionValidator;
+#[cfg(feature = "webhook")]
+use crate::webhook::{WebhookConfig, WebhookDispatcher};
+use serde::Serialize;
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
 
 #[derive(Debug)]
 pub enum Error {
     // Big Error
     TransactionParseError,
     InsufficientHeldFunds,
+    Io(std::io::Error),
+    InvalidSnapshot(String),
+    InvalidConfig(String),
+    InvariantViolation(String),
+    InvalidScenario(String),
 
     // Can ignore
     DuplicateTransaction(TransactionId),
@@ -22,177 +134,1438 @@ pub enum Error {
     AccountLocked,
     InsufficientFunds,
     InvalidTransaction,
+    PendingReview,
+    RuleViolation(String),
+    Rejected(String),
+    Denied(ClientId),
+    ScreeningHit(String),
+    LimitExceeded(String),
+    Dormant(ClientId),
+    InvalidSignature(String),
+    UnknownClient(ClientId),
+    DisputeExceedsFunds(TransactionId),
+    AmountOverCap(Currency),
 }
 
+impl Error {
+    /// Fatal errors stop processing of the whole input, the others only reject one transaction.
+    pub fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            Error::TransactionParseError
+                | Error::InsufficientHeldFunds
+                | Error::Io(_)
+                | Error::InvalidSnapshot(_)
+                | Error::InvalidConfig(_)
+                | Error::InvariantViolation(_)
+                | Error::InvalidScenario(_)
+        )
+    }
+}
+
+#[derive(Default)]
 pub struct Transakt {
     accounts: HashMap<ClientId, Account>,
     transactions: HashMap<TransactionId, Transaction>,
+    /// Secondary indexes of `transactions`, see [`crate::search`].
+    index: TransactionIndex,
+    /// Deposits of which less than the amount is held by their open dispute, a partial one or
+    /// see [`DisputeFundsAction::HoldAvailable`].
+    partial_holds: HashMap<TransactionId, Currency>,
+    /// Deposits and withdrawals undone by a reversal.
+    reversed: HashSet<TransactionId>,
+    /// Open disputes that held less than disputed for lack of available funds, with the rest,
+    /// see [`Account::shortfall`].
+    shortfalls: HashMap<TransactionId, Currency>,
+    /// Deposits charged back, with what their chargeback took and left owed, until a
+    /// [`Transaction::ChargebackReversal`] credits it back.
+    charged_back: HashMap<TransactionId, (Currency, Currency)>,
+    sinks: Vec<Box<dyn NotificationSink>>,
+    large_transaction_threshold: Option<Currency>,
+    /// Deposits and withdrawals of larger amounts are rejected.
+    max_amount: Option<Currency>,
+    /// How many transactions have been applied so far, over the whole life of the ledger.
+    applied: u64,
+    last_applied: Option<TransactionId>,
+    journal: Option<JournalWriter>,
+    /// Only transactions of these clients are executed, if set.
+    clients: Option<HashSet<ClientId>>,
+    /// Transactions of other clients passed over because of `clients`.
+    skipped: u64,
+    /// Data rows of CSV inputs passed over before executing any.
+    skip_rows: u64,
+    /// Most data rows of a CSV input to execute, after `skip_rows`.
+    row_limit: Option<u64>,
+    csv_options: CsvOptions,
+    /// Rows of CSV inputs rejected so far.
+    rejections: RejectionSummary,
+    /// Rows that failed parsing or execution so far, if kept.
+    rejected_rows: Option<Vec<RejectedRow>>,
+    /// Outcomes of the transactions executed so far, see [`crate::summary`].
+    run_counts: RunCounts,
+    /// Where the time went while executing CSV inputs, if profiling.
+    profile: Option<Profile>,
+    /// [`DefaultPolicy`] if not set.
+    policy: Option<Box<dyn ProcessingPolicy>>,
+    rules: Option<RuleEngine>,
+    aml: Option<AmlMonitor>,
+    risk: Option<RiskScoring>,
+    client_stats: HashMap<ClientId, ClientStats>,
+    report_client_stats: bool,
+    account_metadata: bool,
+    balance_history: Option<BalanceHistory>,
+    kyc: Option<KycLimits>,
+    dormancy: Option<DormancyConfig>,
+    denylist: Denylist,
+    screening: Option<Screener>,
+    signatures: Option<SignatureConfig>,
+    validators: Vec<Box<dyn TransactionValidator>>,
+    observers: Vec<Box<dyn Observer>>,
+    audit: Option<AuditWriter>,
+    /// Check the invariants after every transaction.
+    verify: bool,
+    /// Transactions on locked accounts set aside by [`LockedAction::Review`].
+    review: Vec<Transaction>,
 }
 
-impl Default for Transakt {
-    fn default() -> Self {
-        Self {
-            accounts: HashMap::new(),
-            transactions: HashMap::new(),
+/// Configures optional behavior of a [`Transakt`] engine.
+#[derive(Default)]
+pub struct TransaktBuilder {
+    sinks: Vec<Box<dyn NotificationSink>>,
+    large_transaction_threshold: Option<Currency>,
+    max_amount: Option<Currency>,
+    journal: Option<JournalWriter>,
+    clients: Option<HashSet<ClientId>>,
+    skip_rows: u64,
+    row_limit: Option<u64>,
+    csv_options: CsvOptions,
+    rejected_rows: bool,
+    profile: bool,
+    policy: Option<Box<dyn ProcessingPolicy>>,
+    /// The choices of [`TransaktBuilder::locked_accounts`],
+    /// [`TransaktBuilder::unknown_clients`] and [`TransaktBuilder::disputes_exceeding_funds`].
+    default_policy: DefaultPolicy,
+    rules: Option<RuleSet>,
+    aml: Option<AmlConfig>,
+    risk: Option<RiskWeights>,
+    report_client_stats: bool,
+    account_metadata: bool,
+    balance_history: bool,
+    kyc: Option<KycLimits>,
+    dormancy: Option<DormancyConfig>,
+    denylist: Denylist,
+    screening: Option<Box<dyn ScreeningProvider>>,
+    /// How long screening results are cached, forever if not set.
+    screening_ttl: Option<Duration>,
+    signatures: Option<SignatureConfig>,
+    validators: Vec<Box<dyn TransactionValidator>>,
+    observers: Vec<Box<dyn Observer>>,
+    audit: Option<AuditWriter>,
+    /// Check the invariants after every transaction.
+    verify: bool,
+}
+
+impl TransaktBuilder {
+    /// Adds a destination for risk events. Every sink receives every event.
+    pub fn notification_sink<S: NotificationSink + 'static>(mut self, sink: S) -> Self {
+        self.sinks.push(Box::new(sink));
+        self
+    }
+
+    /// POST risk events to a webhook.
+    #[cfg(feature = "webhook")]
+    pub fn webhook(self, config: WebhookConfig) -> Self {
+        self.notification_sink(WebhookDispatcher::new(config))
+    }
+
+    /// Raise an event for every deposit or withdrawal of at least this amount.
+    pub fn large_transaction_threshold(mut self, amount: Currency) -> Self {
+        self.large_transaction_threshold = Some(amount);
+        self
+    }
+
+    /// Reject deposits and withdrawals of more than this amount with [`Error::AmountOverCap`],
+    /// whatever the account, as a guard against corrupted feeds.
+    pub fn max_amount(mut self, amount: Currency) -> Self {
+        self.max_amount = Some(amount);
+        self
+    }
+
+    /// Record every applied transaction in a journal.
+    pub fn journal(mut self, journal: JournalWriter) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Record every decision, with its reason and the balances before and after, in an audit log.
+    pub fn audit_log(mut self, audit: AuditWriter) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Only execute transactions of these clients, and skip the rest. Useful to reproduce the
+    /// balance of a few clients from a large input. Duplicate ids across skipped and executed
+    /// transactions go unnoticed.
+    pub fn clients<I: IntoIterator<Item = ClientId>>(mut self, clients: I) -> Self {
+        self.clients = Some(clients.into_iter().collect());
+        self
+    }
+
+    /// Only execute a slice of every CSV input: skip the first `skip` data rows, then stop after
+    /// `limit` rows. Skipped rows are not parsed, so a malformed one doesn't stop the input.
+    pub fn row_range(mut self, skip: u64, limit: Option<u64>) -> Self {
+        self.skip_rows = skip;
+        self.row_limit = limit;
+        self
+    }
+
+    /// Read CSV inputs written as `options` say, instead of plain comma-separated values.
+    pub fn csv_options(mut self, options: CsvOptions) -> Self {
+        self.csv_options = options;
+        self
+    }
+
+    /// Keep every row that fails parsing or execution, with its line and reason, see
+    /// [`Transakt::rejected_rows`].
+    pub fn rejected_rows(mut self) -> Self {
+        self.rejected_rows = true;
+        self
+    }
+
+    /// Replace the standard business rules.
+    pub fn policy<P: ProcessingPolicy + 'static>(mut self, policy: P) -> Self {
+        self.policy = Some(Box::new(policy));
+        self
+    }
+
+    /// Adds callbacks on applied and rejected transactions, disputes and locks.
+    pub fn observer<O: Observer + 'static>(mut self, observer: O) -> Self {
+        self.observers.push(Box::new(observer));
+        self
+    }
+
+    /// Appends a check to the chain every transaction goes through before it is applied.
+    pub fn validator<V: TransactionValidator + 'static>(mut self, validator: V) -> Self {
+        self.validators.push(Box::new(validator));
+        self
+    }
+
+    /// Reject transactions breaking these rules before executing them.
+    pub fn rules(mut self, rules: RuleSet) -> Self {
+        self.rules = Some(rules);
+        self
+    }
+
+    /// Report suspicious activity of clients, see [`crate::aml`].
+    pub fn aml(mut self, config: AmlConfig) -> Self {
+        self.aml = Some(config);
+        self
+    }
+
+    /// Keep a risk score per account, see [`crate::risk`]. The account report gets a
+    /// `risk_score` column.
+    pub fn risk_scoring(mut self, weights: RiskWeights) -> Self {
+        self.risk = Some(weights);
+        self
+    }
+
+    /// Limit deposits and withdrawals by the KYC tier of each client, see [`crate::kyc`].
+    /// `tiers` holds the tier of each client, typically read from `config.tiers_file`.
+    pub fn kyc(mut self, config: KycConfig, tiers: HashMap<ClientId, String>) -> Self {
+        self.kyc = Some(KycLimits::new(config, tiers));
+        self
+    }
+
+    /// Detect accounts without activity, see [`crate::dormancy`]. The account report gets a
+    /// `dormant` column.
+    pub fn dormancy(mut self, config: DormancyConfig) -> Self {
+        self.dormancy = Some(config);
+        self
+    }
+
+    /// Reject every transaction of these clients with [`Error::Denied`], see [`crate::denylist`].
+    pub fn denylist(mut self, denylist: Denylist) -> Self {
+        self.denylist = denylist;
+        self
+    }
+
+    /// Screen clients against a watchlist on their first transaction, and large deposits and
+    /// withdrawals on their own, see [`crate::screening`].
+    pub fn screening<P: ScreeningProvider + 'static>(mut self, provider: P) -> Self {
+        self.screening = Some(Box::new(provider));
+        self
+    }
+
+    /// Screen clients again once their screening result is this old.
+    pub fn screening_ttl(mut self, ttl: Duration) -> Self {
+        self.screening_ttl = Some(ttl);
+        self
+    }
+
+    /// Verify the `signature` column of CSV rows before executing them, see
+    /// [`crate::signature`].
+    pub fn signatures(mut self, config: SignatureConfig) -> Self {
+        self.signatures = Some(config);
+        self
+    }
+
+    /// Choose what happens to deposits and withdrawals on locked accounts, keeping the other
+    /// standard rules. Replaces any policy set before.
+    pub fn locked_accounts(mut self, deposit: LockedAction, withdrawal: LockedAction) -> Self {
+        self.default_policy.locked_deposit = deposit;
+        self.default_policy.locked_withdrawal = withdrawal;
+        let policy = self.default_policy;
+        self.policy(policy)
+    }
+
+    /// Choose what happens to disputes, resolves and chargebacks on locked accounts, keeping the
+    /// other standard rules. Replaces any policy set before.
+    pub fn locked_disputes(mut self, action: LockedAction) -> Self {
+        self.default_policy.locked_dispute = action;
+        let policy = self.default_policy;
+        self.policy(policy)
+    }
+
+    /// Unlock accounts when a chargeback of theirs is reversed, keeping the other standard rules.
+    /// Replaces any policy set before.
+    pub fn unlock_on_chargeback_reversal(mut self) -> Self {
+        self.default_policy.unlock_on_chargeback_reversal = true;
+        let policy = self.default_policy;
+        self.policy(policy)
+    }
+
+    /// Choose what happens to transactions other than deposits of clients without an account,
+    /// keeping the other standard rules. Replaces any policy set before.
+    pub fn unknown_clients(mut self, action: UnknownClientAction) -> Self {
+        self.default_policy.unknown_client = action;
+        let policy = self.default_policy;
+        self.policy(policy)
+    }
+
+    /// Choose what happens to disputes of deposits larger than the available funds, keeping the
+    /// other standard rules. Replaces any policy set before.
+    pub fn disputes_exceeding_funds(mut self, action: DisputeFundsAction) -> Self {
+        self.default_policy.dispute_exceeding_funds = action;
+        let policy = self.default_policy;
+        self.policy(policy)
+    }
+
+    /// Check ledger-wide invariants after every transaction, stopping with
+    /// [`Error::InvariantViolation`] at the first one broken. Slow, see [`crate::verify`].
+    pub fn verify(mut self) -> Self {
+        self.verify = true;
+        self
+    }
+
+    /// Measure the time and allocations spent reading, parsing and executing CSV inputs, see
+    /// [`Transakt::profile`].
+    pub fn profile(mut self) -> Self {
+        self.profile = true;
+        self
+    }
+
+    pub fn build(self) -> Transakt {
+        let screening_ttl = self.screening_ttl;
+        Transakt {
+            sinks: self.sinks,
+            large_transaction_threshold: self.large_transaction_threshold,
+            max_amount: self.max_amount,
+            journal: self.journal,
+            clients: self.clients,
+            skip_rows: self.skip_rows,
+            row_limit: self.row_limit,
+            csv_options: self.csv_options,
+            rejected_rows: self.rejected_rows.then(Vec::new),
+            profile: self.profile.then(Profile::default),
+            policy: self.policy,
+            rules: self.rules.map(RuleEngine::new),
+            aml: self.aml.map(AmlMonitor::new),
+            risk: self.risk.map(RiskScoring::new),
+            report_client_stats: self.report_client_stats,
+            account_metadata: self.account_metadata,
+            balance_history: self.balance_history.then(BalanceHistory::default),
+            kyc: self.kyc,
+            dormancy: self.dormancy,
+            denylist: self.denylist,
+            screening: self
+                .screening
+                .map(|provider| Screener::new(provider, screening_ttl)),
+            signatures: self.signatures,
+            validators: self.validators,
+            observers: self.observers,
+            audit: self.audit,
+            verify: self.verify,
+            ..Transakt::default()
         }
     }
 }
 
 impl Transakt {
+    pub fn builder() -> TransaktBuilder {
+        TransaktBuilder::default()
+    }
+
     pub fn read_from_csv(filepath: &Path) -> Result<Transakt, Error> {
         let mut transakt = Self::default();
-        let mut csv = csv::ReaderBuilder::new()
-            .has_headers(true)
-            .trim(Trim::All)
-            .from_path(filepath)
-            .expect("Cannot open input file");
-        for record in csv.deserialize() {
-            let transaction: TransactionRow = record.map_err(|_| Error::TransactionParseError)?;
-            let transaction: Transaction = transaction.try_into()?;
-            log::info!("{:?}", transaction);
-            let res = transakt.execute_transaction(transaction);
-            match res {
-                Err(Error::TransactionParseError) =>  return Err(Error::TransactionParseError),
-                Err(Error::InsufficientHeldFunds) =>  return Err(Error::InsufficientHeldFunds),
-                x => log::info!("Result: {:?}", x)
-            }
+        transakt.load_csv(filepath)?;
+        Ok(transakt)
+    }
+
+    /// Executes several CSV files into one ledger, one after the other, as if they were
+    /// concatenated. Transactions of a file can refer to those of the files before it.
+    pub fn read_from_csv_multi(paths: &[PathBuf]) -> Result<Transakt, Error> {
+        let mut transakt = Self::default();
+        for path in paths {
+            transakt.load_csv(path)?;
         }
         Ok(transakt)
     }
 
+    /// Like [`Transakt::read_from_csv`], for CSV input from a pipe, a socket or memory.
+    pub fn read_from_reader<R: std::io::Read>(reader: R) -> Result<Transakt, Error> {
+        let mut transakt = Self::default();
+        transakt.load_csv_from("input", reader)?;
+        Ok(transakt)
+    }
+
+    /// Executes all the transactions in a CSV file on top of the current state.
+    pub fn load_csv(&mut self, filepath: &Path) -> Result<(), Error> {
+        let file = std::fs::File::open(filepath).map_err(Error::Io)?;
+        #[cfg(feature = "compression")]
+        let file = compression::decompressed(std::io::BufReader::new(file))?;
+        self.load_csv_from(&filepath.display().to_string(), file)
+    }
+
+    /// Like [`Transakt::load_csv`], for input that does not come straight from a file. `source`
+    /// names the input in logs and notifications.
+    pub fn load_csv_from<R: std::io::Read>(
+        &mut self,
+        source: &str,
+        reader: R,
+    ) -> Result<(), Error> {
+        self.load_from(source, |transakt| transakt.execute_csv(reader))
+    }
+
+    /// Runs `execute` on the input named `source`, notifying if it is rejected.
+    fn load_from<F>(&mut self, source: &str, execute: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Self) -> Result<(), Error>,
+    {
+        let span = tracing::warn_span!("file", path = %source);
+        let _enter = span.enter();
+        let res = execute(self);
+        if let Err(err) = &res {
+            self.emit(RiskEvent::BatchRejected {
+                source: source.to_string(),
+                reason: format!("{:?}", err),
+            });
+        }
+        res
+    }
+
+    pub(crate) fn execute_csv<R: std::io::Read>(&mut self, reader: R) -> Result<(), Error> {
+        let read = self.read_stats();
+        let rows = self.csv_rows(TimedReader {
+            inner: reader,
+            stats: read.clone(),
+        });
+        self.execute_raw_rows(rows, read)
+    }
+
+    /// Where the time spent reading the input goes when profiling.
+    pub(crate) fn read_stats(&self) -> Option<Rc<Cell<PhaseStats>>> {
+        self.profile
+            .is_some()
+            .then(|| Rc::new(Cell::new(PhaseStats::default())))
+    }
+
+    /// Executes parsed rows of an input, with their line numbers, until they end or one is
+    /// malformed. `read` is the time spent reading the input so far, when profiling.
+    pub(crate) fn execute_rows<I>(
+        &mut self,
+        rows: I,
+        read: Option<Rc<Cell<PhaseStats>>>,
+    ) -> Result<(), Error>
+    where
+        I: Iterator<Item = (u64, Result<TimedRow, Error>)>,
+    {
+        self.execute_raw_rows(rows.map(|(line, row)| (line, None, row)), read)
+    }
+
+    /// Like [`Transakt::execute_rows`], with the rows as they were read, for the rejected rows.
+    fn execute_raw_rows<I>(
+        &mut self,
+        mut rows: I,
+        read: Option<Rc<Cell<PhaseStats>>>,
+    ) -> Result<(), Error>
+    where
+        I: Iterator<Item = (u64, Option<String>, Result<TimedRow, Error>)>,
+    {
+        let profiling = self.profile.is_some();
+        // Pulling rows includes reading the input, which is told apart from parsing at the end
+        let mut pulled = PhaseStats::default();
+        let res = loop {
+            let start = profiling.then(Sample::now);
+            let next = rows.next();
+            if let Some(start) = start {
+                pulled.add_since(start);
+            }
+            let (line, raw, row) = match next {
+                Some(next) => next,
+                None => break Ok(()),
+            };
+            if let Err(err) = self.execute_row(line, raw, row) {
+                break Err(err);
+            }
+        };
+        if let (Some(profile), Some(read)) = (self.profile.as_mut(), read) {
+            let read = read.get();
+            profile.read += read;
+            profile.parse += pulled.saturating_sub(read);
+        }
+        res?;
+        self.flush_journal()
+    }
+
+    /// Executes a row of an input, recording it if it is rejected. Fails if the row is malformed
+    /// or processing has to stop on its error.
+    pub(crate) fn execute_row(
+        &mut self,
+        line: u64,
+        raw: Option<String>,
+        row: Result<TimedRow, Error>,
+    ) -> Result<(), Error> {
+        let (transaction, signature, event_time) = match row {
+            Ok(row) => row,
+            Err(err) => {
+                self.record_rejected_row(line, raw, &err);
+                return Err(err);
+            }
+        };
+        let start = self.profile.is_some().then(Sample::now);
+        let res = self.execute_at(transaction, signature.as_deref(), event_time);
+        if let (Some(profile), Some(start)) = (self.profile.as_mut(), start) {
+            profile.execute.add_since(start);
+        }
+        if let Err(err) = res {
+            self.rejections.record(line, &err);
+            self.record_rejected_row(line, raw, &err);
+            if let Error::InvariantViolation(reason) = err {
+                return Err(Error::InvariantViolation(format!(
+                    "line {}: {}",
+                    line, reason
+                )));
+            }
+            if err.is_fatal() {
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// The transactions in the configured row range of a CSV input, with their line numbers,
+    /// rows as read, signatures and event times. Lines are counted assuming no field spans
+    /// several lines.
+    pub(crate) fn csv_rows<R: std::io::Read>(
+        &self,
+        reader: R,
+    ) -> impl Iterator<Item = (u64, Option<String>, Result<TimedRow, Error>)> {
+        // The header is line 1
+        let rows = transaction::read_raw_csv_with(reader, &self.csv_options);
+        let rows = (2..).zip(rows).map(|(line, (raw, row))| (line, raw, row));
+        self.in_row_range(rows)
+    }
+
+    /// The rows in the configured row range.
+    pub(crate) fn in_row_range<I: Iterator>(&self, rows: I) -> impl Iterator<Item = I::Item> {
+        let skip = usize::try_from(self.skip_rows).unwrap_or(usize::MAX);
+        let limit = self.row_limit.map_or(usize::MAX, |limit| {
+            usize::try_from(limit).unwrap_or(usize::MAX)
+        });
+        rows.skip(skip).take(limit)
+    }
+
+    /// Makes sure everything recorded in the journal and the audit log so far has been written
+    /// out.
+    pub fn flush_journal(&mut self) -> Result<(), Error> {
+        if let Some(audit) = self.audit.as_mut() {
+            audit.flush()?;
+        }
+        match self.journal.as_mut() {
+            Some(journal) => journal.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// The accounts ordered by client, so everything derived from them, like the account report,
+    /// is the same for the same state whatever order the accounts were opened in.
     pub fn get_accounts(&self) -> Vec<Account> {
-        self.accounts.values().cloned().collect()
+        let mut accounts: Vec<Account> = self.accounts.values().cloned().collect();
+        accounts.sort_by_key(|account| account.client());
+        accounts
     }
 
     pub fn get_accounts_map(&self) -> &HashMap<ClientId, Account> {
         &self.accounts
     }
 
-    pub fn print_csv(&self) {
+    /// Writes the account report, a `client,available,held,total,locked` row per account plus
+    /// the columns of the enabled reporting features, ordered by client.
+    pub fn write_accounts_csv<W: std::io::Write>(&self, writer: W) -> Result<(), Error> {
+        self.write_report(writer, self.get_accounts())
+    }
+
+    pub fn print_csv(&self) -> Result<(), Error> {
+        self.write_accounts_csv(std::io::stdout())
+    }
+
+    /// Writes the account report as a JSON array, an object per account with the columns of
+    /// [`Transakt::write_accounts_csv`] as fields, ordered by client.
+    pub fn write_accounts_json<W: std::io::Write>(&self, mut writer: W) -> Result<(), Error> {
         let accounts = self.get_accounts();
-        let mut out = csv::Writer::from_writer(std::io::stdout());
-        for account in accounts {
-            out.serialize(&account).unwrap();
+        let entries: Vec<ReportEntry> = accounts
+            .iter()
+            .map(|account| self.report_entry(account))
+            .collect();
+        serde_json::to_writer(&mut writer, &entries).map_err(|err| Error::Io(err.into()))?;
+        writeln!(writer).map_err(Error::Io)?;
+        writer.flush().map_err(Error::Io)
+    }
+
+    /// Writes the applied deposits, withdrawals and transfers as CSV,
+    /// `type,client,tx,amount,disputed`, ordered by transaction id, with whether each deposit or
+    /// transfer is disputed at the end. Transfers are under the client sending them, reversed
+    /// transactions are left out. Disputes,
+    /// resolutions and chargebacks show in that and in the balances; the journal has each of them.
+    pub fn write_transactions_csv<W: std::io::Write>(&self, writer: W) -> Result<(), Error> {
+        let mut transactions: Vec<&Transaction> = self
+            .transactions
+            .values()
+            .filter(|transaction| !self.reversed.contains(&transaction.tx()))
+            .collect();
+        transactions.sort_by_key(|transaction| transaction.tx());
+        let mut out = csv::Writer::from_writer(writer);
+        for transaction in transactions {
+            let row = LedgerRow {
+                kind: transaction.kind(),
+                client: transaction.client(),
+                tx: transaction.tx(),
+                amount: transaction.amount(),
+                disputed: matches!(
+                    transaction,
+                    Transaction::Deposit { disputed: true, .. }
+                        | Transaction::Transfer { disputed: true, .. }
+                ),
+            };
+            out.serialize(row).map_err(|err| Error::Io(err.into()))?;
+        }
+        out.flush().map_err(Error::Io)
+    }
+
+    fn write_report<W: std::io::Write>(
+        &self,
+        writer: W,
+        accounts: Vec<Account>,
+    ) -> Result<(), Error> {
+        let mut out = csv::Writer::from_writer(writer);
+        for account in accounts.iter() {
+            out.serialize(self.report_entry(account))
+                .map_err(|err| Error::Io(err.into()))?;
+        }
+        out.flush().map_err(Error::Io)
+    }
+
+    /// The report of an account, with the columns of the reporting features that are on.
+    fn report_entry<'a>(&self, account: &'a Account) -> ReportEntry<'a> {
+        let extended = self.risk.is_some()
+            || self.dormancy.is_some()
+            || self.report_client_stats
+            || self.account_metadata;
+        // The CSV writer can't flatten a struct, so the stats are columns of their own
+        let stats = self
+            .report_client_stats
+            .then(|| self.client_stats(account.client()));
+        match account.total() {
+            Some(total) if extended => ReportEntry::Extended(Box::new(ReportRow {
+                client: account.client(),
+                available: *account.available(),
+                held: *account.held(),
+                total,
+                locked: account.is_locked(),
+                risk_score: self
+                    .risk_score(account.client())
+                    .map(|score| format!("{:.2}", score)),
+                dormant: self.is_dormant(account.client()),
+                deposits: stats.map(|stats| stats.deposits),
+                withdrawals: stats.map(|stats| stats.withdrawals),
+                disputes_opened: stats.map(|stats| stats.disputes_opened),
+                disputes_resolved: stats.map(|stats| stats.disputes_resolved),
+                chargebacks: stats.map(|stats| stats.chargebacks),
+                rejected: stats.map(|stats| stats.rejected),
+                created_at: self.account_metadata.then(|| account.created_at()),
+                last_activity: self.account_metadata.then(|| account.last_activity()),
+                dispute_count: self.account_metadata.then(|| account.dispute_count()),
+                chargeback_count: self.account_metadata.then(|| account.chargeback_count()),
+                shortfall: self.account_metadata.then(|| account.shortfall()),
+            })),
+            _ => ReportEntry::Account(account),
         }
-        out.flush().unwrap();
     }
 
     pub fn execute_transaction(&mut self, transaction: Transaction) -> Result<(), Error> {
+        self.execute_signed(transaction, None)
+    }
+
+    /// Executes a transaction with the signature of its row, see [`crate::signature`].
+    pub fn execute_signed(
+        &mut self,
+        transaction: Transaction,
+        signature: Option<&str>,
+    ) -> Result<(), Error> {
+        self.execute_timed(transaction, signature, Timestamps::default())
+    }
+
+    /// Lifts the lock of the client's account, whether it came from a freeze or a chargeback, on
+    /// behalf of `operator`. An administrative change rather than a row, so none of the checks of
+    /// rows apply, signatures included. It is still journaled, as an `unfreeze` with tx 0, and
+    /// audited with the operator. Fails if the account isn't locked.
+    pub fn unlock_account(&mut self, client: ClientId, operator: &str) -> Result<(), Error> {
+        self.unlock_as(client, Some(operator))
+    }
+
+    /// [`Transakt::unlock_account`] by `operator`, or replayed from the journal without one.
+    pub(crate) fn unlock_as(
+        &mut self,
+        client: ClientId,
+        operator: Option<&str>,
+    ) -> Result<(), Error> {
+        let transaction = Transaction::Unfreeze {
+            client,
+            tx: TransactionId::new(0),
+        };
+        let before = self.accounts.get(&client).map(AccountState::from);
+        let res = self.unlock(client).and_then(|()| {
+            self.applied += 1;
+            match self.journal.as_mut() {
+                Some(journal) => journal.append(self.applied, &transaction, None, None),
+                None => Ok(()),
+            }
+        });
+        match self.audit.as_mut() {
+            Some(audit) => {
+                let after = self.accounts.get(&client).map(AccountState::from);
+                let mut record = AuditRecord::new(self.applied, transaction, &res, before, after);
+                record.operator = operator.map(str::to_string);
+                audit.append(&record).and(res)
+            }
+            None => res,
+        }
+    }
+
+    fn unlock(&mut self, client: ClientId) -> Result<(), Error> {
+        match self.accounts.get_mut(&client) {
+            Some(account) if account.is_locked() => {
+                account.unlock();
+                tracing::debug!("account unlocked");
+                Ok(())
+            }
+            _ => {
+                let err = Error::InvalidTransaction;
+                tracing::debug!(code = err.code(), "not locked");
+                Err(err)
+            }
+        }
+    }
+
+    pub(crate) fn execute_timed(
+        &mut self,
+        transaction: Transaction,
+        signature: Option<&str>,
+        times: Timestamps,
+    ) -> Result<(), Error> {
+        // At warn level, so warnings about the transaction keep its context at the default verbosity
+        let span = tracing::warn_span!(
+            "transaction",
+            client = %redact::client(transaction.client()),
+            tx = %transaction.tx(),
+            r#type = transaction.kind(),
+            amount = tracing::field::Empty,
+        );
+        if let Some(amount) = transaction.amount() {
+            span.record("amount", tracing::field::display(redact::amount(amount)));
+        }
+        let _enter = span.enter();
+        if let Some(clients) = &self.clients {
+            if !clients.contains(&transaction.client()) {
+                self.skipped += 1;
+                tracing::debug!(outcome = "skipped");
+                return Ok(());
+            }
+        }
+        let before = match self.audit {
+            Some(_) => self
+                .accounts
+                .get(&transaction.client())
+                .map(AccountState::from),
+            None => None,
+        };
+        let total_before = self.verify.then(|| self.ledger_before(&transaction));
+        let existed = self.accounts.contains_key(&transaction.client());
+        let balance_before = self.balance_before(transaction.client());
+        let mut operator = None;
+        let res = self
+            .check_signature(&transaction, signature)
+            .and_then(|signed_by| {
+                operator = signed_by;
+                self.check_amount_cap(&transaction)
+            })
+            .and_then(|_| self.check_denylist(&transaction))
+            .and_then(|_| self.check_screening(&transaction))
+            .and_then(|_| self.run_validators(transaction))
+            .and_then(|transaction| {
+                self.check_rules(&transaction)?;
+                self.check_kyc_limits(&transaction)?;
+                self.check_dormancy(&transaction)?;
+                self.apply_transaction(transaction)?;
+                Ok(transaction)
+            });
+        let res = match total_before {
+            Some(before) => self.check_invariants(before, res.as_ref().ok()).and(res),
+            None => res,
+        };
+        let res = res.and_then(|transaction| {
+            self.applied += 1;
+            self.last_applied = Some(transaction.tx());
+            self.record_rules(&transaction);
+            self.record_aml(&transaction);
+            self.record_kyc(&transaction);
+            self.record_activity(&transaction);
+            self.record_risk(&transaction, Ok(()));
+            self.record_client_stats(&transaction, Ok(()));
+            self.record_balance(transaction.client(), balance_before, times);
+            let account = self.accounts.get(&transaction.client());
+            for observer in self.observers.iter_mut() {
+                observer.on_applied(&transaction, account);
+            }
+            match self.journal.as_mut() {
+                Some(journal) => {
+                    journal.append(self.applied, &transaction, signature, times.event_time)
+                }
+                None => Ok(()),
+            }
+        });
+        self.record_opened(transaction.client(), existed);
+        if let Err(err) = &res {
+            self.record_risk(&transaction, Err(err));
+            self.record_client_stats(&transaction, Err(err));
+            for observer in self.observers.iter_mut() {
+                observer.on_rejected(&transaction, err);
+            }
+        }
+        let res = match self.audit.as_mut() {
+            Some(audit) => {
+                let after = self.accounts.get(&transaction.client());
+                let mut record = AuditRecord::new(
+                    self.applied,
+                    transaction,
+                    &res,
+                    before,
+                    after.map(AccountState::from),
+                );
+                record.operator = operator;
+                audit.append(&record).and(res)
+            }
+            None => res,
+        };
+        self.run_counts.record(res.as_ref().map(|_| ()));
+        match &res {
+            Ok(()) => tracing::info!(outcome = "applied"),
+            Err(err) => tracing::info!(outcome = "rejected", code = err.code(), error = ?err),
+        }
+        res
+    }
+
+    /// How many transactions were skipped because their client was not selected.
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+
+    /// Clients whose transactions are refused, to change while processing.
+    pub fn denylist_mut(&mut self) -> &mut Denylist {
+        &mut self.denylist
+    }
+
+    /// Suspicious activity reported so far, oldest first. Empty unless built with
+    /// [`TransaktBuilder::aml`].
+    pub fn suspicious_activity(&self) -> &[SuspiciousActivity] {
+        self.aml.as_ref().map_or(&[], |aml| aml.report())
+    }
+
+    /// Rows of CSV inputs rejected so far, grouped by error code.
+    pub fn rejections(&self) -> &RejectionSummary {
+        &self.rejections
+    }
+
+    /// Rows that failed parsing or execution so far, in order. Empty unless built with
+    /// [`TransaktBuilder::rejected_rows`]. A row that fails parsing ends its input.
+    pub fn rejected_rows(&self) -> &[RejectedRow] {
+        self.rejected_rows.as_deref().unwrap_or(&[])
+    }
+
+    pub(crate) fn record_rejected_row(&mut self, line: u64, raw: Option<String>, err: &Error) {
+        if let Some(rows) = self.rejected_rows.as_mut() {
+            rows.push(RejectedRow::new(line, raw, err));
+        }
+    }
+
+    /// Time and allocations spent on CSV inputs so far, if built with
+    /// [`TransaktBuilder::profile`]. The report phase is left for the caller to fill in.
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
+
+    /// Transactions on locked accounts set aside for manual review, oldest first.
+    pub fn review_queue(&self) -> &[Transaction] {
+        &self.review
+    }
+
+    /// Empties the review queue, returning its transactions. Once reviewed, they can be
+    /// executed again with [`Transakt::execute_transaction`].
+    pub fn take_review_queue(&mut self) -> Vec<Transaction> {
+        std::mem::take(&mut self.review)
+    }
+
+    fn queue_for_review(&mut self, transaction: Transaction) -> Error {
+        self.review.push(transaction);
+        let err = Error::PendingReview;
+        tracing::debug!(code = err.code(), "queued for review");
+        err
+    }
+
+    fn account_locked(&mut self, client: ClientId) {
+        self.emit(RiskEvent::AccountLocked { client });
+        for observer in self.observers.iter_mut() {
+            observer.on_account_locked(client);
+        }
+    }
+
+    fn emit(&mut self, event: RiskEvent) {
+        for sink in self.sinks.iter_mut() {
+            sink.notify(&event);
+        }
+    }
+
+    fn check_large_transaction(&mut self, client: ClientId, tx: TransactionId, amount: Currency) {
+        if let Some(threshold) = self.large_transaction_threshold {
+            if amount >= threshold {
+                self.emit(RiskEvent::LargeTransaction { client, tx, amount });
+            }
+        }
+    }
+
+    /// Rejects a deposit or withdrawal over the maximum amount, either way from zero.
+    fn check_amount_cap(&self, transaction: &Transaction) -> Result<(), Error> {
+        let (cap, amount) = match (self.max_amount, transaction.amount()) {
+            (Some(cap), Some(amount)) => (cap, amount),
+            _ => return Ok(()),
+        };
+        let negated = Currency::default().checked_sub(amount);
+        if amount <= cap && negated.is_some_and(|negated| negated <= cap) {
+            return Ok(());
+        }
+        let err = Error::AmountOverCap(amount);
+        tracing::debug!(code = err.code(), "amount over cap");
+        Err(err)
+    }
+
+    fn apply_transaction(&mut self, transaction: Transaction) -> Result<(), Error> {
+        const DEFAULT_POLICY: DefaultPolicy = DefaultPolicy::new();
+        let client = transaction.client();
+        let unknown = !matches!(transaction, Transaction::Deposit { .. })
+            && !self.accounts.contains_key(&client);
+        if unknown {
+            let policy = self.policy.as_deref().unwrap_or(&DEFAULT_POLICY);
+            match policy.on_unknown_client(&transaction) {
+                UnknownClientAction::Create => {}
+                UnknownClientAction::Reject => {
+                    let err = Error::UnknownClient(client);
+                    tracing::debug!(code = err.code(), "unknown client");
+                    return Err(err);
+                }
+                UnknownClientAction::Flag => self.emit(RiskEvent::UnknownClient {
+                    client,
+                    tx: transaction.tx(),
+                    kind: transaction.kind().to_string(),
+                }),
+            }
+        }
+        let policy = self.policy.as_deref().unwrap_or(&DEFAULT_POLICY);
         match transaction {
             Transaction::Deposit {
-                client,
-                tx,
-                amount,
-                ..
+                client, tx, amount, ..
             } => {
-                if amount.is_negative() {
-                    log::warn!("Negative withdraw {:?} {:?}", tx, amount);
-                    return Err(Error::InvalidTransaction);
+                if amount.is_negative() && !policy.allow_negative_amount(&transaction) {
+                    let err = Error::InvalidTransaction;
+                    tracing::debug!(code = err.code(), "negative deposit");
+                    return Err(err);
                 }
                 if self.transactions.contains_key(&tx) {
-                    log::warn!("Duplicate transaction {:?}", tx);
-                    return Err(Error::DuplicateTransaction(tx));
+                    let err = Error::DuplicateTransaction(tx);
+                    tracing::debug!(code = err.code(), "duplicate transaction");
+                    return Err(err);
+                }
+                let account = self
+                    .accounts
+                    .entry(client)
+                    .or_insert_with(|| Account::new(client));
+                let mut transaction = transaction;
+                match locked_action(policy, &transaction, account) {
+                    LockedAction::Apply => account.deposit(amount)?,
+                    LockedAction::Reject => return Err(locked()),
+                    LockedAction::Review => return Err(self.queue_for_review(transaction)),
+                    LockedAction::Hold => {
+                        let mut held = account.clone();
+                        held.deposit(amount)?;
+                        held.hold(amount)?;
+                        *account = held;
+                        transaction = Transaction::Deposit {
+                            client,
+                            tx,
+                            amount,
+                            disputed: true,
+                        };
+                        tracing::debug!("deposit held on locked account");
+                    }
                 }
-                let account = self.accounts.entry(client).or_insert(Account::new(client));
-                //
-                account.deposit(amount)?;
                 self.transactions.insert(tx, transaction);
+                self.index.insert(&transaction);
+                self.check_large_transaction(client, tx, amount);
             }
             Transaction::Withdrawal { client, tx, amount } => {
-                if amount.is_negative() {
-                    log::warn!("Negative withdraw {:?} {:?}", tx, amount);
-                    return Err(Error::InvalidTransaction);
+                if amount.is_negative() && !policy.allow_negative_amount(&transaction) {
+                    let err = Error::InvalidTransaction;
+                    tracing::debug!(code = err.code(), "negative withdrawal");
+                    return Err(err);
                 }
                 if self.transactions.contains_key(&tx) {
-                    log::warn!("Duplicate transaction {:?}", tx);
-                    return Err(Error::DuplicateTransaction(tx));
+                    let err = Error::DuplicateTransaction(tx);
+                    tracing::debug!(code = err.code(), "duplicate transaction");
+                    return Err(err);
+                }
+                let account = self
+                    .accounts
+                    .entry(client)
+                    .or_insert_with(|| Account::new(client));
+                match locked_action(policy, &transaction, account) {
+                    LockedAction::Apply => account.withdraw(amount)?,
+                    LockedAction::Reject | LockedAction::Hold => return Err(locked()),
+                    LockedAction::Review => return Err(self.queue_for_review(transaction)),
                 }
-                let account = self.accounts.entry(client).or_insert(Account::new(client));
-                account.withdraw(amount)?;
                 self.transactions.insert(tx, transaction);
+                self.index.insert(&transaction);
+                self.check_large_transaction(client, tx, amount);
             }
-            Transaction::Dispute { tx, .. } => {
-                if let Some(transaction) = self.transactions.get_mut(&tx) {
-                    match transaction {
-                        Transaction::Deposit {
-                            client ,
-                            tx,
-                            amount,
-                            disputed,
-                        } => {
-                            if *disputed {
-                                log::warn!("Dispute twice on {:?}", tx);
-                                return Err(Error::InvalidTransaction);
+            Transaction::Transfer {
+                from,
+                to,
+                tx,
+                amount,
+                ..
+            } => {
+                if amount.is_negative() && !policy.allow_negative_amount(&transaction) {
+                    let err = Error::InvalidTransaction;
+                    tracing::debug!(code = err.code(), "negative transfer");
+                    return Err(err);
+                }
+                if from == to {
+                    let err = Error::InvalidTransaction;
+                    tracing::debug!(code = err.code(), "transfer to the same client");
+                    return Err(err);
+                }
+                if self.transactions.contains_key(&tx) {
+                    let err = Error::DuplicateTransaction(tx);
+                    tracing::debug!(code = err.code(), "duplicate transaction");
+                    return Err(err);
+                }
+                // Both accounts change on copies, so a failure leaves neither changed
+                let mut source = self
+                    .accounts
+                    .get(&from)
+                    .cloned()
+                    .unwrap_or_else(|| Account::new(from));
+                let mut target = self
+                    .accounts
+                    .get(&to)
+                    .cloned()
+                    .unwrap_or_else(|| Account::new(to));
+                for account in [&source, &target] {
+                    match locked_action(policy, &transaction, account) {
+                        LockedAction::Apply => {}
+                        LockedAction::Reject | LockedAction::Hold => return Err(locked()),
+                        LockedAction::Review => return Err(self.queue_for_review(transaction)),
+                    }
+                }
+                source.withdraw(amount)?;
+                target.deposit(amount)?;
+                self.accounts.insert(from, source);
+                self.accounts.insert(to, target);
+                self.transactions.insert(tx, transaction);
+                self.index.insert(&transaction);
+                self.check_large_transaction(from, tx, amount);
+            }
+            Transaction::Dispute { tx, .. }
+            | Transaction::Resolve { tx, .. }
+            | Transaction::Chargeback { tx, .. } => {
+                // Disputes of transfers hold the funds where they went, `sender` has them back
+                // on a chargeback
+                let (client, amount, disputed, sender) = match self.transactions.get_mut(&tx) {
+                    Some(Transaction::Deposit {
+                        client,
+                        amount,
+                        disputed,
+                        ..
+                    }) => (*client, *amount, disputed, None),
+                    Some(Transaction::Transfer {
+                        from,
+                        to,
+                        amount,
+                        disputed,
+                        ..
+                    }) => (*to, *amount, disputed, Some(*from)),
+                    _ => {
+                        tracing::debug!("invalid dispute");
+                        return policy.unknown_reference(&transaction);
+                    }
+                };
+                // should never happen since we already have an existing transaction.
+                let account = self.accounts.get_mut(&client).unwrap();
+                match locked_action(policy, &transaction, account) {
+                    LockedAction::Apply => {}
+                    LockedAction::Reject | LockedAction::Hold => return Err(locked()),
+                    LockedAction::Review => return Err(self.queue_for_review(transaction)),
+                }
+                match transaction {
+                    Transaction::Dispute {
+                        amount: requested, ..
+                    } => {
+                        if *disputed {
+                            let err = Error::InvalidTransaction;
+                            tracing::debug!(code = err.code(), "dispute twice");
+                            return Err(err);
+                        }
+                        if self.reversed.contains(&tx) {
+                            let err = Error::InvalidTransaction;
+                            tracing::debug!(code = err.code(), "dispute of a reversed deposit");
+                            return Err(err);
+                        }
+                        // A partial dispute holds only part of the deposit
+                        let invalid = requested.is_some_and(|requested| {
+                            requested <= Currency::default() || requested > amount
+                        });
+                        if invalid {
+                            let err = Error::InvalidTransaction;
+                            tracing::debug!(code = err.code(), "dispute of more than the deposit");
+                            return Err(err);
+                        }
+                        let disputed_amount = requested.unwrap_or(amount);
+                        let available = *account.available();
+                        let action = (disputed_amount > available)
+                            .then(|| policy.on_dispute_exceeding_funds(&transaction, account));
+                        let held = match action {
+                            Some(DisputeFundsAction::HoldAvailable) => {
+                                available.max(Currency::default())
+                            }
+                            Some(DisputeFundsAction::Reject) => Currency::default(),
+                            _ => disputed_amount,
+                        };
+                        let rejected = action == Some(DisputeFundsAction::Reject);
+                        if !rejected {
+                            let short = disputed_amount.checked_sub(held).ok_or(Error::Overflow)?;
+                            account.hold(held)?;
+                            account.count_dispute();
+                            if short > Currency::default() {
+                                account.add_shortfall(short)?;
+                                self.shortfalls.insert(tx, short);
                             }
                             *disputed = true;
-                            // should never happen since we already have an existing transaction.
-                            let account = self.accounts.get_mut(client).unwrap();
-                            account.hold(*amount)?;
+                            if held == amount {
+                                self.partial_holds.remove(&tx);
+                            } else {
+                                self.partial_holds.insert(tx, held);
+                            }
+                            for observer in self.observers.iter_mut() {
+                                observer.on_dispute_opened(client, tx, held);
+                            }
+                        }
+                        if action.is_some() {
+                            self.emit(RiskEvent::DisputeExceedsFunds {
+                                client,
+                                tx,
+                                amount: disputed_amount,
+                                available,
+                                held,
+                            });
                         }
-                        _ => {
-                            log::warn!("Invalid dispute on {:?}", tx);
+                        if rejected {
+                            let err = Error::DisputeExceedsFunds(tx);
+                            tracing::debug!(code = err.code(), "dispute exceeds funds");
+                            return Err(err);
                         }
                     }
-                }
-            }
-            Transaction::Resolve { tx, .. } => {
-                if let Some(transaction) = self.transactions.get_mut(&tx) {
-                    match transaction {
-                        Transaction::Deposit {
-                            client,
-                            tx,
-                            amount,
-                            disputed,
-                        } => {
-                            if !*disputed {
-                                log::warn!("No dispute on {:?}", tx);
-                                return Err(Error::InvalidTransaction);
-                            }
-                            *disputed = false;
-                            // should never happen since we already have an existing transaction.
-                            let account = self.accounts.get_mut(client).unwrap();
-                            account.release(*amount)?;
+                    Transaction::Resolve { .. } => {
+                        if !*disputed {
+                            let err = Error::InvalidTransaction;
+                            tracing::debug!(code = err.code(), "no dispute");
+                            return Err(err);
                         }
-                        _ => {
-                            log::warn!("Invalid dispute on {:?}", tx);
+                        let held = self.partial_holds.remove(&tx).unwrap_or(amount);
+                        account.release(held)?;
+                        if let Some(short) = self.shortfalls.remove(&tx) {
+                            account.settle_shortfall(short)?;
                         }
+                        *disputed = false;
                     }
-                }
-            }
-            Transaction::Chargeback { tx, .. } => {
-                if let Some(transaction) = self.transactions.get_mut(&tx) {
-                    match transaction {
-                        Transaction::Deposit {
+                    _ if sender.is_some() => {
+                        if !*disputed {
+                            let err = Error::InvalidTransaction;
+                            tracing::debug!(code = err.code(), "no dispute");
+                            return Err(err);
+                        }
+                        let held = self.partial_holds.remove(&tx).unwrap_or(amount);
+                        // What could not be held stays on the account as owed
+                        self.shortfalls.remove(&tx);
+                        let mut target = account.clone();
+                        target.chargeback(held)?;
+                        target.count_chargeback();
+                        let from = sender.expect("checked above");
+                        let mut source = self.accounts[&from].clone();
+                        source.deposit(held)?;
+                        // No funds left the ledger, so no account is locked
+                        self.accounts.insert(client, target);
+                        self.accounts.insert(from, source);
+                        *disputed = false;
+                        self.emit(RiskEvent::Chargeback {
                             client,
                             tx,
-                            amount,
-                            disputed,
-                        } => {
-                            if !*disputed {
-                                log::warn!("No dispute on {:?}", tx);
-                                return Err(Error::InvalidTransaction);
-                            }
-                            *disputed = false;
-                            // should never happen since we already have an existing transaction.
-                            let account = self.accounts.get_mut(client).unwrap();
-                            account.chargeback(*amount)?;
+                            amount: held,
+                        });
+                    }
+                    _ => {
+                        if !*disputed {
+                            let err = Error::InvalidTransaction;
+                            tracing::debug!(code = err.code(), "no dispute");
+                            return Err(err);
                         }
-                        _ => {
-                            log::warn!("Invalid dispute on {:?}", tx);
+                        let held = self.partial_holds.remove(&tx).unwrap_or(amount);
+                        // What could not be held stays on the account as owed
+                        let short = self.shortfalls.remove(&tx).unwrap_or_default();
+                        account.chargeback(held)?;
+                        self.charged_back.insert(tx, (held, short));
+                        account.count_chargeback();
+                        *disputed = false;
+                        let was_locked = account.is_locked();
+                        if policy.lock_on_chargeback(&transaction, account) {
+                            account.lock();
+                        }
+                        let locked = !was_locked && account.is_locked();
+                        self.emit(RiskEvent::Chargeback {
+                            client,
+                            tx,
+                            amount: held,
+                        });
+                        if locked {
+                            self.account_locked(client);
                         }
                     }
                 }
             }
+            Transaction::Reversal { client, ref_tx, .. } => {
+                let original = match self.transactions.get(&ref_tx) {
+                    Some(
+                        original @ (Transaction::Deposit { .. } | Transaction::Withdrawal { .. }),
+                    ) if original.client() == client => *original,
+                    _ => {
+                        let err = Error::InvalidTransaction;
+                        tracing::debug!(code = err.code(), "nothing to reverse");
+                        return Err(err);
+                    }
+                };
+                if matches!(original, Transaction::Deposit { disputed: true, .. }) {
+                    let err = Error::InvalidTransaction;
+                    tracing::debug!(code = err.code(), "reversal of a disputed deposit");
+                    return Err(err);
+                }
+                if self.reversed.contains(&ref_tx) {
+                    let err = Error::InvalidTransaction;
+                    tracing::debug!(code = err.code(), "reversed already");
+                    return Err(err);
+                }
+                if self.charged_back.contains_key(&ref_tx) {
+                    let err = Error::InvalidTransaction;
+                    tracing::debug!(code = err.code(), "reversal of a charged back deposit");
+                    return Err(err);
+                }
+                // should never happen since the account made the original transaction.
+                let account = self.accounts.get_mut(&client).unwrap();
+                match locked_action(policy, &transaction, account) {
+                    LockedAction::Apply => {}
+                    LockedAction::Reject | LockedAction::Hold => return Err(locked()),
+                    LockedAction::Review => return Err(self.queue_for_review(transaction)),
+                }
+                match original {
+                    Transaction::Deposit { amount, .. } => account.withdraw(amount)?,
+                    _ => account.deposit(original.amount().unwrap_or_default())?,
+                }
+                self.reversed.insert(ref_tx);
+            }
+            Transaction::ChargebackReversal { client, tx } => {
+                let charged = self.charged_back.get(&tx).filter(|_| {
+                    self.transactions.get(&tx).map(Transaction::client) == Some(client)
+                });
+                let Some(&(amount, short)) = charged else {
+                    let err = Error::InvalidTransaction;
+                    tracing::debug!(code = err.code(), "nothing charged back");
+                    return Err(err);
+                };
+                let mut account = self.accounts[&client].clone();
+                match locked_action(policy, &transaction, &account) {
+                    LockedAction::Apply => {}
+                    LockedAction::Reject | LockedAction::Hold => return Err(locked()),
+                    LockedAction::Review => return Err(self.queue_for_review(transaction)),
+                }
+                account.deposit(amount)?;
+                account.settle_shortfall(short)?;
+                if account.is_locked()
+                    && policy.unlock_on_chargeback_reversal(&transaction, &account)
+                {
+                    account.unlock();
+                    tracing::debug!("account unlocked");
+                }
+                self.accounts.insert(client, account);
+                self.charged_back.remove(&tx);
+            }
+            Transaction::Freeze { client, .. } => {
+                let account = self
+                    .accounts
+                    .entry(client)
+                    .or_insert_with(|| Account::new(client));
+                if account.is_locked() {
+                    let err = Error::InvalidTransaction;
+                    tracing::debug!(code = err.code(), "already locked");
+                    return Err(err);
+                }
+                account.lock();
+                self.account_locked(client);
+            }
+            Transaction::Reactivate { client, .. } => {
+                if !self.accounts.contains_key(&client) {
+                    let err = Error::InvalidTransaction;
+                    tracing::debug!(code = err.code(), "no account");
+                    return Err(err);
+                }
+                tracing::debug!("account reactivated");
+            }
+            Transaction::Unfreeze { client, .. } => self.unlock(client)?,
         }
         Ok(())
     }
 }
 
+/// A row of [`Transakt::write_transactions_csv`].
+#[derive(Serialize)]
+struct LedgerRow {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    client: ClientId,
+    tx: TransactionId,
+    amount: Option<Currency>,
+    disputed: bool,
+}
+
+/// An account in the report, with its plain columns if no optional feature is on.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum ReportEntry<'a> {
+    Extended(Box<ReportRow>),
+    Account(&'a Account),
+}
+
+/// A row of the account report, with the columns of optional features that are on.
+#[derive(Serialize)]
+struct ReportRow {
+    client: ClientId,
+    available: Currency,
+    held: Currency,
+    total: Currency,
+    locked: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    risk_score: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dormant: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    deposits: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    withdrawals: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    disputes_opened: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    disputes_resolved: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chargebacks: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rejected: Option<u64>,
+    /// Empty for accounts opened before metadata was kept.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    created_at: Option<Option<u64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_activity: Option<Option<u64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dispute_count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chargeback_count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    shortfall: Option<Currency>,
+}
+
+fn locked_action(
+    policy: &dyn ProcessingPolicy,
+    transaction: &Transaction,
+    account: &Account,
+) -> LockedAction {
+    if account.is_locked() {
+        policy.on_locked(transaction, account)
+    } else {
+        LockedAction::Apply
+    }
+}
+
+fn locked() -> Error {
+    let err = Error::AccountLocked;
+    tracing::debug!(code = err.code(), "locked account");
+    err
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::account::Account;
     use crate::currency::Currency;
-    use crate::transaction::{ClientId, Transaction, TransactionId};
-    use crate::Transakt;
+    use crate::event::RiskEvent;
+    use crate::notification::NotificationSink;
+    use crate::policy::{DisputeFundsAction, LockedAction, ProcessingPolicy, UnknownClientAction};
+    use crate::transaction::{ClientId, CsvOptions, Transaction, TransactionId};
+    use crate::{Error, Transakt, TransaktBuilder};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct CollectingSink {
+        events: Arc<Mutex<Vec<RiskEvent>>>,
+    }
+
+    impl NotificationSink for CollectingSink {
+        fn notify(&mut self, event: &RiskEvent) {
+            self.events.lock().unwrap().push(event.clone());
+        }
+    }
 
     #[test]
     fn execute_deposit() {
@@ -301,6 +1674,7 @@ mod tests {
             .execute_transaction(Transaction::Dispute {
                 client: ClientId::new(1),
                 tx: TransactionId::new(1),
+                amount: None,
             })
             .unwrap();
         // account 1 should have 1.0
@@ -343,6 +1717,7 @@ mod tests {
             .execute_transaction(Transaction::Dispute {
                 client: ClientId::new(1),
                 tx: TransactionId::new(1),
+                amount: None,
             })
             .unwrap();
         // account 1 should have 1.0
@@ -384,6 +1759,7 @@ mod tests {
             .execute_transaction(Transaction::Dispute {
                 client: ClientId::new(1),
                 tx: TransactionId::new(1),
+                amount: None,
             })
             .unwrap();
         // account 1 should have 1.0
@@ -405,4 +1781,733 @@ mod tests {
         assert_eq!(account.total(), Currency::new(0, 0).ok());
         assert!(account.is_locked());
     }
+
+    #[test]
+    fn notify_risk_events() {
+        let sink = CollectingSink::default();
+        let mut transakt = Transakt::builder()
+            .notification_sink(sink.clone())
+            .large_transaction_threshold(Currency::new(100, 0).unwrap())
+            .build();
+        let deposits = [
+            (1, Currency::new(2, 0).unwrap()),
+            (2, Currency::new(100, 0).unwrap()),
+        ];
+        for (tx, amount) in deposits.iter() {
+            transakt
+                .execute_transaction(Transaction::Deposit {
+                    client: ClientId::new(1),
+                    tx: TransactionId::new(*tx),
+                    amount: *amount,
+                    disputed: false,
+                })
+                .unwrap();
+        }
+        transakt
+            .execute_transaction(Transaction::Dispute {
+                client: ClientId::new(1),
+                tx: TransactionId::new(1),
+                amount: None,
+            })
+            .unwrap();
+        transakt
+            .execute_transaction(Transaction::Chargeback {
+                client: ClientId::new(1),
+                tx: TransactionId::new(1),
+            })
+            .unwrap();
+        let events = sink.events.lock().unwrap();
+        assert_eq!(
+            *events,
+            vec![
+                RiskEvent::LargeTransaction {
+                    client: ClientId::new(1),
+                    tx: TransactionId::new(2),
+                    amount: Currency::new(100, 0).unwrap(),
+                },
+                RiskEvent::Chargeback {
+                    client: ClientId::new(1),
+                    tx: TransactionId::new(1),
+                    amount: Currency::new(2, 0).unwrap(),
+                },
+                RiskEvent::AccountLocked {
+                    client: ClientId::new(1),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn client_filter() {
+        let mut transakt = Transakt::builder().clients(vec![ClientId::new(2)]).build();
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,2.0\n\
+                     deposit,2,2,1.5\n\
+                     dispute,1,1,\n\
+                     withdrawal,2,3,0.5\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        assert_eq!(transakt.skipped(), 2);
+        let accounts = transakt.get_accounts();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].client(), ClientId::new(2));
+        assert_eq!(accounts[0].available(), &Currency::new(1, 0).unwrap());
+    }
+
+    #[test]
+    fn row_range() {
+        let mut transakt = Transakt::builder().row_range(1, Some(2)).build();
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,not a number\n\
+                     deposit,1,2,1.5\n\
+                     deposit,1,3,2.0\n\
+                     deposit,1,4,4.0\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        let account = transakt.get_accounts_map().get(&ClientId::new(1)).unwrap();
+        assert_eq!(account.available(), &Currency::new(3, 5000).unwrap());
+    }
+
+    /// Keeps accounts open after a chargeback, but rejects disputes of unknown transactions.
+    struct LenientPolicy;
+
+    impl ProcessingPolicy for LenientPolicy {
+        fn unknown_reference(&self, _transaction: &Transaction) -> Result<(), Error> {
+            Err(Error::InvalidTransaction)
+        }
+
+        fn lock_on_chargeback(&self, _transaction: &Transaction, _account: &Account) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn custom_policy() {
+        let mut transakt = Transakt::builder().policy(LenientPolicy).build();
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,2.0\n\
+                     dispute,1,1,\n\
+                     chargeback,1,1,\n\
+                     deposit,1,2,1.5\n\
+                     dispute,1,9,\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        let account = transakt.get_accounts_map().get(&ClientId::new(1)).unwrap();
+        assert!(!account.is_locked());
+        assert_eq!(account.available(), &Currency::new(1, 5000).unwrap());
+        let rejected: Vec<&str> = transakt
+            .rejections()
+            .groups()
+            .map(|(code, _)| code)
+            .collect();
+        assert_eq!(rejected, vec!["E_INVALID_TX"]);
+    }
+
+    #[test]
+    fn locked_disputes() {
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,2.0\n\
+                     deposit,1,2,3.0\n\
+                     dispute,1,1,\n\
+                     dispute,1,2,\n\
+                     chargeback,1,1,\n\
+                     resolve,1,2,\n";
+        let run = |builder: TransaktBuilder| {
+            let mut transakt = builder.build();
+            transakt.execute_csv(input.as_bytes()).unwrap();
+            let account = transakt.get_accounts_map()[&ClientId::new(1)].clone();
+            (transakt, account)
+        };
+
+        // Settling a dispute opened before the lock goes through by default
+        let (_, account) = run(Transakt::builder());
+        assert!(account.is_locked());
+        assert_eq!(account.available(), &Currency::new(3, 0).unwrap());
+        assert_eq!(account.held(), &Currency::default());
+
+        let (transakt, account) = run(Transakt::builder().locked_disputes(LockedAction::Reject));
+        assert_eq!(account.available(), &Currency::default());
+        assert_eq!(account.held(), &Currency::new(3, 0).unwrap());
+        let rejected: Vec<&str> = transakt
+            .rejections()
+            .groups()
+            .map(|(code, _)| code)
+            .collect();
+        assert_eq!(rejected, vec!["E_LOCKED"]);
+
+        let (transakt, _) = run(Transakt::builder().locked_disputes(LockedAction::Review));
+        assert_eq!(transakt.review_queue().len(), 1);
+        assert_eq!(transakt.review_queue()[0].kind(), "resolve");
+    }
+
+    #[test]
+    fn locked_account_actions() {
+        let mut transakt = Transakt::builder()
+            .locked_accounts(LockedAction::Hold, LockedAction::Review)
+            .build();
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,2.0\n\
+                     deposit,1,2,3.0\n\
+                     dispute,1,1,\n\
+                     chargeback,1,1,\n\
+                     deposit,1,3,4.0\n\
+                     withdrawal,1,4,1.0\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        let account = transakt.get_accounts_map().get(&ClientId::new(1)).unwrap();
+        assert!(account.is_locked());
+        assert_eq!(account.available(), &Currency::new(3, 0).unwrap());
+        assert_eq!(account.held(), &Currency::new(4, 0).unwrap());
+        assert_eq!(transakt.review_queue().len(), 1);
+        assert_eq!(transakt.review_queue()[0].tx(), TransactionId::new(4));
+
+        let resolve = Transaction::Resolve {
+            client: ClientId::new(1),
+            tx: TransactionId::new(3),
+        };
+        transakt.execute_transaction(resolve).unwrap();
+        let account = transakt.get_accounts_map().get(&ClientId::new(1)).unwrap();
+        assert_eq!(account.available(), &Currency::new(7, 0).unwrap());
+        assert_eq!(transakt.take_review_queue().len(), 1);
+        assert!(transakt.review_queue().is_empty());
+    }
+
+    #[test]
+    fn unknown_clients() {
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,2.0\n\
+                     withdrawal,2,2,1.0\n\
+                     freeze,3,3,\n\
+                     withdrawal,1,4,1.0\n";
+        let mut transakt = Transakt::builder()
+            .unknown_clients(UnknownClientAction::Reject)
+            .locked_accounts(LockedAction::Reject, LockedAction::Review)
+            .build();
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        assert_eq!(transakt.get_accounts_map().len(), 1);
+        let rejected: Vec<(&str, Vec<u64>)> = transakt
+            .rejections()
+            .groups()
+            .map(|(code, group)| (code, group.lines.clone()))
+            .collect();
+        assert_eq!(rejected, vec![("E_UNKNOWN_CLIENT", vec![3, 4])]);
+
+        let sink = CollectingSink::default();
+        let mut transakt = Transakt::builder()
+            .notification_sink(sink.clone())
+            .unknown_clients(UnknownClientAction::Flag)
+            .build();
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        assert_eq!(transakt.get_accounts_map().len(), 3);
+        let events = sink.events.lock().unwrap();
+        assert_eq!(
+            events[0],
+            RiskEvent::UnknownClient {
+                client: ClientId::new(2),
+                tx: TransactionId::new(2),
+                kind: "withdrawal".to_string(),
+            }
+        );
+        assert_eq!(events.len(), 3);
+    }
+
+    #[test]
+    fn disputes_exceeding_funds() {
+        // Client 1 withdrew most of the deposit before disputing it
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,10.0\n\
+                     withdrawal,1,2,7.0\n\
+                     dispute,1,1,\n";
+        let run = |action| {
+            let sink = CollectingSink::default();
+            let mut builder = Transakt::builder()
+                .notification_sink(sink.clone())
+                .disputes_exceeding_funds(action);
+            // Holding more than the total is what verification flags
+            if action != DisputeFundsAction::AllowNegative {
+                builder = builder.verify();
+            }
+            let mut transakt = builder.build();
+            transakt.execute_csv(input.as_bytes()).unwrap();
+            let events = sink.events.lock().unwrap().clone();
+            (transakt, events)
+        };
+        let balances = |transakt: &Transakt| {
+            let account = &transakt.get_accounts_map()[&ClientId::new(1)];
+            (account.available().to_string(), account.held().to_string())
+        };
+
+        let (transakt, events) = run(DisputeFundsAction::AllowNegative);
+        assert_eq!(balances(&transakt), ("-7.0000".into(), "10.0000".into()));
+        assert_eq!(
+            events,
+            vec![RiskEvent::DisputeExceedsFunds {
+                client: ClientId::new(1),
+                tx: TransactionId::new(1),
+                amount: Currency::new(10, 0).unwrap(),
+                available: Currency::new(3, 0).unwrap(),
+                held: Currency::new(10, 0).unwrap(),
+            }]
+        );
+
+        let shortfall = |transakt: &Transakt| {
+            transakt.get_accounts_map()[&ClientId::new(1)]
+                .shortfall()
+                .to_string()
+        };
+        assert_eq!(shortfall(&transakt), "0.0000");
+
+        let (mut transakt, _) = run(DisputeFundsAction::HoldAvailable);
+        assert_eq!(balances(&transakt), ("0.0000".into(), "3.0000".into()));
+        assert_eq!(shortfall(&transakt), "7.0000");
+        let snapshot = transakt.snapshot();
+        let held = vec![(TransactionId::new(1), Currency::new(3, 0).unwrap())];
+        assert_eq!(snapshot.disputes(ClientId::new(1)), held);
+        // The partial hold and the shortfall survive a restore
+        let mut resolved = Transakt::default();
+        resolved.restore(snapshot.clone()).unwrap();
+        transakt.restore(snapshot).unwrap();
+        let input = "type,client,tx,amount\n\
+                     resolve,1,1,\n";
+        resolved.execute_csv(input.as_bytes()).unwrap();
+        assert_eq!(balances(&resolved), ("3.0000".into(), "0.0000".into()));
+        assert_eq!(shortfall(&resolved), "0.0000");
+        let input = "type,client,tx,amount\n\
+                     chargeback,1,1,\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        assert_eq!(balances(&transakt), ("0.0000".into(), "0.0000".into()));
+        // What could not be charged back is still owed
+        assert_eq!(shortfall(&transakt), "7.0000");
+
+        let (transakt, events) = run(DisputeFundsAction::Reject);
+        assert_eq!(balances(&transakt), ("3.0000".into(), "0.0000".into()));
+        let rejected: Vec<&str> = transakt
+            .rejections()
+            .groups()
+            .map(|(code, _)| code)
+            .collect();
+        assert_eq!(rejected, vec!["E_DISPUTE_FUNDS"]);
+        assert_eq!(events.len(), 1);
+    }
+
+    #[test]
+    fn partial_disputes() {
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,10.0\n\
+                     dispute,1,1,12.0\n\
+                     dispute,1,1,0\n\
+                     dispute,1,1,4.0\n\
+                     chargeback,1,1,\n";
+        let mut transakt = Transakt::builder().verify().build();
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        let account = &transakt.get_accounts_map()[&ClientId::new(1)];
+        assert_eq!(account.available(), &Currency::new(6, 0).unwrap());
+        assert_eq!(account.held(), &Currency::new(0, 0).unwrap());
+        assert!(account.is_locked());
+        assert_eq!(transakt.rejections().total(), 2);
+        // The chargeback keeps what it took, not the hold of a settled dispute
+        assert!(transakt.snapshot().partial_holds.is_empty());
+        let input = "type,client,tx,amount\n\
+                     chargeback_reversal,1,1,\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        let account = &transakt.get_accounts_map()[&ClientId::new(1)];
+        assert_eq!(account.available(), &Currency::new(10, 0).unwrap());
+
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,10.0\n\
+                     dispute,1,1,4.0\n";
+        let mut transakt = Transakt::read_from_reader(input.as_bytes()).unwrap();
+        assert_eq!(
+            transakt.open_disputes()[0].held,
+            Currency::new(4, 0).unwrap()
+        );
+        let input = "type,client,tx,amount\n\
+                     resolve,1,1,\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        let account = &transakt.get_accounts_map()[&ClientId::new(1)];
+        assert_eq!(account.available(), &Currency::new(10, 0).unwrap());
+        assert_eq!(account.held(), &Currency::new(0, 0).unwrap());
+        assert!(transakt.snapshot().partial_holds.is_empty());
+    }
+
+    #[test]
+    fn amount_cap() {
+        let mut transakt = Transakt::builder()
+            .max_amount(Currency::new(1000, 0).unwrap())
+            .build();
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,1000\n\
+                     deposit,1,2,1000.0001\n\
+                     withdrawal,1,3,99999999999\n\
+                     withdrawal,1,4,10\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        let rejected: Vec<(&str, Vec<u64>)> = transakt
+            .rejections()
+            .groups()
+            .map(|(code, group)| (code, group.lines.clone()))
+            .collect();
+        assert_eq!(rejected, vec![("E_AMOUNT_CAP", vec![3, 4])]);
+        let account = &transakt.get_accounts_map()[&ClientId::new(1)];
+        assert_eq!(account.available(), &Currency::new(990, 0).unwrap());
+    }
+
+    #[test]
+    fn freeze_unfreeze() {
+        let sink = CollectingSink::default();
+        let mut transakt = Transakt::builder().notification_sink(sink.clone()).build();
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,2.0\n\
+                     freeze,1,2,\n\
+                     deposit,1,3,1.0\n\
+                     freeze,1,4,\n\
+                     unfreeze,1,5,\n\
+                     deposit,1,6,1.0\n\
+                     freeze,2,7,\n\
+                     unfreeze,3,8,\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        let accounts = transakt.get_accounts_map();
+        let account = accounts.get(&ClientId::new(1)).unwrap();
+        assert!(!account.is_locked());
+        assert_eq!(account.available(), &Currency::new(3, 0).unwrap());
+        assert!(accounts.get(&ClientId::new(2)).unwrap().is_locked());
+        let rejected: Vec<(&str, u64)> = transakt
+            .rejections()
+            .groups()
+            .map(|(code, group)| (code, group.count))
+            .collect();
+        assert_eq!(rejected, vec![("E_INVALID_TX", 2), ("E_LOCKED", 1)]);
+        assert_eq!(sink.events.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn transfers() {
+        let sink = CollectingSink::default();
+        let mut transakt = Transakt::builder().notification_sink(sink.clone()).build();
+        let input = "type,client,tx,amount,to\n\
+                     deposit,1,1,5.0,\n\
+                     transfer,1,2,2.0,2\n\
+                     transfer,1,3,9.0,2\n\
+                     transfer,1,4,1.0,1\n\
+                     transfer,1,5,1.0,\n";
+        // The last row has no recipient
+        assert!(transakt.execute_csv(input.as_bytes()).is_err());
+        let balances = |transakt: &Transakt| -> Vec<(String, String)> {
+            transakt
+                .get_accounts()
+                .iter()
+                .map(|account| (account.available().to_string(), account.held().to_string()))
+                .collect()
+        };
+        assert_eq!(
+            balances(&transakt),
+            vec![
+                ("3.0000".into(), "0.0000".into()),
+                ("2.0000".into(), "0.0000".into())
+            ]
+        );
+        let rejected: Vec<(&str, Vec<u64>)> = transakt
+            .rejections()
+            .groups()
+            .map(|(code, group)| (code, group.lines.clone()))
+            .collect();
+        assert_eq!(
+            rejected,
+            vec![("E_FUNDS", vec![4]), ("E_INVALID_TX", vec![5])]
+        );
+
+        // Disputed, the funds are held by the recipient, and a chargeback returns them
+        let input = "type,client,tx,amount\n\
+                     dispute,1,2,\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        assert_eq!(
+            balances(&transakt),
+            vec![
+                ("3.0000".into(), "0.0000".into()),
+                ("0.0000".into(), "2.0000".into())
+            ]
+        );
+        assert_eq!(transakt.open_disputes()[0].client, ClientId::new(2));
+        let input = "type,client,tx,amount\n\
+                     chargeback,1,2,\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        assert_eq!(
+            balances(&transakt),
+            vec![
+                ("5.0000".into(), "0.0000".into()),
+                ("0.0000".into(), "0.0000".into())
+            ]
+        );
+        assert!(transakt.get_accounts().iter().all(|a| !a.is_locked()));
+        assert_eq!(sink.events.lock().unwrap().len(), 1);
+
+        // Nothing moves from or to a locked account
+        let input = "type,client,tx,amount,to\n\
+                     freeze,2,6,,\n\
+                     transfer,1,7,1.0,2\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        assert_eq!(transakt.get_accounts()[0].available().to_string(), "5.0000");
+    }
+
+    #[test]
+    fn reversals() {
+        let input = "type,client,tx,amount,ref_tx\n\
+                     deposit,1,1,5.0,\n\
+                     withdrawal,1,2,1.0,\n\
+                     deposit,1,3,2.0,\n\
+                     dispute,1,3,,\n\
+                     reversal,1,4,,2\n\
+                     reversal,1,5,,1\n\
+                     reversal,1,6,,3\n\
+                     reversal,2,7,,1\n";
+        let mut transakt = Transakt::builder().verify().build();
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        let account = &transakt.get_accounts_map()[&ClientId::new(1)];
+        // The withdrawal and the first deposit undone, the disputed deposit left held
+        assert_eq!(account.available(), &Currency::new(0, 0).unwrap());
+        assert_eq!(account.held(), &Currency::new(2, 0).unwrap());
+        let rejected: Vec<(&str, Vec<u64>)> = transakt
+            .rejections()
+            .groups()
+            .map(|(code, group)| (code, group.lines.clone()))
+            .collect();
+        assert_eq!(rejected, vec![("E_INVALID_TX", vec![8, 9])]);
+
+        // Reversed once, even after a restore, and no longer disputable
+        transakt.restore(transakt.snapshot()).unwrap();
+        let input = "type,client,tx,amount,ref_tx\n\
+                     reversal,1,8,,1\n\
+                     dispute,1,1,,\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        assert_eq!(transakt.rejections().total(), 4);
+        let mut out = vec![];
+        transakt.write_transactions_csv(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "type,client,tx,amount,disputed\ndeposit,1,3,2.0000,true\n"
+        );
+    }
+
+    #[test]
+    fn reversal_of_charged_back_deposit() {
+        let input = "type,client,tx,amount,ref_tx\n\
+                     deposit,1,1,5.0,\n\
+                     deposit,1,2,8.0,\n\
+                     dispute,1,1,,\n\
+                     chargeback,1,1,,\n\
+                     reversal,1,3,,1\n";
+        let mut transakt = Transakt::builder().verify().build();
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        // The chargeback took the funds already, so they are not withdrawn twice
+        let account = &transakt.get_accounts_map()[&ClientId::new(1)];
+        assert_eq!(account.available(), &Currency::new(8, 0).unwrap());
+        let rejected: Vec<(&str, Vec<u64>)> = transakt
+            .rejections()
+            .groups()
+            .map(|(code, group)| (code, group.lines.clone()))
+            .collect();
+        assert_eq!(rejected, vec![("E_INVALID_TX", vec![6])]);
+
+        // Once the chargeback is reversed, the deposit can be
+        let input = "type,client,tx,amount,ref_tx\n\
+                     chargeback_reversal,1,1,,\n\
+                     reversal,1,4,,1\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        let account = &transakt.get_accounts_map()[&ClientId::new(1)];
+        assert_eq!(account.available(), &Currency::new(8, 0).unwrap());
+        assert_eq!(transakt.rejections().total(), 1);
+    }
+
+    #[test]
+    fn chargeback_reversals() {
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,5.0\n\
+                     deposit,1,2,3.0\n\
+                     dispute,1,1,\n\
+                     chargeback,1,1,\n";
+        let mut transakt = Transakt::builder().verify().build();
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        let snapshot = transakt.snapshot();
+        let input = "type,client,tx,amount\n\
+                     chargeback_reversal,1,1,\n\
+                     chargeback_reversal,1,1,\n\
+                     chargeback_reversal,1,2,\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        let account = &transakt.get_accounts_map()[&ClientId::new(1)];
+        // Credited back once, and still locked by default
+        assert_eq!(account.available(), &Currency::new(8, 0).unwrap());
+        assert!(account.is_locked());
+        let rejected: Vec<(&str, Vec<u64>)> = transakt
+            .rejections()
+            .groups()
+            .map(|(code, group)| (code, group.lines.clone()))
+            .collect();
+        assert_eq!(rejected, vec![("E_INVALID_TX", vec![3, 4])]);
+
+        // The chargeback survives a restore, and the reversal can unlock the account
+        let mut transakt = Transakt::builder()
+            .verify()
+            .unlock_on_chargeback_reversal()
+            .build();
+        transakt.restore(snapshot).unwrap();
+        let reversal = Transaction::ChargebackReversal {
+            client: ClientId::new(1),
+            tx: TransactionId::new(1),
+        };
+        transakt.execute_transaction(reversal).unwrap();
+        let account = &transakt.get_accounts_map()[&ClientId::new(1)];
+        assert_eq!(account.available(), &Currency::new(8, 0).unwrap());
+        assert!(!account.is_locked());
+    }
+
+    #[test]
+    fn read_from_reader() {
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,2.0\n\
+                     withdrawal,1,2,0.5\n";
+        let transakt = Transakt::read_from_reader(input.as_bytes()).unwrap();
+        let account = &transakt.get_accounts_map()[&ClientId::new(1)];
+        assert_eq!(account.available(), &Currency::new(1, 5000).unwrap());
+        assert!(Transakt::read_from_reader(&b"type,client\nbogus,1\n"[..]).is_err());
+    }
+
+    #[test]
+    fn csv_options() {
+        let options = CsvOptions {
+            delimiter: ';',
+            quote: '\'',
+            trim: false,
+            decimal_separator: ',',
+            ..CsvOptions::default()
+        };
+        let mut transakt = Transakt::builder().csv_options(options.clone()).build();
+        let input = "type;client;tx;amount\n\
+                     deposit;1;1;'2,50'\n\
+                     withdrawal;1;2;0,25\n";
+        transakt.load_csv_from("input", input.as_bytes()).unwrap();
+        let account = &transakt.get_accounts_map()[&ClientId::new(1)];
+        assert_eq!(account.available(), &Currency::new(2, 2500).unwrap());
+
+        // Not trimmed, so the space makes the type unknown
+        let spaced = "type;client;tx;amount\ndeposit ;1;3;1\n";
+        assert!(transakt.load_csv_from("input", spaced.as_bytes()).is_err());
+        let invalid = CsvOptions {
+            delimiter: '→',
+            ..options
+        };
+        let mut transakt = Transakt::builder().csv_options(invalid).build();
+        assert!(matches!(
+            transakt.load_csv_from("input", input.as_bytes()),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn csv_columns() {
+        let columns = [
+            ("txn_type", "type"),
+            ("customer", "client"),
+            ("id", "tx"),
+            ("value", "amount"),
+        ];
+        let options = CsvOptions {
+            columns: columns
+                .iter()
+                .map(|(from, to)| (from.to_string(), to.to_string()))
+                .collect(),
+            decimal_separator: ',',
+            ..CsvOptions::default()
+        };
+        let mut transakt = Transakt::builder().csv_options(options).build();
+        let input = "txn_type,customer,id,value\n\
+                     deposit,1,1,\"2,5\"\n\
+                     withdrawal,1,2,1\n";
+        transakt.load_csv_from("input", input.as_bytes()).unwrap();
+        let account = &transakt.get_accounts_map()[&ClientId::new(1)];
+        assert_eq!(account.available(), &Currency::new(1, 5000).unwrap());
+    }
+
+    #[test]
+    fn write_accounts_csv() {
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,2.0\n\
+                     dispute,1,1,\n";
+        let transakt = Transakt::read_from_reader(input.as_bytes()).unwrap();
+        let mut out = vec![];
+        transakt.write_accounts_csv(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "client,available,held,total,locked\n1,0.0000,2.0000,2.0000,false\n"
+        );
+    }
+
+    #[test]
+    fn accounts_ordered_by_client() {
+        let mut input = String::from("type,client,tx,amount\n");
+        for client in (1..=50u16).rev() {
+            input.push_str(&format!("deposit,{},{},1.0\n", client, client));
+        }
+        let transakt = Transakt::read_from_reader(input.as_bytes()).unwrap();
+        let mut out = vec![];
+        transakt.write_accounts_csv(&mut out).unwrap();
+        let clients: Vec<u16> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .skip(1)
+            .map(|line| line.split(',').next().unwrap().parse().unwrap())
+            .collect();
+        assert_eq!(clients, (1..=50).collect::<Vec<u16>>());
+    }
+
+    #[test]
+    fn write_transactions_csv() {
+        let input = "type,client,tx,amount\n\
+                     deposit,1,2,2.0\n\
+                     deposit,1,1,1.0\n\
+                     withdrawal,1,3,0.5\n\
+                     withdrawal,1,4,9.0\n\
+                     dispute,1,2,\n";
+        let transakt = Transakt::read_from_reader(input.as_bytes()).unwrap();
+        let mut out = vec![];
+        transakt.write_transactions_csv(&mut out).unwrap();
+        // The rejected withdrawal isn't there
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "type,client,tx,amount,disputed\n\
+             deposit,1,1,1.0000,false\n\
+             deposit,1,2,2.0000,true\n\
+             withdrawal,1,3,0.5000,false\n"
+        );
+    }
+
+    #[test]
+    fn write_accounts_json() {
+        let input = "type,client,tx,amount\n\
+                     deposit,2,1,2.0\n\
+                     deposit,1,2,1.0\n\
+                     dispute,1,2,\n";
+        let transakt = Transakt::read_from_reader(input.as_bytes()).unwrap();
+        let mut out = vec![];
+        transakt.write_accounts_json(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "[{\"client\":1,\"available\":\"0.0000\",\"held\":\"1.0000\",\"total\":\"1.0000\",\
+             \"locked\":false},{\"client\":2,\"available\":\"2.0000\",\"held\":\"0.0000\",\
+             \"total\":\"2.0000\",\"locked\":false}]\n"
+        );
+    }
+
+    #[test]
+    fn read_from_csv_multi() {
+        let dir = std::env::temp_dir().join(format!("transakt-multi-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let january = dir.join("january.csv");
+        let february = dir.join("february.csv");
+        std::fs::write(&january, "type,client,tx,amount\ndeposit,1,1,5.0\n").unwrap();
+        std::fs::write(
+            &february,
+            "type,client,tx,amount\nwithdrawal,1,2,1.0\ndispute,1,1,\n",
+        )
+        .unwrap();
+        let transakt = Transakt::read_from_csv_multi(&[january, february]).unwrap();
+        let account = &transakt.get_accounts_map()[&ClientId::new(1)];
+        assert_eq!(account.available(), &Currency::new(-1, 0).unwrap());
+        assert_eq!(account.held(), &Currency::new(5, 0).unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
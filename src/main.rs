@@ -1,12 +1,1640 @@
-use std::path::Path;
-use transakt::Transakt;
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{BufRead, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::EnvFilter;
+use transakt::aml::{self, AmlConfig};
+use transakt::audit::{read_audit, verify_audit, AuditQuery, AuditWriter, Decision};
+use transakt::config::Config;
+use transakt::currency::Currency;
+use transakt::diff::{check_expectations, diff_accounts, load_accounts, read_expectations_csv};
+#[cfg(feature = "encryption")]
+use transakt::encryption::{decrypt_journal, StateCipher, StaticKey};
+use transakt::explain::EXPLANATIONS;
+use transakt::golden::check_golden;
+use transakt::journal::{JournalFormat, JournalWriter, ReplayLimit};
+use transakt::kyc::{self, KycConfig};
+use transakt::minimize::{minimize_csv_with, Outcome};
+use transakt::notification::LogSink;
+use transakt::profile::{CountingAllocator, Sample};
+use transakt::redact::{self, Redaction, RedactionMode};
+use transakt::rejections;
+use transakt::reload::ConfigReload;
+use transakt::replica::Replica;
+use transakt::scenario::Scenario;
+use transakt::serve::SnapshotPolicy;
+use transakt::shadow::Shadow;
+use transakt::snapshot::{AccountState, Snapshot};
+use transakt::transaction::{ClientId, TransactionId};
+use transakt::{Error, Transakt, TransaktBuilder};
+
+const EXIT_OK: i32 = 0;
+/// `validate` found problems, `diff` found differences, balances did not match `--assert`,
+/// `minimize` could not reproduce the outcome, or an audit log failed `audit --verify`.
+const EXIT_FINDINGS: i32 = 1;
+/// Malformed input, snapshot or journal, or invalid command line usage.
+const EXIT_PARSE: i32 = 2;
+/// A file could not be read or written.
+const EXIT_IO: i32 = 3;
+/// Processing would have left the ledger inconsistent.
+const EXIT_INVARIANT: i32 = 4;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[derive(Parser)]
+#[command(
+    name = "transakt",
+    about = "Processes transactions and prints account balances"
+)]
+#[command(
+    after_help = "Exit codes: 0 success, 1 problems, differences, assertion failures or no reproducer found, \
+2 malformed input or usage, 3 I/O error, 4 ledger invariant violated"
+)]
+struct Cli {
+    /// CSV files with the transactions to process, one after the other into the same ledger, `-`
+    /// for stdin
+    inputs: Vec<PathBuf>,
+
+    /// TOML file with engine, storage and output settings. TRANSAKT_* environment variables
+    /// override it, and command line options override both.
+    #[arg(long, global = true, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Start from the engine state saved in a snapshot file, instead of an empty ledger
+    #[arg(long, value_name = "SNAPSHOT")]
+    state_in: Option<PathBuf>,
+
+    /// Save the final engine state to a snapshot file
+    #[arg(long, value_name = "SNAPSHOT")]
+    state_out: Option<PathBuf>,
+
+    /// Append every applied transaction to this journal file
+    #[arg(long, value_name = "JOURNAL")]
+    journal: Option<PathBuf>,
+
+    /// Write the applied deposits and withdrawals, with whether they end up disputed, to this CSV
+    #[arg(long, value_name = "FILE")]
+    transactions_out: Option<PathBuf>,
+
+    /// Write a summary of the run, with the transactions applied and rejected by error code and
+    /// the funds of all accounts, to this JSON file
+    #[arg(long, value_name = "FILE")]
+    summary_out: Option<PathBuf>,
+
+    /// Write the rows that failed parsing or execution, with their line and reason, to this file,
+    /// as JSON if it ends in `.json` and CSV otherwise
+    #[arg(long, value_name = "FILE")]
+    rejected_out: Option<PathBuf>,
+
+    /// Append every decision, with its reason and the balances before and after, to this file
+    #[arg(long, value_name = "FILE")]
+    audit_log: Option<PathBuf>,
+
+    /// Write the suspicious activity detected by the `[aml]` rules to this file, as JSON lines
+    #[arg(long, value_name = "FILE")]
+    sar_report: Option<PathBuf>,
+
+    /// Only process the transactions of these clients, skipping the rest
+    #[arg(long, value_name = "ID", value_delimiter = ',')]
+    client: Vec<u16>,
+
+    /// Skip this many data rows of each input before processing
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    skip: u64,
+
+    /// Process at most this many data rows of each input
+    #[arg(long, value_name = "M")]
+    limit: Option<u64>,
+
+    /// Format of the input [default: from the file extension, csv for others]
+    #[arg(long, value_enum)]
+    input_format: Option<InputFormat>,
+
+    /// Table of a SQLite input that holds the transactions
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "NAME", default_value = "transactions")]
+    table: String,
+
+    /// Format of the account report
+    #[arg(long, value_enum, default_value = "csv")]
+    format: ReportFormat,
+
+    /// CSV of expected balances per client; fail if the results differ
+    #[arg(long = "assert", value_name = "EXPECTED")]
+    expected: Option<PathBuf>,
+
+    /// Show a live dashboard while processing
+    #[cfg(feature = "tui")]
+    #[arg(long)]
+    tui: bool,
+
+    /// Check ledger invariants after every transaction and stop at the first row breaking one
+    #[arg(long)]
+    verify: bool,
+
+    /// Print the time and allocations spent reading, parsing, executing and reporting to stderr
+    #[arg(long)]
+    profile: bool,
+
+    /// Only report errors, not warnings about rejected rows or risk events
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Report more: -v for the outcome of every transaction, -vv for debugging output
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Format of the diagnostics written to stderr [default: text]
+    #[arg(long, global = true, value_enum)]
+    log_format: Option<LogFormat>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ReportFormat {
+    Csv,
+    /// An array of account objects, ordered by client
+    Json,
+    /// An Avro object container file, ordered by client
+    #[cfg(feature = "avro")]
+    Avro,
+    /// An HTML page with a summary of the run, ordered by client
+    #[cfg(feature = "report-html")]
+    Html,
+    /// An XLSX workbook with the balances, ordered by client, and the rejections
+    #[cfg(feature = "xlsx")]
+    Xlsx,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum InputFormat {
+    Csv,
+    /// One JSON object per line
+    Jsonl,
+    /// An Avro object container file
+    #[cfg(feature = "avro")]
+    Avro,
+    /// Records laid out as the `[fixed_width]` section of the configuration says
+    FixedWidth,
+    /// Length-prefixed ISO 8583 messages
+    #[cfg(feature = "iso8583")]
+    Iso8583,
+    /// An ISO 20022 pain.001 or camt.053 document
+    #[cfg(feature = "iso20022")]
+    Iso20022,
+    /// A table of a SQLite database, see --table
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum LogFormat {
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Table,
+    Json,
+}
+
+#[derive(Subcommand)]
+enum Report {
+    /// List the deposits under an open dispute, with what they hold
+    Disputes {
+        snapshot: PathBuf,
+        /// Clients to show, all of them if not given
+        #[arg(long, value_name = "ID", value_delimiter = ',')]
+        client: Vec<u16>,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum StatementFormat {
+    Text,
+    Csv,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Check an input file for problems without executing it
+    Validate { input: PathBuf },
+    /// Profile an input file without executing it
+    Stats { input: PathBuf },
+    /// Describe an error code: what it means, common causes and how the row was handled
+    Explain { code: String },
+    /// Shrink an input to the fewest rows that still end with an outcome, and print them
+    #[command(group(ArgGroup::new("outcome").required(true)))]
+    Minimize {
+        input: PathBuf,
+        /// The client's account ends locked
+        #[arg(long, value_name = "CLIENT", group = "outcome")]
+        locked: Option<u16>,
+        /// Processing stops with this error code
+        #[arg(long, value_name = "CODE", group = "outcome")]
+        fails_with: Option<String>,
+        /// Some row is rejected with this error code
+        #[arg(long, value_name = "CODE", group = "outcome")]
+        rejects: Option<String>,
+    },
+    /// Run scenario files and check their expectations, see src/scenario.rs
+    Scenario {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Run an input through the configured engine and a shadow engine with another configuration,
+    /// and report the first row they disagree on. Nothing is journaled or sent to webhooks
+    Shadow {
+        input: PathBuf,
+        /// Configuration of the shadow engine. Environment variables override it too
+        #[arg(long, value_name = "FILE")]
+        shadow_config: PathBuf,
+    },
+    /// Run every CSV input of a directory and compare the account reports with the `.golden`
+    /// files next to them. Nothing is journaled or sent to webhooks
+    Golden {
+        dir: PathBuf,
+        /// Write the golden files with the current outputs instead of comparing
+        #[arg(long)]
+        update: bool,
+    },
+    /// Print summary information about a saved snapshot
+    Inspect { snapshot: PathBuf },
+    /// Process a CSV stream until it ends, saving snapshots periodically
+    Serve {
+        /// Input stream, stdin if not given
+        input: Option<PathBuf>,
+        /// Directory for the snapshots; processing resumes from the latest one in it
+        #[arg(long, value_name = "DIR")]
+        snapshot_dir: PathBuf,
+        /// How often to save a snapshot, like `90s`, `5m` or `1h`
+        #[arg(long, value_name = "INTERVAL", default_value = "5m", value_parser = parse_interval)]
+        snapshot_every: Duration,
+        /// How many snapshots to retain
+        #[arg(long, value_name = "N", default_value_t = 10)]
+        keep: usize,
+    },
+    /// Answer balance queries from the latest snapshot of a `serve` snapshot directory, switching
+    /// to newer ones as they are saved. Reads one query per line from stdin: client ids separated
+    /// by commas, or an empty line for all clients
+    Replica {
+        /// Snapshot directory of the `serve` process
+        #[arg(long, value_name = "DIR")]
+        snapshot_dir: PathBuf,
+        /// How often to look for a newer snapshot, like `90s`, `5m` or `1h`
+        #[arg(long, value_name = "INTERVAL", default_value = "30s", value_parser = parse_interval)]
+        refresh_every: Duration,
+        /// Also list the transactions under an open dispute
+        #[arg(long)]
+        disputes: bool,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+    /// Look up client balances in a snapshot
+    Accounts {
+        snapshot: PathBuf,
+        /// Clients to show, all of them if not given
+        #[arg(long, value_name = "ID", value_delimiter = ',')]
+        client: Vec<u16>,
+        /// Also list the transactions under an open dispute
+        #[arg(long)]
+        disputes: bool,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+    /// Print the journaled transactions of a client with running balances
+    History {
+        client: u16,
+        #[arg(long, value_name = "JOURNAL")]
+        journal: PathBuf,
+        /// Start from this snapshot, showing only the journal entries after it
+        #[arg(long, value_name = "SNAPSHOT")]
+        snapshot: Option<PathBuf>,
+        /// Stop at the last entry recorded at or before this time, in seconds since the epoch, so
+        /// the last row has the balances at that time
+        #[arg(long, value_name = "TIMESTAMP")]
+        as_of: Option<u64>,
+    },
+    /// Print the statement of a client: their deposits, withdrawals and disputes with running
+    /// balances, from the journal
+    Statement {
+        client: u16,
+        #[arg(long, value_name = "JOURNAL")]
+        journal: PathBuf,
+        /// Start from the balances of this snapshot, covering only the journal entries after it
+        #[arg(long, value_name = "SNAPSHOT")]
+        snapshot: Option<PathBuf>,
+        #[arg(long, value_enum, default_value_t = StatementFormat::Text)]
+        format: StatementFormat,
+    },
+    /// Print a report on a saved state
+    Report {
+        #[command(subcommand)]
+        report: Report,
+    },
+    /// Compare the balances in two account reports or snapshots
+    Diff { left: PathBuf, right: PathBuf },
+    /// Print the records of an audit log matching all the given filters, as JSON lines
+    Audit {
+        log: PathBuf,
+        #[arg(long, value_name = "ID")]
+        client: Option<u16>,
+        /// Only applied transactions
+        #[arg(long, conflicts_with = "rejected")]
+        applied: bool,
+        /// Only rejected transactions
+        #[arg(long)]
+        rejected: bool,
+        /// Only rejections with this error code
+        #[arg(long, value_name = "CODE")]
+        code: Option<String>,
+        /// Check the hash chain of the log instead of printing records
+        #[arg(long, conflicts_with_all = ["client", "applied", "rejected", "code"])]
+        verify: bool,
+    },
+    /// Rebuild the state from a journal and print the account balances
+    Replay {
+        journal: PathBuf,
+        /// Stop after the entry with this sequence number
+        #[arg(long, value_name = "SEQ")]
+        until_seq: Option<u64>,
+        /// Stop after the last entry recorded at or before this time, in seconds since the epoch
+        #[arg(long, value_name = "TIMESTAMP")]
+        until: Option<u64>,
+    },
+    /// Write the final balances and the transactions of a journal as Parquet files
+    #[cfg(feature = "parquet")]
+    #[command(group(ArgGroup::new("output").required(true).multiple(true)))]
+    Export {
+        journal: PathBuf,
+        /// Parquet file for the balances of the accounts the journal leaves behind
+        #[arg(long, value_name = "FILE", group = "output")]
+        accounts: Option<PathBuf>,
+        /// Parquet file for the journaled transactions
+        #[arg(long, value_name = "FILE", group = "output")]
+        transactions: Option<PathBuf>,
+    },
+}
 
 fn main() {
-    env_logger::init();
-    let filename = std::env::args()
-        .nth(1)
-        .expect("Usage: cargo run -- <input_file>");
-    let filepath = Path::new(&filename);
-    let transakt = Transakt::read_from_csv(filepath).unwrap();
-    transakt.print_csv();
+    let cli = Cli::parse();
+    let config = load_config(&cli);
+    if let Ok(config) = &config {
+        redact::init(Redaction {
+            mode: config.output.redaction.clone(),
+            key: config.output.redaction_key.clone().unwrap_or_default(),
+        });
+    }
+    init_logging(&cli, config.as_ref().ok());
+    let res = config.and_then(|config| match (&cli.command, cli.inputs.as_slice()) {
+        (Some(Command::Validate { input }), _) => validate(input, &config),
+        (Some(Command::Stats { input }), _) => stats(input, &config),
+        (
+            Some(Command::Minimize {
+                input,
+                locked,
+                fails_with,
+                rejects,
+            }),
+            _,
+        ) => {
+            let outcome = match (locked, fails_with, rejects) {
+                (Some(client), _, _) => Outcome::Locked(ClientId::new(*client)),
+                (_, Some(code), _) => Outcome::FailsWith(code.clone()),
+                (_, _, Some(code)) => Outcome::Rejects(code.clone()),
+                // Enforced by the `outcome` argument group
+                (None, None, None) => unreachable!(),
+            };
+            minimize(input, &outcome, &config)
+        }
+        (
+            Some(Command::Serve {
+                input,
+                snapshot_dir,
+                snapshot_every,
+                keep,
+            }),
+            _,
+        ) => serve(
+            input.as_deref(),
+            &SnapshotPolicy {
+                dir: snapshot_dir.clone(),
+                every: *snapshot_every,
+                keep: *keep,
+                #[cfg(feature = "encryption")]
+                cipher: state_cipher(&config)?,
+            },
+            cli.config.as_deref().map(ConfigReload::new).as_ref(),
+            &config,
+        ),
+        (Some(Command::Explain { code }), _) => explain(code),
+        (Some(Command::Inspect { snapshot }), _) => inspect(snapshot, &config),
+        (
+            Some(Command::Accounts {
+                snapshot,
+                client,
+                disputes,
+                format,
+            }),
+            _,
+        ) => accounts(snapshot, client, *disputes, *format, &config),
+        (
+            Some(Command::Replica {
+                snapshot_dir,
+                refresh_every,
+                disputes,
+                format,
+            }),
+            _,
+        ) => replica(
+            SnapshotPolicy {
+                #[cfg(feature = "encryption")]
+                cipher: state_cipher(&config)?,
+                ..SnapshotPolicy::new(snapshot_dir)
+            },
+            *refresh_every,
+            *disputes,
+            *format,
+        ),
+        (
+            Some(Command::History {
+                client,
+                journal,
+                snapshot,
+                as_of,
+            }),
+            _,
+        ) => history(
+            ClientId::new(*client),
+            journal,
+            snapshot.as_deref(),
+            *as_of,
+            &config,
+        ),
+        (
+            Some(Command::Statement {
+                client,
+                journal,
+                snapshot,
+                format,
+            }),
+            _,
+        ) => statement(
+            ClientId::new(*client),
+            journal,
+            snapshot.as_deref(),
+            *format,
+            &config,
+        ),
+        (
+            Some(Command::Report {
+                report:
+                    Report::Disputes {
+                        snapshot,
+                        client,
+                        format,
+                    },
+            }),
+            _,
+        ) => disputes_report(snapshot, client, *format, &config),
+        (Some(Command::Diff { left, right }), _) => diff(left, right),
+        (Some(Command::Scenario { files }), _) => scenarios(files, &cli, &config),
+        (
+            Some(Command::Shadow {
+                input,
+                shadow_config,
+            }),
+            _,
+        ) => shadow(input, shadow_config, &config),
+        (Some(Command::Golden { dir, update }), _) => golden(dir, *update, &cli, &config),
+        (
+            Some(Command::Audit {
+                log,
+                client,
+                applied,
+                rejected,
+                code,
+                verify,
+            }),
+            _,
+        ) => {
+            if *verify {
+                return verify_audit_log(log);
+            }
+            let decision = match (applied, rejected) {
+                (true, _) => Some(Decision::Applied),
+                (_, true) => Some(Decision::Rejected),
+                _ => None,
+            };
+            let query = AuditQuery {
+                client: client.map(ClientId::new),
+                decision,
+                code: code.clone(),
+            };
+            audit(log, &query)
+        }
+        (
+            Some(Command::Replay {
+                journal,
+                until_seq,
+                until,
+            }),
+            _,
+        ) => replay(
+            journal,
+            &ReplayLimit {
+                until_seq: *until_seq,
+                until_time: *until,
+            },
+            &config,
+        ),
+        #[cfg(feature = "parquet")]
+        (
+            Some(Command::Export {
+                journal,
+                accounts,
+                transactions,
+            }),
+            _,
+        ) => export(
+            journal,
+            accounts.as_deref(),
+            transactions.as_deref(),
+            &config,
+        ),
+        (None, []) => {
+            eprintln!("Usage: transakt <input_file>, or - for stdin, see --help");
+            Ok(EXIT_PARSE)
+        }
+        (None, inputs) => process(inputs, &cli, &config),
+    });
+    match res {
+        Ok(code) => std::process::exit(code),
+        Err(err) => {
+            tracing::error!(code = err.code(), error = ?err, "processing failed");
+            std::process::exit(exit_code(&err));
+        }
+    }
+}
+
+fn exit_code(err: &Error) -> i32 {
+    match err {
+        Error::TransactionParseError
+        | Error::InvalidSnapshot(_)
+        | Error::InvalidConfig(_)
+        | Error::InvalidScenario(_) => EXIT_PARSE,
+        Error::Io(_) => EXIT_IO,
+        Error::InvariantViolation(_) => EXIT_INVARIANT,
+        // Rejections of single transactions never end a run, so getting one here is a bug too
+        Error::InsufficientHeldFunds
+        | Error::DuplicateTransaction(_)
+        | Error::Overflow
+        | Error::AccountLocked
+        | Error::InsufficientFunds
+        | Error::InvalidTransaction
+        | Error::PendingReview
+        | Error::RuleViolation(_)
+        | Error::Rejected(_)
+        | Error::Denied(_)
+        | Error::ScreeningHit(_)
+        | Error::LimitExceeded(_)
+        | Error::Dormant(_)
+        | Error::InvalidSignature(_)
+        | Error::UnknownClient(_)
+        | Error::DisputeExceedsFunds(_)
+        | Error::AmountOverCap(_) => EXIT_INVARIANT,
+    }
+}
+
+fn load_config(cli: &Cli) -> Result<Config, Error> {
+    let mut config = match &cli.config {
+        Some(path) => Config::load(path)?,
+        None => Config::default(),
+    };
+    config.apply_env()?;
+    if let Some(format) = &config.output.log_format {
+        LogFormat::from_str(format, true)
+            .map_err(|_| Error::InvalidConfig(format!("unknown log format `{}`", format)))?;
+    }
+    if config.output.redaction == RedactionMode::Hash && config.output.redaction_key.is_none() {
+        return Err(Error::InvalidConfig(
+            "`hash` redaction needs a redaction key".to_string(),
+        ));
+    }
+    if cfg!(not(feature = "encryption")) && config.storage.state_key.is_some() {
+        return Err(Error::InvalidConfig(
+            "built without encryption support, can't use a state key".to_string(),
+        ));
+    }
+    if let Some(journal) = &cli.journal {
+        config.storage.journal = Some(journal.clone());
+    }
+    if let Some(audit_log) = &cli.audit_log {
+        config.storage.audit_log = Some(audit_log.clone());
+    }
+    if let Some(sar_report) = &cli.sar_report {
+        config.storage.sar_report = Some(sar_report.clone());
+    }
+    if let Some(state_in) = &cli.state_in {
+        config.storage.state_in = Some(state_in.clone());
+    }
+    if let Some(state_out) = &cli.state_out {
+        config.storage.state_out = Some(state_out.clone());
+    }
+    Ok(config)
+}
+
+/// `RUST_LOG` directives, if set, take precedence over the verbosity flags.
+fn init_logging(cli: &Cli, config: Option<&Config>) {
+    // Rejections are shown on the dashboard, and log lines would draw over it
+    let quiet = cli.quiet || dashboard(cli);
+    let level = match (quiet, cli.verbose) {
+        (true, _) => LevelFilter::ERROR,
+        (false, 0) => LevelFilter::WARN,
+        (false, 1) => LevelFilter::INFO,
+        (false, _) => LevelFilter::DEBUG,
+    };
+    let filter = EnvFilter::builder()
+        .with_default_directive(level.into())
+        .from_env_lossy();
+    // stdout is reserved for the account report, so all diagnostics go to stderr.
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+    let format = cli.log_format.or_else(|| {
+        let format = config?.output.log_format.as_deref()?;
+        LogFormat::from_str(format, true).ok()
+    });
+    match format.unwrap_or(LogFormat::Text) {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().init(),
+    }
+}
+
+/// Engine with the sinks, policies and storage of the configuration.
+fn engine_builder(config: &Config) -> Result<TransaktBuilder, Error> {
+    let mut builder = Transakt::builder().notification_sink(LogSink);
+    if let Some(webhook) = &config.webhook {
+        #[cfg(feature = "webhook")]
+        {
+            builder = builder.webhook(webhook.into());
+        }
+        #[cfg(not(feature = "webhook"))]
+        tracing::warn!(url = %webhook.url, "built without webhook support, ignoring webhook");
+    }
+    if let Some(threshold) = config.engine.large_transaction_threshold {
+        builder = builder.large_transaction_threshold(threshold);
+    }
+    if let Some(cap) = config.engine.max_amount {
+        builder = builder.max_amount(cap);
+    }
+    if let Some(action) = config.engine.unknown_clients {
+        builder = builder.unknown_clients(action);
+    }
+    if let Some(action) = config.engine.disputes_exceeding_funds {
+        builder = builder.disputes_exceeding_funds(action);
+    }
+    if let Some(action) = config.engine.locked_disputes {
+        builder = builder.locked_disputes(action);
+    }
+    if config.engine.unlock_on_chargeback_reversal {
+        builder = builder.unlock_on_chargeback_reversal();
+    }
+    if config.engine.balance_history {
+        builder = builder.balance_history();
+    }
+    builder = builder.csv_options(config.csv.clone());
+    if let Some(journal) = &config.storage.journal {
+        builder = builder.journal(open_journal(journal, config)?);
+    }
+    if let Some(audit_log) = &config.storage.audit_log {
+        builder = builder.audit_log(AuditWriter::open(audit_log)?);
+    }
+    if !config.rules.is_empty() {
+        builder = builder.rules(config.rules.clone());
+    }
+    if let Some(dormancy) = &config.dormancy {
+        builder = builder.dormancy(dormancy.clone());
+    }
+    if let Some(weights) = &config.risk {
+        builder = builder.risk_scoring(weights.clone());
+    }
+    if config.output.client_stats {
+        builder = builder.report_client_stats();
+    }
+    if config.output.account_metadata {
+        builder = builder.account_metadata();
+    }
+    if config.kyc != KycConfig::default() {
+        let tiers = match &config.kyc.tiers_file {
+            Some(path) => kyc::load_tiers(path)?,
+            None => HashMap::new(),
+        };
+        builder = builder.kyc(config.kyc.clone(), tiers);
+    }
+    if config.aml != AmlConfig::default() {
+        builder = builder.aml(config.aml.clone());
+    }
+    if !config.denylist.is_empty() {
+        builder = builder.denylist(config.denylist.clone());
+    }
+    if !config.signatures.is_empty() {
+        builder = builder.signatures(config.signatures.clone());
+    }
+    Ok(builder)
+}
+
+fn process(inputs: &[PathBuf], cli: &Cli, config: &Config) -> Result<i32, Error> {
+    // Read before processing, so a bad file doesn't waste a long run
+    let expected = match &cli.expected {
+        Some(path) => {
+            let file = std::fs::File::open(path).map_err(Error::Io)?;
+            Some(read_expectations_csv(file)?)
+        }
+        None => None,
+    };
+    let mut builder = engine_builder(config)?;
+    if !cli.client.is_empty() {
+        builder = builder.clients(cli.client.iter().copied().map(ClientId::new));
+    }
+    if cli.skip > 0 || cli.limit.is_some() {
+        builder = builder.row_range(cli.skip, cli.limit);
+    }
+    if cli.profile {
+        CountingAllocator::start();
+        builder = builder.profile();
+    }
+    if cli.verify {
+        builder = builder.verify();
+    }
+    if cli.rejected_out.is_some() {
+        builder = builder.rejected_rows();
+    }
+    let mut transakt = builder.build();
+    if let Some(state_in) = &config.storage.state_in {
+        transakt.restore(load_snapshot(state_in, config)?)?;
+    }
+    let loaded = inputs
+        .iter()
+        .try_for_each(|input| load_input(&mut transakt, input, cli, config));
+    // Also when an input is malformed, which is when the report is most wanted
+    if let Some(path) = &cli.rejected_out {
+        let file = std::io::BufWriter::new(std::fs::File::create(path).map_err(Error::Io)?);
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => rejections::write_rejected_json(transakt.rejected_rows(), file)?,
+            _ => rejections::write_rejected_csv(transakt.rejected_rows(), file)?,
+        }
+    }
+    loaded?;
+    transakt.rejections().log();
+    if transakt.skipped() > 0 {
+        tracing::info!(
+            skipped = transakt.skipped(),
+            "skipped transactions of other clients"
+        );
+    }
+    let report_start = Sample::now();
+    match cli.format {
+        ReportFormat::Csv => transakt.write_accounts_csv(std::io::stdout().lock())?,
+        ReportFormat::Json => transakt.write_accounts_json(std::io::stdout().lock())?,
+        #[cfg(feature = "avro")]
+        ReportFormat::Avro => transakt.write_accounts_avro(std::io::stdout().lock())?,
+        #[cfg(feature = "report-html")]
+        ReportFormat::Html => transakt.write_accounts_html(std::io::stdout().lock())?,
+        #[cfg(feature = "xlsx")]
+        ReportFormat::Xlsx => transakt.write_accounts_xlsx(std::io::stdout().lock())?,
+    }
+    if let Some(mut profile) = transakt.profile().cloned() {
+        profile.report.add_since(report_start);
+        eprintln!("{}", profile);
+    }
+    if let Some(state_out) = &config.storage.state_out {
+        save_snapshot(&transakt.snapshot(), state_out, config)?;
+    }
+    if let Some(path) = &cli.transactions_out {
+        let file = std::fs::File::create(path).map_err(Error::Io)?;
+        transakt.write_transactions_csv(std::io::BufWriter::new(file))?;
+    }
+    if let Some(path) = &cli.summary_out {
+        let file = std::fs::File::create(path).map_err(Error::Io)?;
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), &transakt.summary())
+            .map_err(|err| Error::Io(err.into()))?;
+    }
+    if let Some(sar_report) = &config.storage.sar_report {
+        let file = std::fs::File::create(sar_report).map_err(Error::Io)?;
+        aml::write_report(
+            transakt.suspicious_activity(),
+            std::io::BufWriter::new(file),
+        )?;
+    }
+    if let Some(expected) = expected {
+        let mismatches = check_expectations(&transakt.snapshot().accounts, &expected);
+        for mismatch in mismatches.iter() {
+            eprintln!("{}", mismatch);
+        }
+        if !mismatches.is_empty() {
+            return Ok(EXIT_FINDINGS);
+        }
+    }
+    Ok(EXIT_OK)
+}
+
+/// Executes the transactions of an input file, or stdin, on top of the state so far.
+fn load_input(
+    transakt: &mut Transakt,
+    filepath: &Path,
+    cli: &Cli,
+    config: &Config,
+) -> Result<(), Error> {
+    let format = cli.input_format.unwrap_or_else(|| {
+        match filepath
+            .extension()
+            .and_then(|extension| extension.to_str())
+        {
+            Some("jsonl" | "ndjson") => InputFormat::Jsonl,
+            #[cfg(feature = "avro")]
+            Some("avro") => InputFormat::Avro,
+            #[cfg(feature = "iso20022")]
+            Some("xml") => InputFormat::Iso20022,
+            #[cfg(feature = "sqlite")]
+            Some("db" | "sqlite" | "sqlite3") => InputFormat::Sqlite,
+            _ => InputFormat::Csv,
+        }
+    });
+    if format != InputFormat::Csv && dashboard(cli) {
+        return Err(Error::InvalidConfig(
+            "the dashboard reads CSV input only".to_string(),
+        ));
+    }
+    let layout = config.fixed_width.as_ref();
+    if format == InputFormat::FixedWidth && layout.is_none() {
+        return Err(Error::InvalidConfig(
+            "fixed-width input needs a [fixed_width] layout".to_string(),
+        ));
+    }
+    // Opened by SQLite itself
+    #[cfg(feature = "sqlite")]
+    if format == InputFormat::Sqlite {
+        if filepath == Path::new("-") {
+            return Err(Error::InvalidConfig(
+                "SQLite input can't be read from stdin".to_string(),
+            ));
+        }
+        return transakt.load_sqlite_from(filepath, &cli.table);
+    }
+    let (source, file) = if filepath == Path::new("-") {
+        ("stdin".to_string(), None)
+    } else {
+        let file = std::fs::File::open(filepath).map_err(Error::Io)?;
+        (
+            filepath.display().to_string(),
+            Some(std::io::BufReader::new(file)),
+        )
+    };
+    #[cfg(feature = "compression")]
+    let mut file = file;
+    #[cfg(feature = "compression")]
+    let compressed = match file.as_mut() {
+        Some(file) => {
+            let header = file.fill_buf().map_err(Error::Io)?;
+            transakt::compression::Compression::detect(header).is_some()
+        }
+        None => false,
+    };
+    let bar = match config.output.progress {
+        _ if dashboard(cli) => None,
+        Some(false) => None,
+        // The progress of the compressed bytes says nothing about the rows
+        #[cfg(feature = "compression")]
+        _ if compressed => None,
+        // The progress counts lines as rows
+        #[cfg(feature = "avro")]
+        _ if format == InputFormat::Avro => None,
+        #[cfg(feature = "iso8583")]
+        _ if format == InputFormat::Iso8583 => None,
+        #[cfg(feature = "iso20022")]
+        _ if format == InputFormat::Iso20022 => None,
+        _ => file.as_ref().and_then(|file| progress_bar(file.get_ref())),
+    };
+    let file: Box<dyn Read> = match file {
+        Some(file) => Box::new(file),
+        None => Box::new(std::io::stdin().lock()),
+    };
+    #[cfg(feature = "compression")]
+    let file = transakt::compression::decompressed(std::io::BufReader::new(file))?;
+    let load = |transakt: &mut Transakt, reader: &mut dyn Read| match format {
+        InputFormat::Csv => transakt.load_csv_from(&source, reader),
+        InputFormat::Jsonl => transakt.load_jsonl_from(&source, reader),
+        #[cfg(feature = "avro")]
+        InputFormat::Avro => transakt.load_avro_from(&source, reader),
+        InputFormat::FixedWidth => {
+            let layout = layout.expect("checked above");
+            transakt.load_fixed_width_from(&source, reader, layout)
+        }
+        #[cfg(feature = "iso8583")]
+        InputFormat::Iso8583 => transakt.load_iso8583_from(&source, reader),
+        #[cfg(feature = "iso20022")]
+        InputFormat::Iso20022 => transakt.load_iso20022_from(&source, reader),
+        #[cfg(feature = "sqlite")]
+        InputFormat::Sqlite => unreachable!("read from the file above"),
+    };
+    if dashboard(cli) {
+        #[cfg(feature = "tui")]
+        transakt.load_csv_with_dashboard(&source, file)?;
+    } else if let Some(bar) = bar {
+        let mut reader = ProgressReader {
+            inner: file,
+            bar: bar.clone(),
+            rows: 0,
+        };
+        let res = load(transakt, &mut reader);
+        bar.finish_and_clear();
+        res?;
+    } else {
+        let mut file = file;
+        load(transakt, &mut file)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "tui")]
+fn dashboard(cli: &Cli) -> bool {
+    cli.tui
+}
+
+#[cfg(not(feature = "tui"))]
+fn dashboard(_cli: &Cli) -> bool {
+    false
+}
+
+/// Progress bar for the input, if stderr is a terminal and the input size is known.
+fn progress_bar(file: &std::fs::File) -> Option<ProgressBar> {
+    if !std::io::stderr().is_terminal() {
+        return None;
+    }
+    let metadata = file.metadata().ok().filter(|m| m.is_file())?;
+    let style = ProgressStyle::with_template("{wide_bar} {bytes}/{total_bytes} {msg} ETA {eta}")
+        .expect("progress template is valid");
+    Some(ProgressBar::new(metadata.len()).with_style(style))
+}
+
+/// Advances the progress bar by the bytes read, and counts rows to report throughput.
+struct ProgressReader<R> {
+    inner: R,
+    bar: ProgressBar,
+    rows: u64,
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.rows += buf[..read].iter().filter(|&&b| b == b'\n').count() as u64;
+        let elapsed = self.bar.elapsed().as_secs_f64();
+        if elapsed > 0.0 {
+            self.bar.set_message(format!(
+                "{} rows, {:.0} rows/s",
+                self.rows,
+                self.rows as f64 / elapsed
+            ));
+        }
+        self.bar.inc(read as u64);
+        Ok(read)
+    }
+}
+
+fn audit(log: &Path, query: &AuditQuery) -> Result<i32, Error> {
+    let file = std::fs::File::open(log).map_err(Error::Io)?;
+    let mut out = std::io::stdout().lock();
+    for record in read_audit(std::io::BufReader::new(file)) {
+        let record = record?;
+        if query.matches(&record) {
+            serde_json::to_writer(&mut out, &record).map_err(|err| Error::Io(err.into()))?;
+            writeln!(out).map_err(Error::Io)?;
+        }
+    }
+    Ok(EXIT_OK)
+}
+
+fn verify_audit_log(log: &Path) -> Result<i32, Error> {
+    let file = std::fs::File::open(log).map_err(Error::Io)?;
+    match verify_audit(std::io::BufReader::new(file)) {
+        Ok(verified) => {
+            println!(
+                "{} records, chain intact, head {}",
+                verified.records, verified.head
+            );
+            Ok(EXIT_OK)
+        }
+        Err(Error::InvalidSnapshot(reason)) => {
+            println!("{}", reason);
+            Ok(EXIT_FINDINGS)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Key persisted state is encrypted with, if one is configured.
+#[cfg(feature = "encryption")]
+fn state_cipher(config: &Config) -> Result<Option<StateCipher>, Error> {
+    let key = match &config.storage.state_key {
+        Some(key) => key,
+        None => return Ok(None),
+    };
+    let id = config.storage.state_key_id.as_deref().unwrap_or("default");
+    Ok(Some(StateCipher::new(StaticKey::from_hex(id, key)?)))
+}
+
+#[cfg_attr(not(feature = "encryption"), allow(unused_variables))]
+fn load_snapshot(path: &Path, config: &Config) -> Result<Snapshot, Error> {
+    #[cfg(feature = "encryption")]
+    if let Some(cipher) = state_cipher(config)? {
+        return Snapshot::load_encrypted(path, &cipher);
+    }
+    Snapshot::load(path)
+}
+
+#[cfg_attr(not(feature = "encryption"), allow(unused_variables))]
+fn save_snapshot(snapshot: &Snapshot, path: &Path, config: &Config) -> Result<(), Error> {
+    #[cfg(feature = "encryption")]
+    if let Some(cipher) = state_cipher(config)? {
+        return snapshot.save_encrypted(path, &cipher);
+    }
+    snapshot.save(path)
+}
+
+#[cfg_attr(not(feature = "encryption"), allow(unused_variables))]
+fn open_journal(path: &Path, config: &Config) -> Result<JournalWriter, Error> {
+    if config.storage.journal_format == JournalFormat::Binary {
+        #[cfg(feature = "encryption")]
+        if config.storage.state_key.is_some() {
+            return Err(Error::InvalidConfig(
+                "binary journals can't be encrypted".to_string(),
+            ));
+        }
+        return JournalWriter::open_binary(path);
+    }
+    let journal = JournalWriter::open(path)?;
+    #[cfg(feature = "encryption")]
+    if let Some(cipher) = state_cipher(config)? {
+        return Ok(journal.encrypted(cipher));
+    }
+    Ok(journal)
+}
+
+/// The journal to replay, decrypted first if it's encrypted.
+#[cfg_attr(not(feature = "encryption"), allow(unused_variables))]
+fn journal_reader(path: &Path, config: &Config) -> Result<Box<dyn BufRead>, Error> {
+    let file = std::io::BufReader::new(std::fs::File::open(path).map_err(Error::Io)?);
+    #[cfg(feature = "encryption")]
+    if let Some(cipher) = state_cipher(config)? {
+        let journal = decrypt_journal(file, &cipher)?;
+        return Ok(Box::new(std::io::Cursor::new(journal)));
+    }
+    Ok(Box::new(file))
+}
+
+fn replay(journal: &Path, limit: &ReplayLimit, config: &Config) -> Result<i32, Error> {
+    let mut transakt = Transakt::default();
+    transakt.replay(journal_reader(journal, config)?, limit)?;
+    transakt.write_accounts_csv(std::io::stdout().lock())?;
+    Ok(EXIT_OK)
+}
+
+#[cfg(feature = "parquet")]
+fn export(
+    journal: &Path,
+    accounts: Option<&Path>,
+    transactions: Option<&Path>,
+    config: &Config,
+) -> Result<i32, Error> {
+    if let Some(accounts) = accounts {
+        let mut transakt = Transakt::default();
+        transakt.replay(journal_reader(journal, config)?, &ReplayLimit::default())?;
+        let file = std::fs::File::create(accounts).map_err(Error::Io)?;
+        transakt.write_accounts_parquet(file)?;
+    }
+    if let Some(transactions) = transactions {
+        let file = std::fs::File::create(transactions).map_err(Error::Io)?;
+        let written =
+            transakt::parquet::write_journal_parquet(journal_reader(journal, config)?, file)?;
+        tracing::info!(written, "exported transactions");
+    }
+    Ok(EXIT_OK)
+}
+
+fn serve(
+    input: Option<&Path>,
+    policy: &SnapshotPolicy,
+    reload: Option<&ConfigReload>,
+    config: &Config,
+) -> Result<i32, Error> {
+    let mut transakt = engine_builder(config)?.build();
+    let options = config.ingest.options()?;
+    let stats = match input {
+        Some(path) => {
+            let file = std::fs::File::open(path).map_err(Error::Io)?;
+            transakt.serve(file, policy, &options, reload)?
+        }
+        None => transakt.serve(std::io::stdin(), policy, &options, reload)?,
+    };
+    transakt.rejections().log();
+    tracing::info!(
+        executed = stats.executed,
+        shed = stats.shed,
+        spilled = stats.spilled,
+        applied = transakt.snapshot().applied,
+        "input ended"
+    );
+    Ok(EXIT_OK)
+}
+
+/// Parses intervals like `30s`, `5m` or `2h`. A bare number is in seconds.
+fn parse_interval(text: &str) -> Result<Duration, String> {
+    let (number, unit) = match text.find(|c: char| !c.is_ascii_digit()) {
+        Some(split) => text.split_at(split),
+        None => (text, "s"),
+    };
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid interval `{}`", text))?;
+    let seconds = match unit {
+        "s" => number,
+        "m" => number * 60,
+        "h" => number * 3600,
+        _ => return Err(format!("unknown unit `{}`, use s, m or h", unit)),
+    };
+    if seconds == 0 {
+        return Err("interval must be positive".to_string());
+    }
+    Ok(Duration::from_secs(seconds))
+}
+
+fn stats(filepath: &Path, config: &Config) -> Result<i32, Error> {
+    let file = std::fs::File::open(filepath).map_err(Error::Io)?;
+    let stats = transakt::stats::profile_csv_with(std::io::BufReader::new(file), &config.csv)?;
+    println!("{}", stats);
+    Ok(EXIT_OK)
+}
+
+fn explain(code: &str) -> Result<i32, Error> {
+    match transakt::explain::explain(code) {
+        Some(explanation) => {
+            println!("{}", explanation);
+            Ok(EXIT_OK)
+        }
+        None => {
+            let codes: Vec<&str> = EXPLANATIONS.iter().map(|e| e.code).collect();
+            eprintln!(
+                "Unknown error code `{}`, known codes: {}",
+                code,
+                codes.join(", ")
+            );
+            Ok(EXIT_PARSE)
+        }
+    }
+}
+
+fn inspect(snapshot: &Path, config: &Config) -> Result<i32, Error> {
+    let snapshot = load_snapshot(snapshot, config)?;
+    println!("{}", snapshot.summary());
+    Ok(EXIT_OK)
+}
+
+fn minimize(filepath: &Path, outcome: &Outcome, config: &Config) -> Result<i32, Error> {
+    let file = std::fs::File::open(filepath).map_err(Error::Io)?;
+    let reader = std::io::BufReader::new(file);
+    let reproducer = match minimize_csv_with(reader, outcome, &config.csv)? {
+        Some(reproducer) => reproducer,
+        None => {
+            eprintln!("the input does not reproduce the outcome");
+            return Ok(EXIT_FINDINGS);
+        }
+    };
+    reproducer.write_csv(std::io::stdout())?;
+    eprintln!(
+        "{} of {} rows kept",
+        reproducer.rows.len(),
+        reproducer.original_rows
+    );
+    Ok(EXIT_OK)
+}
+
+fn scenarios(files: &[PathBuf], cli: &Cli, config: &Config) -> Result<i32, Error> {
+    let mut failed = 0;
+    for path in files {
+        let scenario = Scenario::load(path).map_err(|err| match err {
+            Error::InvalidScenario(reason) => {
+                Error::InvalidScenario(format!("{}: {}", path.display(), reason))
+            }
+            err => err,
+        })?;
+        let mut builder = engine_builder(config)?;
+        if cli.verify {
+            builder = builder.verify();
+        }
+        let report = scenario.run(builder);
+        for failure in report.failures.iter() {
+            println!("{}: {}", path.display(), failure);
+        }
+        if !report.is_ok() {
+            failed += 1;
+        }
+    }
+    println!("{} scenarios run, {} failed", files.len(), failed);
+    Ok(if failed == 0 { EXIT_OK } else { EXIT_FINDINGS })
+}
+
+/// The configuration without journal, audit log and webhook, for engines whose effects must not
+/// leave the process.
+fn without_outputs(config: &Config) -> Config {
+    let mut config = config.clone();
+    config.storage.journal = None;
+    config.storage.audit_log = None;
+    config.webhook = None;
+    config
+}
+
+fn shadow(filepath: &Path, shadow_config: &Path, config: &Config) -> Result<i32, Error> {
+    let mut shadow_config = Config::load(shadow_config)?;
+    shadow_config.apply_env()?;
+    // Both start from the primary's state
+    shadow_config.storage.state_in = config.storage.state_in.clone();
+    let build = |config: &Config| -> Result<Transakt, Error> {
+        let config = without_outputs(config);
+        let mut transakt = engine_builder(&config)?.build();
+        if let Some(state_in) = &config.storage.state_in {
+            transakt.restore(load_snapshot(state_in, &config)?)?;
+        }
+        Ok(transakt)
+    };
+    let mut shadow = Shadow::new(build(config)?, build(&shadow_config)?);
+    let file = std::fs::File::open(filepath).map_err(Error::Io)?;
+    let divergence = shadow.run_csv(std::io::BufReader::new(file))?;
+    if let Some(divergence) = &divergence {
+        println!("{}", divergence);
+    }
+    println!(
+        "{} rows compared, {}",
+        shadow.rows(),
+        if divergence.is_some() {
+            "diverged"
+        } else {
+            "no divergence"
+        }
+    );
+    Ok(if divergence.is_some() {
+        EXIT_FINDINGS
+    } else {
+        EXIT_OK
+    })
+}
+
+fn golden(dir: &Path, update: bool, cli: &Cli, config: &Config) -> Result<i32, Error> {
+    let config = without_outputs(config);
+    // Fails here rather than for every input
+    engine_builder(&config)?;
+    let builder = || {
+        let builder = engine_builder(&config).expect("engine built above");
+        if cli.verify {
+            builder.verify()
+        } else {
+            builder
+        }
+    };
+    let results = check_golden(dir, builder, update)?;
+    for result in results.iter() {
+        println!("{}", result);
+    }
+    let failed = results.iter().filter(|result| !result.is_ok()).count();
+    println!("{} inputs checked, {} failed", results.len(), failed);
+    Ok(if failed == 0 { EXIT_OK } else { EXIT_FINDINGS })
+}
+
+fn validate(filepath: &Path, config: &Config) -> Result<i32, Error> {
+    let file = std::fs::File::open(filepath).map_err(Error::Io)?;
+    let report = transakt::validate::validate_csv_with(file, &config.csv)?;
+    for problem in report.problems.iter() {
+        println!("{}", problem);
+    }
+    println!(
+        "{} rows checked, {} problems",
+        report.rows,
+        report.problems.len()
+    );
+    Ok(if report.is_ok() {
+        EXIT_OK
+    } else {
+        EXIT_FINDINGS
+    })
+}
+
+/// One client of the `accounts` subcommand output.
+#[derive(Serialize)]
+struct AccountView {
+    client: ClientId,
+    available: Currency,
+    held: Currency,
+    /// `None` if the sum overflows.
+    total: Option<Currency>,
+    locked: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    disputes: Option<Vec<DisputeView>>,
+}
+
+#[derive(Serialize)]
+struct DisputeView {
+    tx: TransactionId,
+    amount: Currency,
+}
+
+fn accounts(
+    snapshot: &Path,
+    clients: &[u16],
+    disputes: bool,
+    format: OutputFormat,
+    config: &Config,
+) -> Result<i32, Error> {
+    let snapshot = load_snapshot(snapshot, config)?;
+    let (views, missing) = account_views(&snapshot, clients, disputes);
+    print_accounts(&views, format)?;
+    Ok(if missing { EXIT_FINDINGS } else { EXIT_OK })
+}
+
+/// The accounts of the clients in the snapshot, all of them if none are given, and whether any
+/// of the clients has none.
+fn account_views(snapshot: &Snapshot, clients: &[u16], disputes: bool) -> (Vec<AccountView>, bool) {
+    let mut selected = vec![];
+    let mut missing = false;
+    if clients.is_empty() {
+        selected.extend(snapshot.accounts.iter());
+    }
+    for client in clients.iter().copied().map(ClientId::new) {
+        match snapshot.account(client) {
+            Some(account) => selected.push(account),
+            None => {
+                eprintln!("client {}: no account", client);
+                missing = true;
+            }
+        }
+    }
+    let views = selected
+        .into_iter()
+        .map(|account| AccountView {
+            client: account.client,
+            available: account.available,
+            held: account.held,
+            total: account.available.checked_add(account.held),
+            locked: account.locked,
+            disputes: if disputes {
+                let disputes = snapshot.disputes(account.client).into_iter();
+                Some(
+                    disputes
+                        .map(|(tx, amount)| DisputeView { tx, amount })
+                        .collect(),
+                )
+            } else {
+                None
+            },
+        })
+        .collect();
+    (views, missing)
+}
+
+fn print_accounts(views: &[AccountView], format: OutputFormat) -> Result<(), Error> {
+    match format {
+        OutputFormat::Json => {
+            let stdout = std::io::stdout();
+            serde_json::to_writer_pretty(stdout.lock(), &views)
+                .map_err(|err| Error::Io(err.into()))?;
+            println!();
+        }
+        OutputFormat::Table => {
+            println!(
+                "{:>6} {:>16} {:>16} {:>16} {:>6}",
+                "client", "available", "held", "total", "locked"
+            );
+            for view in views.iter() {
+                let total = view.total.map_or("overflow".to_string(), |t| t.to_string());
+                println!(
+                    "{:>6} {:>16} {:>16} {:>16} {:>6}",
+                    // The ids and amounts don't pad themselves
+                    view.client.to_string(),
+                    view.available.to_string(),
+                    view.held.to_string(),
+                    total,
+                    view.locked
+                );
+                for dispute in view.disputes.iter().flatten() {
+                    println!("{:>6} disputed tx {} {}", "", dispute.tx, dispute.amount);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Answers the queries on stdin until it ends. A query that can't be parsed, or that comes
+/// before the first snapshot, is reported on stderr and skipped.
+fn replica(
+    policy: SnapshotPolicy,
+    every: Duration,
+    disputes: bool,
+    format: OutputFormat,
+) -> Result<i32, Error> {
+    let dir = policy.dir.clone();
+    let mut replica = Replica::new(policy);
+    replica.every = every;
+    for line in std::io::stdin().lock().lines() {
+        let line = line.map_err(Error::Io)?;
+        let clients: Result<Vec<u16>, _> = line
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(str::parse)
+            .collect();
+        let clients = match clients {
+            Ok(clients) => clients,
+            Err(_) => {
+                eprintln!(
+                    "invalid query `{}`, expected client ids separated by commas",
+                    line
+                );
+                continue;
+            }
+        };
+        match replica.snapshot()? {
+            Some(snapshot) => {
+                let (views, _) = account_views(snapshot, &clients, disputes);
+                print_accounts(&views, format)?;
+            }
+            None => eprintln!("no snapshot in {} yet", dir.display()),
+        }
+    }
+    Ok(EXIT_OK)
+}
+
+fn history(
+    client: ClientId,
+    journal: &Path,
+    snapshot: Option<&Path>,
+    as_of: Option<u64>,
+    config: &Config,
+) -> Result<i32, Error> {
+    let mut transakt = Transakt::default();
+    let mut start = None;
+    if let Some(snapshot) = snapshot {
+        let snapshot = load_snapshot(snapshot, config)?;
+        start = snapshot.account(client).cloned();
+        transakt.restore(snapshot)?;
+    }
+    let mut history = transakt.client_history(journal_reader(journal, config)?, client)?;
+    if let Some(as_of) = as_of {
+        history.retain(|entry| entry.recorded_at <= as_of);
+    }
+    let row = |cells: [&str; 9]| {
+        println!(
+            "{:>8} {:>10} {:<10} {:>10} {:>14} {:>14} {:>14} {:>14} {:>6}",
+            cells[0],
+            cells[1],
+            cells[2],
+            cells[3],
+            cells[4],
+            cells[5],
+            cells[6],
+            cells[7],
+            cells[8]
+        )
+    };
+    let balances = |account: &AccountState| {
+        let total = account.available.checked_add(account.held);
+        [
+            account.available.to_string(),
+            account.held.to_string(),
+            total.map_or("overflow".to_string(), |total| total.to_string()),
+            account.locked.to_string(),
+        ]
+    };
+    row([
+        "seq",
+        "recorded",
+        "type",
+        "tx",
+        "amount",
+        "available",
+        "held",
+        "total",
+        "locked",
+    ]);
+    if let Some(account) = &start {
+        let [available, held, total, locked] = balances(account);
+        row([
+            "", "", "snapshot", "", "", &available, &held, &total, &locked,
+        ]);
+    }
+    for entry in history.iter() {
+        let transaction = &entry.transaction;
+        let amount = transaction.amount().map(|amount| amount.to_string());
+        let [available, held, total, locked] = balances(&entry.account);
+        row([
+            &entry.seq.to_string(),
+            &entry.recorded_at.to_string(),
+            transaction.kind(),
+            &transaction.tx().to_string(),
+            amount.as_deref().unwrap_or(""),
+            &available,
+            &held,
+            &total,
+            &locked,
+        ]);
+    }
+    if start.is_none() && history.is_empty() {
+        eprintln!("client {}: no transactions", client);
+        return Ok(EXIT_FINDINGS);
+    }
+    Ok(EXIT_OK)
+}
+
+fn statement(
+    client: ClientId,
+    journal: &Path,
+    snapshot: Option<&Path>,
+    format: StatementFormat,
+    config: &Config,
+) -> Result<i32, Error> {
+    let mut transakt = Transakt::default();
+    if let Some(snapshot) = snapshot {
+        transakt.restore(load_snapshot(snapshot, config)?)?;
+    }
+    let statement = transakt.statement(journal_reader(journal, config)?, client)?;
+    match format {
+        StatementFormat::Text => statement.write_text(std::io::stdout().lock())?,
+        StatementFormat::Csv => statement.write_csv(std::io::stdout().lock())?,
+    }
+    if statement.opening.is_none() && statement.lines.is_empty() {
+        eprintln!("client {}: no transactions", client);
+        return Ok(EXIT_FINDINGS);
+    }
+    Ok(EXIT_OK)
+}
+
+fn disputes_report(
+    snapshot: &Path,
+    clients: &[u16],
+    format: OutputFormat,
+    config: &Config,
+) -> Result<i32, Error> {
+    let mut transakt = Transakt::default();
+    transakt.restore(load_snapshot(snapshot, config)?)?;
+    let mut disputes = transakt.open_disputes();
+    if !clients.is_empty() {
+        disputes.retain(|dispute| clients.contains(&dispute.client.id()));
+    }
+    match format {
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(std::io::stdout().lock(), &disputes)
+                .map_err(|err| Error::Io(err.into()))?;
+            println!();
+        }
+        OutputFormat::Table => {
+            println!(
+                "{:>6} {:>10} {:>16} {:>16}",
+                "client", "tx", "amount", "held"
+            );
+            for dispute in disputes.iter() {
+                println!(
+                    "{:>6} {:>10} {:>16} {:>16}",
+                    dispute.client.to_string(),
+                    dispute.tx.to_string(),
+                    dispute.amount.to_string(),
+                    dispute.held.to_string()
+                );
+            }
+        }
+    }
+    Ok(EXIT_OK)
+}
+
+fn diff(left: &Path, right: &Path) -> Result<i32, Error> {
+    let left = load_accounts(left)?;
+    let right = load_accounts(right)?;
+    let diffs = diff_accounts(&left, &right);
+    for diff in diffs.iter() {
+        println!("{}", diff);
+    }
+    Ok(if diffs.is_empty() {
+        EXIT_OK
+    } else {
+        EXIT_FINDINGS
+    })
 }
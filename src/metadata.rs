@@ -0,0 +1,91 @@
+//! Account metadata for risk and support workflows: when the account was opened and last active,
+//! how many disputes and chargebacks it went through, and its
+//! [shortfall](crate::account::Account::shortfall). The counts are always kept, and are part of
+//! snapshots. With [`TransaktBuilder::account_metadata`], or `account_metadata = true` in the
+//! `[output]` section of the configuration, accounts are also timed and the account report gets
+//! `created_at`, `last_activity`, `dispute_count`, `chargeback_count` and `shortfall` columns.
+//!
+//! Like dormancy, times are taken from the clock of the machine processing the input, in seconds
+//! since the Unix epoch. Accounts opened before metadata was kept have no `created_at`.
+
+use crate::dormancy::now;
+use crate::transaction::ClientId;
+use crate::{Transakt, TransaktBuilder};
+
+impl TransaktBuilder {
+    /// Time accounts and add their metadata to the account report.
+    pub fn account_metadata(mut self) -> Self {
+        self.account_metadata = true;
+        self
+    }
+}
+
+impl Transakt {
+    /// Times the opening of the client's account, if a transaction just opened it.
+    pub(crate) fn record_opened(&mut self, client: ClientId, existed: bool) {
+        if !self.account_metadata || existed {
+            return;
+        }
+        if let Some(account) = self.accounts.get_mut(&client) {
+            account.set_created_at(now());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::snapshot::Snapshot;
+    use crate::transaction::ClientId;
+    use crate::Transakt;
+
+    #[test]
+    fn account_metadata() {
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,10.0\n\
+                     deposit,1,2,5.0\n\
+                     dispute,1,1,\n\
+                     resolve,1,1,\n\
+                     dispute,1,2,\n\
+                     chargeback,1,2,\n\
+                     deposit,2,3,1.0\n";
+        let mut transakt = Transakt::builder().account_metadata().build();
+        transakt.load_csv_from("input", input.as_bytes()).unwrap();
+        let account = &transakt.get_accounts_map()[&ClientId::new(1)];
+        assert_eq!(account.dispute_count(), 2);
+        assert_eq!(account.chargeback_count(), 1);
+        let created_at = account.created_at().unwrap();
+        assert!(account.last_activity().unwrap() >= created_at);
+
+        let mut out = vec![];
+        transakt.write_accounts_csv(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let mut lines = out.lines();
+        assert_eq!(
+            lines.next(),
+            Some(
+                "client,available,held,total,locked,created_at,last_activity,dispute_count,\
+                 chargeback_count,shortfall"
+            )
+        );
+        assert!(lines.next().unwrap().ends_with(",2,1,0.0000"));
+        assert!(lines.next().unwrap().ends_with(",0,0,0.0000"));
+
+        // The counts are kept without timing, and in snapshots
+        let mut untimed = Transakt::default();
+        untimed.load_csv_from("input", input.as_bytes()).unwrap();
+        let snapshot = untimed.snapshot();
+        let state = snapshot.account(ClientId::new(1)).unwrap();
+        assert_eq!((state.disputes, state.chargebacks), (2, 1));
+        assert_eq!((state.created_at, state.last_activity), (None, None));
+        let mut written = vec![];
+        snapshot.write(&mut written).unwrap();
+        let mut restored = Transakt::default();
+        restored
+            .restore(Snapshot::read(&written[..]).unwrap())
+            .unwrap();
+        assert_eq!(
+            restored.get_accounts_map()[&ClientId::new(1)].dispute_count(),
+            2
+        );
+    }
+}
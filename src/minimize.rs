@@ -0,0 +1,218 @@
+//! Shrinking an input to the fewest rows that still reproduce an outcome, to turn a large file
+//! into a small reproducer for a bug report.
+
+use crate::transaction::{ClientId, CsvOptions};
+use crate::{Error, Transakt};
+use csv::StringRecord;
+use std::io::{Read, Write};
+
+/// What a run of the shrunk input has to end with.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// The client's account ends locked.
+    Locked(ClientId),
+    /// Processing stops with an error of this code, see [`crate::explain`].
+    FailsWith(String),
+    /// At least one row is rejected with an error of this code.
+    Rejects(String),
+}
+
+impl Outcome {
+    /// Runs the rows on an empty ledger and checks whether they end with this outcome.
+    pub fn reproduced(&self, header: &StringRecord, rows: &[StringRecord]) -> bool {
+        self.reproduced_with(header, rows, &CsvOptions::default())
+    }
+
+    /// Like [`Outcome::reproduced`], for rows of CSV input written as `options` say.
+    pub fn reproduced_with(
+        &self,
+        header: &StringRecord,
+        rows: &[StringRecord],
+        options: &CsvOptions,
+    ) -> bool {
+        let mut transakt = Transakt::builder().csv_options(options.clone()).build();
+        let input = csv_bytes(header, rows, options);
+        let res = transakt.load_csv_from("minimize", input.as_slice());
+        match self {
+            Outcome::Locked(client) => transakt
+                .get_accounts_map()
+                .get(client)
+                .is_some_and(|account| account.is_locked()),
+            Outcome::FailsWith(code) => res.is_err_and(|err| err.code().eq_ignore_ascii_case(code)),
+            Outcome::Rejects(code) => transakt
+                .rejections()
+                .groups()
+                .any(|(rejected, _)| rejected.eq_ignore_ascii_case(code)),
+        }
+    }
+}
+
+/// Shrunk input, with the header of the original.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reproducer {
+    pub header: StringRecord,
+    pub rows: Vec<StringRecord>,
+    /// Data rows of the original input.
+    pub original_rows: usize,
+    /// How the original input is written, and the reproducer is.
+    pub options: CsvOptions,
+}
+
+impl Reproducer {
+    pub fn write_csv<W: Write>(&self, writer: W) -> Result<(), Error> {
+        let mut out = self.options.writer_builder()?.from_writer(writer);
+        for record in std::iter::once(&self.header).chain(self.rows.iter()) {
+            out.write_record(record)
+                .map_err(|err| Error::Io(err.into()))?;
+        }
+        out.flush().map_err(Error::Io)
+    }
+}
+
+/// Shrinks a CSV input to a minimal sequence of rows that reproduces `outcome`: removing any
+/// single remaining row loses it. Returns `None` if the whole input doesn't reproduce it.
+///
+/// Every step runs the candidate rows from scratch, so this takes a while on large inputs; cutting
+/// them down by hand first pays off.
+pub fn minimize_csv<R: Read>(reader: R, outcome: &Outcome) -> Result<Option<Reproducer>, Error> {
+    minimize_csv_with(reader, outcome, &CsvOptions::default())
+}
+
+/// Like [`minimize_csv`], for CSV input written as `options` say. The reproducer is written the
+/// same way, with the original headers.
+pub fn minimize_csv_with<R: Read>(
+    reader: R,
+    outcome: &Outcome,
+    options: &CsvOptions,
+) -> Result<Option<Reproducer>, Error> {
+    let mut csv = options.reader_builder()?.flexible(true).from_reader(reader);
+    let header = csv.headers().map_err(|err| Error::Io(err.into()))?.clone();
+    let rows = csv
+        .records()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| Error::Io(err.into()))?;
+    if !outcome.reproduced_with(&header, &rows, options) {
+        return Ok(None);
+    }
+    let original_rows = rows.len();
+    let rows = ddmin(rows, |candidate| {
+        outcome.reproduced_with(&header, candidate, options)
+    });
+    Ok(Some(Reproducer {
+        header,
+        rows,
+        original_rows,
+        options: options.clone(),
+    }))
+}
+
+/// Delta debugging: removes ever smaller chunks of `items` as long as `test` keeps passing.
+/// `test` must pass on `items` to begin with.
+fn ddmin<T: Clone, F: FnMut(&[T]) -> bool>(mut items: Vec<T>, mut test: F) -> Vec<T> {
+    let mut chunks = 2;
+    while items.len() >= 2 {
+        let chunk = items.len().div_ceil(chunks);
+        let mut reduced = false;
+        for start in (0..items.len()).step_by(chunk) {
+            let end = (start + chunk).min(items.len());
+            let complement: Vec<T> = items[..start]
+                .iter()
+                .chain(items[end..].iter())
+                .cloned()
+                .collect();
+            if test(&complement) {
+                items = complement;
+                chunks = (chunks - 1).max(2);
+                reduced = true;
+                break;
+            }
+        }
+        if !reduced {
+            if chunks >= items.len() {
+                break;
+            }
+            chunks = (chunks * 2).min(items.len());
+        }
+    }
+    if items.len() == 1 && test(&[]) {
+        items.clear();
+    }
+    items
+}
+
+fn csv_bytes(header: &StringRecord, rows: &[StringRecord], options: &CsvOptions) -> Vec<u8> {
+    // Options that can't be used fail the run, like they would the original input
+    let Ok(mut out) = options
+        .writer_builder()
+        .map(|builder| builder.from_writer(vec![]))
+    else {
+        return vec![];
+    };
+    for record in std::iter::once(header).chain(rows.iter()) {
+        // Writing to memory only fails on records of different lengths, which `flexible` allows
+        let _ = out.write_record(record);
+    }
+    out.into_inner().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{minimize_csv, minimize_csv_with, Outcome};
+    use crate::transaction::{ClientId, CsvOptions};
+
+    #[test]
+    fn minimize_locked_client() {
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,5.0\n\
+                     deposit,7,2,3.0\n\
+                     withdrawal,1,3,1.0\n\
+                     deposit,7,4,2.0\n\
+                     dispute,1,3,\n\
+                     dispute,7,4,\n\
+                     deposit,2,5,1.0\n\
+                     chargeback,7,4,\n\
+                     deposit,7,6,1.0\n";
+        let reproducer = minimize_csv(input.as_bytes(), &Outcome::Locked(ClientId::new(7)))
+            .unwrap()
+            .unwrap();
+        assert_eq!(reproducer.original_rows, 9);
+        let mut out = vec![];
+        reproducer.write_csv(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "type,client,tx,amount\n\
+             deposit,7,4,2.0\n\
+             dispute,7,4,\n\
+             chargeback,7,4,\n"
+        );
+
+        let reproducer = minimize_csv(input.as_bytes(), &Outcome::Rejects("e_funds".into()));
+        assert_eq!(reproducer.unwrap(), None);
+    }
+
+    #[test]
+    fn minimize_partner_format() {
+        let mut options = CsvOptions {
+            delimiter: ';',
+            decimal_separator: ',',
+            ..CsvOptions::default()
+        };
+        options
+            .columns
+            .insert("customer".to_string(), "client".to_string());
+        let input = "type;customer;tx;amount\n\
+                     deposit;1;1;5,5\n\
+                     deposit;2;2;1,0\n\
+                     withdrawal;1;3;6,0\n";
+        let outcome = Outcome::Rejects("E_FUNDS".into());
+        let reproducer = minimize_csv_with(input.as_bytes(), &outcome, &options)
+            .unwrap()
+            .unwrap();
+        let mut out = vec![];
+        reproducer.write_csv(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "type;customer;tx;amount\nwithdrawal;1;3;6,0\n"
+        );
+    }
+}
@@ -0,0 +1,107 @@
+//! Node.js bindings, so TypeScript services can reuse the settlement logic.
+
+use crate::account::Account;
+use crate::currency::Currency;
+use crate::transaction::{ClientId, Transaction, TransactionId, TransactionRow, TransactionType};
+use crate::{Error, Transakt};
+use napi::bindgen_prelude::Buffer;
+use napi_derive::napi;
+use std::convert::{TryFrom, TryInto};
+use std::str::FromStr;
+
+/// Balances of one account. Amounts are decimal strings, to avoid losing precision in JS numbers.
+#[napi(object)]
+pub struct AccountBalance {
+    pub client: u32,
+    pub available: String,
+    pub held: String,
+    pub total: String,
+    pub locked: bool,
+}
+
+impl From<&Account> for AccountBalance {
+    fn from(account: &Account) -> Self {
+        Self {
+            client: account.client().id().into(),
+            available: account.available().to_string(),
+            held: account.held().to_string(),
+            total: account
+                .total()
+                .map(|total| total.to_string())
+                .unwrap_or_default(),
+            locked: account.is_locked(),
+        }
+    }
+}
+
+#[napi(js_name = "Engine")]
+#[derive(Default)]
+pub struct NodeEngine {
+    inner: Transakt,
+}
+
+fn to_napi(err: Error) -> napi::Error {
+    napi::Error::from_reason(format!("{:?}", err))
+}
+
+#[napi]
+impl NodeEngine {
+    #[napi(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Executes one transaction. Returns whether it was applied; throws only on malformed input
+    /// or errors that would stop a batch.
+    #[napi]
+    pub fn submit(
+        &mut self,
+        kind: String,
+        client: u32,
+        tx: u32,
+        amount: Option<String>,
+    ) -> napi::Result<bool> {
+        let tx_type = TransactionType::from_str(&kind).map_err(to_napi)?;
+        let client = u16::try_from(client).map_err(|_| to_napi(Error::TransactionParseError))?;
+        let amount = amount
+            .map(|amount| Currency::from_str(&amount))
+            .transpose()
+            .map_err(|_| to_napi(Error::TransactionParseError))?;
+        let row = TransactionRow::new(
+            tx_type,
+            ClientId::new(client),
+            TransactionId::new(tx),
+            amount,
+        );
+        let transaction: Transaction = row.try_into().map_err(to_napi)?;
+        match self.inner.execute_transaction(transaction) {
+            Ok(()) => Ok(true),
+            Err(err) if err.is_fatal() => Err(to_napi(err)),
+            Err(_) => Ok(false),
+        }
+    }
+
+    /// Executes all the transactions in a CSV buffer.
+    #[napi]
+    pub fn load_csv(&mut self, csv: Buffer) -> napi::Result<()> {
+        self.inner.execute_csv(csv.as_ref()).map_err(to_napi)
+    }
+
+    #[napi]
+    pub fn account(&self, client: u32) -> Option<AccountBalance> {
+        let client = ClientId::new(u16::try_from(client).ok()?);
+        self.inner
+            .get_accounts_map()
+            .get(&client)
+            .map(AccountBalance::from)
+    }
+
+    #[napi]
+    pub fn accounts(&self) -> Vec<AccountBalance> {
+        self.inner
+            .get_accounts()
+            .iter()
+            .map(AccountBalance::from)
+            .collect()
+    }
+}
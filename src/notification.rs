@@ -0,0 +1,107 @@
+use crate::event::RiskEvent;
+use crate::redact;
+
+/// Destination for [`RiskEvent`]s raised by the engine.
+/// Implement this to forward alerts to chat, email, paging or anything else.
+pub trait NotificationSink: Send {
+    fn notify(&mut self, event: &RiskEvent);
+}
+
+/// Reports events as warnings through `tracing`, with client ids and amounts redacted if
+/// configured.
+#[derive(Debug, Default)]
+pub struct LogSink;
+
+impl NotificationSink for LogSink {
+    fn notify(&mut self, event: &RiskEvent) {
+        match event {
+            RiskEvent::AccountLocked { client } => {
+                tracing::warn!(client = %redact::client(*client), "account locked")
+            }
+            RiskEvent::Chargeback { client, tx, amount } => {
+                tracing::warn!(client = %redact::client(*client), tx = %tx, amount = %redact::amount(*amount), "chargeback")
+            }
+            RiskEvent::LargeTransaction { client, tx, amount } => {
+                tracing::warn!(client = %redact::client(*client), tx = %tx, amount = %redact::amount(*amount), "large transaction")
+            }
+            RiskEvent::DeniedClient {
+                client,
+                tx,
+                aliases,
+            } => {
+                let redaction = redact::current();
+                let aliases: Vec<String> =
+                    aliases.iter().map(|alias| redaction.alias(alias)).collect();
+                tracing::warn!(client = %redact::client(*client), tx = %tx, aliases = ?aliases, "denied client")
+            }
+            RiskEvent::ScreeningHit {
+                client,
+                tx,
+                reason,
+                blocked,
+            } => {
+                tracing::warn!(client = %redact::client(*client), tx = %tx, reason = %reason, blocked, "screening hit")
+            }
+            RiskEvent::SuspiciousActivity {
+                client,
+                kind,
+                transactions,
+                total,
+            } => {
+                let transactions: Vec<String> =
+                    transactions.iter().map(|tx| tx.to_string()).collect();
+                tracing::warn!(
+                    client = %redact::client(*client),
+                    kind = %kind,
+                    transactions = %transactions.join(", "),
+                    total = %redact::amount(*total),
+                    "suspicious activity"
+                )
+            }
+            RiskEvent::ChargebackRatioExceeded {
+                client,
+                ratio,
+                locked,
+            } => {
+                tracing::warn!(client = %redact::client(*client), ratio, locked, "chargeback ratio exceeded")
+            }
+            RiskEvent::LimitExceeded {
+                client,
+                tx,
+                tier,
+                limit,
+            } => {
+                tracing::warn!(client = %redact::client(*client), tx = %tx, tier = %tier, limit = %limit, "limit exceeded")
+            }
+            RiskEvent::AccountDormant {
+                client,
+                tx,
+                last_activity,
+            } => {
+                tracing::warn!(client = %redact::client(*client), tx = %tx, last_activity, "dormant account")
+            }
+            RiskEvent::DisputeExceedsFunds {
+                client,
+                tx,
+                amount,
+                available,
+                held,
+            } => {
+                tracing::warn!(
+                    client = %redact::client(*client),
+                    tx = %tx,
+                    amount = %redact::amount(*amount),
+                    available = %redact::amount(*available),
+                    held = %redact::amount(*held),
+                    "dispute exceeds available funds"
+                )
+            }
+            RiskEvent::UnknownClient { client, tx, kind } => {
+                tracing::warn!(client = %redact::client(*client), tx = %tx, kind = %kind, "unknown client")
+            }
+            RiskEvent::BatchRejected { source, reason } => {
+                tracing::warn!(source = %source, reason = %reason, "batch rejected")
+            }
+        }
+    }
+}
@@ -0,0 +1,96 @@
+//! Callbacks on what happens inside the engine, for embedders keeping side indexes, caches or
+//! alerts in sync with the ledger.
+
+use crate::account::Account;
+use crate::currency::Currency;
+use crate::transaction::{ClientId, Transaction, TransactionId};
+use crate::Error;
+
+/// Every callback does nothing by default, so an implementation only overrides what it needs.
+/// Callbacks run on the engine's thread, in the middle of processing, so they should be quick.
+pub trait Observer: Send {
+    /// The transaction was applied; `account` is the state of its client afterwards. Disputes of
+    /// unknown transactions that the policy lets through have no account.
+    fn on_applied(&mut self, _transaction: &Transaction, _account: Option<&Account>) {}
+
+    /// The transaction was rejected, and left no trace in the ledger.
+    fn on_rejected(&mut self, _transaction: &Transaction, _error: &Error) {}
+
+    /// Funds of a deposit were put on hold by a dispute.
+    fn on_dispute_opened(&mut self, _client: ClientId, _tx: TransactionId, _amount: Currency) {}
+
+    /// The account was locked, by a chargeback or a rule.
+    fn on_account_locked(&mut self, _client: ClientId) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Observer;
+    use crate::account::Account;
+    use crate::currency::Currency;
+    use crate::transaction::{ClientId, Transaction, TransactionId};
+    use crate::Error;
+    use crate::Transakt;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Recorder {
+        calls: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Observer for Recorder {
+        fn on_applied(&mut self, transaction: &Transaction, account: Option<&Account>) {
+            let available = account.map(|account| account.available().to_string());
+            self.calls.lock().unwrap().push(format!(
+                "applied {} {:?}",
+                transaction.tx(),
+                available
+            ));
+        }
+
+        fn on_rejected(&mut self, transaction: &Transaction, error: &Error) {
+            self.calls.lock().unwrap().push(format!(
+                "rejected {} {}",
+                transaction.tx(),
+                error.code()
+            ));
+        }
+
+        fn on_dispute_opened(&mut self, client: ClientId, tx: TransactionId, amount: Currency) {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("dispute {} {} {}", client, tx, amount));
+        }
+
+        fn on_account_locked(&mut self, client: ClientId) {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("locked {}", client));
+        }
+    }
+
+    #[test]
+    fn observer_callbacks() {
+        let recorder = Recorder::default();
+        let mut transakt = Transakt::builder().observer(recorder.clone()).build();
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,2.0\n\
+                     withdrawal,1,2,5.0\n\
+                     dispute,1,1,\n\
+                     chargeback,1,1,\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        assert_eq!(
+            *recorder.calls.lock().unwrap(),
+            vec![
+                "applied 1 Some(\"2.0000\")",
+                "rejected 2 E_FUNDS",
+                "dispute 1 1 2.0000",
+                "applied 1 Some(\"0.0000\")",
+                "locked 1",
+                "applied 1 Some(\"0.0000\")",
+            ]
+        );
+    }
+}
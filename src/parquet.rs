@@ -0,0 +1,231 @@
+//! Parquet files of the account report and the journal, for loading results straight into
+//! analytics tooling. Amounts are `DECIMAL(18, 4)`, times are seconds since the Unix epoch like
+//! everywhere else.
+
+use crate::currency::Currency;
+use crate::journal::read_journal;
+use crate::{Error, Transakt};
+use ::parquet::data_type::{BoolType, ByteArray, ByteArrayType, DataType, Int32Type, Int64Type};
+use ::parquet::errors::ParquetError;
+use ::parquet::file::properties::WriterProperties;
+use ::parquet::file::writer::{SerializedColumnWriter, SerializedFileWriter};
+use ::parquet::schema::parser::parse_message_type;
+use std::io::{BufRead, Write};
+use std::sync::Arc;
+
+const ACCOUNT_SCHEMA: &str = "message account {
+    required int32 client (INTEGER(16, false));
+    required int64 available (DECIMAL(18, 4));
+    required int64 held (DECIMAL(18, 4));
+    required int64 total (DECIMAL(18, 4));
+    required boolean locked;
+}";
+
+const TRANSACTION_SCHEMA: &str = "message transaction {
+    required int64 seq (INTEGER(64, false));
+    required int64 recorded_at;
+    optional int64 event_time;
+    required binary type (UTF8);
+    required int32 client (INTEGER(16, false));
+    required int32 tx (INTEGER(32, false));
+    optional int64 amount (DECIMAL(18, 4));
+    optional binary signature (UTF8);
+}";
+
+/// The values of a column, `None` for nulls.
+enum Column {
+    Int32(Vec<Option<i32>>),
+    Int64(Vec<Option<i64>>),
+    Boolean(Vec<Option<bool>>),
+    Text(Vec<Option<String>>),
+}
+
+impl Transakt {
+    /// Writes the `client,available,held,total,locked` columns of the account report as Parquet,
+    /// ordered by client. Fails if the total of an account overflows.
+    pub fn write_accounts_parquet<W: Write + Send>(&self, writer: W) -> Result<(), Error> {
+        let accounts = self.get_accounts();
+        let mut totals = vec![];
+        for account in accounts.iter() {
+            let total = account.total().ok_or_else(|| {
+                Error::Io(std::io::Error::other(format!(
+                    "total of client {} overflows",
+                    account.client()
+                )))
+            })?;
+            totals.push(Some(total.scaled()));
+        }
+        let amounts = |amount: fn(&crate::account::Account) -> &Currency| {
+            accounts
+                .iter()
+                .map(|account| Some(amount(account).scaled()))
+                .collect()
+        };
+        let columns = vec![
+            Column::Int32(
+                accounts
+                    .iter()
+                    .map(|account| Some(i32::from(account.client().id())))
+                    .collect(),
+            ),
+            Column::Int64(amounts(|account| account.available())),
+            Column::Int64(amounts(|account| account.held())),
+            Column::Int64(totals),
+            Column::Boolean(
+                accounts
+                    .iter()
+                    .map(|account| Some(account.is_locked()))
+                    .collect(),
+            ),
+        ];
+        write_columns(writer, ACCOUNT_SCHEMA, columns)
+    }
+}
+
+/// Writes the entries of a journal as Parquet, a row per transaction in journal order. Returns
+/// how many were written.
+pub fn write_journal_parquet<R: BufRead, W: Write + Send>(
+    journal: R,
+    writer: W,
+) -> Result<u64, Error> {
+    let (mut seq, mut recorded_at, mut event_time) = (vec![], vec![], vec![]);
+    let (mut kind, mut client, mut tx, mut amount, mut signature) =
+        (vec![], vec![], vec![], vec![], vec![]);
+    for entry in read_journal(journal) {
+        let entry = entry?;
+        let transaction = &entry.transaction;
+        // Unsigned columns keep the bits of the value
+        seq.push(Some(entry.seq as i64));
+        recorded_at.push(Some(entry.recorded_at as i64));
+        event_time.push(entry.event_time.map(|time| time as i64));
+        kind.push(Some(transaction.kind().to_string()));
+        client.push(Some(i32::from(transaction.client().id())));
+        tx.push(Some(transaction.tx().id() as i32));
+        amount.push(transaction.amount().map(Currency::scaled));
+        signature.push(entry.signature);
+    }
+    let written = seq.len() as u64;
+    let columns = vec![
+        Column::Int64(seq),
+        Column::Int64(recorded_at),
+        Column::Int64(event_time),
+        Column::Text(kind),
+        Column::Int32(client),
+        Column::Int32(tx),
+        Column::Int64(amount),
+        Column::Text(signature),
+    ];
+    write_columns(writer, TRANSACTION_SCHEMA, columns)?;
+    Ok(written)
+}
+
+/// Writes the columns, in schema order, as a single row group.
+fn write_columns<W: Write + Send>(
+    writer: W,
+    schema: &str,
+    columns: Vec<Column>,
+) -> Result<(), Error> {
+    let to_error = |err: ParquetError| Error::Io(std::io::Error::other(err));
+    let schema = Arc::new(parse_message_type(schema).map_err(to_error)?);
+    let properties = Arc::new(WriterProperties::builder().build());
+    let mut file = SerializedFileWriter::new(writer, schema, properties).map_err(to_error)?;
+    let mut row_group = file.next_row_group().map_err(to_error)?;
+    let mut columns = columns.into_iter();
+    while let Some(mut writer) = row_group.next_column().map_err(to_error)? {
+        match columns.next() {
+            Some(Column::Int32(values)) => write_column::<Int32Type>(&mut writer, values),
+            Some(Column::Int64(values)) => write_column::<Int64Type>(&mut writer, values),
+            Some(Column::Boolean(values)) => write_column::<BoolType>(&mut writer, values),
+            Some(Column::Text(values)) => write_column::<ByteArrayType>(
+                &mut writer,
+                values
+                    .into_iter()
+                    .map(|value| value.map(|text| ByteArray::from(text.into_bytes())))
+                    .collect(),
+            ),
+            None => unreachable!("a column for every field of the schema"),
+        }
+        .map_err(to_error)?;
+        writer.close().map_err(to_error)?;
+    }
+    row_group.close().map_err(to_error)?;
+    file.close().map_err(to_error)?;
+    Ok(())
+}
+
+fn write_column<T: DataType>(
+    writer: &mut SerializedColumnWriter,
+    values: Vec<Option<T::T>>,
+) -> Result<(), ParquetError> {
+    // Definition levels are ignored for required columns
+    let levels: Vec<i16> = values
+        .iter()
+        .map(|value| i16::from(value.is_some()))
+        .collect();
+    let present: Vec<T::T> = values.into_iter().flatten().collect();
+    writer
+        .typed::<T>()
+        .write_batch(&present, Some(&levels), None)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write_journal_parquet;
+    use crate::journal::JournalWriter;
+    use crate::testing::SharedBuffer;
+    use crate::Transakt;
+    use ::parquet::data_type::Decimal;
+    use ::parquet::file::reader::{FileReader, SerializedFileReader};
+    use ::parquet::record::RowAccessor;
+
+    #[test]
+    fn write_parquet() {
+        let input = "type,client,tx,amount\n\
+                     deposit,2,1,2.5\n\
+                     deposit,1,2,1.0\n\
+                     dispute,1,2,\n";
+        let journal = SharedBuffer::default();
+        let mut transakt = Transakt::builder()
+            .journal(JournalWriter::new(journal.clone()))
+            .build();
+        transakt.load_csv_from("input", input.as_bytes()).unwrap();
+        let dir = std::env::temp_dir().join(format!("transakt-parquet-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("accounts.parquet");
+        let file = std::fs::File::create(&path).unwrap();
+        transakt.write_accounts_parquet(file).unwrap();
+        let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+        let rows: Vec<_> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].get_ushort(0).unwrap(), 1);
+        assert_eq!(
+            rows[0].get_decimal(2).unwrap(),
+            &Decimal::from_i64(10000, 18, 4)
+        );
+        assert_eq!(
+            rows[1].get_decimal(1).unwrap(),
+            &Decimal::from_i64(25000, 18, 4)
+        );
+
+        let path = dir.join("journal.parquet");
+        let file = std::fs::File::create(&path).unwrap();
+        let written = write_journal_parquet(&journal.contents()[..], file).unwrap();
+        assert_eq!(written, 3);
+        let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 3);
+        let rows: Vec<_> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(rows[2].get_string(3).unwrap(), "dispute");
+        assert!(rows[2].get_decimal(6).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
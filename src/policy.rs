@@ -0,0 +1,187 @@
+//! Business rules the engine consults while executing transactions. Implement
+//! [`ProcessingPolicy`] to change them without patching the engine.
+
+use crate::account::Account;
+use crate::transaction::Transaction;
+use crate::Error;
+use serde::Deserialize;
+
+/// Decisions left to the business. Every method defaults to the standard rules, so an
+/// implementation only overrides what it needs to change.
+pub trait ProcessingPolicy: Send {
+    /// Whether a deposit or withdrawal of a negative amount is executed. By default it is
+    /// rejected with [`Error::InvalidTransaction`].
+    fn allow_negative_amount(&self, _transaction: &Transaction) -> bool {
+        false
+    }
+
+    /// Handles a dispute, resolve or chargeback referring to a tx id that is unknown, or that is
+    /// not a deposit. Returning an error rejects it; by default it is ignored.
+    fn unknown_reference(&self, _transaction: &Transaction) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// What to do with a transaction on a locked account. By default deposits, withdrawals and
+    /// transfers are rejected with [`Error::AccountLocked`], while disputes of earlier deposits
+    /// still go through. Transfers are asked about for each of their accounts that is locked.
+    fn on_locked(&self, transaction: &Transaction, _account: &Account) -> LockedAction {
+        match transaction {
+            Transaction::Deposit { .. }
+            | Transaction::Withdrawal { .. }
+            | Transaction::Transfer { .. } => LockedAction::Reject,
+            _ => LockedAction::Apply,
+        }
+    }
+
+    /// What to do with a transaction other than a deposit for a client without an account. By
+    /// default the account is created, as it always was.
+    fn on_unknown_client(&self, _transaction: &Transaction) -> UnknownClientAction {
+        UnknownClientAction::Create
+    }
+
+    /// What to do with a dispute of a deposit larger than the available funds, as when the
+    /// deposit was withdrawn already. By default the whole deposit is held anyway.
+    fn on_dispute_exceeding_funds(
+        &self,
+        _transaction: &Transaction,
+        _account: &Account,
+    ) -> DisputeFundsAction {
+        DisputeFundsAction::AllowNegative
+    }
+
+    /// Whether a chargeback locks the account. By default it always does.
+    fn lock_on_chargeback(&self, _transaction: &Transaction, _account: &Account) -> bool {
+        true
+    }
+
+    /// Whether a chargeback reversal unlocks the account, if it is locked. By default it doesn't,
+    /// as the lock may have other reasons than the chargeback.
+    fn unlock_on_chargeback_reversal(
+        &self,
+        _transaction: &Transaction,
+        _account: &Account,
+    ) -> bool {
+        false
+    }
+}
+
+/// What happens to a transaction on a locked account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LockedAction {
+    /// Execute it as if the account weren't locked.
+    Apply,
+    /// Reject it with [`Error::AccountLocked`].
+    Reject,
+    /// For deposits: credit the funds as held, as if the deposit were disputed, so a resolve
+    /// releases them and a chargeback reverses them. Other transactions, transfers included, are
+    /// rejected.
+    Hold,
+    /// Set it aside for manual review instead of executing it, see
+    /// [`Transakt::review_queue`](crate::Transakt::review_queue). It is reported as rejected with
+    /// [`Error::PendingReview`].
+    Review,
+}
+
+/// What happens to a withdrawal, dispute or other transaction of a client that has no account
+/// yet. Created accounts stay empty, and skew reports with clients that never had funds.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnknownClientAction {
+    /// Execute it, creating the account where the transaction would.
+    #[default]
+    Create,
+    /// Reject it with [`Error::UnknownClient`].
+    Reject,
+    /// Execute it like `Create`, raising an
+    /// [`UnknownClient`](crate::event::RiskEvent::UnknownClient) risk event.
+    Flag,
+}
+
+/// What happens to a dispute of a deposit larger than the available funds. Either way the
+/// dispute raises a
+/// [`DisputeExceedsFunds`](crate::event::RiskEvent::DisputeExceedsFunds) risk event.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DisputeFundsAction {
+    /// Hold the whole deposit, leaving available funds negative.
+    #[default]
+    AllowNegative,
+    /// Hold only what is available, if anything. A resolve releases, and a chargeback reverses,
+    /// just that. The rest is added to the account's
+    /// [shortfall](crate::account::Account::shortfall) until the dispute is resolved.
+    HoldAvailable,
+    /// Reject it with [`Error::DisputeExceedsFunds`].
+    Reject,
+}
+
+/// The standard rules, with a choice of what happens to deposits, withdrawals and disputes on
+/// locked accounts, to transactions of unknown clients, and to disputes exceeding available funds.
+/// Transfers out of a locked account are handled like withdrawals, and into one like deposits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DefaultPolicy {
+    pub locked_deposit: LockedAction,
+    pub locked_withdrawal: LockedAction,
+    /// For disputes, resolves and chargebacks, of which [`LockedAction::Hold`] rejects all.
+    /// [`LockedAction::Apply`] by default, so disputes opened before a lock can still be settled.
+    pub locked_dispute: LockedAction,
+    pub unknown_client: UnknownClientAction,
+    pub dispute_exceeding_funds: DisputeFundsAction,
+    pub unlock_on_chargeback_reversal: bool,
+}
+
+impl DefaultPolicy {
+    pub const fn new() -> Self {
+        Self {
+            locked_deposit: LockedAction::Reject,
+            locked_withdrawal: LockedAction::Reject,
+            locked_dispute: LockedAction::Apply,
+            unknown_client: UnknownClientAction::Create,
+            dispute_exceeding_funds: DisputeFundsAction::AllowNegative,
+            unlock_on_chargeback_reversal: false,
+        }
+    }
+}
+
+impl Default for DefaultPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProcessingPolicy for DefaultPolicy {
+    fn on_locked(&self, transaction: &Transaction, account: &Account) -> LockedAction {
+        match transaction {
+            Transaction::Deposit { .. } => self.locked_deposit,
+            Transaction::Withdrawal { .. } => self.locked_withdrawal,
+            Transaction::Transfer { from, .. } if *from == account.client() => {
+                self.locked_withdrawal
+            }
+            Transaction::Transfer { .. } => self.locked_deposit,
+            Transaction::Dispute { .. }
+            | Transaction::Resolve { .. }
+            | Transaction::Chargeback { .. } => self.locked_dispute,
+            _ => LockedAction::Apply,
+        }
+    }
+
+    fn on_unknown_client(&self, _transaction: &Transaction) -> UnknownClientAction {
+        self.unknown_client
+    }
+
+    fn on_dispute_exceeding_funds(
+        &self,
+        _transaction: &Transaction,
+        _account: &Account,
+    ) -> DisputeFundsAction {
+        self.dispute_exceeding_funds
+    }
+
+    fn unlock_on_chargeback_reversal(
+        &self,
+        _transaction: &Transaction,
+        _account: &Account,
+    ) -> bool {
+        self.unlock_on_chargeback_reversal
+    }
+}
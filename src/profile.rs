@@ -0,0 +1,193 @@
+//! Time and allocations spent in each phase of a run, to see where throughput goes.
+//!
+//! Allocations are only counted if the program installs [`CountingAllocator`] as its global
+//! allocator and turns counting on with [`CountingAllocator::start`], otherwise they read as
+//! zero. They are counted across all threads, so a phase also gets the allocations of background
+//! work like webhook deliveries happening meanwhile.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::fmt::{Display, Formatter};
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::AddAssign;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+static COUNTING: AtomicBool = AtomicBool::new(false);
+
+/// The system allocator, counting allocations once started, so runs that don't profile only pay
+/// for checking whether to count.
+pub struct CountingAllocator;
+
+impl CountingAllocator {
+    /// Counts the allocations from now on.
+    pub fn start() {
+        COUNTING.store(true, Ordering::Relaxed);
+    }
+
+    fn count(size: usize) {
+        if COUNTING.load(Ordering::Relaxed) {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            ALLOCATED_BYTES.fetch_add(size as u64, Ordering::Relaxed);
+        }
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        Self::count(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        Self::count(new_size);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// Clock and allocation counters at one point in time.
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    at: Instant,
+    allocations: u64,
+    allocated_bytes: u64,
+}
+
+impl Sample {
+    pub fn now() -> Self {
+        Self {
+            at: Instant::now(),
+            allocations: ALLOCATIONS.load(Ordering::Relaxed),
+            allocated_bytes: ALLOCATED_BYTES.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct PhaseStats {
+    pub elapsed: Duration,
+    pub allocations: u64,
+    pub allocated_bytes: u64,
+}
+
+impl PhaseStats {
+    /// Adds what was spent since `start`.
+    pub fn add_since(&mut self, start: Sample) {
+        let now = Sample::now();
+        self.elapsed += now.at - start.at;
+        self.allocations += now.allocations - start.allocations;
+        self.allocated_bytes += now.allocated_bytes - start.allocated_bytes;
+    }
+
+    pub(crate) fn saturating_sub(self, other: PhaseStats) -> PhaseStats {
+        PhaseStats {
+            elapsed: self.elapsed.saturating_sub(other.elapsed),
+            allocations: self.allocations.saturating_sub(other.allocations),
+            allocated_bytes: self.allocated_bytes.saturating_sub(other.allocated_bytes),
+        }
+    }
+}
+
+impl AddAssign for PhaseStats {
+    fn add_assign(&mut self, other: PhaseStats) {
+        self.elapsed += other.elapsed;
+        self.allocations += other.allocations;
+        self.allocated_bytes += other.allocated_bytes;
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Profile {
+    /// Reading the raw input.
+    pub read: PhaseStats,
+    /// Splitting rows and converting them to transactions.
+    pub parse: PhaseStats,
+    /// Executing transactions against the ledger.
+    pub execute: PhaseStats,
+    /// Writing the account report.
+    pub report: PhaseStats,
+}
+
+impl Display for Profile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:<8} {:>12} {:>12} {:>14}",
+            "phase", "time (ms)", "allocations", "bytes"
+        )?;
+        let phases = [
+            ("read", self.read),
+            ("parse", self.parse),
+            ("execute", self.execute),
+            ("report", self.report),
+        ];
+        for (i, (name, stats)) in phases.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(
+                f,
+                "{:<8} {:>12.3} {:>12} {:>14}",
+                name,
+                stats.elapsed.as_secs_f64() * 1000.0,
+                stats.allocations,
+                stats.allocated_bytes
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Accumulates the time spent reading from the inner reader.
+pub(crate) struct TimedReader<R> {
+    pub(crate) inner: R,
+    pub(crate) stats: Option<Rc<Cell<PhaseStats>>>,
+}
+
+impl<R: Read> Read for TimedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let stats = match &self.stats {
+            Some(stats) => stats,
+            None => return self.inner.read(buf),
+        };
+        let start = Sample::now();
+        let read = self.inner.read(buf);
+        let mut total = stats.get();
+        total.add_since(start);
+        stats.set(total);
+        read
+    }
+}
+
+/// For inputs read in pages, like SQLite databases; the time goes into the reads.
+impl<R: Seek> Seek for TimedReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Transakt;
+
+    #[test]
+    fn profile_phases() {
+        let input = "type,client,tx,amount\ndeposit,1,1,2.0\nwithdrawal,1,2,1.0\n";
+        let mut transakt = Transakt::builder().profile().build();
+        transakt
+            .load_csv_from("input.csv", input.as_bytes())
+            .unwrap();
+        let profile = transakt.profile().unwrap();
+        assert!(profile.read.elapsed > std::time::Duration::ZERO);
+        assert!(profile.execute.elapsed > std::time::Duration::ZERO);
+        assert_eq!(profile.report, Default::default());
+        assert!(Transakt::default().profile().is_none());
+    }
+}
@@ -0,0 +1,226 @@
+//! Keeping customer data out of diagnostics, for log aggregation in regulated environments.
+//! Configured in the `[output]` section:
+//!
+//! ```toml
+//! [output]
+//! # `off`, `mask` to replace client ids and amounts with `***`, or `hash`
+//! redaction = "hash"
+//! # Secret the hashes are keyed with, so they can't be reversed by hashing every client id
+//! redaction_key = "..."
+//! ```
+//!
+//! Redaction covers logs, the audit log and error messages: everything formatted with `Debug`
+//! goes through [`ClientId`] and [`Currency`], which redact themselves, and log fields use
+//! [`client`] and [`amount`]. Account reports, snapshots and the journal are data, not
+//! diagnostics, and are never redacted. Hashes are stable for a key, so the activity of one
+//! client can still be followed.
+//!
+//! A redacted audit log can't be read back by the `audit` subcommand.
+
+use crate::currency::Currency;
+use crate::transaction::ClientId;
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::OnceLock;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RedactionMode {
+    #[default]
+    Off,
+    Mask,
+    Hash,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Redaction {
+    pub mode: RedactionMode,
+    pub key: String,
+}
+
+static REDACTION: OnceLock<Redaction> = OnceLock::new();
+
+/// Sets the redaction of the whole process. Only the first call has an effect, so it belongs at
+/// startup, before anything is logged.
+pub fn init(redaction: Redaction) {
+    let _ = REDACTION.set(redaction);
+}
+
+pub(crate) fn current() -> &'static Redaction {
+    static OFF: Redaction = Redaction {
+        mode: RedactionMode::Off,
+        key: String::new(),
+    };
+    REDACTION.get().unwrap_or(&OFF)
+}
+
+impl Redaction {
+    pub fn is_off(&self) -> bool {
+        self.mode == RedactionMode::Off
+    }
+
+    pub fn client(&self, client: ClientId) -> String {
+        self.value("client", &client.to_string())
+    }
+
+    pub fn amount(&self, amount: Currency) -> String {
+        self.value("amount", &amount.to_string())
+    }
+
+    /// An identifier of the client in another system, see [`crate::denylist`].
+    pub fn alias(&self, alias: &str) -> String {
+        self.value("alias", alias)
+    }
+
+    fn value(&self, kind: &str, raw: &str) -> String {
+        match self.mode {
+            RedactionMode::Off => raw.to_string(),
+            RedactionMode::Mask => "***".to_string(),
+            RedactionMode::Hash => {
+                let mut hasher = Sha256::new();
+                for part in [self.key.as_str(), kind, raw].iter() {
+                    hasher.update(part.as_bytes());
+                    hasher.update([0]);
+                }
+                let digest = hasher.finalize();
+                let hex: String = digest[..6]
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect();
+                format!("#{}", hex)
+            }
+        }
+    }
+
+    /// Redacts the client ids, aliases and amounts of a JSON document, like an audit record or
+    /// a risk event.
+    pub(crate) fn json(&self, value: &mut Value) {
+        match value {
+            Value::Object(fields) => {
+                for (name, field) in fields.iter_mut() {
+                    let redacted = match (name.as_str(), &*field) {
+                        ("client", Value::Number(id)) => {
+                            Some(self.value("client", &id.to_string()))
+                        }
+                        ("amount" | "available" | "held" | "total", Value::String(amount)) => {
+                            Some(self.value("amount", amount))
+                        }
+                        _ => None,
+                    };
+                    match (redacted, name.as_str(), field) {
+                        (Some(redacted), _, field) => *field = Value::String(redacted),
+                        (None, "aliases", Value::Array(aliases)) => {
+                            for alias in aliases.iter_mut() {
+                                if let Value::String(raw) = alias {
+                                    *alias = Value::String(self.alias(raw));
+                                }
+                            }
+                        }
+                        (None, _, field) => self.json(field),
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.json(item)),
+            _ => {}
+        }
+    }
+}
+
+/// A value formatted with the redaction of the process.
+pub struct Redacted(Inner);
+
+enum Inner {
+    Client(ClientId),
+    Amount(Currency),
+    UnparsedAmount(String),
+}
+
+/// The client id as it may appear in logs.
+pub fn client(client: ClientId) -> Redacted {
+    Redacted(Inner::Client(client))
+}
+
+/// The amount as it may appear in logs.
+pub fn amount(amount: Currency) -> Redacted {
+    Redacted(Inner::Amount(amount))
+}
+
+/// Text that was meant to be an amount but didn't parse, as it may appear in logs.
+pub fn unparsed_amount(text: &str) -> Redacted {
+    Redacted(Inner::UnparsedAmount(text.to_string()))
+}
+
+impl Display for Redacted {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let redaction = current();
+        match &self.0 {
+            Inner::Client(client) => f.write_str(&redaction.client(*client)),
+            Inner::Amount(amount) => f.write_str(&redaction.amount(*amount)),
+            Inner::UnparsedAmount(text) => f.write_str(&redaction.value("amount", text)),
+        }
+    }
+}
+
+impl Debug for Redacted {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Redaction, RedactionMode};
+    use crate::currency::Currency;
+    use crate::transaction::ClientId;
+    use serde_json::json;
+
+    #[test]
+    fn redaction() {
+        let amount = Currency::new(12, 5).unwrap();
+        let off = Redaction::default();
+        assert_eq!(off.client(ClientId::new(7)), "7");
+        assert_eq!(off.amount(amount), "12.0005");
+        let mask = Redaction {
+            mode: RedactionMode::Mask,
+            key: String::new(),
+        };
+        assert_eq!(mask.client(ClientId::new(7)), "***");
+
+        let hash = |key: &str| Redaction {
+            mode: RedactionMode::Hash,
+            key: key.to_string(),
+        };
+        let hashed = hash("secret").client(ClientId::new(7));
+        assert_eq!(hashed.len(), 13);
+        assert_eq!(hashed, hash("secret").client(ClientId::new(7)));
+        assert_ne!(hashed, hash("other").client(ClientId::new(7)));
+        assert_ne!(hashed, hash("secret").client(ClientId::new(8)));
+
+        let mut record = json!({
+            "seq": 3,
+            "transaction": {"type": "deposit", "client": 7, "tx": 3, "amount": "12.0005"},
+            "before": null,
+            "after": {"client": 7, "available": "12.0005", "held": "0.0000", "locked": false}
+        });
+        mask.json(&mut record);
+        assert_eq!(
+            record,
+            json!({
+                "seq": 3,
+                "transaction": {"type": "deposit", "client": "***", "tx": 3, "amount": "***"},
+                "before": null,
+                "after": {"client": "***", "available": "***", "held": "***", "locked": false}
+            })
+        );
+
+        let mut event =
+            json!({"event": "denied_client", "client": 7, "tx": 3, "aliases": ["acme"]});
+        mask.json(&mut event);
+        assert_eq!(
+            event,
+            json!({"event": "denied_client", "client": "***", "tx": 3, "aliases": ["***"]})
+        );
+    }
+}
@@ -0,0 +1,178 @@
+//! Rejected rows of CSV inputs, grouped by error code, so a run ends with one line per kind of
+//! problem instead of one per row.
+//!
+//! The rows themselves can be kept too, see
+//! [`TransaktBuilder::rejected_rows`](crate::TransaktBuilder::rejected_rows), for a report to
+//! hand back to whoever sent the input.
+
+use crate::explain::explain;
+use crate::Error;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+
+/// How many line numbers are kept per error code.
+const EXAMPLES: usize = 3;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RejectionSummary {
+    groups: BTreeMap<&'static str, RejectionGroup>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RejectionGroup {
+    pub count: u64,
+    /// Lines of the first rejected rows, counting the header as line 1.
+    pub lines: Vec<u64>,
+}
+
+impl RejectionSummary {
+    pub fn record(&mut self, line: u64, err: &Error) {
+        let group = self.groups.entry(err.code()).or_default();
+        group.count += 1;
+        if group.lines.len() < EXAMPLES {
+            group.lines.push(line);
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.groups.values().map(|group| group.count).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Groups by error code, in code order.
+    pub fn groups(&self) -> impl Iterator<Item = (&'static str, &RejectionGroup)> {
+        self.groups.iter().map(|(code, group)| (*code, group))
+    }
+
+    /// Logs one warning per error code.
+    pub fn log(&self) {
+        for (code, group) in self.groups() {
+            tracing::warn!(
+                code,
+                count = group.count,
+                lines = %group.example_lines(),
+                "rows rejected: {}",
+                explain(code).map_or("", |explanation| explanation.summary)
+            );
+        }
+    }
+}
+
+impl RejectionGroup {
+    fn example_lines(&self) -> String {
+        let lines: Vec<String> = self.lines.iter().map(u64::to_string).collect();
+        if self.count > self.lines.len() as u64 {
+            format!("{}, ...", lines.join(", "))
+        } else {
+            lines.join(", ")
+        }
+    }
+}
+
+/// A row that failed parsing or execution.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RejectedRow {
+    /// Counting the header as line 1 for CSV inputs, see the input formats for the others.
+    pub line: u64,
+    /// The fields of the row joined by the delimiter, for CSV inputs only.
+    pub row: Option<String>,
+    pub code: &'static str,
+    pub reason: String,
+}
+
+impl RejectedRow {
+    pub fn new(line: u64, row: Option<String>, err: &Error) -> Self {
+        Self {
+            line,
+            row,
+            code: err.code(),
+            reason: format!("{:?}", err),
+        }
+    }
+}
+
+/// Writes rejected rows as CSV, `line,row,code,reason`.
+pub fn write_rejected_csv<W: Write>(rows: &[RejectedRow], writer: W) -> Result<(), Error> {
+    let mut out = csv::Writer::from_writer(writer);
+    for row in rows {
+        out.serialize(row).map_err(|err| Error::Io(err.into()))?;
+    }
+    out.flush().map_err(Error::Io)
+}
+
+/// Writes rejected rows as a JSON array, an object per row with the columns of
+/// [`write_rejected_csv`] as fields.
+pub fn write_rejected_json<W: Write>(rows: &[RejectedRow], mut writer: W) -> Result<(), Error> {
+    serde_json::to_writer(&mut writer, rows).map_err(|err| Error::Io(err.into()))?;
+    writeln!(writer).map_err(Error::Io)?;
+    writer.flush().map_err(Error::Io)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_rejected_csv, write_rejected_json};
+    use crate::Transakt;
+
+    #[test]
+    fn rejections_grouped() {
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,2.0\n\
+                     withdrawal,1,2,5.0\n\
+                     deposit,1,1,1.0\n\
+                     withdrawal,1,3,5.0\n\
+                     withdrawal,1,4,5.0\n\
+                     withdrawal,1,5,5.0\n\
+                     withdrawal,1,6,1.0\n";
+        let mut transakt = Transakt::default();
+        transakt
+            .load_csv_from("input.csv", input.as_bytes())
+            .unwrap();
+        let rejections = transakt.rejections();
+        assert_eq!(rejections.total(), 5);
+        let groups: Vec<(&str, u64, Vec<u64>)> = rejections
+            .groups()
+            .map(|(code, group)| (code, group.count, group.lines.clone()))
+            .collect();
+        assert_eq!(
+            groups,
+            vec![("E_DUP_TX", 1, vec![4]), ("E_FUNDS", 4, vec![3, 5, 6])]
+        );
+    }
+
+    #[test]
+    fn rejected_rows() {
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,2.0\n\
+                     withdrawal,1,2,5.0\n\
+                     deposit,1,3,a lot\n\
+                     deposit,1,4,1.0\n";
+        let mut transakt = Transakt::builder().rejected_rows().build();
+        assert!(transakt
+            .load_csv_from("input.csv", input.as_bytes())
+            .is_err());
+        let mut csv = vec![];
+        write_rejected_csv(transakt.rejected_rows(), &mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "line,row,code,reason\n\
+             3,\"withdrawal,1,2,5.0\",E_FUNDS,InsufficientFunds\n\
+             4,\"deposit,1,3,a lot\",E_PARSE,TransactionParseError\n"
+        );
+        let mut json = vec![];
+        write_rejected_json(&transakt.rejected_rows()[..1], &mut json).unwrap();
+        assert_eq!(
+            String::from_utf8(json).unwrap(),
+            "[{\"line\":3,\"row\":\"withdrawal,1,2,5.0\",\"code\":\"E_FUNDS\",\
+             \"reason\":\"InsufficientFunds\"}]\n"
+        );
+        // Not kept unless asked for
+        let input = "type,client,tx,amount\nwithdrawal,1,1,5.0\n";
+        let transakt = Transakt::read_from_reader(input.as_bytes()).unwrap();
+        assert_eq!(transakt.rejections().total(), 1);
+        assert!(transakt.rejected_rows().is_empty());
+    }
+}
@@ -0,0 +1,196 @@
+//! Changes to the configuration file applied while [`Transakt::serve`] runs, without a restart.
+//! The file is read on an interval, and when it changed these settings replace the ones in force
+//! between two transactions, keeping balances, disputes and what the rules and KYC limits
+//! recorded so far:
+//!
+//! * `max_amount` and `large_transaction_threshold` of the `[engine]` section
+//! * the `[rules]` section, with the dispute window and the blocked clients
+//! * the `[denylist]` section, which replaces clients denied through [`Transakt::denylist_mut`]
+//! * `default_tier` and `limits` of the `[kyc]` section
+//!
+//! `TRANSAKT_*` environment variables still take precedence. Other settings apply on the next
+//! start, and changing them logs a warning. A file that can't be parsed is ignored with a
+//! warning, leaving the settings in force until it is fixed. A dispute window set while running
+//! only covers deposits applied from then on.
+
+use crate::config::Config;
+use crate::kyc::{load_tiers, KycConfig, KycLimits};
+use crate::rules::RuleEngine;
+use crate::{Error, Transakt};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub struct ConfigReload {
+    pub path: PathBuf,
+    /// How often the file is read.
+    pub every: Duration,
+}
+
+impl ConfigReload {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            every: Duration::from_secs(5),
+        }
+    }
+}
+
+/// The file as last read, and the configuration in force.
+pub(crate) struct ConfigWatcher<'a> {
+    reload: &'a ConfigReload,
+    text: Option<String>,
+    config: Option<Config>,
+}
+
+impl<'a> ConfigWatcher<'a> {
+    /// Starts from the file as it is now, which the engine is assumed to be built with.
+    pub(crate) fn new(reload: &'a ConfigReload) -> Self {
+        let text = std::fs::read_to_string(&reload.path).ok();
+        let config = text.as_deref().and_then(|text| load(text).ok());
+        Self {
+            reload,
+            text,
+            config,
+        }
+    }
+
+    /// Applies the file to the engine if it changed since it was last read.
+    pub(crate) fn check(&mut self, transakt: &mut Transakt) {
+        let path = self.reload.path.display();
+        let text = match std::fs::read_to_string(&self.reload.path) {
+            Ok(text) => text,
+            // Likely being replaced, it is read again on the next check
+            Err(err) => {
+                tracing::debug!(path = %path, error = %err, "config not readable");
+                return;
+            }
+        };
+        if self.text.as_ref() == Some(&text) {
+            return;
+        }
+        let config = load(&text).and_then(|config| transakt.reload_config(&config).map(|_| config));
+        self.text = Some(text);
+        let config = match config {
+            Ok(config) => config,
+            Err(err) => {
+                tracing::warn!(path = %path, code = err.code(), error = ?err, "config not reloaded");
+                return;
+            }
+        };
+        if let Some(previous) = &self.config {
+            if !reloadable_only(previous, &config) {
+                tracing::warn!(path = %path, "other config changes apply on restart");
+            }
+        }
+        tracing::info!(path = %path, "config reloaded");
+        self.config = Some(config);
+    }
+}
+
+fn load(text: &str) -> Result<Config, Error> {
+    let mut config = Config::parse(text)?;
+    config.apply_env()?;
+    Ok(config)
+}
+
+/// Whether the configurations differ only in settings [`Transakt::reload_config`] applies.
+fn reloadable_only(previous: &Config, config: &Config) -> bool {
+    let mut config = config.clone();
+    config.engine.max_amount = previous.engine.max_amount;
+    config.engine.large_transaction_threshold = previous.engine.large_transaction_threshold;
+    config.rules = previous.rules.clone();
+    config.denylist = previous.denylist.clone();
+    config.kyc.default_tier = previous.kyc.default_tier.clone();
+    config.kyc.limits = previous.kyc.limits.clone();
+    config == *previous
+}
+
+impl Transakt {
+    /// Applies the limits, rules and denylist of the configuration, see the
+    /// [module docs](self), keeping the state of the engine. Nothing is applied on an error.
+    pub fn reload_config(&mut self, config: &Config) -> Result<(), Error> {
+        // Read before changing anything, for KYC limits configured while running
+        let tiers = match (&self.kyc, &config.kyc.tiers_file) {
+            (None, Some(path)) => load_tiers(path)?,
+            _ => Default::default(),
+        };
+        self.max_amount = config.engine.max_amount;
+        self.large_transaction_threshold = config.engine.large_transaction_threshold;
+        match self.rules.as_mut() {
+            Some(engine) => engine.set_rules(config.rules.clone()),
+            None if !config.rules.is_empty() => {
+                self.rules = Some(RuleEngine::new(config.rules.clone()))
+            }
+            None => {}
+        }
+        self.denylist = config.denylist.clone();
+        match self.kyc.as_mut() {
+            Some(kyc) => kyc.set_config(config.kyc.clone()),
+            None if config.kyc != KycConfig::default() => {
+                self.kyc = Some(KycLimits::new(config.kyc.clone(), tiers))
+            }
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConfigReload, ConfigWatcher};
+    use crate::config::Config;
+    use crate::transaction::ClientId;
+    use crate::Transakt;
+
+    #[test]
+    fn reload_keeps_state() {
+        let dir = std::env::temp_dir().join(format!("transakt-reload-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("transakt.toml");
+        let settings = "[rules]\ndispute_window = 100\n";
+        std::fs::write(&path, settings).unwrap();
+        let config = Config::load(&path).unwrap();
+        let mut transakt = Transakt::builder().rules(config.rules).build();
+        let reload = ConfigReload::new(&path);
+        let mut watcher = ConfigWatcher::new(&reload);
+        let input = "type,client,tx,amount\ndeposit,1,1,5.0\n";
+        transakt.load_csv_from("first", input.as_bytes()).unwrap();
+
+        // Unchanged, nothing to apply
+        watcher.check(&mut transakt);
+        assert!(!transakt.denylist_mut().is_denied(ClientId::new(2)));
+
+        let settings = "[rules]\ndispute_window = 1\n\n[rules.max_amount]\ndeposit = \"10\"\n\n\
+                        [denylist]\nclients = [2]\n";
+        std::fs::write(&path, settings).unwrap();
+        watcher.check(&mut transakt);
+        let input = "type,client,tx,amount\n\
+                     deposit,1,2,1.0\n\
+                     deposit,1,3,20.0\n\
+                     deposit,2,4,1.0\n\
+                     deposit,1,5,1.0\n\
+                     dispute,1,1,\n";
+        transakt.load_csv_from("second", input.as_bytes()).unwrap();
+        let account = &transakt.get_accounts_map()[&ClientId::new(1)];
+        // The first deposit is kept and out of the new window, the large one over the cap
+        assert_eq!(account.available().to_string(), "7.0000");
+        assert_eq!(account.held().to_string(), "0.0000");
+        assert!(!transakt.get_accounts_map().contains_key(&ClientId::new(2)));
+        let codes: Vec<&str> = transakt
+            .rejections()
+            .groups()
+            .map(|(code, _)| code)
+            .collect();
+        assert_eq!(codes, ["E_DENIED", "E_RULE"]);
+
+        // A broken file leaves the settings in force
+        std::fs::write(&path, "[rules\n").unwrap();
+        watcher.check(&mut transakt);
+        assert!(transakt.denylist_mut().is_denied(ClientId::new(2)));
+        std::fs::write(&path, "").unwrap();
+        watcher.check(&mut transakt);
+        assert!(!transakt.denylist_mut().is_denied(ClientId::new(2)));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
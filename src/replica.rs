@@ -0,0 +1,126 @@
+//! Read-only copies of the state of a [`Transakt::serve`](crate::Transakt::serve) process, so
+//! balance queries can be answered elsewhere than on the node applying transactions. A replica
+//! loads the latest snapshot in the snapshot directory and switches to a newer one when it shows
+//! up, looking at most once per interval. Its answers are as old as the snapshot it holds; the
+//! journal written since is not followed.
+
+use crate::serve::SnapshotPolicy;
+use crate::snapshot::Snapshot;
+use crate::Error;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+pub struct Replica {
+    policy: SnapshotPolicy,
+    /// How often the directory is checked for a newer snapshot.
+    pub every: Duration,
+    current: Option<(PathBuf, Snapshot)>,
+    checked_at: Option<Instant>,
+}
+
+impl Replica {
+    /// Reads the snapshots of `policy`, with its cipher if encrypted; the other settings are
+    /// those of the serving process and are not used.
+    pub fn new(policy: SnapshotPolicy) -> Self {
+        Self {
+            policy,
+            every: Duration::from_secs(30),
+            current: None,
+            checked_at: None,
+        }
+    }
+
+    /// Loads the latest snapshot if it isn't the one held, returning whether it was. A snapshot
+    /// that can't be loaded, like one pruned in the meantime, leaves the one held in place.
+    pub fn refresh(&mut self) -> Result<bool, Error> {
+        self.checked_at = Some(Instant::now());
+        let latest = match self.policy.latest()? {
+            Some(latest) => latest,
+            None => return Ok(false),
+        };
+        if self
+            .current
+            .as_ref()
+            .is_some_and(|(path, _)| *path == latest)
+        {
+            return Ok(false);
+        }
+        match self.policy.load(&latest) {
+            Ok(snapshot) => {
+                tracing::info!(path = %latest.display(), applied = snapshot.applied, "replica loaded snapshot");
+                self.current = Some((latest, snapshot));
+                Ok(true)
+            }
+            Err(err) => {
+                tracing::warn!(path = %latest.display(), code = err.code(), error = ?err, "snapshot not loaded");
+                Ok(false)
+            }
+        }
+    }
+
+    /// The snapshot to answer from, after checking for a newer one if due. `None` until the
+    /// serving process saved one.
+    pub fn snapshot(&mut self) -> Result<Option<&Snapshot>, Error> {
+        let due = self
+            .checked_at
+            .is_none_or(|checked_at| checked_at.elapsed() >= self.every);
+        if due {
+            self.refresh()?;
+        }
+        Ok(self.current.as_ref().map(|(_, snapshot)| snapshot))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Replica;
+    use crate::ingest::IngestOptions;
+    use crate::serve::SnapshotPolicy;
+    use crate::transaction::ClientId;
+    use crate::Transakt;
+    use std::time::Duration;
+
+    #[test]
+    fn replica_follows_snapshots() {
+        let dir = std::env::temp_dir().join(format!("transakt-replica-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let policy = SnapshotPolicy::new(&dir);
+        let mut replica = Replica::new(policy.clone());
+        replica.every = Duration::ZERO;
+        assert!(replica.snapshot().unwrap().is_none());
+
+        let available = |replica: &mut Replica| {
+            let snapshot = replica.snapshot().unwrap().unwrap();
+            snapshot
+                .account(ClientId::new(1))
+                .unwrap()
+                .available
+                .to_string()
+        };
+        let options = IngestOptions::default();
+        for day in 1..=2u32 {
+            let input = format!("type,client,tx,amount\ndeposit,1,{},1.0\n", day);
+            Transakt::default()
+                .serve(std::io::Cursor::new(input), &policy, &options, None)
+                .unwrap();
+            assert_eq!(available(&mut replica), format!("{}.0000", day));
+        }
+
+        // Not checked again before the interval
+        replica.every = Duration::from_secs(3600);
+        let input = "type,client,tx,amount\ndeposit,1,3,1.0\n";
+        Transakt::default()
+            .serve(std::io::Cursor::new(input), &policy, &options, None)
+            .unwrap();
+        assert_eq!(available(&mut replica), "2.0000");
+        assert!(replica.refresh().unwrap());
+        assert_eq!(available(&mut replica), "3.0000");
+
+        // A broken snapshot keeps the one held
+        std::fs::write(dir.join("snapshot-00000000000000000099.json"), "{").unwrap();
+        assert!(!replica.refresh().unwrap());
+        assert_eq!(available(&mut replica), "3.0000");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
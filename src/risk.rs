@@ -0,0 +1,165 @@
+//! A running risk score per account, from signals weighted in the `[risk]` section of the
+//! configuration. The section turns scoring on; weights left out keep their defaults.
+//!
+//! ```toml
+//! [risk]
+//! chargeback = 50.0
+//! rule_violation = 10.0
+//! failed_withdrawal = 5.0
+//! dispute_ratio = 100.0
+//! ```
+//!
+//! The score is the sum of each signal times its weight. Like the rule state, the signals are not
+//! part of snapshots, so scores start over after a restore.
+
+use crate::transaction::{ClientId, Transaction};
+use crate::{Error, Transakt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RiskWeights {
+    /// Per chargeback.
+    pub chargeback: f64,
+    /// Per transaction rejected by the `[rules]`, like one over the maximum amount.
+    pub rule_violation: f64,
+    /// Per rejected withdrawal.
+    pub failed_withdrawal: f64,
+    /// Times the share of deposits that were disputed, from 0 to 1.
+    pub dispute_ratio: f64,
+}
+
+impl Default for RiskWeights {
+    fn default() -> Self {
+        Self {
+            chargeback: 50.0,
+            rule_violation: 10.0,
+            failed_withdrawal: 5.0,
+            dispute_ratio: 100.0,
+        }
+    }
+}
+
+/// What happened on an account so far that makes up its score.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+pub struct RiskSignals {
+    pub chargebacks: u32,
+    pub rule_violations: u32,
+    pub failed_withdrawals: u32,
+    pub deposits: u32,
+    pub disputes: u32,
+}
+
+impl RiskSignals {
+    /// Disputes per deposit, 0 without deposits.
+    pub fn dispute_ratio(&self) -> f64 {
+        if self.deposits == 0 {
+            0.0
+        } else {
+            f64::from(self.disputes) / f64::from(self.deposits)
+        }
+    }
+
+    pub fn score(&self, weights: &RiskWeights) -> f64 {
+        f64::from(self.chargebacks) * weights.chargeback
+            + f64::from(self.rule_violations) * weights.rule_violation
+            + f64::from(self.failed_withdrawals) * weights.failed_withdrawal
+            + self.dispute_ratio() * weights.dispute_ratio
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct RiskScoring {
+    weights: RiskWeights,
+    signals: HashMap<ClientId, RiskSignals>,
+}
+
+impl RiskScoring {
+    pub(crate) fn new(weights: RiskWeights) -> Self {
+        Self {
+            weights,
+            signals: HashMap::new(),
+        }
+    }
+}
+
+impl Transakt {
+    /// Risk score of the client's account, if scoring is on. Clients without a signal score 0.
+    pub fn risk_score(&self, client: ClientId) -> Option<f64> {
+        let scoring = self.risk.as_ref()?;
+        let signals = scoring.signals.get(&client).copied().unwrap_or_default();
+        Some(signals.score(&scoring.weights))
+    }
+
+    /// What makes up the client's risk score, if scoring is on.
+    pub fn risk_signals(&self, client: ClientId) -> Option<RiskSignals> {
+        let scoring = self.risk.as_ref()?;
+        Some(scoring.signals.get(&client).copied().unwrap_or_default())
+    }
+
+    /// Updates the signals of the transaction's client with its outcome.
+    pub(crate) fn record_risk(&mut self, transaction: &Transaction, res: Result<(), &Error>) {
+        let scoring = match self.risk.as_mut() {
+            Some(scoring) => scoring,
+            None => return,
+        };
+        let signals = scoring.signals.entry(transaction.client()).or_default();
+        match (transaction, res) {
+            (Transaction::Deposit { .. }, Ok(())) => signals.deposits += 1,
+            (Transaction::Dispute { .. }, Ok(())) => signals.disputes += 1,
+            (Transaction::Chargeback { .. }, Ok(())) => signals.chargebacks += 1,
+            (_, Err(Error::RuleViolation(_))) => signals.rule_violations += 1,
+            (Transaction::Withdrawal { .. }, Err(_)) => signals.failed_withdrawals += 1,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RiskSignals;
+    use crate::config::Config;
+    use crate::transaction::ClientId;
+    use crate::Transakt;
+
+    #[test]
+    fn risk_scores() {
+        let config = Config::parse(
+            "[risk]\n\
+             chargeback = 40.0\n\
+             [rules.max_amount]\n\
+             withdrawal = \"100\"\n",
+        )
+        .unwrap();
+        let mut transakt = Transakt::builder()
+            .rules(config.rules)
+            .risk_scoring(config.risk.unwrap())
+            .build();
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,10.0\n\
+                     deposit,1,2,10.0\n\
+                     withdrawal,1,3,500.0\n\
+                     withdrawal,1,4,50.0\n\
+                     dispute,1,1,\n\
+                     chargeback,1,1,\n\
+                     deposit,2,5,10.0\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        let signals = transakt.risk_signals(ClientId::new(1)).unwrap();
+        assert_eq!(
+            signals,
+            RiskSignals {
+                chargebacks: 1,
+                rule_violations: 1,
+                // The one over the maximum counts as a rule violation
+                failed_withdrawals: 1,
+                deposits: 2,
+                disputes: 1,
+            }
+        );
+        // 40 + 10 + 5 + 0.5 * 100
+        assert_eq!(transakt.risk_score(ClientId::new(1)), Some(105.0));
+        assert_eq!(transakt.risk_score(ClientId::new(2)), Some(0.0));
+        assert_eq!(Transakt::default().risk_score(ClientId::new(1)), None);
+    }
+}
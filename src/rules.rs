@@ -0,0 +1,335 @@
+//! Constraints checked before executing each transaction, loaded from the `[rules]` section of
+//! the configuration so they can change without rebuilding.
+//!
+//! ```toml
+//! [rules]
+//! blocked_clients = [13, 42]
+//! dispute_window = 10000
+//! lock_after_disputes = 3
+//!
+//! [rules.max_amount]
+//! deposit = "50000"
+//! withdrawal = "10000"
+//!
+//! # Lock accounts with more than one chargeback per 20 deposits in the last 1000 transactions
+//! [rules.chargeback_ratio]
+//! threshold = 0.05
+//! window = 1000
+//! action = "lock"
+//! ```
+//!
+//! The chargeback ratio is a softer alternative to locking on every chargeback, for which the
+//! [processing policy](crate::policy::ProcessingPolicy::lock_on_chargeback) has to stop locking.
+
+use crate::currency::Currency;
+use crate::event::RiskEvent;
+use crate::redact;
+use crate::transaction::{ClientId, Transaction, TransactionId};
+use crate::{Error, Transakt};
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuleSet {
+    /// Every transaction of these clients is rejected.
+    pub blocked_clients: Vec<u16>,
+    pub max_amount: MaxAmount,
+    /// A deposit can only be disputed within this many applied transactions after it.
+    pub dispute_window: Option<u64>,
+    /// Lock an account once this many of its deposits have been disputed.
+    pub lock_after_disputes: Option<u32>,
+    pub chargeback_ratio: Option<ChargebackRatio>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChargebackRatio {
+    /// Most chargebacks per deposit of a client within the window. Chargebacks without any
+    /// deposit in the window count as if there was one.
+    pub threshold: f64,
+    /// Rolling window, in applied transactions.
+    pub window: u64,
+    #[serde(default)]
+    pub action: RatioAction,
+}
+
+/// What happens to an account over the chargeback ratio, besides a
+/// [`RiskEvent::ChargebackRatioExceeded`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RatioAction {
+    #[default]
+    Lock,
+    /// Only raise the event.
+    Flag,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MaxAmount {
+    pub deposit: Option<Currency>,
+    pub withdrawal: Option<Currency>,
+}
+
+impl RuleSet {
+    pub fn is_empty(&self) -> bool {
+        *self == RuleSet::default()
+    }
+}
+
+/// A [`RuleSet`] and what the engine has to remember to evaluate it. That memory is not part of
+/// snapshots, so after a restore the dispute window and count start over.
+#[derive(Debug, Default)]
+pub(crate) struct RuleEngine {
+    rules: RuleSet,
+    /// When each deposit was applied, if there is a dispute window.
+    deposited_at: HashMap<TransactionId, u64>,
+    disputes: HashMap<ClientId, u32>,
+    /// Deposits and chargebacks of each client, with when they were applied, if there is a
+    /// chargeback ratio. `true` for chargebacks.
+    recent: HashMap<ClientId, VecDeque<(u64, bool)>>,
+}
+
+impl RuleEngine {
+    pub(crate) fn new(rules: RuleSet) -> Self {
+        Self {
+            rules,
+            ..Self::default()
+        }
+    }
+
+    /// Replaces the rules, keeping what was recorded under the old ones.
+    pub(crate) fn set_rules(&mut self, rules: RuleSet) {
+        self.rules = rules;
+    }
+}
+
+impl Transakt {
+    /// Rejects the transaction if it breaks a rule.
+    pub(crate) fn check_rules(&self, transaction: &Transaction) -> Result<(), Error> {
+        let engine = match &self.rules {
+            Some(engine) => engine,
+            None => return Ok(()),
+        };
+        let rules = &engine.rules;
+        let client = transaction.client();
+        if rules.blocked_clients.contains(&client.id()) {
+            return Err(violation(format!(
+                "client {} is blocked",
+                redact::client(client)
+            )));
+        }
+        let max = match transaction {
+            Transaction::Deposit { .. } => rules.max_amount.deposit,
+            Transaction::Withdrawal { .. } => rules.max_amount.withdrawal,
+            _ => None,
+        };
+        if let (Some(max), Some(amount)) = (max, transaction.amount()) {
+            if amount > max {
+                return Err(violation(format!(
+                    "{} of {} is over the maximum of {}",
+                    transaction.kind(),
+                    redact::amount(amount),
+                    max
+                )));
+            }
+        }
+        if let (Transaction::Dispute { tx, .. }, Some(window)) = (transaction, rules.dispute_window)
+        {
+            if let Some(deposited_at) = engine.deposited_at.get(tx) {
+                if self.applied - deposited_at > window {
+                    return Err(violation(format!(
+                        "dispute of tx {} after {} transactions, the window is {}",
+                        tx,
+                        self.applied - deposited_at,
+                        window
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Updates the rule state after the transaction was applied, locking the account if it hit
+    /// the dispute limit.
+    pub(crate) fn record_rules(&mut self, transaction: &Transaction) {
+        let engine = match self.rules.as_mut() {
+            Some(engine) => engine,
+            None => return,
+        };
+        match transaction {
+            Transaction::Deposit { client, tx, .. } => {
+                if engine.rules.dispute_window.is_some() {
+                    engine.deposited_at.insert(*tx, self.applied);
+                }
+                if engine.rules.chargeback_ratio.is_some() {
+                    let recent = engine.recent.entry(*client).or_default();
+                    recent.push_back((self.applied, false));
+                }
+            }
+            Transaction::Chargeback { tx, .. } => {
+                let rule = match engine.rules.chargeback_ratio.as_ref() {
+                    Some(rule) => rule,
+                    None => return,
+                };
+                let client = match self.transactions.get(tx) {
+                    Some(charged_back) => charged_back.client(),
+                    None => return,
+                };
+                let applied = self.applied;
+                let recent = engine.recent.entry(client).or_default();
+                recent.push_back((applied, true));
+                while recent
+                    .front()
+                    .is_some_and(|(at, _)| applied - at >= rule.window)
+                {
+                    recent.pop_front();
+                }
+                let chargebacks = recent.iter().filter(|(_, chargeback)| *chargeback).count();
+                let deposits = recent.len() - chargebacks;
+                let ratio = chargebacks as f64 / deposits.max(1) as f64;
+                if ratio <= rule.threshold {
+                    return;
+                }
+                let action = rule.action;
+                let account = self.accounts.get_mut(&client);
+                let lock = match account {
+                    Some(account) if action == RatioAction::Lock && !account.is_locked() => {
+                        account.lock();
+                        true
+                    }
+                    _ => false,
+                };
+                tracing::debug!(ratio, "chargeback ratio exceeded");
+                self.emit(RiskEvent::ChargebackRatioExceeded {
+                    client,
+                    ratio,
+                    locked: lock,
+                });
+                if lock {
+                    self.account_locked(client);
+                }
+            }
+            Transaction::Dispute { tx, .. } => {
+                // Disputes of unknown transactions may be let through by the policy
+                let client = match self.transactions.get(tx) {
+                    Some(disputed) => disputed.client(),
+                    None => return,
+                };
+                let disputes = engine.disputes.entry(client).or_default();
+                *disputes += 1;
+                let limit_hit = engine
+                    .rules
+                    .lock_after_disputes
+                    .is_some_and(|limit| *disputes >= limit);
+                let account = self.accounts.get_mut(&client);
+                if let (true, Some(account)) = (limit_hit, account) {
+                    if !account.is_locked() {
+                        account.lock();
+                        tracing::debug!("locked after too many disputes");
+                        self.account_locked(client);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn violation(reason: String) -> Error {
+    let err = Error::RuleViolation(reason);
+    tracing::debug!(code = err.code(), error = ?err, "rule violated");
+    err
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChargebackRatio, RatioAction, RuleSet};
+    use crate::account::Account;
+    use crate::config::Config;
+    use crate::policy::ProcessingPolicy;
+    use crate::transaction::{ClientId, Transaction};
+    use crate::Transakt;
+
+    #[test]
+    fn rules_from_config() {
+        let config = Config::parse(
+            "[rules]\n\
+             blocked_clients = [3]\n\
+             dispute_window = 1\n\
+             lock_after_disputes = 2\n\
+             [rules.max_amount]\n\
+             withdrawal = \"5\"\n",
+        )
+        .unwrap();
+        assert!(!config.rules.is_empty());
+        assert!(RuleSet::default().is_empty());
+
+        let mut transakt = Transakt::builder().rules(config.rules).build();
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,20.0\n\
+                     deposit,1,2,1.0\n\
+                     deposit,1,3,1.0\n\
+                     withdrawal,1,4,6.0\n\
+                     deposit,3,5,1.0\n\
+                     dispute,1,1,\n\
+                     dispute,1,2,\n\
+                     dispute,1,3,\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        let rejections = transakt.rejections();
+        let rule_violation = rejections.groups().find(|(code, _)| *code == "E_RULE");
+        // The withdrawal, client 3 and the dispute of tx 1, out of the window
+        assert_eq!(rule_violation.unwrap().1.lines, vec![5, 6, 7]);
+        let account = transakt.get_accounts_map().get(&ClientId::new(1)).unwrap();
+        assert!(account.is_locked());
+        assert!(!transakt.get_accounts_map().contains_key(&ClientId::new(3)));
+    }
+
+    #[test]
+    fn chargeback_ratio() {
+        let config = Config::parse(
+            "[rules.chargeback_ratio]\n\
+             threshold = 0.4\n\
+             window = 5\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config.rules.chargeback_ratio,
+            Some(ChargebackRatio {
+                threshold: 0.4,
+                window: 5,
+                action: RatioAction::Lock,
+            })
+        );
+        let policy = NoChargebackLock;
+        let mut transakt = Transakt::builder()
+            .policy(policy)
+            .rules(config.rules)
+            .build();
+        let client = ClientId::new(1);
+        // One chargeback for three deposits is under the ratio
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,1.0\n\
+                     deposit,1,2,1.0\n\
+                     deposit,1,3,1.0\n\
+                     dispute,1,1,\n\
+                     chargeback,1,1,\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        assert!(!transakt.get_accounts_map()[&client].is_locked());
+        // By the second, the first two deposits are out of the window
+        let input = "type,client,tx,amount\n\
+                     dispute,1,2,\n\
+                     chargeback,1,2,\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        assert!(transakt.get_accounts_map()[&client].is_locked());
+    }
+
+    struct NoChargebackLock;
+
+    impl ProcessingPolicy for NoChargebackLock {
+        fn lock_on_chargeback(&self, _transaction: &Transaction, _account: &Account) -> bool {
+            false
+        }
+    }
+}
@@ -0,0 +1,285 @@
+//! Regression cases written down as text. A scenario has transaction rows, written like the rows
+//! of a CSV input without the header, and `expect` lines checking the accounts at that point:
+//!
+//! ```text
+//! # A resolved dispute gives the funds back
+//! deposit, 1, 1, 1.0
+//! dispute, 1, 1,
+//! resolve, 1, 1,
+//! withdrawal, 1, 2, 0.1
+//! expect client=1 total=0.9 held=0 locked=false
+//! expect client=2 exists=false
+//! withdrawal, 1, 3, 5
+//! expect rejected=E_FUNDS
+//! ```
+//!
+//! An expectation of a client checks any of `available`, `held`, `total` and `locked`, or with
+//! `exists=false` that the client has no account. `expect rejected=CODE` checks that the row
+//! before it was rejected with the error code. Blank lines and lines starting with `#` are
+//! skipped.
+//!
+//! `transakt scenario FILE...` runs scenarios with the engine of the configuration.
+
+use crate::diff::{check_expectations, Expectation};
+use crate::transaction::{ClientId, Transaction, TransactionRow};
+use crate::validate::Problem;
+use crate::{Error, TransaktBuilder};
+use csv::{StringRecord, Trim};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Columns of the rows, in order.
+const COLUMNS: [&str; 5] = ["type", "client", "tx", "amount", "signature"];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Row(Transaction, Option<String>),
+    Expect(Expectation),
+    NoAccount(ClientId),
+    Rejected(String),
+}
+
+/// A parsed scenario, see the [module documentation](self).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scenario {
+    /// Steps with their line.
+    steps: Vec<(u64, Step)>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ScenarioReport {
+    pub rows: u64,
+    pub expectations: u64,
+    pub failures: Vec<Problem>,
+}
+
+impl ScenarioReport {
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    fn failure(&mut self, line: u64, message: String) {
+        self.failures.push(Problem { line, message });
+    }
+}
+
+impl Scenario {
+    pub fn parse(text: &str) -> Result<Scenario, Error> {
+        let mut steps = vec![];
+        for (line, text) in (1..).zip(text.lines()) {
+            let text = text.trim();
+            if text.is_empty() || text.starts_with('#') {
+                continue;
+            }
+            let step = match text.strip_prefix("expect ") {
+                Some(expectation) => parse_expectation(expectation),
+                None => parse_row(text),
+            }
+            .map_err(|reason| Error::InvalidScenario(format!("line {}: {}", line, reason)))?;
+            if matches!(step, Step::Rejected(_))
+                && !matches!(steps.last(), Some((_, Step::Row(..))))
+            {
+                return Err(Error::InvalidScenario(format!(
+                    "line {}: `expect rejected` must follow a row",
+                    line
+                )));
+            }
+            steps.push((line, step));
+        }
+        Ok(Scenario { steps })
+    }
+
+    pub fn load(path: &Path) -> Result<Scenario, Error> {
+        Self::parse(&std::fs::read_to_string(path).map_err(Error::Io)?)
+    }
+
+    /// Runs the scenario on an engine from the builder. Rows may be rejected, but an error that
+    /// stops processing, like a broken invariant with verification on, fails the scenario there.
+    pub fn run(&self, builder: TransaktBuilder) -> ScenarioReport {
+        let mut transakt = builder.build();
+        let mut report = ScenarioReport::default();
+        // Line and rejection of the latest row
+        let mut last: Option<(u64, Option<Error>)> = None;
+        for (line, step) in self.steps.iter() {
+            let line = *line;
+            match step {
+                Step::Row(transaction, signature) => {
+                    report.rows += 1;
+                    let err = transakt
+                        .execute_signed(*transaction, signature.as_deref())
+                        .err();
+                    if let Some(err) = &err {
+                        if err.is_fatal() {
+                            report.failure(line, format!("stopped with {:?}", err));
+                            break;
+                        }
+                    }
+                    last = Some((line, err));
+                }
+                Step::Expect(expectation) => {
+                    report.expectations += 1;
+                    let accounts = transakt.snapshot().accounts;
+                    for mismatch in check_expectations(&accounts, std::slice::from_ref(expectation))
+                    {
+                        report.failure(line, mismatch);
+                    }
+                }
+                Step::NoAccount(client) => {
+                    report.expectations += 1;
+                    if transakt.get_accounts_map().contains_key(client) {
+                        report.failure(line, format!("client {}: has an account", client));
+                    }
+                }
+                Step::Rejected(code) => {
+                    report.expectations += 1;
+                    match &last {
+                        Some((row, None)) => report
+                            .failure(line, format!("line {} was applied, expected {}", row, code)),
+                        Some((row, Some(err))) if !err.code().eq_ignore_ascii_case(code) => report
+                            .failure(
+                                line,
+                                format!(
+                                    "line {} was rejected with {}, expected {}",
+                                    row,
+                                    err.code(),
+                                    code
+                                ),
+                            ),
+                        _ => {}
+                    }
+                }
+            }
+        }
+        report
+    }
+}
+
+fn parse_row(text: &str) -> Result<Step, String> {
+    let headers = StringRecord::from(COLUMNS.to_vec());
+    let record = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(Trim::All)
+        .from_reader(text.as_bytes())
+        .records()
+        .next()
+        .and_then(Result::ok)
+        .filter(|record| record.len() <= COLUMNS.len())
+        .ok_or_else(|| format!("invalid row `{}`", text))?;
+    let (transaction, signature) = record
+        .deserialize::<TransactionRow>(Some(&headers))
+        .map_err(|_| Error::TransactionParseError)
+        .and_then(TransactionRow::into_signed)
+        .map_err(|_| format!("invalid row `{}`", text))?;
+    Ok(Step::Row(transaction, signature))
+}
+
+fn parse_expectation(text: &str) -> Result<Step, String> {
+    let mut expectation = Expectation {
+        client: ClientId::new(0),
+        available: None,
+        held: None,
+        total: None,
+        locked: None,
+    };
+    let mut client = None;
+    let mut exists = true;
+    let mut rejected = None;
+    for pair in text.split_whitespace() {
+        let (key, value) = pair
+            .split_once('=')
+            .ok_or_else(|| format!("expected `key=value`, got `{}`", pair))?;
+        let invalid = || format!("invalid value of `{}`: `{}`", key, value);
+        match key {
+            "client" => client = Some(ClientId::new(value.parse().map_err(|_| invalid())?)),
+            "available" => {
+                expectation.available = Some(FromStr::from_str(value).map_err(|_| invalid())?)
+            }
+            "held" => expectation.held = Some(FromStr::from_str(value).map_err(|_| invalid())?),
+            "total" => expectation.total = Some(FromStr::from_str(value).map_err(|_| invalid())?),
+            "locked" => expectation.locked = Some(value.parse().map_err(|_| invalid())?),
+            "exists" => exists = value.parse().map_err(|_| invalid())?,
+            "rejected" => rejected = Some(value.to_string()),
+            _ => return Err(format!("unknown key `{}`", key)),
+        }
+    }
+    let checks_balances = expectation.available.is_some()
+        || expectation.held.is_some()
+        || expectation.total.is_some()
+        || expectation.locked.is_some();
+    match (client, rejected) {
+        (None, Some(code)) => Ok(Step::Rejected(code)),
+        (Some(_), Some(_)) => Err("`rejected` doesn't go with a client".to_string()),
+        (None, None) => Err("missing `client`".to_string()),
+        (Some(client), None) if !exists => {
+            if checks_balances {
+                Err("`exists=false` doesn't go with balances".to_string())
+            } else {
+                Ok(Step::NoAccount(client))
+            }
+        }
+        (Some(client), None) => {
+            expectation.client = client;
+            Ok(Step::Expect(expectation))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Scenario;
+    use crate::validate::Problem;
+    use crate::{Error, Transakt};
+
+    #[test]
+    fn scenario() {
+        let text = "# Comment\n\
+                    deposit, 1, 1, 1.0\n\
+                    \n\
+                    withdrawal,1,2,5\n\
+                    expect rejected=E_FUNDS\n\
+                    dispute,1,1\n\
+                    expect client=1 available=0 held=1 locked=false\n\
+                    expect client=2 exists=false\n\
+                    expect client=1 total=2 locked=true\n\
+                    chargeback,1,1,\n\
+                    expect rejected=E_LOCKED\n";
+        let scenario = Scenario::parse(text).unwrap();
+        let report = scenario.run(Transakt::builder());
+        assert_eq!(report.rows, 4);
+        assert_eq!(report.expectations, 5);
+        let failures = vec![
+            Problem {
+                line: 9,
+                message: "client 1: total 1.0000, expected 2.0000, locked false, expected true"
+                    .to_string(),
+            },
+            Problem {
+                line: 11,
+                message: "line 10 was applied, expected E_LOCKED".to_string(),
+            },
+        ];
+        assert_eq!(report.failures, failures);
+
+        for (text, reason) in [
+            ("deposit,1,1", "line 1: invalid row `deposit,1,1`"),
+            (
+                "expect rejected=E_FUNDS",
+                "line 1: `expect rejected` must follow a row",
+            ),
+            ("expect total=1", "line 1: missing `client`"),
+            ("expect client=1 size=1", "line 1: unknown key `size`"),
+            (
+                "expect client=1 exists=false held=0",
+                "line 1: `exists=false` doesn't go with balances",
+            ),
+        ]
+        .iter()
+        {
+            match Scenario::parse(text) {
+                Err(Error::InvalidScenario(message)) => assert_eq!(&message, reason),
+                res => panic!("{:?}", res),
+            }
+        }
+    }
+}
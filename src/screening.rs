@@ -0,0 +1,227 @@
+//! Sanctions and KYC screening against an external watchlist, set up with
+//! [`TransaktBuilder::screening`](crate::TransaktBuilder::screening).
+//!
+//! A client is screened on its first transaction, and the result is cached so the watchlist
+//! service isn't asked again for every row; see
+//! [`TransaktBuilder::screening_ttl`](crate::TransaktBuilder::screening_ttl) to have results
+//! expire. Deposits and withdrawals at or above the large transaction threshold can be screened
+//! on their own as well.
+
+use crate::event::RiskEvent;
+use crate::transaction::{ClientId, Transaction};
+use crate::{Error, Transakt};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// What the watchlist says about a client or a transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScreeningResult {
+    Clear,
+    /// Let the transaction through, raising a [`RiskEvent::ScreeningHit`].
+    Flag(String),
+    /// Reject the transaction with [`Error::ScreeningHit`], raising a [`RiskEvent::ScreeningHit`].
+    Block(String),
+}
+
+/// A watchlist service. Calls run on the engine's thread, in the middle of processing, so a slow
+/// service slows down processing; results of [`ScreeningProvider::screen_client`] are cached for
+/// that reason.
+pub trait ScreeningProvider: Send {
+    /// Screens a client on its first transaction, or once its cached result expired.
+    fn screen_client(&mut self, client: ClientId) -> ScreeningResult;
+
+    /// Screens a deposit or withdrawal at or above the large transaction threshold, after its
+    /// client was cleared. Never cached; by default every transaction is clear.
+    fn screen_transaction(&mut self, _transaction: &Transaction) -> ScreeningResult {
+        ScreeningResult::Clear
+    }
+}
+
+/// A provider and the results it gave for each client.
+pub(crate) struct Screener {
+    provider: Box<dyn ScreeningProvider>,
+    /// Cached results are screened again after this long, if set.
+    ttl: Option<Duration>,
+    cache: HashMap<ClientId, (Instant, ScreeningResult)>,
+}
+
+impl Screener {
+    pub(crate) fn new(provider: Box<dyn ScreeningProvider>, ttl: Option<Duration>) -> Self {
+        Self {
+            provider,
+            ttl,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// The client's result, and whether it comes from the provider rather than the cache.
+    fn client_result(&mut self, client: ClientId) -> (ScreeningResult, bool) {
+        let ttl = self.ttl;
+        match self.cache.get(&client) {
+            Some((at, result)) if ttl.is_none_or(|ttl| at.elapsed() < ttl) => {
+                (result.clone(), false)
+            }
+            _ => {
+                let result = self.provider.screen_client(client);
+                self.cache.insert(client, (Instant::now(), result.clone()));
+                (result, true)
+            }
+        }
+    }
+}
+
+impl Transakt {
+    /// Forgets the cached screening result of a client, so its next transaction is screened
+    /// again, e.g. after it was cleared by hand.
+    pub fn rescreen(&mut self, client: ClientId) {
+        if let Some(screener) = self.screening.as_mut() {
+            screener.cache.remove(&client);
+        }
+    }
+
+    /// Rejects the transaction if the client or the transaction is blocked by screening.
+    pub(crate) fn check_screening(&mut self, transaction: &Transaction) -> Result<(), Error> {
+        let screener = match self.screening.as_mut() {
+            Some(screener) => screener,
+            None => return Ok(()),
+        };
+        let client = transaction.client();
+        let (mut result, mut fresh) = screener.client_result(client);
+        let large = match (transaction.amount(), self.large_transaction_threshold) {
+            (Some(amount), Some(threshold)) => amount >= threshold,
+            _ => false,
+        };
+        if result == ScreeningResult::Clear && large {
+            result = screener.provider.screen_transaction(transaction);
+            fresh = true;
+        }
+        match result {
+            ScreeningResult::Clear => Ok(()),
+            ScreeningResult::Flag(reason) => {
+                // A cached flag was reported when the client was screened
+                if fresh {
+                    tracing::debug!(reason = %reason, "flagged by screening");
+                    self.emit(screening_hit(transaction, reason, false));
+                }
+                Ok(())
+            }
+            ScreeningResult::Block(reason) => {
+                self.emit(screening_hit(transaction, reason.clone(), true));
+                let err = Error::ScreeningHit(reason);
+                tracing::debug!(code = err.code(), error = ?err, "blocked by screening");
+                Err(err)
+            }
+        }
+    }
+}
+
+fn screening_hit(transaction: &Transaction, reason: String, blocked: bool) -> RiskEvent {
+    RiskEvent::ScreeningHit {
+        client: transaction.client(),
+        tx: transaction.tx(),
+        reason,
+        blocked,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ScreeningProvider, ScreeningResult};
+    use crate::currency::Currency;
+    use crate::event::RiskEvent;
+    use crate::notification::NotificationSink;
+    use crate::transaction::{ClientId, Transaction};
+    use crate::Transakt;
+    use std::sync::{Arc, Mutex};
+
+    /// Blocks client 2 and flags client 3, counting the calls.
+    #[derive(Clone, Default)]
+    struct Watchlist {
+        calls: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl ScreeningProvider for Watchlist {
+        fn screen_client(&mut self, client: ClientId) -> ScreeningResult {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("client {}", client));
+            match client.id() {
+                2 => ScreeningResult::Block("sanctioned".to_string()),
+                3 => ScreeningResult::Flag("politically exposed".to_string()),
+                _ => ScreeningResult::Clear,
+            }
+        }
+
+        fn screen_transaction(&mut self, transaction: &Transaction) -> ScreeningResult {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("tx {}", transaction.tx()));
+            ScreeningResult::Block("suspicious amount".to_string())
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct Events(Arc<Mutex<Vec<RiskEvent>>>);
+
+    impl NotificationSink for Events {
+        fn notify(&mut self, event: &RiskEvent) {
+            self.0.lock().unwrap().push(event.clone());
+        }
+    }
+
+    #[test]
+    fn screening() {
+        let watchlist = Watchlist::default();
+        let events = Events::default();
+        let mut transakt = Transakt::builder()
+            .screening(watchlist.clone())
+            .large_transaction_threshold(Currency::new(100, 0).unwrap())
+            .notification_sink(events.clone())
+            .build();
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,1.0\n\
+                     deposit,1,2,1.0\n\
+                     deposit,2,3,1.0\n\
+                     deposit,2,4,1.0\n\
+                     deposit,3,5,1.0\n\
+                     deposit,3,6,1.0\n\
+                     deposit,1,7,100.0\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        assert_eq!(
+            *watchlist.calls.lock().unwrap(),
+            vec!["client 1", "client 2", "client 3", "tx 7"]
+        );
+        let blocked: Vec<(ClientId, bool)> = events
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|event| match event {
+                RiskEvent::ScreeningHit {
+                    client, blocked, ..
+                } => Some((*client, *blocked)),
+                _ => None,
+            })
+            .collect();
+        let (first, second, third) = (ClientId::new(1), ClientId::new(2), ClientId::new(3));
+        assert_eq!(
+            blocked,
+            vec![
+                (second, true),
+                (second, true),
+                (third, false),
+                (first, true)
+            ]
+        );
+        let accounts = transakt.get_accounts_map();
+        assert_eq!(accounts[&first].available(), &Currency::new(2, 0).unwrap());
+        assert!(!accounts.contains_key(&second));
+
+        transakt.rescreen(second);
+        let input = "type,client,tx,amount\ndeposit,2,8,1.0\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        assert_eq!(watchlist.calls.lock().unwrap().len(), 5);
+    }
+}
@@ -0,0 +1,200 @@
+//! Looking up stored deposits and withdrawals without going through all of them. Besides the
+//! transactions by id, the engine keeps their ids by client and by the time they were applied,
+//! which [`Transakt::find_transactions`] narrows the search down with before checking the rest of
+//! the filter.
+//!
+//! Inputs have no timestamps, so like dormancy, transactions are timed by the clock of the
+//! machine processing them, in seconds since the Unix epoch. Times are not part of snapshots:
+//! transactions restored from one are left out by a filter on the time. So are all transactions
+//! in the browser bindings, which have no clock.
+
+use crate::currency::Currency;
+use crate::dormancy::now;
+use crate::transaction::{ClientId, Transaction, TransactionId};
+use crate::Transakt;
+use std::collections::{BTreeSet, HashMap};
+
+/// Which transactions to find. Unset fields match everything, bounds are inclusive.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TransactionFilter {
+    pub client: Option<ClientId>,
+    /// `deposit`, `withdrawal` or `transfer`, the only types stored.
+    pub kind: Option<String>,
+    pub min_amount: Option<Currency>,
+    pub max_amount: Option<Currency>,
+    /// Whether the deposit or transfer is under dispute. Withdrawals never are.
+    pub disputed: Option<bool>,
+    /// Applied at or after, in seconds since the Unix epoch.
+    pub applied_from: Option<u64>,
+    /// Applied at or before, in seconds since the Unix epoch.
+    pub applied_until: Option<u64>,
+}
+
+impl TransactionFilter {
+    /// Whether the transaction, applied at the given time if known, matches.
+    pub fn matches(&self, transaction: &Transaction, applied_at: Option<u64>) -> bool {
+        let amount = transaction.amount();
+        let disputed = matches!(
+            transaction,
+            Transaction::Deposit { disputed: true, .. }
+                | Transaction::Transfer { disputed: true, .. }
+        );
+        let timed = self.applied_from.is_some() || self.applied_until.is_some();
+        self.client
+            .is_none_or(|client| transaction.client() == client)
+            && self
+                .kind
+                .as_ref()
+                .is_none_or(|kind| transaction.kind() == kind)
+            && self
+                .min_amount
+                .is_none_or(|min| amount.is_some_and(|amount| amount >= min))
+            && self
+                .max_amount
+                .is_none_or(|max| amount.is_some_and(|amount| amount <= max))
+            && self.disputed.is_none_or(|expected| disputed == expected)
+            && (!timed
+                || applied_at.is_some_and(|at| {
+                    self.applied_from.is_none_or(|from| at >= from)
+                        && self.applied_until.is_none_or(|until| at <= until)
+                }))
+    }
+}
+
+/// Secondary indexes of the stored transactions.
+#[derive(Debug, Default)]
+pub(crate) struct TransactionIndex {
+    /// In the order they were applied.
+    by_client: HashMap<ClientId, Vec<TransactionId>>,
+    applied_at: HashMap<TransactionId, u64>,
+    by_time: BTreeSet<(u64, TransactionId)>,
+}
+
+impl TransactionIndex {
+    /// Indexes a transaction just stored.
+    pub(crate) fn insert(&mut self, transaction: &Transaction) {
+        let tx = transaction.tx();
+        self.by_client
+            .entry(transaction.client())
+            .or_default()
+            .push(tx);
+        // `SystemTime::now` panics on wasm32
+        if cfg!(not(target_arch = "wasm32")) {
+            let at = now();
+            self.applied_at.insert(tx, at);
+            self.by_time.insert((at, tx));
+        }
+    }
+
+    /// Indexes transactions restored from a snapshot, which have no time.
+    pub(crate) fn restored<'a, I: IntoIterator<Item = &'a Transaction>>(transactions: I) -> Self {
+        let mut index = Self::default();
+        for transaction in transactions {
+            index
+                .by_client
+                .entry(transaction.client())
+                .or_default()
+                .push(transaction.tx());
+        }
+        index
+    }
+}
+
+impl Transakt {
+    /// The stored deposits and withdrawals matching the filter, ordered by id.
+    pub fn find_transactions(&self, filter: &TransactionFilter) -> Vec<&Transaction> {
+        let index = &self.index;
+        let candidates: Box<dyn Iterator<Item = &TransactionId>> =
+            match (filter.client, filter.applied_from, filter.applied_until) {
+                (Some(client), _, _) => {
+                    Box::new(index.by_client.get(&client).into_iter().flatten())
+                }
+                (None, None, None) => Box::new(self.transactions.keys()),
+                (None, from, until) => {
+                    let first = (from.unwrap_or(0), TransactionId::new(0));
+                    let last = (until.unwrap_or(u64::MAX), TransactionId::new(u32::MAX));
+                    Box::new(index.by_time.range(first..=last).map(|(_, tx)| tx))
+                }
+            };
+        let mut found: Vec<&Transaction> = candidates
+            .filter_map(|tx| self.transactions.get(tx))
+            .filter(|transaction| {
+                filter.matches(
+                    transaction,
+                    index.applied_at.get(&transaction.tx()).copied(),
+                )
+            })
+            .collect();
+        found.sort_by_key(|transaction| transaction.tx());
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TransactionFilter;
+    use crate::currency::Currency;
+    use crate::transaction::ClientId;
+    use crate::Transakt;
+    use std::str::FromStr;
+
+    #[test]
+    fn find_transactions() {
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,10.0\n\
+                     deposit,2,2,50.0\n\
+                     withdrawal,1,3,2.0\n\
+                     deposit,1,4,30.0\n\
+                     dispute,1,4,\n\
+                     withdrawal,2,5,100.0\n";
+        let mut transakt = Transakt::default();
+        transakt.load_csv_from("input", input.as_bytes()).unwrap();
+        let find = |transakt: &Transakt, filter: TransactionFilter| -> Vec<u32> {
+            transakt
+                .find_transactions(&filter)
+                .iter()
+                .map(|transaction| transaction.tx().id())
+                .collect()
+        };
+
+        assert_eq!(find(&transakt, TransactionFilter::default()), [1, 2, 3, 4]);
+        let client = TransactionFilter {
+            client: Some(ClientId::new(1)),
+            ..TransactionFilter::default()
+        };
+        assert_eq!(find(&transakt, client.clone()), [1, 3, 4]);
+        let deposits = TransactionFilter {
+            kind: Some("deposit".to_string()),
+            min_amount: Some(Currency::from_str("10").unwrap()),
+            max_amount: Some(Currency::from_str("30").unwrap()),
+            ..client.clone()
+        };
+        assert_eq!(find(&transakt, deposits), [1, 4]);
+        let disputed = TransactionFilter {
+            disputed: Some(true),
+            ..TransactionFilter::default()
+        };
+        assert_eq!(find(&transakt, disputed), [4]);
+
+        let since_epoch = TransactionFilter {
+            applied_from: Some(0),
+            ..TransactionFilter::default()
+        };
+        assert_eq!(find(&transakt, since_epoch.clone()), [1, 2, 3, 4]);
+        let future = TransactionFilter {
+            applied_from: Some(u64::MAX),
+            ..client
+        };
+        assert!(find(&transakt, future).is_empty());
+
+        // Restored transactions have no time
+        let mut restored = Transakt::default();
+        restored.restore(transakt.snapshot()).unwrap();
+        let client = TransactionFilter {
+            client: Some(ClientId::new(2)),
+            ..TransactionFilter::default()
+        };
+        assert_eq!(find(&restored, client), [2]);
+        assert!(find(&restored, since_epoch).is_empty());
+    }
+}
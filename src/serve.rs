@@ -0,0 +1,201 @@
+//! Long-running processing of a CSV stream, persisting the state periodically so a restart can
+//! pick up where the last snapshot left off, and applying changes to the configuration file as
+//! it runs, see [`crate::reload`].
+
+use crate::ingest::{produce, Ingest, IngestOptions, IngestStats, Tick};
+use crate::reload::{ConfigReload, ConfigWatcher};
+use crate::snapshot::Snapshot;
+use crate::{Error, Transakt};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const SNAPSHOT_PREFIX: &str = "snapshot-";
+const SNAPSHOT_SUFFIX: &str = ".json";
+
+#[derive(Debug, Clone)]
+pub struct SnapshotPolicy {
+    pub dir: PathBuf,
+    /// How often the state is saved, if it changed.
+    pub every: Duration,
+    /// How many snapshots to retain; older ones are deleted.
+    pub keep: usize,
+    /// Seals the snapshots, see [`crate::encryption`].
+    #[cfg(feature = "encryption")]
+    pub cipher: Option<crate::encryption::StateCipher>,
+}
+
+impl SnapshotPolicy {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            every: Duration::from_secs(300),
+            keep: 10,
+            #[cfg(feature = "encryption")]
+            cipher: None,
+        }
+    }
+
+    /// Snapshots in the directory, oldest first. Names embed the applied count, zero padded, so
+    /// they sort in the order they were taken.
+    pub fn snapshots(&self) -> Result<Vec<PathBuf>, Error> {
+        let mut snapshots = vec![];
+        for entry in std::fs::read_dir(&self.dir).map_err(Error::Io)? {
+            let path = entry.map_err(Error::Io)?.path();
+            let is_snapshot = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| {
+                    name.starts_with(SNAPSHOT_PREFIX) && name.ends_with(SNAPSHOT_SUFFIX)
+                });
+            if is_snapshot {
+                snapshots.push(path);
+            }
+        }
+        snapshots.sort();
+        Ok(snapshots)
+    }
+
+    pub fn latest(&self) -> Result<Option<PathBuf>, Error> {
+        Ok(self.snapshots()?.pop())
+    }
+
+    pub(crate) fn load(&self, path: &Path) -> Result<Snapshot, Error> {
+        #[cfg(feature = "encryption")]
+        if let Some(cipher) = &self.cipher {
+            return Snapshot::load_encrypted(path, cipher);
+        }
+        Snapshot::load(path)
+    }
+
+    fn save(&self, snapshot: &Snapshot) -> Result<(), Error> {
+        let name = format!(
+            "{}{:020}{}",
+            SNAPSHOT_PREFIX, snapshot.applied, SNAPSHOT_SUFFIX
+        );
+        let path = self.dir.join(name);
+        #[cfg(feature = "encryption")]
+        match &self.cipher {
+            Some(cipher) => snapshot.save_encrypted(&path, cipher)?,
+            None => snapshot.save(&path)?,
+        }
+        #[cfg(not(feature = "encryption"))]
+        snapshot.save(&path)?;
+        tracing::info!(path = %path.display(), applied = snapshot.applied, "snapshot saved");
+        let snapshots = self.snapshots()?;
+        let prune = snapshots.len().saturating_sub(self.keep.max(1));
+        for old in snapshots.iter().take(prune) {
+            std::fs::remove_file(old).map_err(Error::Io)?;
+        }
+        Ok(())
+    }
+}
+
+impl Transakt {
+    /// Executes transactions from a CSV stream until it ends, saving snapshots according to the
+    /// policy, and a last one at the end. Starts from the latest snapshot in the directory, if
+    /// any. Rows are ingested like with [`Transakt::ingest_csv`], with the same options.
+    ///
+    /// Reading happens on its own thread, so snapshots are taken and the configuration file of
+    /// `reload` is checked on time even while the stream is idle. If processing stops on an
+    /// error, that thread is left blocked on the stream.
+    pub fn serve<R: Read + Send + 'static>(
+        &mut self,
+        reader: R,
+        policy: &SnapshotPolicy,
+        options: &IngestOptions,
+        reload: Option<&ConfigReload>,
+    ) -> Result<IngestStats, Error> {
+        std::fs::create_dir_all(&policy.dir).map_err(Error::Io)?;
+        if let Some(latest) = policy.latest()? {
+            self.restore(policy.load(&latest)?)?;
+            tracing::info!(path = %latest.display(), applied = self.applied, "resumed from snapshot");
+        }
+        let rows = self.csv_rows(reader);
+        let (sender, receiver) = mpsc::sync_channel(options.queue_capacity);
+        let backpressure = options.backpressure.clone();
+        let producer = thread::spawn(move || produce(rows, sender, &backpressure));
+
+        let mut saved = self.applied;
+        let mut save = |transakt: &mut Transakt| {
+            if transakt.applied != saved {
+                transakt.flush_journal()?;
+                policy.save(&transakt.snapshot())?;
+                saved = transakt.applied;
+            }
+            Ok(())
+        };
+        let mut watcher = reload.map(ConfigWatcher::new);
+        let mut saved_at = Instant::now();
+        let mut run = |transakt: &mut Transakt| {
+            if let Some(watcher) = watcher.as_mut() {
+                watcher.check(transakt);
+            }
+            if saved_at.elapsed() >= policy.every {
+                save(transakt)?;
+                saved_at = Instant::now();
+            }
+            Ok(())
+        };
+        let mut ingest = Ingest::new(options);
+        let tick = Tick {
+            every: reload.map_or(policy.every, |reload| reload.every.min(policy.every)),
+            run: &mut run,
+        };
+        self.consume(receiver, &mut ingest, Some(tick))?;
+        let produced = producer.join().expect("serve reader panicked")?;
+        let stats = self.finish_ingest(ingest, produced, options)?;
+        save(self)?;
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SnapshotPolicy;
+    use crate::ingest::IngestOptions;
+    use crate::transaction::ClientId;
+    use crate::Transakt;
+    use std::time::Duration;
+
+    #[test]
+    fn serve_snapshots_and_resumes() {
+        let dir = std::env::temp_dir().join(format!("transakt-serve-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut policy = SnapshotPolicy::new(&dir);
+        policy.every = Duration::from_secs(3600);
+        policy.keep = 2;
+        let options = IngestOptions::default();
+
+        for day in 1..=3u32 {
+            let input = format!("type,client,tx,amount\ndeposit,1,{},1.0\n", day);
+            let mut transakt = Transakt::default();
+            let stats = transakt
+                .serve(std::io::Cursor::new(input), &policy, &options, None)
+                .unwrap();
+            assert_eq!(stats.executed, 1);
+        }
+        let snapshots = policy.snapshots().unwrap();
+        let names: Vec<String> = snapshots
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "snapshot-00000000000000000002.json",
+                "snapshot-00000000000000000003.json"
+            ]
+        );
+
+        let mut transakt = Transakt::default();
+        transakt
+            .restore(crate::snapshot::Snapshot::load(&snapshots[1]).unwrap())
+            .unwrap();
+        let account = transakt.get_accounts_map().get(&ClientId::new(1)).unwrap();
+        assert_eq!(account.available().to_string(), "3.0000");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,211 @@
+//! Shadow mode: the same transactions go through two engines, the primary and a shadow with
+//! another configuration, and the first row where they part ways is reported. Meant for checking
+//! a refactor or a policy change against production data before switching over.
+//!
+//! Both engines must start from the same state, empty or restored from the same snapshot. A row
+//! only changes the account of its client, so comparing that account after every row finds the
+//! first divergence without walking the whole ledger.
+
+use crate::snapshot::AccountState;
+use crate::transaction::{self, Transaction};
+use crate::{Error, Transakt};
+use std::fmt::{Display, Formatter};
+use std::io::Read;
+
+/// How the two engines handled a row differently.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DivergenceKind {
+    /// One applied the row and the other rejected it, or they rejected it for different reasons.
+    /// `None` is applied, otherwise the error code.
+    Disposition {
+        primary: Option<&'static str>,
+        shadow: Option<&'static str>,
+    },
+    /// Both handled the row the same way, but left the client's account different. `None` if
+    /// there is no account.
+    Balances {
+        primary: Option<AccountState>,
+        shadow: Option<AccountState>,
+    },
+}
+
+/// The first row the engines disagree on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    pub line: u64,
+    pub transaction: Transaction,
+    pub kind: DivergenceKind,
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {}, {} {} of client {}: ",
+            self.line,
+            self.transaction.kind(),
+            self.transaction.tx(),
+            self.transaction.client()
+        )?;
+        match &self.kind {
+            DivergenceKind::Disposition { primary, shadow } => {
+                let show = |code: &Option<&str>| code.unwrap_or("applied").to_string();
+                write!(f, "primary {}, shadow {}", show(primary), show(shadow))
+            }
+            DivergenceKind::Balances { primary, shadow } => {
+                let show = |account: &Option<AccountState>| match account {
+                    Some(account) => format!(
+                        "available {}, held {}, locked {}",
+                        account.available, account.held, account.locked
+                    ),
+                    None => "no account".to_string(),
+                };
+                write!(f, "primary {}; shadow {}", show(primary), show(shadow))
+            }
+        }
+    }
+}
+
+pub struct Shadow {
+    primary: Transakt,
+    shadow: Transakt,
+    rows: u64,
+}
+
+impl Shadow {
+    pub fn new(primary: Transakt, shadow: Transakt) -> Self {
+        Self {
+            primary,
+            shadow,
+            rows: 0,
+        }
+    }
+
+    /// Executes a row on both engines, returning how they diverged, if they did. Errors that stop
+    /// processing are returned when both engines stop with the same one.
+    pub fn execute_signed(
+        &mut self,
+        line: u64,
+        transaction: Transaction,
+        signature: Option<&str>,
+    ) -> Result<Option<Divergence>, Error> {
+        self.rows += 1;
+        let primary = self.primary.execute_signed(transaction, signature).err();
+        let shadow = self.shadow.execute_signed(transaction, signature).err();
+        let divergence = |kind| {
+            Ok(Some(Divergence {
+                line,
+                transaction,
+                kind,
+            }))
+        };
+        let (primary_code, shadow_code) = (
+            primary.as_ref().map(Error::code),
+            shadow.as_ref().map(Error::code),
+        );
+        if primary_code != shadow_code {
+            return divergence(DivergenceKind::Disposition {
+                primary: primary_code,
+                shadow: shadow_code,
+            });
+        }
+        if let Some(err) = primary.filter(Error::is_fatal) {
+            return Err(err);
+        }
+        let client = transaction.client();
+        // Times depend on the clock, not on the configuration
+        let account = |transakt: &Transakt| {
+            transakt
+                .get_accounts_map()
+                .get(&client)
+                .map(|account| AccountState {
+                    last_activity: None,
+                    created_at: None,
+                    ..AccountState::from(account)
+                })
+        };
+        let (primary, shadow) = (account(&self.primary), account(&self.shadow));
+        if primary != shadow {
+            return divergence(DivergenceKind::Balances { primary, shadow });
+        }
+        Ok(None)
+    }
+
+    /// Executes a CSV input on both engines, stopping at the first divergence.
+    pub fn run_csv<R: Read>(&mut self, reader: R) -> Result<Option<Divergence>, Error> {
+        // The header is line 1
+        for (line, row) in (2..).zip(transaction::read_signed_csv(reader)) {
+            let (transaction, signature) = row?;
+            if let Some(divergence) =
+                self.execute_signed(line, transaction, signature.as_deref())?
+            {
+                return Ok(Some(divergence));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Rows executed so far, including the one that diverged.
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+
+    pub fn primary(&self) -> &Transakt {
+        &self.primary
+    }
+
+    pub fn shadow(&self) -> &Transakt {
+        &self.shadow
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DivergenceKind, Shadow};
+    use crate::currency::Currency;
+    use crate::policy::DisputeFundsAction;
+    use crate::Transakt;
+
+    #[test]
+    fn first_divergence() {
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,10.0\n\
+                     deposit,2,2,5000\n\
+                     withdrawal,1,3,7.0\n\
+                     dispute,1,1,\n\
+                     resolve,1,1,\n";
+        let mut shadow = Shadow::new(Transakt::default(), Transakt::default());
+        assert_eq!(shadow.run_csv(input.as_bytes()).unwrap(), None);
+        assert_eq!(shadow.rows(), 5);
+
+        let capped = Transakt::builder()
+            .max_amount(Currency::new(1000, 0).unwrap())
+            .build();
+        let mut shadow = Shadow::new(Transakt::default(), capped);
+        let divergence = shadow.run_csv(input.as_bytes()).unwrap().unwrap();
+        assert_eq!(divergence.line, 3);
+        assert_eq!(
+            divergence.kind,
+            DivergenceKind::Disposition {
+                primary: None,
+                shadow: Some("E_AMOUNT_CAP")
+            }
+        );
+        assert_eq!(
+            divergence.to_string(),
+            "line 3, deposit 2 of client 2: primary applied, shadow E_AMOUNT_CAP"
+        );
+
+        let holding = Transakt::builder()
+            .disputes_exceeding_funds(DisputeFundsAction::HoldAvailable)
+            .build();
+        let mut shadow = Shadow::new(Transakt::default(), holding);
+        let divergence = shadow.run_csv(input.as_bytes()).unwrap().unwrap();
+        assert_eq!(
+            divergence.to_string(),
+            "line 5, dispute 1 of client 1: primary available -7.0000, held 10.0000, \
+             locked false; shadow available 0.0000, held 3.0000, locked false"
+        );
+        assert_eq!(shadow.rows(), 4);
+    }
+}
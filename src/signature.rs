@@ -0,0 +1,180 @@
+//! Rows signed by the system that originated them, so every applied transaction can be shown to
+//! have been authorized. Inputs get a `signature` column with an ed25519 signature, as hex, of
+//! the [canonical row](canonical_row); the keys it is checked against are configured in the
+//! `[signatures]` section:
+//!
+//! ```toml
+//! [signatures]
+//! # Reject rows without a signature
+//! required = true
+//!
+//! # Public keys of the originating systems, as hex
+//! [signatures.keys]
+//! payments = "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c"
+//! ```
+//!
+//! A row passes if any of the keys verifies its signature. Signatures of applied transactions
+//! are kept in the journal, where replaying checks them again.
+
+use crate::hex;
+use crate::transaction::Transaction;
+use crate::{Error, Transakt};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SignatureConfig {
+    pub required: bool,
+    /// Public keys, by name of the system holding the private key.
+    pub keys: BTreeMap<String, PublicKey>,
+}
+
+impl SignatureConfig {
+    pub fn is_empty(&self) -> bool {
+        !self.required && self.keys.is_empty()
+    }
+}
+
+/// An ed25519 public key, written as 64 hex digits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicKey(VerifyingKey);
+
+impl PublicKey {
+    pub fn from_hex(digits: &str) -> Option<Self> {
+        let bytes = <[u8; 32]>::try_from(hex::decode(digits)?).ok()?;
+        VerifyingKey::from_bytes(&bytes).ok().map(PublicKey)
+    }
+}
+
+impl From<VerifyingKey> for PublicKey {
+    fn from(key: VerifyingKey) -> Self {
+        PublicKey(key)
+    }
+}
+
+impl<'de> Deserialize<'de> for PublicKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let digits = String::deserialize(deserializer)?;
+        PublicKey::from_hex(&digits)
+            .ok_or_else(|| serde::de::Error::custom("invalid ed25519 public key"))
+    }
+}
+
+/// What is signed for a transaction: `type,client,tx,amount`, with the amount written with four
+/// decimals and left empty for transactions without one, like `deposit,1,7,2.5000`.
+pub fn canonical_row(transaction: &Transaction) -> String {
+    let amount = transaction
+        .amount()
+        .map_or(String::new(), |amount| amount.to_string());
+    format!(
+        "{},{},{},{}",
+        transaction.kind(),
+        transaction.client(),
+        transaction.tx(),
+        amount
+    )
+}
+
+/// Signs the canonical row of the transaction, giving the value of its `signature` column.
+pub fn sign(transaction: &Transaction, key: &SigningKey) -> String {
+    hex::encode(&key.sign(canonical_row(transaction).as_bytes()).to_bytes())
+}
+
+impl Transakt {
+    /// Rejects a transaction whose signature isn't from one of the configured keys, or without
+    /// one if they are required. Returns the name of the key that signed it, if any.
+    pub(crate) fn check_signature(
+        &self,
+        transaction: &Transaction,
+        signature: Option<&str>,
+    ) -> Result<Option<String>, Error> {
+        let config = match &self.signatures {
+            Some(config) => config,
+            None => return Ok(None),
+        };
+        let reason = match signature {
+            None if config.required => "missing signature",
+            None => return Ok(None),
+            Some(signature) => {
+                let signature =
+                    hex::decode(signature).and_then(|bytes| Signature::from_slice(&bytes).ok());
+                match signature {
+                    None => "malformed signature",
+                    Some(signature) => {
+                        let row = canonical_row(transaction);
+                        let signed_by = config.keys.iter().find(|(_, key)| {
+                            key.0.verify_strict(row.as_bytes(), &signature).is_ok()
+                        });
+                        if let Some((name, _)) = signed_by {
+                            tracing::debug!(key = %name, "signature verified");
+                            return Ok(Some(name.clone()));
+                        }
+                        "not signed by a configured key"
+                    }
+                }
+            }
+        };
+        let err = Error::InvalidSignature(reason.to_string());
+        tracing::debug!(code = err.code(), error = ?err, "signature rejected");
+        Err(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{canonical_row, sign, PublicKey, SignatureConfig};
+    use crate::transaction::{ClientId, Transaction, TransactionId};
+    use crate::Transakt;
+    use ed25519_dalek::SigningKey;
+
+    #[test]
+    fn signed_rows() {
+        let originator = SigningKey::from_bytes(&[1; 32]);
+        let other = SigningKey::from_bytes(&[2; 32]);
+        let mut config = SignatureConfig {
+            required: true,
+            ..Default::default()
+        };
+        let key = PublicKey::from(originator.verifying_key());
+        config.keys.insert("payments".to_string(), key);
+        let mut transakt = Transakt::builder().signatures(config).build();
+
+        let deposit = Transaction::Deposit {
+            client: ClientId::new(1),
+            tx: TransactionId::new(1),
+            amount: "10".parse().unwrap(),
+            disputed: false,
+        };
+        assert_eq!(canonical_row(&deposit), "deposit,1,1,10.0000");
+        let dispute = Transaction::Dispute {
+            client: ClientId::new(1),
+            tx: TransactionId::new(1),
+            amount: None,
+        };
+        assert_eq!(canonical_row(&dispute), "dispute,1,1,");
+
+        let input = format!(
+            "type,client,tx,amount,signature\n\
+             deposit,1,1,10.0,{}\n\
+             deposit,1,2,5,{}\n\
+             deposit,1,3,5,\n\
+             deposit,1,4,5,zz\n\
+             dispute,1,1,,{}\n",
+            sign(&deposit, &originator),
+            sign(&deposit, &other),
+            sign(&dispute, &originator),
+        );
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        let rejected: Vec<(&str, Vec<u64>)> = transakt
+            .rejections()
+            .groups()
+            .map(|(code, group)| (code, group.lines.clone()))
+            .collect();
+        assert_eq!(rejected, vec![("E_SIGNATURE", vec![3, 4, 5])]);
+        let account = &transakt.get_accounts_map()[&ClientId::new(1)];
+        assert_eq!(account.held().to_string(), "10.0000");
+    }
+}
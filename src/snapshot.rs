@@ -0,0 +1,417 @@
+//! Point-in-time copies of the engine state, so it can be inspected or resumed later.
+
+use crate::account::Account;
+use crate::currency::Currency;
+use crate::search::TransactionIndex;
+use crate::transaction::{ClientId, Transaction, TransactionId};
+use crate::{Error, Transakt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt::{Display, Formatter};
+use std::io::{Read, Write};
+use std::path::Path;
+
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountState {
+    pub client: ClientId,
+    pub available: Currency,
+    pub held: Currency,
+    pub locked: bool,
+    /// See [`Account::last_activity`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_activity: Option<u64>,
+    /// See [`Account::created_at`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub disputes: u32,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub chargebacks: u32,
+    /// See [`Account::shortfall`].
+    #[serde(default, skip_serializing_if = "is_nothing")]
+    pub shortfall: Currency,
+}
+
+fn is_zero(count: &u32) -> bool {
+    *count == 0
+}
+
+fn is_nothing(amount: &Currency) -> bool {
+    *amount == Currency::default()
+}
+
+impl From<&Account> for AccountState {
+    fn from(account: &Account) -> Self {
+        Self {
+            client: account.client(),
+            available: *account.available(),
+            held: *account.held(),
+            locked: account.is_locked(),
+            last_activity: account.last_activity(),
+            created_at: account.created_at(),
+            disputes: account.dispute_count(),
+            chargebacks: account.chargeback_count(),
+            shortfall: account.shortfall(),
+        }
+    }
+}
+
+/// Accounts and stored transactions are kept sorted, so the same state always serializes the same
+/// way.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    /// Number of transactions applied to the ledger when the snapshot was taken.
+    pub applied: u64,
+    pub last_applied: Option<TransactionId>,
+    pub accounts: Vec<AccountState>,
+    pub transactions: Vec<Transaction>,
+    /// Deposits of which only part was held by their last dispute, with the part: disputed only
+    /// in part, or see
+    /// [`DisputeFundsAction::HoldAvailable`](crate::policy::DisputeFundsAction::HoldAvailable).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub partial_holds: Vec<(TransactionId, Currency)>,
+    /// Deposits and withdrawals undone by a reversal.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reversed: Vec<TransactionId>,
+    /// Open disputes that held less than disputed for lack of available funds, with the rest.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shortfalls: Vec<(TransactionId, Currency)>,
+    /// Deposits charged back and not reversed yet, with what the chargeback took and what it left
+    /// owed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub charged_back: Vec<(TransactionId, Currency, Currency)>,
+}
+
+impl Snapshot {
+    pub fn read<R: Read>(reader: R) -> Result<Snapshot, Error> {
+        let snapshot: Snapshot = serde_json::from_reader(reader)
+            .map_err(|err| Error::InvalidSnapshot(err.to_string()))?;
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(Error::InvalidSnapshot(format!(
+                "unsupported version {}",
+                snapshot.version
+            )));
+        }
+        Ok(snapshot)
+    }
+
+    pub fn write<W: Write>(&self, writer: W) -> Result<(), Error> {
+        serde_json::to_writer(writer, self).map_err(|err| Error::Io(err.into()))
+    }
+
+    pub fn load(path: &Path) -> Result<Snapshot, Error> {
+        let file = std::fs::File::open(path).map_err(Error::Io)?;
+        Self::read(std::io::BufReader::new(file))
+    }
+
+    /// Writes the snapshot next to its destination first, so a crash never leaves a truncated
+    /// snapshot behind.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        save_atomically(path, |writer| self.write(writer))
+    }
+
+    /// SHA-256 over the accounts and stored transactions, as a hex string. Two ledgers with the
+    /// same balances and history have the same digest, whenever they were processed.
+    pub fn digest(&self) -> String {
+        let accounts: Vec<AccountState> = self
+            .accounts
+            .iter()
+            .map(|account| AccountState {
+                last_activity: None,
+                created_at: None,
+                ..account.clone()
+            })
+            .collect();
+        let state = serde_json::to_vec(&(&accounts, &self.transactions))
+            .expect("snapshot state is always serializable");
+        Sha256::digest(&state)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    pub fn account(&self, client: ClientId) -> Option<&AccountState> {
+        self.accounts
+            .binary_search_by_key(&client, |account| account.client)
+            .ok()
+            .map(|index| &self.accounts[index])
+    }
+
+    /// Deposits and transfers to the client under an open dispute, with the amount held, ordered
+    /// by tx id.
+    pub fn disputes(&self, client: ClientId) -> Vec<(TransactionId, Currency)> {
+        self.transactions
+            .iter()
+            .filter_map(|transaction| match transaction {
+                Transaction::Deposit {
+                    client: owner,
+                    tx,
+                    amount,
+                    disputed: true,
+                }
+                | Transaction::Transfer {
+                    to: owner,
+                    tx,
+                    amount,
+                    disputed: true,
+                    ..
+                } if *owner == client => {
+                    let held = self
+                        .partial_holds
+                        .iter()
+                        .find(|(partial, _)| partial == tx)
+                        .map_or(*amount, |(_, held)| *held);
+                    Some((*tx, held))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn summary(&self) -> SnapshotSummary {
+        let sum = |amount: fn(&AccountState) -> Currency| {
+            self.accounts
+                .iter()
+                .try_fold(Currency::default(), |total, account| {
+                    total.checked_add(amount(account))
+                })
+        };
+        SnapshotSummary {
+            accounts: self.accounts.len(),
+            locked_accounts: self.accounts.iter().filter(|a| a.locked).count(),
+            total_available: sum(|account| account.available),
+            total_held: sum(|account| account.held),
+            applied: self.applied,
+            last_applied: self.last_applied,
+            digest: self.digest(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapshotSummary {
+    pub accounts: usize,
+    pub locked_accounts: usize,
+    /// `None` if the sum overflows.
+    pub total_available: Option<Currency>,
+    pub total_held: Option<Currency>,
+    pub applied: u64,
+    pub last_applied: Option<TransactionId>,
+    pub digest: String,
+}
+
+impl Display for SnapshotSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let or_overflow = |amount: Option<Currency>| {
+            amount.map_or("overflow".to_string(), |amount| amount.to_string())
+        };
+        writeln!(f, "accounts:        {}", self.accounts)?;
+        writeln!(f, "locked accounts: {}", self.locked_accounts)?;
+        writeln!(f, "total available: {}", or_overflow(self.total_available))?;
+        writeln!(f, "total held:      {}", or_overflow(self.total_held))?;
+        writeln!(f, "applied:         {}", self.applied)?;
+        match self.last_applied {
+            Some(tx) => writeln!(f, "last applied tx: {}", tx)?,
+            None => writeln!(f, "last applied tx: -")?,
+        }
+        write!(f, "digest:          {}", self.digest)
+    }
+}
+
+/// Writes a file next to its destination, then moves it there.
+pub(crate) fn save_atomically<F>(path: &Path, write: F) -> Result<(), Error>
+where
+    F: FnOnce(&mut std::io::BufWriter<std::fs::File>) -> Result<(), Error>,
+{
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let file = std::fs::File::create(&tmp).map_err(Error::Io)?;
+    let mut writer = std::io::BufWriter::new(file);
+    write(&mut writer)?;
+    writer.flush().map_err(Error::Io)?;
+    std::fs::rename(&tmp, path).map_err(Error::Io)
+}
+
+impl Transakt {
+    pub fn snapshot(&self) -> Snapshot {
+        let mut accounts: Vec<AccountState> =
+            self.accounts.values().map(AccountState::from).collect();
+        accounts.sort_by_key(|account| account.client);
+        let mut transactions: Vec<Transaction> = self.transactions.values().cloned().collect();
+        transactions.sort_by_key(|transaction| transaction.tx());
+        let mut partial_holds: Vec<(TransactionId, Currency)> = self
+            .partial_holds
+            .iter()
+            .map(|(tx, held)| (*tx, *held))
+            .collect();
+        partial_holds.sort();
+        let mut reversed: Vec<TransactionId> = self.reversed.iter().copied().collect();
+        reversed.sort();
+        let mut shortfalls: Vec<(TransactionId, Currency)> = self
+            .shortfalls
+            .iter()
+            .map(|(tx, short)| (*tx, *short))
+            .collect();
+        shortfalls.sort();
+        let mut charged_back: Vec<(TransactionId, Currency, Currency)> = self
+            .charged_back
+            .iter()
+            .map(|(tx, (amount, short))| (*tx, *amount, *short))
+            .collect();
+        charged_back.sort();
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            applied: self.applied,
+            last_applied: self.last_applied,
+            accounts,
+            transactions,
+            partial_holds,
+            reversed,
+            shortfalls,
+            charged_back,
+        }
+    }
+
+    /// Replaces the ledger state with the one in the snapshot. Configuration, like notification
+    /// sinks, is kept.
+    pub fn restore(&mut self, snapshot: Snapshot) -> Result<(), Error> {
+        for transaction in snapshot.transactions.iter() {
+            let client = transaction.client();
+            if !snapshot.accounts.iter().any(|a| a.client == client) {
+                return Err(Error::InvalidSnapshot(format!(
+                    "tx {} belongs to missing client {}",
+                    transaction.tx(),
+                    client
+                )));
+            }
+        }
+        self.accounts = snapshot
+            .accounts
+            .into_iter()
+            .map(|a| (a.client, Account::restore(a)))
+            .collect();
+        self.transactions = snapshot
+            .transactions
+            .into_iter()
+            .map(|transaction| (transaction.tx(), transaction))
+            .collect();
+        self.index = TransactionIndex::restored(self.transactions.values());
+        self.partial_holds = snapshot.partial_holds.into_iter().collect();
+        self.reversed = snapshot.reversed.into_iter().collect();
+        self.shortfalls = snapshot.shortfalls.into_iter().collect();
+        self.charged_back = snapshot
+            .charged_back
+            .into_iter()
+            .map(|(tx, amount, short)| (tx, (amount, short)))
+            .collect();
+        self.applied = snapshot.applied;
+        self.last_applied = snapshot.last_applied;
+        Ok(())
+    }
+
+    /// The state of the ledger, accounts and stored transactions with their dispute flags, as the
+    /// JSON of its [`Snapshot`], to hand over to another run or tool.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.snapshot()).expect("snapshot state is always serializable")
+    }
+
+    /// An engine with the standard configuration and the state of [`Transakt::to_json`]. To keep
+    /// another configuration, [`Transakt::restore`] the [`Snapshot::read`] of the JSON instead.
+    pub fn from_json(json: &str) -> Result<Transakt, Error> {
+        let mut transakt = Transakt::default();
+        transakt.restore(Snapshot::read(json.as_bytes())?)?;
+        Ok(transakt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Snapshot;
+    use crate::currency::Currency;
+    use crate::transaction::{ClientId, Transaction, TransactionId};
+    use crate::Transakt;
+    use std::str::FromStr;
+
+    #[test]
+    fn snapshot_round_trip() {
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,2.0\n\
+                     deposit,2,2,1.5\n\
+                     dispute,1,1,\n";
+        let mut transakt = Transakt::default();
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        let snapshot = transakt.snapshot();
+
+        let mut bytes = vec![];
+        snapshot.write(&mut bytes).unwrap();
+        let loaded = Snapshot::read(bytes.as_slice()).unwrap();
+        assert_eq!(loaded, snapshot);
+        assert_eq!(
+            loaded.disputes(ClientId::new(1)),
+            vec![(TransactionId::new(1), Currency::from_str("2").unwrap())]
+        );
+        assert!(loaded.disputes(ClientId::new(2)).is_empty());
+        assert_eq!(
+            loaded.account(ClientId::new(2)).unwrap().available,
+            Currency::from_str("1.5").unwrap()
+        );
+
+        let mut restored = Transakt::default();
+        restored.restore(loaded).unwrap();
+        assert_eq!(restored.snapshot().digest(), snapshot.digest());
+        // The dispute is still open and can be resolved after restoring
+        restored
+            .execute_transaction(Transaction::Resolve {
+                client: ClientId::new(1),
+                tx: TransactionId::new(1),
+            })
+            .unwrap();
+        let account = restored.get_accounts_map().get(&ClientId::new(1)).unwrap();
+        assert_eq!(account.available(), &Currency::from_str("2").unwrap());
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,2.0\n\
+                     deposit,1,2,1.5\n\
+                     withdrawal,1,3,0.5\n\
+                     dispute,1,2,\n";
+        let mut transakt = Transakt::default();
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        let json = transakt.to_json();
+        let mut loaded = Transakt::from_json(&json).unwrap();
+        assert_eq!(loaded.to_json(), json);
+        assert_eq!(loaded.open_disputes().len(), 1);
+        // The duplicate id is still known
+        let duplicate = Transaction::Deposit {
+            client: ClientId::new(1),
+            tx: TransactionId::new(3),
+            amount: Currency::from_str("1").unwrap(),
+            disputed: false,
+        };
+        assert!(loaded.execute_transaction(duplicate).is_err());
+        assert!(Transakt::from_json("{\"version\": 1}").is_err());
+    }
+
+    #[test]
+    fn snapshot_summary() {
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,2.0\n\
+                     deposit,2,2,1.5\n\
+                     dispute,1,1,\n\
+                     chargeback,1,1,\n";
+        let mut transakt = Transakt::default();
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        let summary = transakt.snapshot().summary();
+        assert_eq!(summary.accounts, 2);
+        assert_eq!(summary.locked_accounts, 1);
+        assert_eq!(summary.total_available, Currency::from_str("1.5").ok());
+        assert_eq!(summary.total_held, Currency::from_str("0").ok());
+        assert_eq!(summary.applied, 4);
+        assert_eq!(summary.last_applied, Some(TransactionId::new(1)));
+    }
+}
@@ -0,0 +1,238 @@
+//! Tables of SQLite databases, for running the engine straight against exports of operational
+//! databases. A table holds transactions if it has `type`, `client`, `tx` and `amount` columns,
+//! and optionally `signature` and `timestamp` ones, named like the CSV columns. Other columns
+//! are ignored.
+//!
+//! Rows are executed in rowid order, which is insertion order unless rowids were given, and
+//! rejections report the rowid as their line. They are selected a batch at a time, so large
+//! tables aren't read into memory. `WITHOUT ROWID` tables are not supported.
+
+use crate::transaction::{TimedRow, TransactionRow};
+use crate::{Error, Transakt};
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OpenFlags};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::VecDeque;
+use std::path::Path;
+
+/// The columns that make a transaction row.
+const COLUMNS: &[&str] = &["type", "client", "tx", "amount", "signature", "timestamp"];
+/// How many rows are selected at once.
+const BATCH: usize = 1024;
+
+/// A transaction, or why the row isn't one, by rowid.
+type NumberedRow = (u64, Result<TimedRow, Error>);
+
+impl Transakt {
+    pub fn read_from_sqlite(filepath: &Path, table: &str) -> Result<Transakt, Error> {
+        let mut transakt = Self::default();
+        transakt.load_sqlite_from(filepath, table)?;
+        Ok(transakt)
+    }
+
+    /// Like [`Transakt::load_csv_from`], for the rows of a table of a SQLite database.
+    pub fn load_sqlite_from(&mut self, filepath: &Path, table: &str) -> Result<(), Error> {
+        self.load_from(&filepath.display().to_string(), |transakt| {
+            let read = transakt.read_stats();
+            let rows = transakt.in_row_range(read_timed_sqlite(filepath, table));
+            transakt.execute_rows(rows, read)
+        })
+    }
+}
+
+/// Reads transactions from a table of a SQLite database, in rowid order, with their rowids. A
+/// database or table that can't be read ends the input with its error.
+pub fn read_timed_sqlite<'a>(
+    filepath: &'a Path,
+    table: &'a str,
+) -> impl Iterator<Item = NumberedRow> + 'a {
+    let mut rows = None;
+    let mut opened = Some((filepath, table));
+    let mut done = false;
+    std::iter::from_fn(move || {
+        if done {
+            return None;
+        }
+        if let Some((filepath, table)) = opened.take() {
+            match TableRows::open(filepath, table) {
+                Ok(table) => rows = Some(table),
+                Err(err) => {
+                    done = true;
+                    return Some((0, Err(err)));
+                }
+            }
+        }
+        let row = rows.as_mut()?.next_row().transpose()?;
+        done = row.is_err();
+        Some(match row {
+            Ok((rowid, row)) => (rowid, row),
+            Err(err) => (0, Err(err)),
+        })
+    })
+}
+
+/// The rows of a table, each parsed as a transaction.
+struct TableRows {
+    connection: Connection,
+    /// Selects a batch of rows from a rowid on, the rowid first.
+    select: String,
+    /// The names of the columns, lowercase.
+    columns: Vec<String>,
+    batch: VecDeque<NumberedRow>,
+    /// The rowid the next batch starts at, `None` after the last one.
+    next: Option<i64>,
+}
+
+impl TableRows {
+    fn open(filepath: &Path, table: &str) -> Result<Self, Error> {
+        let connection = Connection::open_with_flags(filepath, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(sqlite)?;
+        let select = format!(
+            "SELECT rowid, * FROM \"{}\" WHERE rowid >= ?1 ORDER BY rowid LIMIT {}",
+            table.replace('"', "\"\""),
+            BATCH
+        );
+        let columns = connection
+            .prepare(&select)
+            .map_err(sqlite)?
+            .column_names()
+            .iter()
+            .skip(1)
+            .map(|name| name.to_lowercase())
+            .collect();
+        Ok(Self {
+            connection,
+            select,
+            columns,
+            batch: VecDeque::new(),
+            next: Some(i64::MIN),
+        })
+    }
+
+    /// The next rowid and the transaction of its row, the error of the row if it isn't one.
+    /// Errors reading the database itself end the rows.
+    fn next_row(&mut self) -> Result<Option<NumberedRow>, Error> {
+        if self.batch.is_empty() {
+            self.read_batch().map_err(sqlite)?;
+        }
+        Ok(self.batch.pop_front())
+    }
+
+    fn read_batch(&mut self) -> Result<(), rusqlite::Error> {
+        let Some(from) = self.next.take() else {
+            return Ok(());
+        };
+        let mut statement = self.connection.prepare_cached(&self.select)?;
+        let mut rows = statement.query([from])?;
+        let mut count = 0;
+        while let Some(row) = rows.next()? {
+            let rowid: i64 = row.get(0)?;
+            count += 1;
+            self.next = rowid.checked_add(1).filter(|_| count == BATCH);
+            let mut fields = Map::new();
+            let mut blob = false;
+            for (index, name) in self.columns.iter().enumerate() {
+                if !COLUMNS.contains(&name.as_str()) {
+                    continue;
+                }
+                let value = match row.get_ref(index + 1)? {
+                    ValueRef::Null => Value::Null,
+                    // Amounts are parsed from text, as from the CSV
+                    ValueRef::Integer(value) if name == "amount" => {
+                        Value::String(value.to_string())
+                    }
+                    ValueRef::Integer(value) => Value::from(value),
+                    ValueRef::Real(value) => Value::String(value.to_string()),
+                    ValueRef::Text(text) => Value::String(String::from_utf8_lossy(text).into()),
+                    // Never part of a transaction
+                    ValueRef::Blob(_) => {
+                        blob = true;
+                        break;
+                    }
+                };
+                fields.insert(name.clone(), value);
+            }
+            let parsed = if blob {
+                Err(Error::TransactionParseError)
+            } else {
+                TransactionRow::deserialize(Value::Object(fields))
+                    .map_err(|_| Error::TransactionParseError)
+                    .and_then(TransactionRow::into_timed)
+            };
+            self.batch.push_back((rowid as u64, parsed));
+        }
+        Ok(())
+    }
+}
+
+fn sqlite(err: rusqlite::Error) -> Error {
+    Error::Io(std::io::Error::other(err))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::currency::Currency;
+    use crate::transaction::ClientId;
+    use crate::Transakt;
+    use std::path::Path;
+    use std::str::FromStr;
+
+    #[test]
+    fn read_sqlite() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/sqlite/ledger.db");
+        let transakt = Transakt::read_from_sqlite(&path, "Ledger").unwrap();
+        let accounts = transakt.get_accounts_map();
+        let available = |client| *accounts[&ClientId::new(client)].available();
+        assert_eq!(available(1), Currency::from_str("420.75").unwrap());
+        assert_eq!(available(2), Currency::from_str("429.25").unwrap());
+        assert_eq!(available(3), Currency::from_str("412").unwrap());
+        assert_eq!(
+            accounts[&ClientId::new(3)].held(),
+            &Currency::from_str("0.5").unwrap()
+        );
+        // The withdrawal exceeding funds, by its rowid
+        let rejected: Vec<u64> = transakt
+            .rejections()
+            .groups()
+            .flat_map(|(_, group)| group.lines.clone())
+            .collect();
+        assert_eq!(rejected, [101]);
+
+        assert!(Transakt::read_from_sqlite(&path, "missing").is_err());
+    }
+
+    #[test]
+    fn read_in_batches() {
+        let dir = std::env::temp_dir().join(format!("transakt-sqlite-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("batches.db");
+        let _ = std::fs::remove_file(&path);
+        let connection = rusqlite::Connection::open(&path).unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE t (type TEXT, client INTEGER, tx INTEGER, amount TEXT);
+                 INSERT INTO t (rowid, type, client, tx, amount)
+                 VALUES (-5, 'deposit', 1, 1, '1'), (9223372036854775807, 'withdrawal', 1, 2, '1');
+                 WITH RECURSIVE n(i) AS (SELECT 3 UNION ALL SELECT i + 1 FROM n WHERE i < 3000)
+                 INSERT INTO t (rowid, type, client, tx, amount)
+                 SELECT i, 'deposit', 1, i, '0.0001' FROM n;",
+            )
+            .unwrap();
+        drop(connection);
+        let rows: Vec<u64> = super::read_timed_sqlite(&path, "t")
+            .map(|(rowid, row)| {
+                row.unwrap();
+                rowid
+            })
+            .collect();
+        assert_eq!(rows.len(), 3000);
+        assert_eq!(rows[0], -5i64 as u64);
+        assert_eq!(rows[1], 3);
+        assert_eq!(rows[2999], i64::MAX as u64);
+        let transakt = Transakt::read_from_sqlite(&path, "t").unwrap();
+        let account = &transakt.get_accounts_map()[&ClientId::new(1)];
+        assert_eq!(account.available(), &Currency::from_str("0.2998").unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,251 @@
+//! Statements of clients: their deposits, withdrawals and disputes in the order they were applied,
+//! each with the balances it left behind, as text to send to the client or as CSV for other
+//! tools.
+//!
+//! Statements are built by replaying the journal, see [`Transakt::statement`]. Replayed on top of
+//! a snapshot, the statement starts from the balances of the snapshot and only covers the journal
+//! entries after it, which makes statements of a period out of the daily snapshots:
+//!
+//! ```text
+//! transakt statement 7 --journal journal.jsonl --snapshot monday.snap
+//! ```
+
+use crate::currency::Currency;
+use crate::journal::HistoryEntry;
+use crate::snapshot::AccountState;
+use crate::transaction::{ClientId, Transaction, TransactionId};
+use crate::{Error, Transakt};
+use serde::Serialize;
+use std::io::{BufRead, Write};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub client: ClientId,
+    /// The balances before the first line, if the client had an account then.
+    pub opening: Option<AccountState>,
+    pub lines: Vec<StatementLine>,
+}
+
+/// A deposit, withdrawal, transfer, reversal, dispute, resolution, chargeback or chargeback
+/// reversal, with the running balances.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StatementLine {
+    pub seq: u64,
+    /// When the transaction was applied, in seconds since the Unix epoch.
+    pub recorded_at: u64,
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub tx: TransactionId,
+    /// What the transaction moved: the amount of deposits, withdrawals and transfers and of those
+    /// reversed, what disputes held and resolutions and chargebacks released, which is less than
+    /// the deposit for partial holds, and what chargeback reversals credited back.
+    pub amount: Currency,
+    pub available: Currency,
+    pub held: Currency,
+    /// `None` if the sum overflows.
+    pub total: Option<Currency>,
+    pub locked: bool,
+}
+
+impl Statement {
+    /// The statement of the client from their journaled history, starting from `opening`.
+    pub fn new(client: ClientId, opening: Option<AccountState>, history: &[HistoryEntry]) -> Self {
+        let (mut available, mut held) = opening.as_ref().map_or_else(Default::default, |account| {
+            (account.available, account.held)
+        });
+        let mut lines = vec![];
+        for entry in history {
+            let account = &entry.account;
+            let moved = match entry.transaction {
+                Transaction::Deposit { amount, .. }
+                | Transaction::Withdrawal { amount, .. }
+                | Transaction::Transfer { amount, .. } => Some(amount),
+                Transaction::Dispute { .. } => account.held.checked_sub(held),
+                Transaction::Resolve { .. } | Transaction::Chargeback { .. } => {
+                    held.checked_sub(account.held)
+                }
+                Transaction::Reversal { .. } if account.available < available => {
+                    available.checked_sub(account.available)
+                }
+                Transaction::Reversal { .. } | Transaction::ChargebackReversal { .. } => {
+                    account.available.checked_sub(available)
+                }
+                // Locks and reactivations move no money
+                _ => None,
+            };
+            available = account.available;
+            held = account.held;
+            if let Some(amount) = moved {
+                lines.push(StatementLine {
+                    seq: entry.seq,
+                    recorded_at: entry.recorded_at,
+                    kind: entry.transaction.kind(),
+                    tx: entry.transaction.tx(),
+                    amount,
+                    available: account.available,
+                    held: account.held,
+                    total: account.available.checked_add(account.held),
+                    locked: account.locked,
+                });
+            }
+        }
+        Self {
+            client,
+            opening,
+            lines,
+        }
+    }
+
+    /// The balances after the last line, or the opening ones without lines.
+    pub fn closing(&self) -> Option<(Currency, Currency)> {
+        match self.lines.last() {
+            Some(line) => Some((line.available, line.held)),
+            None => self
+                .opening
+                .as_ref()
+                .map(|account| (account.available, account.held)),
+        }
+    }
+
+    /// Writes the lines as CSV, `seq,recorded_at,type,tx,amount,available,held,total,locked`.
+    pub fn write_csv<W: Write>(&self, writer: W) -> Result<(), Error> {
+        let mut out = csv::Writer::from_writer(writer);
+        for line in self.lines.iter() {
+            out.serialize(line).map_err(|err| Error::Io(err.into()))?;
+        }
+        out.flush().map_err(Error::Io)
+    }
+
+    /// Writes the statement as a table, between the opening and the closing balances.
+    pub fn write_text<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        self.write_table(&mut writer).map_err(Error::Io)
+    }
+
+    fn write_table<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        let mut row = |cells: [&str; 7]| {
+            writeln!(
+                writer,
+                "{:>8} {:<10} {:>10} {:>14} {:>14} {:>14} {:>14}",
+                cells[0], cells[1], cells[2], cells[3], cells[4], cells[5], cells[6]
+            )
+        };
+        let total =
+            |total: Option<Currency>| total.map_or("overflow".to_string(), |t| t.to_string());
+        let (available, held) = self
+            .opening
+            .as_ref()
+            .map_or_else(Default::default, |account| {
+                (account.available, account.held)
+            });
+        row(["seq", "type", "tx", "amount", "available", "held", "total"])?;
+        row([
+            "",
+            "opening",
+            "",
+            "",
+            &available.to_string(),
+            &held.to_string(),
+            &total(available.checked_add(held)),
+        ])?;
+        for line in self.lines.iter() {
+            row([
+                &line.seq.to_string(),
+                line.kind,
+                &line.tx.to_string(),
+                &line.amount.to_string(),
+                &line.available.to_string(),
+                &line.held.to_string(),
+                &total(line.total),
+            ])?;
+        }
+        let (available, held) = self.closing().unwrap_or_default();
+        row([
+            "",
+            "closing",
+            "",
+            "",
+            &available.to_string(),
+            &held.to_string(),
+            &total(available.checked_add(held)),
+        ])
+    }
+}
+
+impl Transakt {
+    /// Replays the journal like [`Transakt::client_history`], and returns the statement of the
+    /// client from the current state to the end of the journal.
+    pub fn statement<R: BufRead>(
+        &mut self,
+        journal: R,
+        client: ClientId,
+    ) -> Result<Statement, Error> {
+        let opening = self.accounts.get(&client).map(AccountState::from);
+        let history = self.client_history(journal, client)?;
+        Ok(Statement::new(client, opening, &history))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::journal::JournalWriter;
+    use crate::transaction::ClientId;
+    use crate::Transakt;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn statement() {
+        let journal = SharedBuffer::default();
+        let mut transakt = Transakt::builder()
+            .journal(JournalWriter::new(journal.clone()))
+            .build();
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,5.0\n\
+                     deposit,2,2,1.0\n\
+                     withdrawal,1,3,2.0\n\
+                     dispute,1,1,\n\
+                     freeze,1,4,\n\
+                     chargeback,1,1,\n";
+        transakt.load_csv_from("input", input.as_bytes()).unwrap();
+        let journal = journal.0.lock().unwrap().clone();
+
+        let statement = Transakt::default()
+            .statement(&journal[..], ClientId::new(1))
+            .unwrap();
+        assert_eq!(statement.opening, None);
+        let mut out = vec![];
+        statement.write_csv(&mut out).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = csv
+            .lines()
+            .map(|line| line.split_once(',').unwrap().1)
+            .map(|line| line.split_once(',').unwrap().1)
+            .collect();
+        // Without the sequence numbers and times, and the freeze
+        assert_eq!(
+            lines,
+            vec![
+                "type,tx,amount,available,held,total,locked",
+                "deposit,1,5.0000,5.0000,0.0000,5.0000,false",
+                "withdrawal,3,2.0000,3.0000,0.0000,3.0000,false",
+                "dispute,1,5.0000,-2.0000,5.0000,3.0000,false",
+                "chargeback,1,5.0000,-2.0000,0.0000,-2.0000,true",
+            ]
+        );
+        let (available, held) = statement.closing().unwrap();
+        assert_eq!(available.to_string(), "-2.0000");
+        assert_eq!(held.to_string(), "0.0000");
+    }
+}
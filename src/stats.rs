@@ -0,0 +1,246 @@
+//! Profile of an input file, gathered without executing any of it.
+
+use crate::currency::Currency;
+use crate::transaction::{ClientId, CsvOptions, Transaction, TransactionId, TransactionRow};
+use crate::Error;
+use std::collections::{BTreeMap, HashSet};
+use std::convert::TryInto;
+use std::fmt::{Display, Formatter};
+use std::io::Read;
+
+/// Upper bounds of the amount histogram buckets, in whole currency units. Amounts at or above
+/// the last bound fall into an extra, open-ended bucket.
+const BUCKET_BOUNDS: [i64; 5] = [1, 10, 100, 1_000, 10_000];
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DatasetStats {
+    pub rows: u64,
+    /// Rows that could not be read as a transaction.
+    pub malformed: u64,
+    pub per_type: BTreeMap<&'static str, u64>,
+    pub clients: usize,
+    /// Deposits and withdrawals reusing the id of an earlier one.
+    pub duplicate_tx: u64,
+    pub amounts: AmountStats,
+    /// Earliest and latest `timestamp` of the transactions, in seconds since the Unix epoch.
+    /// `None` if no row has one.
+    pub timestamps: Option<(u64, u64)>,
+}
+
+/// Amounts of deposits and withdrawals.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AmountStats {
+    pub count: u64,
+    pub negative: u64,
+    pub min: Option<Currency>,
+    pub max: Option<Currency>,
+    /// `None` if the sum overflows.
+    pub total: Option<Currency>,
+    /// Counts of non-negative amounts below each of [`BUCKET_BOUNDS`], then at or above the last.
+    pub histogram: [u64; BUCKET_BOUNDS.len() + 1],
+}
+
+impl AmountStats {
+    fn record(&mut self, amount: Currency) {
+        self.total = match self.count {
+            0 => Some(amount),
+            _ => self.total.and_then(|total| total.checked_add(amount)),
+        };
+        self.count += 1;
+        self.min = Some(self.min.map_or(amount, |min| min.min(amount)));
+        self.max = Some(self.max.map_or(amount, |max| max.max(amount)));
+        if amount.is_negative() {
+            self.negative += 1;
+            return;
+        }
+        let bucket = BUCKET_BOUNDS
+            .iter()
+            .position(|bound| amount < Currency::new(*bound, 0).expect("bucket bounds are valid"))
+            .unwrap_or(BUCKET_BOUNDS.len());
+        self.histogram[bucket] += 1;
+    }
+}
+
+/// Scans a CSV input and profiles it. Only I/O failures are returned as errors, malformed rows
+/// are counted.
+pub fn profile_csv<R: Read>(reader: R) -> Result<DatasetStats, Error> {
+    profile_csv_with(reader, &CsvOptions::default())
+}
+
+/// Like [`profile_csv`], for CSV input written as `options` say. Options that can't be used are
+/// an error.
+pub fn profile_csv_with<R: Read>(reader: R, options: &CsvOptions) -> Result<DatasetStats, Error> {
+    let mut stats = DatasetStats::default();
+    let mut csv = options.reader_builder()?.from_reader(reader);
+    let headers = match csv.headers() {
+        Ok(headers) => options.map_headers(headers),
+        Err(err) => {
+            if let csv::ErrorKind::Io(_) = err.kind() {
+                return Err(Error::Io(err.into()));
+            }
+            // Counted like a row that can't be read
+            stats.rows += 1;
+            stats.malformed += 1;
+            return Ok(stats);
+        }
+    };
+    let amount = headers.iter().position(|header| header == "amount");
+    let mut clients: HashSet<ClientId> = HashSet::new();
+    let mut seen: HashSet<TransactionId> = HashSet::new();
+    for record in csv.records() {
+        let row = record.and_then(|record| {
+            options
+                .decimal_point(record, amount)
+                .deserialize::<TransactionRow>(Some(&headers))
+        });
+        let row = match row {
+            Ok(row) => row,
+            Err(err) => {
+                if let csv::ErrorKind::Io(_) = err.kind() {
+                    return Err(Error::Io(err.into()));
+                }
+                stats.rows += 1;
+                stats.malformed += 1;
+                continue;
+            }
+        };
+        stats.rows += 1;
+        let timestamp = row.timestamp();
+        let transaction: Transaction = match row.try_into() {
+            Ok(transaction) => transaction,
+            Err(_) => {
+                stats.malformed += 1;
+                continue;
+            }
+        };
+        if let Some(time) = timestamp {
+            stats.timestamps = Some(match stats.timestamps {
+                Some((first, last)) => (first.min(time), last.max(time)),
+                None => (time, time),
+            });
+        }
+        *stats.per_type.entry(transaction.kind()).or_default() += 1;
+        clients.insert(transaction.client());
+        match transaction {
+            Transaction::Deposit { amount, tx, .. }
+            | Transaction::Withdrawal { amount, tx, .. }
+            | Transaction::Transfer { amount, tx, .. } => {
+                stats.amounts.record(amount);
+                if !seen.insert(tx) {
+                    stats.duplicate_tx += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    stats.clients = clients.len();
+    Ok(stats)
+}
+
+impl Display for DatasetStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let or_dash = |amount: Option<Currency>| amount.map_or("-".to_string(), |a| a.to_string());
+        writeln!(f, "rows:           {}", self.rows)?;
+        writeln!(f, "malformed rows: {}", self.malformed)?;
+        for (kind, count) in self.per_type.iter() {
+            writeln!(f, "  {:<12}  {}", kind, count)?;
+        }
+        writeln!(f, "clients:        {}", self.clients)?;
+        writeln!(f, "duplicate tx:   {}", self.duplicate_tx)?;
+        match self.timestamps {
+            Some((first, last)) => writeln!(f, "timestamps:     {} to {}", first, last)?,
+            None => writeln!(f, "timestamps:     -")?,
+        }
+        let amounts = &self.amounts;
+        writeln!(f, "amounts:        {}", amounts.count)?;
+        writeln!(f, "  negative      {}", amounts.negative)?;
+        writeln!(f, "  min           {}", or_dash(amounts.min))?;
+        writeln!(f, "  max           {}", or_dash(amounts.max))?;
+        match (amounts.count, amounts.total) {
+            (0, _) => writeln!(f, "  total         -")?,
+            (_, Some(total)) => writeln!(f, "  total         {}", total)?,
+            (_, None) => writeln!(f, "  total         overflow")?,
+        }
+        let mut lower = 0;
+        for (bound, count) in BUCKET_BOUNDS.iter().zip(amounts.histogram.iter()) {
+            writeln!(f, "  [{}, {})  {}", lower, bound, count)?;
+            lower = *bound;
+        }
+        write!(
+            f,
+            "  [{}, ...)  {}",
+            lower,
+            amounts.histogram[BUCKET_BOUNDS.len()]
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{profile_csv, profile_csv_with};
+    use crate::currency::Currency;
+    use crate::transaction::CsvOptions;
+    use std::str::FromStr;
+
+    #[test]
+    fn profile() {
+        let input = "type, client, tx, amount\n\
+                     deposit, 1, 1, 0.5\n\
+                     deposit, 2, 2, 15\n\
+                     deposit, 2, 2, 20000\n\
+                     withdrawal, 1, 3, 0.25\n\
+                     dispute, 2, 2,\n\
+                     deposit, 3, 4,\n\
+                     transfer, 1, 5, 1.0\n";
+        let stats = profile_csv(input.as_bytes()).unwrap();
+        assert_eq!(stats.rows, 7);
+        assert_eq!(stats.malformed, 2);
+        assert_eq!(stats.per_type.get("deposit"), Some(&3));
+        assert_eq!(stats.per_type.get("withdrawal"), Some(&1));
+        assert_eq!(stats.per_type.get("dispute"), Some(&1));
+        assert_eq!(stats.clients, 2);
+        assert_eq!(stats.duplicate_tx, 1);
+        assert_eq!(stats.amounts.count, 4);
+        assert_eq!(stats.amounts.min, Currency::from_str("0.25").ok());
+        assert_eq!(stats.amounts.max, Currency::from_str("20000").ok());
+        assert_eq!(stats.amounts.total, Currency::from_str("20015.75").ok());
+        assert_eq!(stats.amounts.histogram, [2, 0, 1, 0, 0, 1]);
+        assert_eq!(stats.timestamps, None);
+        assert!(stats.to_string().contains("timestamps:     -\n"));
+    }
+
+    #[test]
+    fn profile_timestamps() {
+        let input = "type, client, tx, amount, timestamp\n\
+                     deposit, 1, 1, 1.0, 1700000100\n\
+                     deposit, 1, 2, 1.0,\n\
+                     dispute, 1, 1,, 1700000000\n\
+                     withdrawal, 1, 3, 0.5, 1700000050\n\
+                     deposit, 1, 4,, 1600000000\n";
+        let stats = profile_csv(input.as_bytes()).unwrap();
+        // The malformed row doesn't count
+        assert_eq!(stats.timestamps, Some((1700000000, 1700000100)));
+        assert!(stats
+            .to_string()
+            .contains("timestamps:     1700000000 to 1700000100\n"));
+    }
+
+    #[test]
+    fn profile_partner_format() {
+        let mut options = CsvOptions {
+            delimiter: ';',
+            decimal_separator: ',',
+            ..CsvOptions::default()
+        };
+        options
+            .columns
+            .insert("value".to_string(), "amount".to_string());
+        let input = "type;client;tx;value\n\
+                     deposit;1;1;1,5\n\
+                     withdrawal;1;2;0,25\n";
+        let stats = profile_csv_with(input.as_bytes(), &options).unwrap();
+        assert_eq!(stats.rows, 2);
+        assert_eq!(stats.malformed, 0);
+        assert_eq!(stats.amounts.total, Currency::from_str("1.75").ok());
+    }
+}
@@ -0,0 +1,115 @@
+//! What a run did, for operational reporting: how many transactions the engine executed, how many
+//! of them were applied and why the others were rejected, and the accounts it ended with.
+//!
+//! The counts cover the transactions executed since the engine was built, replays of a journal
+//! included, but not those of a restored snapshot; transactions of clients passed over by
+//! [`TransaktBuilder::clients`](crate::TransaktBuilder::clients) aren't counted. Rows that don't
+//! parse end their input before being executed, so they aren't counted either.
+
+use crate::account::Account;
+use crate::currency::Currency;
+use crate::{Error, Transakt};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+/// Outcomes of the transactions executed so far.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct RunCounts {
+    accepted: u64,
+    rejected: BTreeMap<&'static str, u64>,
+}
+
+impl RunCounts {
+    pub(crate) fn record(&mut self, res: Result<(), &Error>) {
+        match res {
+            Ok(()) => self.accepted += 1,
+            Err(err) => *self.rejected.entry(err.code()).or_default() += 1,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct RunSummary {
+    pub processed: u64,
+    pub accepted: u64,
+    /// Rejected transactions by error code, in code order.
+    pub rejected: BTreeMap<&'static str, u64>,
+    pub accounts: usize,
+    /// Available funds of all accounts, `None` if the sum overflows.
+    pub available: Option<Currency>,
+    /// Held funds of all accounts, `None` if the sum overflows.
+    pub held: Option<Currency>,
+}
+
+impl RunSummary {
+    /// Transactions rejected, for any reason.
+    pub fn rejected_total(&self) -> u64 {
+        self.rejected.values().sum()
+    }
+}
+
+impl Transakt {
+    /// What the engine did so far, see the [module docs](self).
+    pub fn summary(&self) -> RunSummary {
+        let sum = |amount: fn(&Account) -> Currency| {
+            self.accounts
+                .values()
+                .try_fold(Currency::default(), |sum, account| {
+                    sum.checked_add(amount(account))
+                })
+        };
+        let counts = &self.run_counts;
+        RunSummary {
+            processed: counts.accepted + counts.rejected.values().sum::<u64>(),
+            accepted: counts.accepted,
+            rejected: counts.rejected.clone(),
+            accounts: self.accounts.len(),
+            available: sum(|account| *account.available()),
+            held: sum(|account| *account.held()),
+        }
+    }
+}
+
+impl Display for RunSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let or_overflow =
+            |amount: Option<Currency>| amount.map_or("overflow".to_string(), |a| a.to_string());
+        writeln!(f, "processed:  {}", self.processed)?;
+        writeln!(f, "accepted:   {}", self.accepted)?;
+        writeln!(f, "rejected:   {}", self.rejected_total())?;
+        for (code, count) in self.rejected.iter() {
+            writeln!(f, "  {:<18}  {}", code, count)?;
+        }
+        writeln!(f, "accounts:   {}", self.accounts)?;
+        writeln!(f, "available:  {}", or_overflow(self.available))?;
+        write!(f, "held:       {}", or_overflow(self.held))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::currency::Currency;
+    use crate::Transakt;
+    use std::str::FromStr;
+
+    #[test]
+    fn summary() {
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,2.0\n\
+                     deposit,2,2,3.5\n\
+                     withdrawal,1,3,5.0\n\
+                     deposit,1,1,1.0\n\
+                     dispute,2,2,\n\
+                     withdrawal,2,4,1.0\n";
+        let transakt = Transakt::read_from_reader(input.as_bytes()).unwrap();
+        let summary = transakt.summary();
+        assert_eq!(summary.processed, 6);
+        assert_eq!(summary.accepted, 3);
+        let rejected: Vec<(&str, u64)> = summary.rejected.into_iter().collect();
+        assert_eq!(rejected, vec![("E_DUP_TX", 1), ("E_FUNDS", 2)]);
+        assert_eq!(summary.accounts, 2);
+        assert_eq!(summary.available, Currency::from_str("2.0").ok());
+        assert_eq!(summary.held, Currency::from_str("3.5").ok());
+    }
+}
@@ -0,0 +1,210 @@
+//! Helpers for testing code that embeds the engine, built with the `testing` feature. Add it to
+//! the dev-dependencies:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! transakt = { version = "0.1", features = ["testing"] }
+//! ```
+//!
+//! - [`seeded`] and [`seeded_with`] build an engine and run some rows through it,
+//! - [`assert_balances!`](crate::assert_balances) checks a client's account,
+//! - [`check_properties`] runs rows through engines from a builder and checks what must hold
+//!   whatever the configuration: the ledger invariants after every row, and that snapshots and the
+//!   journal rebuild the same state.
+
+use crate::diff::{check_expectations, Expectation};
+use crate::journal::{JournalWriter, ReplayLimit};
+use crate::transaction::ClientId;
+use crate::{Error, Transakt, TransaktBuilder};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+/// Asserts balances of a client, giving amounts as strings and `locked` as a bool. Only the
+/// values given are checked:
+///
+/// ```ignore
+/// assert_balances!(transakt, client = 1, available = "0.5", held = "1", locked = false);
+/// ```
+#[macro_export]
+macro_rules! assert_balances {
+    ($transakt:expr, client = $client:expr $(, $check:ident = $value:expr)* $(,)?) => {{
+        let balances = $crate::testing::Balances::of($client)$(.$check($value))*;
+        balances.assert(&$transakt);
+    }};
+}
+
+/// Expected balances of a client, see [`assert_balances!`](crate::assert_balances).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Balances(Expectation);
+
+impl Balances {
+    pub fn of(client: u16) -> Self {
+        Self(Expectation {
+            client: ClientId::new(client),
+            available: None,
+            held: None,
+            total: None,
+            locked: None,
+        })
+    }
+
+    pub fn available(mut self, amount: &str) -> Self {
+        self.0.available = Some(amount.parse().expect("invalid amount"));
+        self
+    }
+
+    pub fn held(mut self, amount: &str) -> Self {
+        self.0.held = Some(amount.parse().expect("invalid amount"));
+        self
+    }
+
+    pub fn total(mut self, amount: &str) -> Self {
+        self.0.total = Some(amount.parse().expect("invalid amount"));
+        self
+    }
+
+    pub fn locked(mut self, locked: bool) -> Self {
+        self.0.locked = Some(locked);
+        self
+    }
+
+    /// Panics with what differs, if anything does.
+    pub fn assert(&self, transakt: &Transakt) {
+        let mismatches =
+            check_expectations(&transakt.snapshot().accounts, std::slice::from_ref(&self.0));
+        assert!(mismatches.is_empty(), "{}", mismatches.join("; "));
+    }
+}
+
+/// A buffer that stays readable after a clone of it was moved into a journal.
+#[derive(Debug, Clone, Default)]
+pub struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    pub fn contents(&self) -> Vec<u8> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Adds the `type,client,tx,amount` header unless the rows start with one.
+fn with_header(rows: &str) -> String {
+    let rows = rows.trim_start();
+    if rows.starts_with("type") {
+        rows.to_string()
+    } else {
+        format!("type,client,tx,amount\n{}", rows)
+    }
+}
+
+/// An engine with the standard rules after the rows, given like in a CSV input, with or without
+/// the header. Rejected rows are fine, errors that stop processing panic.
+pub fn seeded(rows: &str) -> Transakt {
+    seeded_with(Transakt::builder(), rows)
+}
+
+/// Like [`seeded`], with an engine from the builder.
+pub fn seeded_with(builder: TransaktBuilder, rows: &str) -> Transakt {
+    let mut transakt = builder.build();
+    transakt
+        .load_csv_from("seed", with_header(rows).as_bytes())
+        .expect("seed rows failed");
+    transakt
+}
+
+/// Runs the rows through engines from `builder`, panicking at the first property broken:
+///
+/// - the ledger invariants hold after every row, see [`crate::verify`],
+/// - restoring a snapshot of the final state gives the same state,
+/// - replaying the journal on an empty engine gives the same state.
+///
+/// Builders can't be cloned, so `builder` is called for every engine it needs. Returns the engine
+/// the rows ran on.
+pub fn check_properties<F>(builder: F, rows: &str) -> Transakt
+where
+    F: Fn() -> TransaktBuilder,
+{
+    let journal = SharedBuffer::default();
+    let mut transakt = builder()
+        .verify()
+        .journal(JournalWriter::new(journal.clone()))
+        .build();
+    match transakt.load_csv_from("properties", with_header(rows).as_bytes()) {
+        Err(Error::InvariantViolation(reason)) => panic!("invariant broken at {}", reason),
+        res => res.expect("rows failed"),
+    }
+    let snapshot = transakt.snapshot();
+
+    let mut restored = builder().build();
+    restored.restore(snapshot.clone()).expect("restore failed");
+    assert_eq!(
+        restored.snapshot(),
+        snapshot,
+        "restoring a snapshot changed the state"
+    );
+
+    let mut replayed = builder().build();
+    replayed
+        .replay(&journal.contents()[..], &ReplayLimit::default())
+        .expect("replay failed");
+    assert_eq!(
+        replayed.snapshot().digest(),
+        snapshot.digest(),
+        "replaying the journal gave another state"
+    );
+    transakt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_properties, seeded, seeded_with, Balances};
+    use crate::policy::DisputeFundsAction;
+    use crate::Transakt;
+    use std::panic::{self, AssertUnwindSafe};
+
+    #[test]
+    fn helpers() {
+        let transakt = seeded(
+            "deposit,1,1,10.0\n\
+             withdrawal,1,2,2.5\n\
+             dispute,1,1,\n",
+        );
+        assert_balances!(transakt, client = 1, available = "-2.5", held = "10");
+        assert_balances!(transakt, client = 1, total = "7.5", locked = false,);
+        let wrong = panic::catch_unwind(AssertUnwindSafe(|| {
+            Balances::of(1).held("1").assert(&transakt);
+        }));
+        assert!(wrong.is_err());
+
+        let holding =
+            || Transakt::builder().disputes_exceeding_funds(DisputeFundsAction::HoldAvailable);
+        let transakt = seeded_with(
+            holding(),
+            "type,client,tx,amount\n\
+             deposit,1,1,10.0\n\
+             withdrawal,1,2,2.5\n\
+             dispute,1,1,\n",
+        );
+        assert_balances!(transakt, client = 1, available = "0", held = "7.5");
+
+        let rows = "deposit,1,1,10.0\n\
+                    deposit,2,2,5.0\n\
+                    withdrawal,1,3,2.5\n\
+                    dispute,1,1,\n\
+                    chargeback,1,1,\n\
+                    dispute,2,2,\n";
+        check_properties(holding, rows);
+        // Holding the whole deposit breaks the invariants
+        let broken = panic::catch_unwind(|| check_properties(Transakt::builder, rows));
+        assert!(broken.is_err());
+    }
+}
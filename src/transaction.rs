@@ -1,10 +1,14 @@
 use crate::currency::Currency;
+use crate::redact;
 use crate::Error;
 use serde::Deserialize;
 use serde::Serialize;
+use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::fmt::{Debug, Display, Formatter};
+use std::str::FromStr;
 
-#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Hash, Copy, Clone)]
+#[derive(Deserialize, Serialize, Eq, PartialEq, Ord, PartialOrd, Hash, Copy, Clone)]
 #[serde(transparent)]
 pub struct ClientId {
     id: u16,
@@ -14,9 +18,44 @@ impl ClientId {
     pub fn new(id: u16) -> Self {
         Self { id }
     }
+
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+}
+
+/// Mostly one of a few clients, so that transactions meet the same accounts.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for ClientId {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let id = if u.ratio(1, 16)? {
+            u.arbitrary()?
+        } else {
+            u.int_in_range(1..=4)?
+        };
+        Ok(Self { id })
+    }
+}
+
+impl Display for ClientId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.id)
+    }
+}
+
+/// Redacted if configured, see [`crate::redact`].
+impl Debug for ClientId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let redaction = redact::current();
+        if redaction.is_off() {
+            f.debug_struct("ClientId").field("id", &self.id).finish()
+        } else {
+            write!(f, "ClientId({})", redaction.client(*self))
+        }
+    }
 }
 
-#[derive(Debug, Deserialize, Eq, PartialEq, Hash, Copy, Clone)]
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Ord, PartialOrd, Hash, Copy, Clone)]
 #[serde(transparent)]
 pub struct TransactionId {
     id: u32,
@@ -26,10 +65,34 @@ impl TransactionId {
     pub fn new(id: u32) -> Self {
         Self { id }
     }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+/// Mostly one of a few ids, so that disputes find the deposits and ids get reused.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for TransactionId {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let id = if u.ratio(1, 16)? {
+            u.arbitrary()?
+        } else {
+            u.int_in_range(1..=32)?
+        };
+        Ok(Self { id })
+    }
+}
+
+impl Display for TransactionId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.id)
+    }
 }
 
 /// Represents a transaction.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
 pub enum Transaction {
     Deposit {
         client: ClientId,
@@ -42,9 +105,12 @@ pub enum Transaction {
         tx: TransactionId,
         amount: Currency,
     },
+    /// Holds the deposit, or only `amount` of it for a partial dispute.
     Dispute {
         client: ClientId,
         tx: TransactionId,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        amount: Option<Currency>,
     },
     Resolve {
         client: ClientId,
@@ -54,11 +120,165 @@ pub enum Transaction {
         client: ClientId,
         tx: TransactionId,
     },
+    /// Credits back what the chargeback of the deposit took, when the merchant wins the
+    /// representment.
+    #[serde(rename = "chargeback_reversal")]
+    ChargebackReversal {
+        client: ClientId,
+        tx: TransactionId,
+    },
+    /// Administrative lock of the client's account. `tx` only identifies the row.
+    Freeze {
+        client: ClientId,
+        tx: TransactionId,
+    },
+    /// Lifts a lock, whether it came from a freeze or a chargeback.
+    Unfreeze {
+        client: ClientId,
+        tx: TransactionId,
+    },
+    /// Marks a dormant account active again, see [`crate::dormancy`].
+    Reactivate {
+        client: ClientId,
+        tx: TransactionId,
+    },
+    /// Moves funds from the account of `from` to that of `to`, both or neither. Stored like a
+    /// deposit to `to`, so a dispute holds the funds there and a chargeback returns them to `from`.
+    Transfer {
+        from: ClientId,
+        to: ClientId,
+        tx: TransactionId,
+        amount: Currency,
+        disputed: bool,
+    },
+    /// Undoes the deposit or withdrawal `ref_tx` of the client, to correct an operator error. The
+    /// deposit can't be under dispute. `tx` only identifies the row.
+    Reversal {
+        client: ClientId,
+        tx: TransactionId,
+        ref_tx: TransactionId,
+    },
+}
+
+impl Transaction {
+    /// The lowercase name of the transaction type, as it appears in the input.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Transaction::Deposit { .. } => "deposit",
+            Transaction::Withdrawal { .. } => "withdrawal",
+            Transaction::Dispute { .. } => "dispute",
+            Transaction::Resolve { .. } => "resolve",
+            Transaction::Chargeback { .. } => "chargeback",
+            Transaction::ChargebackReversal { .. } => "chargeback_reversal",
+            Transaction::Freeze { .. } => "freeze",
+            Transaction::Unfreeze { .. } => "unfreeze",
+            Transaction::Reactivate { .. } => "reactivate",
+            Transaction::Transfer { .. } => "transfer",
+            Transaction::Reversal { .. } => "reversal",
+        }
+    }
+
+    /// The client the transaction is from, the sender of a transfer.
+    pub fn client(&self) -> ClientId {
+        match self {
+            Transaction::Deposit { client, .. }
+            | Transaction::Withdrawal { client, .. }
+            | Transaction::Dispute { client, .. }
+            | Transaction::Resolve { client, .. }
+            | Transaction::Chargeback { client, .. }
+            | Transaction::ChargebackReversal { client, .. }
+            | Transaction::Freeze { client, .. }
+            | Transaction::Unfreeze { client, .. }
+            | Transaction::Reactivate { client, .. }
+            | Transaction::Reversal { client, .. } => *client,
+            Transaction::Transfer { from, .. } => *from,
+        }
+    }
+
+    /// The other client of a transfer, who receives the funds.
+    pub fn counterparty(&self) -> Option<ClientId> {
+        match self {
+            Transaction::Transfer { to, .. } => Some(*to),
+            _ => None,
+        }
+    }
+
+    pub fn tx(&self) -> TransactionId {
+        match self {
+            Transaction::Deposit { tx, .. }
+            | Transaction::Withdrawal { tx, .. }
+            | Transaction::Dispute { tx, .. }
+            | Transaction::Resolve { tx, .. }
+            | Transaction::Chargeback { tx, .. }
+            | Transaction::ChargebackReversal { tx, .. }
+            | Transaction::Freeze { tx, .. }
+            | Transaction::Unfreeze { tx, .. }
+            | Transaction::Reactivate { tx, .. }
+            | Transaction::Transfer { tx, .. }
+            | Transaction::Reversal { tx, .. } => *tx,
+        }
+    }
+
+    /// The amount of deposits, withdrawals and transfers, and of partial disputes.
+    pub fn amount(&self) -> Option<Currency> {
+        match self {
+            Transaction::Deposit { amount, .. }
+            | Transaction::Withdrawal { amount, .. }
+            | Transaction::Transfer { amount, .. } => Some(*amount),
+            Transaction::Dispute { amount, .. } => *amount,
+            _ => None,
+        }
+    }
+}
+
+/// Any transaction as it could come from an input, so deposits are never already disputed.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Transaction {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let client = u.arbitrary()?;
+        let tx = u.arbitrary()?;
+        Ok(match u.choose_index(11)? {
+            0 => Transaction::Deposit {
+                client,
+                tx,
+                amount: u.arbitrary()?,
+                disputed: false,
+            },
+            1 => Transaction::Withdrawal {
+                client,
+                tx,
+                amount: u.arbitrary()?,
+            },
+            2 => Transaction::Dispute {
+                client,
+                tx,
+                amount: u.arbitrary()?,
+            },
+            3 => Transaction::Resolve { client, tx },
+            4 => Transaction::Chargeback { client, tx },
+            5 => Transaction::Freeze { client, tx },
+            6 => Transaction::Unfreeze { client, tx },
+            7 => Transaction::Reactivate { client, tx },
+            8 => Transaction::Reversal {
+                client,
+                tx,
+                ref_tx: u.arbitrary()?,
+            },
+            9 => Transaction::ChargebackReversal { client, tx },
+            _ => Transaction::Transfer {
+                from: client,
+                to: u.arbitrary()?,
+                tx,
+                amount: u.arbitrary()?,
+                disputed: false,
+            },
+        })
+    }
 }
 
 /// This is a helper type that allows CSV deserialization since CSVs can't deserialize into a
 /// typed enum directly
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum TransactionType {
     Deposit,
@@ -66,6 +286,36 @@ pub enum TransactionType {
     Dispute,
     Resolve,
     Chargeback,
+    Freeze,
+    /// Also read from `unlock`, the name admin inputs use for it.
+    #[serde(alias = "unlock")]
+    Unfreeze,
+    Reactivate,
+    Transfer,
+    Reversal,
+    #[serde(rename = "chargeback_reversal")]
+    ChargebackReversal,
+}
+
+impl FromStr for TransactionType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "deposit" => Ok(TransactionType::Deposit),
+            "withdrawal" => Ok(TransactionType::Withdrawal),
+            "dispute" => Ok(TransactionType::Dispute),
+            "resolve" => Ok(TransactionType::Resolve),
+            "chargeback" => Ok(TransactionType::Chargeback),
+            "freeze" => Ok(TransactionType::Freeze),
+            "unfreeze" | "unlock" => Ok(TransactionType::Unfreeze),
+            "reactivate" => Ok(TransactionType::Reactivate),
+            "transfer" => Ok(TransactionType::Transfer),
+            "reversal" => Ok(TransactionType::Reversal),
+            "chargeback_reversal" => Ok(TransactionType::ChargebackReversal),
+            _ => Err(Error::TransactionParseError),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -76,8 +326,84 @@ pub struct TransactionRow {
     client: ClientId,
     tx: TransactionId,
     amount: Option<Currency>,
+    /// The client receiving a transfer, who `client` sends it to.
+    #[serde(default)]
+    to: Option<ClientId>,
+    /// The transaction a reversal undoes.
+    #[serde(default)]
+    ref_tx: Option<TransactionId>,
+    /// See [`crate::signature`].
+    #[serde(default)]
+    signature: Option<String>,
+    /// When the transaction happened, see [`crate::bitemporal`].
+    #[serde(default)]
+    timestamp: Option<u64>,
 }
 
+impl TransactionRow {
+    /// Builds a row for inputs that aren't deserialized through serde.
+    pub fn new(
+        tx_type: TransactionType,
+        client: ClientId,
+        tx: TransactionId,
+        amount: Option<Currency>,
+    ) -> Self {
+        Self {
+            tx_type,
+            client,
+            tx,
+            amount,
+            to: None,
+            ref_tx: None,
+            signature: None,
+            timestamp: None,
+        }
+    }
+
+    pub(crate) fn tx_type(&self) -> TransactionType {
+        self.tx_type
+    }
+
+    pub(crate) fn to(&self) -> Option<ClientId> {
+        self.to
+    }
+
+    pub(crate) fn timestamp(&self) -> Option<u64> {
+        self.timestamp
+    }
+
+    /// Sets the client receiving a transfer.
+    pub fn with_to(mut self, to: ClientId) -> Self {
+        self.to = Some(to);
+        self
+    }
+
+    /// Sets the transaction a reversal undoes.
+    pub fn with_ref_tx(mut self, ref_tx: TransactionId) -> Self {
+        self.ref_tx = Some(ref_tx);
+        self
+    }
+
+    /// The transaction, with the signature of the row if it has one.
+    pub fn into_signed(mut self) -> Result<(Transaction, Option<String>), Error> {
+        let signature = self
+            .signature
+            .take()
+            .filter(|signature| !signature.is_empty());
+        Ok((Transaction::try_from(self)?, signature))
+    }
+
+    /// Like [`TransactionRow::into_signed`], with the event time of the row if it has one.
+    pub fn into_timed(self) -> Result<TimedRow, Error> {
+        let timestamp = self.timestamp;
+        let (transaction, signature) = self.into_signed()?;
+        Ok((transaction, signature, timestamp))
+    }
+}
+
+/// A transaction with the signature and event time of its row.
+pub type TimedRow = (Transaction, Option<String>, Option<u64>);
+
 impl TryFrom<TransactionRow> for Transaction {
     type Error = Error;
 
@@ -88,32 +414,281 @@ impl TryFrom<TransactionRow> for Transaction {
                 client,
                 tx,
                 amount: Some(amount),
-            } => Ok(Transaction::Deposit { client, tx, amount , disputed: false}),
+                ..
+            } => Ok(Transaction::Deposit {
+                client,
+                tx,
+                amount,
+                disputed: false,
+            }),
             TransactionRow {
                 tx_type: TransactionType::Withdrawal,
                 client,
                 tx,
                 amount: Some(amount),
+                ..
             } => Ok(Transaction::Withdrawal { client, tx, amount }),
             TransactionRow {
                 tx_type: TransactionType::Dispute,
                 client,
                 tx,
-                amount: None,
-            } => Ok(Transaction::Dispute { client, tx }),
+                amount,
+                ..
+            } => Ok(Transaction::Dispute { client, tx, amount }),
             TransactionRow {
                 tx_type: TransactionType::Resolve,
                 client,
                 tx,
                 amount: None,
+                ..
             } => Ok(Transaction::Resolve { client, tx }),
             TransactionRow {
                 tx_type: TransactionType::Chargeback,
                 client,
                 tx,
                 amount: None,
+                ..
             } => Ok(Transaction::Chargeback { client, tx }),
+            TransactionRow {
+                tx_type: TransactionType::ChargebackReversal,
+                client,
+                tx,
+                amount: None,
+                ..
+            } => Ok(Transaction::ChargebackReversal { client, tx }),
+            TransactionRow {
+                tx_type: TransactionType::Freeze,
+                client,
+                tx,
+                amount: None,
+                ..
+            } => Ok(Transaction::Freeze { client, tx }),
+            TransactionRow {
+                tx_type: TransactionType::Unfreeze,
+                client,
+                tx,
+                amount: None,
+                ..
+            } => Ok(Transaction::Unfreeze { client, tx }),
+            TransactionRow {
+                tx_type: TransactionType::Reactivate,
+                client,
+                tx,
+                amount: None,
+                ..
+            } => Ok(Transaction::Reactivate { client, tx }),
+            TransactionRow {
+                tx_type: TransactionType::Transfer,
+                client,
+                tx,
+                amount: Some(amount),
+                to: Some(to),
+                ..
+            } => Ok(Transaction::Transfer {
+                from: client,
+                to,
+                tx,
+                amount,
+                disputed: false,
+            }),
+            TransactionRow {
+                tx_type: TransactionType::Reversal,
+                client,
+                tx,
+                amount: None,
+                ref_tx: Some(ref_tx),
+                ..
+            } => Ok(Transaction::Reversal { client, tx, ref_tx }),
             _ => Err(Error::TransactionParseError),
         }
     }
 }
+
+/// Reads transactions from CSV input, in order. A malformed row ends the input with
+/// [`Error::TransactionParseError`].
+pub fn read_csv<R: std::io::Read>(reader: R) -> impl Iterator<Item = Result<Transaction, Error>> {
+    read_signed_csv(reader).map(|row| row.map(|(transaction, _)| transaction))
+}
+
+/// Like [`read_csv`], with the `signature` column of each row, if there is one.
+pub fn read_signed_csv<R: std::io::Read>(
+    reader: R,
+) -> impl Iterator<Item = Result<(Transaction, Option<String>), Error>> {
+    read_timed_csv(reader)
+        .map(|row| row.map(|(transaction, signature, _)| (transaction, signature)))
+}
+
+/// Like [`read_signed_csv`], with the `timestamp` column of each row too.
+pub fn read_timed_csv<R: std::io::Read>(
+    reader: R,
+) -> impl Iterator<Item = Result<TimedRow, Error>> {
+    read_timed_csv_with(reader, &CsvOptions::default())
+}
+
+/// How CSV input is written, for partners whose files aren't plain comma-separated values:
+///
+/// ```toml
+/// [csv]
+/// delimiter = ";"
+/// quote = "'"
+/// decimal_separator = ","
+///
+/// [csv.columns]
+/// txn_type = "type"
+/// customer = "client"
+/// id = "tx"
+/// value = "amount"
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CsvOptions {
+    /// Between fields, an ASCII character.
+    pub delimiter: char,
+    /// Around fields that hold the delimiter, an ASCII character.
+    pub quote: char,
+    /// Whether spaces around fields and headers are dropped.
+    pub trim: bool,
+    /// Between the units and the decimals of amounts. Amounts with a `,` need quotes if it is
+    /// also the delimiter.
+    pub decimal_separator: char,
+    /// The field each header of the input holds, for headers other than `type`, `client`, `tx`,
+    /// `amount`, `to`, `ref_tx`, `signature` and `timestamp`. Other headers are read as they are.
+    pub columns: HashMap<String, String>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            quote: '"',
+            trim: true,
+            decimal_separator: '.',
+            columns: HashMap::new(),
+        }
+    }
+}
+
+impl CsvOptions {
+    pub(crate) fn reader_builder(&self) -> Result<csv::ReaderBuilder, Error> {
+        let mut builder = csv::ReaderBuilder::new();
+        builder
+            .has_headers(true)
+            .delimiter(ascii("delimiter", self.delimiter)?)
+            .quote(ascii("quote", self.quote)?)
+            .trim(if self.trim {
+                csv::Trim::All
+            } else {
+                csv::Trim::None
+            });
+        Ok(builder)
+    }
+
+    /// Writes CSV with the delimiter and quote of the options.
+    pub(crate) fn writer_builder(&self) -> Result<csv::WriterBuilder, Error> {
+        let mut builder = csv::WriterBuilder::new();
+        builder
+            .delimiter(ascii("delimiter", self.delimiter)?)
+            .quote(ascii("quote", self.quote)?);
+        Ok(builder)
+    }
+
+    /// The headers of an input, renamed to the fields they hold.
+    pub(crate) fn map_headers(&self, headers: &csv::StringRecord) -> csv::StringRecord {
+        if self.columns.is_empty() {
+            return headers.clone();
+        }
+        headers
+            .iter()
+            .map(|header| self.columns.get(header).map_or(header, String::as_str))
+            .collect()
+    }
+
+    /// The record with a `.` between the units and decimals of its amount, the field at `amount`
+    /// of the mapped headers.
+    pub(crate) fn decimal_point(
+        &self,
+        record: csv::StringRecord,
+        amount: Option<usize>,
+    ) -> csv::StringRecord {
+        let separator = self.decimal_separator;
+        let Some(amount) = amount.filter(|_| separator != '.') else {
+            return record;
+        };
+        record
+            .iter()
+            .enumerate()
+            .map(|(i, field)| {
+                if i == amount {
+                    field.replace(separator, ".")
+                } else {
+                    field.to_string()
+                }
+            })
+            .collect()
+    }
+}
+
+fn ascii(name: &str, c: char) -> Result<u8, Error> {
+    u8::try_from(c)
+        .ok()
+        .filter(u8::is_ascii)
+        .ok_or_else(|| Error::InvalidConfig(format!("CSV {} `{}` isn't ASCII", name, c)))
+}
+
+/// Like [`read_timed_csv`], for CSV input written as `options` say. Options that can't be used
+/// end the input with [`Error::InvalidConfig`] before any row.
+pub fn read_timed_csv_with<R: std::io::Read>(
+    reader: R,
+    options: &CsvOptions,
+) -> impl Iterator<Item = Result<TimedRow, Error>> {
+    read_raw_csv_with(reader, options).map(|(_, row)| row)
+}
+
+/// Like [`read_timed_csv_with`], with the fields of each row as they were read, joined by the
+/// delimiter, if the row could be read at all.
+pub(crate) fn read_raw_csv_with<R: std::io::Read>(
+    reader: R,
+    options: &CsvOptions,
+) -> impl Iterator<Item = (Option<String>, Result<TimedRow, Error>)> {
+    let delimiter = options.delimiter.to_string();
+    let options = options.clone();
+    let (invalid, rows) = match options.reader_builder() {
+        Ok(builder) => (None, Some(builder.from_reader(reader))),
+        Err(err) => (Some(Err(err)), None),
+    };
+    let rows = rows.into_iter().flat_map(move |mut csv| {
+        let headers = csv.headers().map(|headers| options.map_headers(headers));
+        let amount = headers
+            .as_ref()
+            .ok()
+            .and_then(|headers| headers.iter().position(|header| header == "amount"));
+        let delimiter = delimiter.clone();
+        let options = options.clone();
+        csv.into_records().map(move |record| {
+            let raw = record
+                .as_ref()
+                .ok()
+                .map(|record| record.iter().collect::<Vec<_>>().join(&delimiter));
+            (
+                raw,
+                parse_record(record, headers.as_ref(), amount, &options),
+            )
+        })
+    });
+    invalid.into_iter().map(|err| (None, err)).chain(rows)
+}
+
+fn parse_record(
+    record: Result<csv::StringRecord, csv::Error>,
+    headers: Result<&csv::StringRecord, &csv::Error>,
+    amount: Option<usize>,
+    options: &CsvOptions,
+) -> Result<TimedRow, Error> {
+    let headers = headers.map_err(|_| Error::TransactionParseError)?;
+    let record = record.map_err(|_| Error::TransactionParseError)?;
+    options
+        .decimal_point(record, amount)
+        .deserialize::<TransactionRow>(Some(headers))
+        .map_err(|_| Error::TransactionParseError)?
+        .into_timed()
+}
@@ -0,0 +1,313 @@
+//! Terminal dashboard showing progress while a batch runs: throughput, the largest and the locked
+//! accounts, and the latest rejected transactions.
+//!
+//! The dashboard is drawn on stderr, so stdout stays free for the account report.
+
+use crate::account::Account;
+use crate::event::RiskEvent;
+use crate::transaction::Transaction;
+use crate::{Error, Transakt};
+use ratatui::backend::{Backend, CrosstermBackend};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::crossterm::ExecutableCommand;
+use ratatui::layout::{Constraint, Layout};
+use ratatui::widgets::{Block, List, Paragraph, Row, Table};
+use ratatui::{Frame, Terminal};
+use std::collections::VecDeque;
+use std::io::Read;
+use std::time::{Duration, Instant};
+
+/// How long transactions are executed between redraws.
+const FRAME: Duration = Duration::from_millis(100);
+const TOP_ACCOUNTS: usize = 10;
+const RECENT_REJECTIONS: usize = 10;
+
+/// What the dashboard shows besides the accounts, which are read from the engine.
+struct Dashboard {
+    source: String,
+    started: Instant,
+    rows: u64,
+    rejected: u64,
+    recent_rejections: VecDeque<String>,
+    done: bool,
+}
+
+impl Dashboard {
+    fn new(source: &str) -> Self {
+        Self {
+            source: source.to_string(),
+            started: Instant::now(),
+            rows: 0,
+            rejected: 0,
+            recent_rejections: VecDeque::with_capacity(RECENT_REJECTIONS),
+            done: false,
+        }
+    }
+
+    fn record(&mut self, transaction: &Transaction, res: &Result<(), Error>) {
+        self.rows += 1;
+        if let Err(err) = res {
+            self.rejected += 1;
+            if self.recent_rejections.len() == RECENT_REJECTIONS {
+                self.recent_rejections.pop_back();
+            }
+            self.recent_rejections.push_front(format!(
+                "tx {} client {} {}: {:?}",
+                transaction.tx(),
+                transaction.client(),
+                transaction.kind(),
+                err
+            ));
+        }
+    }
+}
+
+/// Restores the terminal even if drawing fails halfway.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> std::io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        std::io::stderr().execute(EnterAlternateScreen)?;
+        Ok(TerminalGuard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = std::io::stderr().execute(LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+impl Transakt {
+    /// Like [`Transakt::load_csv_from`], showing the dashboard until the input is processed and the
+    /// user presses `q`. Ctrl-C stops processing with an [`std::io::ErrorKind::Interrupted`] error.
+    pub fn load_csv_with_dashboard<R: Read>(
+        &mut self,
+        source: &str,
+        reader: R,
+    ) -> Result<(), Error> {
+        let span = tracing::warn_span!("file", path = %source);
+        let _enter = span.enter();
+        let res = self.run_dashboard(source, reader);
+        if let Err(err) = &res {
+            self.emit(RiskEvent::BatchRejected {
+                source: source.to_string(),
+                reason: format!("{:?}", err),
+            });
+        }
+        res
+    }
+
+    fn run_dashboard<R: Read>(&mut self, source: &str, reader: R) -> Result<(), Error> {
+        let _guard = TerminalGuard::enter().map_err(Error::Io)?;
+        let mut terminal =
+            Terminal::new(CrosstermBackend::new(std::io::stderr())).map_err(Error::Io)?;
+        let mut dashboard = Dashboard::new(source);
+        let mut transactions = self.csv_rows(reader);
+        while !dashboard.done {
+            let frame_start = Instant::now();
+            while frame_start.elapsed() < FRAME {
+                let (line, raw, (transaction, signature, event_time)) = match transactions.next() {
+                    Some((line, raw, Ok(transaction))) => (line, raw, transaction),
+                    Some((line, raw, Err(err))) => {
+                        self.record_rejected_row(line, raw, &err);
+                        return Err(err);
+                    }
+                    None => {
+                        dashboard.done = true;
+                        break;
+                    }
+                };
+                let res = self.execute_at(transaction, signature.as_deref(), event_time);
+                dashboard.record(&transaction, &res);
+                if let Err(err) = res {
+                    self.rejections.record(line, &err);
+                    self.record_rejected_row(line, raw, &err);
+                    if err.is_fatal() {
+                        return Err(err);
+                    }
+                }
+            }
+            self.draw_dashboard(&mut terminal, &dashboard)?;
+            if poll_key(Duration::ZERO)? == Some(Key::Interrupt) {
+                return Err(interrupted());
+            }
+        }
+        self.flush_journal()?;
+        loop {
+            match poll_key(FRAME)? {
+                Some(Key::Quit) => return Ok(()),
+                Some(Key::Interrupt) => return Err(interrupted()),
+                None => {}
+            }
+        }
+    }
+
+    fn draw_dashboard<B: Backend>(
+        &self,
+        terminal: &mut Terminal<B>,
+        dashboard: &Dashboard,
+    ) -> Result<(), Error> {
+        terminal
+            .draw(|frame| self.render_dashboard(frame, dashboard))
+            .map(|_| ())
+            .map_err(Error::Io)
+    }
+
+    fn render_dashboard(&self, frame: &mut Frame, dashboard: &Dashboard) {
+        let [header, accounts, rejections] = Layout::vertical([
+            Constraint::Length(4),
+            Constraint::Min(TOP_ACCOUNTS as u16 + 3),
+            Constraint::Length(RECENT_REJECTIONS as u16 + 2),
+        ])
+        .areas(frame.area());
+        let [top, locked] =
+            Layout::horizontal([Constraint::Percentage(70), Constraint::Percentage(30)])
+                .areas(accounts);
+
+        let elapsed = dashboard.started.elapsed().as_secs_f64();
+        let throughput = if elapsed > 0.0 {
+            dashboard.rows as f64 / elapsed
+        } else {
+            0.0
+        };
+        let status = if dashboard.done {
+            "done, press q to exit"
+        } else {
+            "processing, Ctrl-C to abort"
+        };
+        let summary = Paragraph::new(vec![
+            format!(
+                "{} rows in {:.1}s, {:.0} rows/s",
+                dashboard.rows, elapsed, throughput
+            )
+            .into(),
+            format!(
+                "{} applied, {} rejected, {} accounts, {}",
+                self.applied,
+                dashboard.rejected,
+                self.accounts.len(),
+                status
+            )
+            .into(),
+        ])
+        .block(Block::bordered().title(format!(" transakt: {} ", dashboard.source)));
+        frame.render_widget(summary, header);
+
+        let mut largest: Vec<&Account> = self.accounts.values().collect();
+        largest.sort_by(|a, b| b.total().cmp(&a.total()).then(a.client().cmp(&b.client())));
+        let rows = largest.iter().take(TOP_ACCOUNTS).map(|account| {
+            Row::new(vec![
+                account.client().to_string(),
+                account.available().to_string(),
+                account.held().to_string(),
+                account
+                    .total()
+                    .map_or("overflow".to_string(), |total| total.to_string()),
+            ])
+        });
+        let table = Table::new(rows, [Constraint::Ratio(1, 4); 4])
+            .header(Row::new(vec!["client", "available", "held", "total"]))
+            .block(Block::bordered().title(" Top accounts "));
+        frame.render_widget(table, top);
+
+        let mut locked_clients: Vec<String> = self
+            .accounts
+            .values()
+            .filter(|account| account.is_locked())
+            .map(|account| account.client())
+            .collect::<std::collections::BTreeSet<_>>()
+            .into_iter()
+            .map(|client| client.to_string())
+            .collect();
+        let title = format!(" Locked accounts ({}) ", locked_clients.len());
+        locked_clients.truncate(locked.height.saturating_sub(2) as usize);
+        frame.render_widget(
+            List::new(locked_clients).block(Block::bordered().title(title)),
+            locked,
+        );
+
+        let recent: Vec<&str> = dashboard
+            .recent_rejections
+            .iter()
+            .map(String::as_str)
+            .collect();
+        frame.render_widget(
+            List::new(recent).block(Block::bordered().title(" Recent rejections ")),
+            rejections,
+        );
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Key {
+    Quit,
+    Interrupt,
+}
+
+fn poll_key(timeout: Duration) -> Result<Option<Key>, Error> {
+    if !event::poll(timeout).map_err(Error::Io)? {
+        return Ok(None);
+    }
+    let key = match event::read().map_err(Error::Io)? {
+        Event::Key(key) if key.kind == KeyEventKind::Press => key,
+        _ => return Ok(None),
+    };
+    Ok(match key.code {
+        // Raw mode swallows the signal, so Ctrl-C arrives as a key press
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Some(Key::Interrupt),
+        KeyCode::Char('q') | KeyCode::Esc => Some(Key::Quit),
+        _ => None,
+    })
+}
+
+fn interrupted() -> Error {
+    Error::Io(std::io::Error::new(
+        std::io::ErrorKind::Interrupted,
+        "stopped from the dashboard",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Dashboard;
+    use crate::transaction::{self, Transaction};
+    use crate::Transakt;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    #[test]
+    fn dashboard_render() {
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,2.0\n\
+                     deposit,2,2,5.0\n\
+                     withdrawal,1,3,9.0\n\
+                     dispute,2,2,\n\
+                     chargeback,2,2,\n";
+        let mut transakt = Transakt::default();
+        let mut dashboard = Dashboard::new("input.csv");
+        for transaction in transaction::read_csv(input.as_bytes()) {
+            let transaction: Transaction = transaction.unwrap();
+            let res = transakt.execute_transaction(transaction);
+            dashboard.record(&transaction, &res);
+        }
+        dashboard.done = true;
+
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        transakt.draw_dashboard(&mut terminal, &dashboard).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains("5 rows"));
+        assert!(screen.contains("4 applied, 1 rejected, 2 accounts, done"));
+        assert!(screen.contains("Locked accounts (1)"));
+        assert!(screen.contains("tx 3 client 1 withdrawal: InsufficientFunds"));
+    }
+}
@@ -0,0 +1,364 @@
+//! Checks an input file for problems without executing any of it.
+
+use crate::transaction::{
+    ClientId, CsvOptions, Transaction, TransactionId, TransactionRow, TransactionType,
+};
+use crate::Error;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fmt::{Display, Formatter};
+use std::io::Read;
+
+const COLUMNS: [&str; 4] = ["type", "client", "tx", "amount"];
+/// Columns rows may have: the receiver of transfers, the transaction a reversal undoes, and see
+/// [`crate::signature`] and [`crate::bitemporal`].
+const OPTIONAL_COLUMNS: [&str; 4] = ["to", "ref_tx", "signature", "timestamp"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    pub line: u64,
+    pub message: String,
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ValidationReport {
+    pub rows: u64,
+    pub problems: Vec<Problem>,
+}
+
+impl ValidationReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    fn problem(&mut self, line: u64, message: String) {
+        self.problems.push(Problem { line, message });
+    }
+}
+
+/// Lints a CSV input: headers, field types, amount formats and references between transactions.
+/// Only I/O failures are returned as errors, everything else ends up in the report.
+pub fn validate_csv<R: Read>(reader: R) -> Result<ValidationReport, Error> {
+    validate_csv_with(reader, &CsvOptions::default())
+}
+
+/// Like [`validate_csv`], for CSV input written as `options` say. Headers are checked once
+/// mapped to the fields they hold. Options that can't be used are an error.
+pub fn validate_csv_with<R: Read>(
+    reader: R,
+    options: &CsvOptions,
+) -> Result<ValidationReport, Error> {
+    let mut report = ValidationReport::default();
+    let mut csv = options.reader_builder()?.from_reader(reader);
+    let headers = match csv.headers() {
+        Ok(headers) => options.map_headers(headers),
+        Err(err) => {
+            report.problem(1, describe(&err));
+            return Ok(report);
+        }
+    };
+    for column in COLUMNS.iter() {
+        if !headers.iter().any(|header| header == *column) {
+            report.problem(1, format!("missing column `{}`", column));
+        }
+    }
+    for header in headers.iter() {
+        if !COLUMNS.contains(&header) && !OPTIONAL_COLUMNS.contains(&header) {
+            report.problem(1, format!("unexpected column `{}`", header));
+        }
+    }
+    if !report.is_ok() {
+        return Ok(report);
+    }
+    let amount = headers.iter().position(|header| header == "amount");
+
+    // Line of every row that brings a new tx id
+    let mut ids: HashMap<TransactionId, u64> = HashMap::new();
+    // Line and client of every deposit, withdrawal and transfer seen so far, and whether it can
+    // be reversed
+    let mut seen: HashMap<TransactionId, (u64, ClientId, bool)> = HashMap::new();
+    for record in csv.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(err) => match err.kind() {
+                csv::ErrorKind::Io(_) => return Err(Error::Io(err.into())),
+                _ => {
+                    let line = err.position().map_or(0, |p| p.line());
+                    report.problem(line, describe(&err));
+                    continue;
+                }
+            },
+        };
+        report.rows += 1;
+        let line = record.position().map_or(0, |p| p.line());
+        let record = options.decimal_point(record, amount);
+        let row: TransactionRow = match record.deserialize(Some(&headers)) {
+            Ok(row) => row,
+            Err(err) => {
+                report.problem(line, describe(&err));
+                continue;
+            }
+        };
+        if row.tx_type() == TransactionType::Transfer && row.to().is_none() {
+            report.problem(line, "transfer without `to`".to_string());
+            continue;
+        }
+        let transaction: Transaction = match row.try_into() {
+            Ok(transaction) => transaction,
+            Err(_) => {
+                report.problem(
+                    line,
+                    "amount missing, or given where not allowed".to_string(),
+                );
+                continue;
+            }
+        };
+        let (client, tx) = (transaction.client(), transaction.tx());
+        match transaction {
+            Transaction::Deposit { amount, .. }
+            | Transaction::Withdrawal { amount, .. }
+            | Transaction::Transfer { amount, .. } => {
+                if amount.is_negative() {
+                    report.problem(line, format!("negative amount {}", amount));
+                }
+                if new_id(&mut report, &mut ids, tx, line) {
+                    let reversible = !matches!(transaction, Transaction::Transfer { .. });
+                    seen.insert(tx, (line, client, reversible));
+                }
+            }
+            Transaction::Reversal { ref_tx, .. } => {
+                new_id(&mut report, &mut ids, tx, line);
+                match seen.get(&ref_tx) {
+                    Some((first, _, false)) => report.problem(
+                        line,
+                        format!(
+                            "reversal of tx {}, a transfer (line {}), only deposits and \
+                             withdrawals can be reversed",
+                            ref_tx, first
+                        ),
+                    ),
+                    _ => reference(&mut report, &seen, line, &transaction, ref_tx),
+                }
+            }
+            // Admin actions, `tx` only identifies the row
+            Transaction::Freeze { .. }
+            | Transaction::Unfreeze { .. }
+            | Transaction::Reactivate { .. } => {
+                new_id(&mut report, &mut ids, tx, line);
+            }
+            Transaction::Dispute { .. }
+            | Transaction::Resolve { .. }
+            | Transaction::Chargeback { .. }
+            | Transaction::ChargebackReversal { .. } => {
+                reference(&mut report, &seen, line, &transaction, tx)
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Records the tx id of a row that brings a new one, reporting it if it was seen already.
+fn new_id(
+    report: &mut ValidationReport,
+    ids: &mut HashMap<TransactionId, u64>,
+    tx: TransactionId,
+    line: u64,
+) -> bool {
+    match ids.get(&tx) {
+        Some(first) => {
+            report.problem(
+                line,
+                format!("duplicate tx {}, first seen on line {}", tx, first),
+            );
+            false
+        }
+        None => {
+            ids.insert(tx, line);
+            true
+        }
+    }
+}
+
+/// Checks that `tx`, which the transaction refers to, was seen and is of the same client.
+fn reference(
+    report: &mut ValidationReport,
+    seen: &HashMap<TransactionId, (u64, ClientId, bool)>,
+    line: u64,
+    transaction: &Transaction,
+    tx: TransactionId,
+) {
+    let client = transaction.client();
+    match seen.get(&tx) {
+        None => report.problem(
+            line,
+            format!("{} references unknown tx {}", transaction.kind(), tx),
+        ),
+        Some((first, owner, _)) if *owner != client => report.problem(
+            line,
+            format!(
+                "{} by client {} references tx {} of client {} (line {})",
+                transaction.kind(),
+                client,
+                tx,
+                owner,
+                first
+            ),
+        ),
+        Some(_) => {}
+    }
+}
+
+fn describe(err: &csv::Error) -> String {
+    match err.kind() {
+        csv::ErrorKind::Deserialize { err, .. } => err.to_string(),
+        csv::ErrorKind::UnequalLengths {
+            expected_len, len, ..
+        } => format!("expected {} fields, found {}", expected_len, len),
+        _ => err.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate_csv, validate_csv_with};
+    use crate::transaction::CsvOptions;
+
+    #[test]
+    fn validate_ok() {
+        let input = "type, client, tx, amount\n\
+                     deposit, 1, 1, 1.0\n\
+                     dispute, 1, 1,\n";
+        let report = validate_csv(input.as_bytes()).unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.rows, 2);
+    }
+
+    #[test]
+    fn validate_problems() {
+        let input = "type, client, tx, amount\n\
+                     deposit, 1, 1, 1.0\n\
+                     deposit, 1, 1, 2.0\n\
+                     withdrawal, 1, 2, 1.0a\n\
+                     transfer, 1, 3, 1.0\n\
+                     dispute, 1, 9,\n\
+                     resolve, 2, 1,\n\
+                     deposit, 1, 4,\n";
+        let report = validate_csv(input.as_bytes()).unwrap();
+        let lines: Vec<u64> = report.problems.iter().map(|p| p.line).collect();
+        assert_eq!(lines, vec![3, 4, 5, 6, 7, 8]);
+        assert_eq!(
+            report.problems[0].message,
+            "duplicate tx 1, first seen on line 2"
+        );
+    }
+
+    #[test]
+    fn validate_transfers() {
+        let input = "type, client, tx, amount, to\n\
+                     deposit, 1, 1, 5.0,\n\
+                     transfer, 1, 2, 2.0, 2\n\
+                     dispute, 1, 2,,\n\
+                     transfer, 1, 3, 1.0,\n\
+                     transfer, 1, 2, 1.0, 3\n";
+        let report = validate_csv(input.as_bytes()).unwrap();
+        assert_eq!(report.rows, 5);
+        let problems: Vec<String> = report.problems.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            problems,
+            [
+                "line 5: transfer without `to`",
+                "line 6: duplicate tx 2, first seen on line 3"
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_reversals() {
+        let input = "type, client, tx, amount, to, ref_tx\n\
+                     deposit, 1, 1, 5.0,,\n\
+                     withdrawal, 1, 2, 1.0,,\n\
+                     transfer, 1, 3, 1.0, 2,\n\
+                     reversal, 1, 4,,, 1\n\
+                     reversal, 1, 5,,, 2\n\
+                     reversal, 1, 4,,, 2\n\
+                     reversal, 1, 6,,, 9\n\
+                     reversal, 2, 7,,, 1\n\
+                     reversal, 1, 8,,, 3\n\
+                     dispute, 1, 4,,,\n";
+        let report = validate_csv(input.as_bytes()).unwrap();
+        assert_eq!(report.rows, 10);
+        let problems: Vec<String> = report.problems.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            problems,
+            [
+                "line 7: duplicate tx 4, first seen on line 5",
+                "line 8: reversal references unknown tx 9",
+                "line 9: reversal by client 2 references tx 1 of client 1 (line 2)",
+                "line 10: reversal of tx 3, a transfer (line 4), only deposits and withdrawals \
+                 can be reversed",
+                "line 11: dispute references unknown tx 4",
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_admin_actions() {
+        let input = "type, client, tx, amount\n\
+                     deposit, 1, 1, 5.0\n\
+                     freeze, 1, 100,\n\
+                     unfreeze, 1, 101,\n\
+                     unlock, 2, 102,\n\
+                     reactivate, 1, 103,\n\
+                     freeze, 1, 1,\n\
+                     dispute, 1, 100,\n\
+                     reactivate, 1, 103,\n";
+        let report = validate_csv(input.as_bytes()).unwrap();
+        let problems: Vec<String> = report.problems.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            problems,
+            [
+                "line 7: duplicate tx 1, first seen on line 2",
+                "line 8: dispute references unknown tx 100",
+                "line 9: duplicate tx 103, first seen on line 6",
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_partner_format() {
+        let mut options = CsvOptions {
+            delimiter: ';',
+            decimal_separator: ',',
+            ..CsvOptions::default()
+        };
+        for (header, field) in [("kind", "type"), ("customer", "client"), ("id", "tx")] {
+            options
+                .columns
+                .insert(header.to_string(), field.to_string());
+        }
+        let input = "kind;customer;id;amount\n\
+                     deposit;1;1;1,5\n\
+                     dispute;1;1;\n\
+                     dispute;1;2;\n";
+        let report = validate_csv_with(input.as_bytes(), &options).unwrap();
+        assert_eq!(report.rows, 3);
+        let problems: Vec<String> = report.problems.iter().map(ToString::to_string).collect();
+        assert_eq!(problems, ["line 4: dispute references unknown tx 2"]);
+    }
+
+    #[test]
+    fn validate_headers() {
+        let report = validate_csv("type,client,tx\n".as_bytes()).unwrap();
+        assert_eq!(report.problems.len(), 1);
+        assert_eq!(report.problems[0].message, "missing column `amount`");
+        let report = validate_csv("type,client,tx,amount,to,timestamp,note\n".as_bytes()).unwrap();
+        assert_eq!(report.problems.len(), 1);
+        assert_eq!(report.problems[0].message, "unexpected column `note`");
+    }
+}
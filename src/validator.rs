@@ -0,0 +1,123 @@
+//! Custom checks run on every transaction before the engine applies it, in the order they were
+//! registered with [`TransaktBuilder::validator`](crate::TransaktBuilder::validator).
+
+use crate::account::Account;
+use crate::transaction::Transaction;
+use crate::{Error, Transakt};
+
+/// What a [`TransactionValidator`] decided about a transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    /// Pass the transaction on unchanged.
+    Accept,
+    /// Reject the transaction with [`Error::Rejected`]. Later validators don't see it.
+    Reject(String),
+    /// Pass this transaction on instead. It is what gets applied and journaled.
+    Replace(Transaction),
+}
+
+/// A fraud or business check. Implement this to veto or rewrite transactions at the point where
+/// they enter the engine.
+pub trait TransactionValidator: Send {
+    /// `account` is the current state of the transaction's client, if it has an account.
+    fn validate(&mut self, transaction: &Transaction, account: Option<&Account>) -> Verdict;
+}
+
+impl Transakt {
+    /// Runs the transaction through the validator chain, returning the one to apply.
+    pub(crate) fn run_validators(
+        &mut self,
+        transaction: Transaction,
+    ) -> Result<Transaction, Error> {
+        let mut transaction = transaction;
+        for validator in self.validators.iter_mut() {
+            let account = self.accounts.get(&transaction.client());
+            match validator.validate(&transaction, account) {
+                Verdict::Accept => {}
+                Verdict::Reject(reason) => {
+                    let err = Error::Rejected(reason);
+                    tracing::debug!(code = err.code(), error = ?err, "rejected by validator");
+                    return Err(err);
+                }
+                Verdict::Replace(replacement) => {
+                    tracing::debug!(replacement = ?replacement, "replaced by validator");
+                    transaction = replacement;
+                }
+            }
+        }
+        Ok(transaction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TransactionValidator, Verdict};
+    use crate::account::Account;
+    use crate::currency::Currency;
+    use crate::transaction::{ClientId, Transaction};
+    use crate::Transakt;
+
+    /// Caps withdrawals at the amount available, and rejects them on empty accounts.
+    struct CapWithdrawals;
+
+    impl TransactionValidator for CapWithdrawals {
+        fn validate(&mut self, transaction: &Transaction, account: Option<&Account>) -> Verdict {
+            let (client, tx, amount) = match *transaction {
+                Transaction::Withdrawal { client, tx, amount } => (client, tx, amount),
+                _ => return Verdict::Accept,
+            };
+            let available = account.map_or(Currency::default(), |account| *account.available());
+            if available == Currency::default() {
+                Verdict::Reject("nothing to withdraw".to_string())
+            } else if amount > available {
+                Verdict::Replace(Transaction::Withdrawal {
+                    client,
+                    tx,
+                    amount: available,
+                })
+            } else {
+                Verdict::Accept
+            }
+        }
+    }
+
+    /// Sees the withdrawals after `CapWithdrawals` changed them.
+    struct NoWithdrawalsOver(Currency);
+
+    impl TransactionValidator for NoWithdrawalsOver {
+        fn validate(&mut self, transaction: &Transaction, _account: Option<&Account>) -> Verdict {
+            match transaction {
+                Transaction::Withdrawal { amount, .. } if *amount > self.0 => {
+                    Verdict::Reject("too large".to_string())
+                }
+                _ => Verdict::Accept,
+            }
+        }
+    }
+
+    #[test]
+    fn validator_chain() {
+        let mut transakt = Transakt::builder()
+            .validator(CapWithdrawals)
+            .validator(NoWithdrawalsOver(Currency::new(3, 0).unwrap()))
+            .build();
+        let input = "type,client,tx,amount\n\
+                     withdrawal,1,1,1.0\n\
+                     deposit,1,2,2.0\n\
+                     withdrawal,1,3,5.0\n\
+                     deposit,2,4,10.0\n\
+                     withdrawal,2,5,20.0\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+        let accounts = transakt.get_accounts_map();
+        let first = accounts.get(&ClientId::new(1)).unwrap();
+        assert_eq!(first.available(), &Currency::default());
+        let second = accounts.get(&ClientId::new(2)).unwrap();
+        assert_eq!(second.available(), &Currency::new(10, 0).unwrap());
+        let rejected: Vec<(&str, u64)> = transakt
+            .rejections()
+            .groups()
+            .map(|(code, group)| (code, group.count))
+            .collect();
+        assert_eq!(rejected, vec![("E_REJECTED", 2)]);
+    }
+}
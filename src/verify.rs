@@ -0,0 +1,150 @@
+//! Checks of ledger-wide invariants after every transaction, to catch logic bugs at the row that
+//! causes them rather than in the final report. Enabled with
+//! [`TransaktBuilder::verify`](crate::TransaktBuilder::verify).
+//!
+//! Every check walks all the accounts, so this is meant for debugging, not for production runs.
+
+use crate::currency::Currency;
+use crate::redact;
+use crate::transaction::Transaction;
+use crate::{Error, Transakt};
+
+impl Transakt {
+    /// Sum of the totals of all accounts, `None` if it overflows.
+    pub(crate) fn ledger_total(&self) -> Option<Currency> {
+        self.accounts
+            .values()
+            .try_fold(Currency::default(), |sum, account| {
+                sum.checked_add(account.total()?)
+            })
+    }
+
+    /// [`Transakt::ledger_total`] before `transaction`, with what it credits back if it is a
+    /// chargeback reversal, as the chargeback is forgotten once reversed.
+    pub(crate) fn ledger_before(
+        &self,
+        transaction: &Transaction,
+    ) -> (Option<Currency>, Option<Currency>) {
+        let credited = match transaction {
+            Transaction::ChargebackReversal { tx, .. } => {
+                self.charged_back.get(tx).map(|(held, _)| *held)
+            }
+            _ => None,
+        };
+        (self.ledger_total(), credited)
+    }
+
+    /// Checks the invariants after `applied` was applied, or after a rejection if it is `None`.
+    /// `before` is [`Transakt::ledger_before`] the transaction.
+    pub(crate) fn check_invariants(
+        &self,
+        (before, credited): (Option<Currency>, Option<Currency>),
+        applied: Option<&Transaction>,
+    ) -> Result<(), Error> {
+        for account in self.accounts.values() {
+            let client = account.client();
+            if account.held().is_negative() {
+                return Err(violated(format!(
+                    "client {} holds a negative amount, {}",
+                    redact::client(client),
+                    redact::amount(*account.held())
+                )));
+            }
+            match account.total() {
+                Some(total) if account.held() > &total => {
+                    return Err(violated(format!(
+                        "client {} holds {}, more than its total of {}",
+                        redact::client(client),
+                        redact::amount(*account.held()),
+                        redact::amount(total)
+                    )))
+                }
+                Some(_) => {}
+                None => {
+                    return Err(violated(format!(
+                        "total of client {} overflows",
+                        redact::client(client)
+                    )))
+                }
+            }
+        }
+        if let Some(tx) = self
+            .reversed
+            .iter()
+            .find(|tx| self.charged_back.contains_key(tx))
+        {
+            return Err(violated(format!(
+                "deposit {} both charged back and reversed",
+                tx
+            )));
+        }
+        // Only deposits, withdrawals, their reversals, chargebacks and theirs move funds in or out
+        // of the ledger
+        let delta = match applied {
+            Some(Transaction::Deposit { amount, .. }) => Some(*amount),
+            Some(Transaction::Withdrawal { amount, .. }) => {
+                Currency::default().checked_sub(*amount)
+            }
+            Some(Transaction::Chargeback { tx, .. }) => match self.charged_back.get(tx) {
+                Some((held, _)) => Currency::default().checked_sub(*held),
+                None => Some(Currency::default()),
+            },
+            Some(Transaction::ChargebackReversal { .. }) => Some(credited.unwrap_or_default()),
+            Some(Transaction::Reversal { ref_tx, .. }) => match self.transactions.get(ref_tx) {
+                Some(Transaction::Deposit { amount, .. }) => {
+                    Currency::default().checked_sub(*amount)
+                }
+                Some(Transaction::Withdrawal { amount, .. }) => Some(*amount),
+                _ => Some(Currency::default()),
+            },
+            _ => Some(Currency::default()),
+        };
+        let expected = before
+            .zip(delta)
+            .and_then(|(before, delta)| before.checked_add(delta));
+        if let (Some(expected), Some(after)) = (expected, self.ledger_total()) {
+            if expected != after {
+                return Err(violated(format!(
+                    "funds in the ledger went to {}, expected {}",
+                    redact::amount(after),
+                    redact::amount(expected)
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn violated(reason: String) -> Error {
+    let err = Error::InvariantViolation(reason);
+    tracing::error!(code = err.code(), error = ?err, "invariant violated");
+    err
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Transakt;
+
+    #[test]
+    fn verify_reports_row() {
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,2.0\n\
+                     withdrawal,1,2,1.0\n\
+                     dispute,1,1,\n\
+                     chargeback,1,1,\n";
+        let mut transakt = Transakt::builder().verify().build();
+        let err = transakt.execute_csv(input.as_bytes()).unwrap_err();
+        assert_eq!(err.code(), "E_INVARIANT");
+        let message = format!("{:?}", err);
+        assert!(message.contains("line 4: client 1 holds 2.0000, more than its total of 1.0000"));
+
+        let mut transakt = Transakt::builder().verify().build();
+        let input = "type,client,tx,amount\n\
+                     deposit,1,1,2.0\n\
+                     deposit,2,2,1.0\n\
+                     withdrawal,1,3,5.0\n\
+                     dispute,2,2,\n\
+                     chargeback,2,2,\n";
+        transakt.execute_csv(input.as_bytes()).unwrap();
+    }
+}
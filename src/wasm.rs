@@ -0,0 +1,64 @@
+//! JavaScript bindings, so the engine can check files client-side in a browser.
+
+use crate::transaction::ClientId;
+use crate::Transakt;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct Engine {
+    inner: Transakt,
+}
+
+/// Balances of one account. Amounts are decimal strings, to avoid losing precision in JS numbers.
+#[wasm_bindgen(getter_with_clone)]
+pub struct AccountView {
+    pub client: u16,
+    pub available: String,
+    pub held: String,
+    pub total: String,
+    pub locked: bool,
+}
+
+#[wasm_bindgen]
+impl Engine {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Engine {
+        Engine::default()
+    }
+
+    /// Executes all the transactions in CSV text. Fails only on errors that would stop a batch.
+    #[wasm_bindgen(js_name = executeCsv)]
+    pub fn execute_csv(&mut self, csv: &str) -> Result<(), JsError> {
+        self.inner
+            .execute_csv(csv.as_bytes())
+            .map_err(|err| JsError::new(&format!("{:?}", err)))
+    }
+
+    pub fn account(&self, client: u16) -> Option<AccountView> {
+        let account = self.inner.get_accounts_map().get(&ClientId::new(client))?;
+        Some(AccountView {
+            client,
+            available: account.available().to_string(),
+            held: account.held().to_string(),
+            total: account
+                .total()
+                .map(|total| total.to_string())
+                .unwrap_or_default(),
+            locked: account.is_locked(),
+        })
+    }
+
+    /// The account report, in the same CSV format the command line tool prints.
+    #[wasm_bindgen(js_name = accountsCsv)]
+    pub fn accounts_csv(&self) -> Result<String, JsError> {
+        let mut out = csv::Writer::from_writer(vec![]);
+        for account in self.inner.get_accounts() {
+            out.serialize(&account)?;
+        }
+        let bytes = out
+            .into_inner()
+            .map_err(|err| JsError::new(&err.to_string()))?;
+        Ok(String::from_utf8(bytes)?)
+    }
+}
@@ -0,0 +1,182 @@
+use crate::config::WebhookSettings;
+use crate::event::RiskEvent;
+use crate::notification::NotificationSink;
+use crate::redact;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// Header carrying the HMAC-SHA256 signature of the request body, when a secret is configured.
+pub const SIGNATURE_HEADER: &str = "X-Transakt-Signature";
+
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Shared secret used to sign payloads, so the receiver can check they came from us.
+    pub secret: Option<String>,
+    /// How many times a failed delivery is retried before the event is dropped.
+    pub max_retries: u32,
+    /// Delay before the first retry; doubled on every subsequent attempt.
+    pub retry_backoff: Duration,
+    pub timeout: Duration,
+}
+
+impl WebhookConfig {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            secret: None,
+            max_retries: 3,
+            retry_backoff: Duration::from_millis(500),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl From<&WebhookSettings> for WebhookConfig {
+    fn from(settings: &WebhookSettings) -> Self {
+        let mut config = WebhookConfig::new(&settings.url);
+        config.secret = settings.secret.clone();
+        if let Some(max_retries) = settings.max_retries {
+            config.max_retries = max_retries;
+        }
+        if let Some(backoff) = settings.retry_backoff_ms {
+            config.retry_backoff = Duration::from_millis(backoff);
+        }
+        if let Some(timeout) = settings.timeout_secs {
+            config.timeout = Duration::from_secs(timeout);
+        }
+        config
+    }
+}
+
+/// POSTs risk events as JSON to a configured endpoint.
+/// Deliveries happen on a background thread, so a slow or unreachable endpoint doesn't stall
+/// transaction processing. Dropping the dispatcher waits for queued events to be delivered.
+pub struct WebhookDispatcher {
+    sender: Option<mpsc::Sender<RiskEvent>>,
+    worker: Option<thread::JoinHandle<()>>,
+}
+
+impl WebhookDispatcher {
+    pub fn new(config: WebhookConfig) -> Self {
+        let (sender, receiver) = mpsc::channel::<RiskEvent>();
+        let worker = thread::spawn(move || {
+            let agent = ureq::AgentBuilder::new().timeout(config.timeout).build();
+            for event in receiver {
+                deliver(&agent, &config, &event);
+            }
+        });
+        Self {
+            sender: Some(sender),
+            worker: Some(worker),
+        }
+    }
+}
+
+impl NotificationSink for WebhookDispatcher {
+    fn notify(&mut self, event: &RiskEvent) {
+        if let Some(sender) = &self.sender {
+            if sender.send(event.clone()).is_err() {
+                tracing::error!("webhook worker has stopped, dropping event");
+            }
+        }
+    }
+}
+
+impl Drop for WebhookDispatcher {
+    fn drop(&mut self) {
+        // Closing the channel lets the worker drain the queue and exit.
+        self.sender.take();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn deliver(agent: &ureq::Agent, config: &WebhookConfig, event: &RiskEvent) {
+    let body = match serde_json::to_string(event) {
+        Ok(body) => body,
+        Err(err) => {
+            tracing::error!(error = %err, "cannot serialize webhook payload");
+            return;
+        }
+    };
+    let signature = config
+        .secret
+        .as_ref()
+        .map(|secret| sign(secret.as_bytes(), body.as_bytes()));
+    for attempt in 0..=config.max_retries {
+        if attempt > 0 {
+            thread::sleep(config.retry_backoff * 2u32.pow(attempt - 1));
+        }
+        let mut request = agent
+            .post(&config.url)
+            .set("Content-Type", "application/json");
+        if let Some(signature) = &signature {
+            request = request.set(SIGNATURE_HEADER, signature);
+        }
+        match request.send_string(&body) {
+            Ok(_) => return,
+            // The endpoint understood and refused the request, trying again won't help
+            Err(ureq::Error::Status(status, _)) if status < 500 && status != 429 => {
+                tracing::error!(status, url = %config.url, "webhook rejected");
+                return;
+            }
+            Err(err) => {
+                tracing::warn!(attempt, error = %err, url = %config.url, "webhook delivery failed")
+            }
+        }
+    }
+    let mut dropped = serde_json::to_value(event).unwrap_or_default();
+    redact::current().json(&mut dropped);
+    tracing::error!(url = %config.url, event = %dropped, "webhook retries exhausted, dropping event");
+}
+
+/// Computes the value of the signature header for a payload.
+pub fn sign(secret: &[u8], body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    let digest = mac.finalize().into_bytes();
+    let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("sha256={}", hex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sign;
+    use crate::currency::Currency;
+    use crate::event::RiskEvent;
+    use crate::transaction::{ClientId, TransactionId};
+
+    #[test]
+    fn test_sign() {
+        // RFC 4231, test case 2
+        assert_eq!(
+            sign(b"Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_payload() {
+        let event = RiskEvent::Chargeback {
+            client: ClientId::new(1),
+            tx: TransactionId::new(2),
+            amount: Currency::new(1, 5000).unwrap(),
+        };
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"event":"chargeback","client":1,"tx":2,"amount":"1.5000"}"#
+        );
+        let event = RiskEvent::AccountLocked {
+            client: ClientId::new(1),
+        };
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"event":"account_locked","client":1}"#
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! Account balances as an XLSX workbook, for finance to open in a spreadsheet: an `Accounts`
+//! sheet with the `client,available,held,total,locked` columns of the account report, ordered by
+//! client, and a `Rejections` sheet with the rows rejected so far by error code.
+//!
+//! Amounts are numbers shown with four decimals, so they add up in the spreadsheet.
+
+use crate::currency::Currency;
+use crate::explain::explain;
+use crate::{Error, Transakt};
+use rust_xlsxwriter::{Format, Workbook, Worksheet, XlsxError};
+use std::io::Write;
+
+impl Transakt {
+    /// Writes the account balances and the rejected rows as an XLSX workbook, see the
+    /// [module docs](self). Fails if the total of an account overflows.
+    pub fn write_accounts_xlsx<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        let mut workbook = Workbook::new();
+        let amount = Format::new().set_num_format("0.0000");
+
+        let balances = workbook
+            .add_worksheet()
+            .set_name("Accounts")
+            .map_err(xlsx)?;
+        header(
+            balances,
+            &["client", "available", "held", "total", "locked"],
+        )?;
+        for (row, account) in (1..).zip(self.get_accounts().iter()) {
+            let total = account.total().ok_or_else(|| {
+                Error::Io(std::io::Error::other(format!(
+                    "total of client {} overflows",
+                    account.client()
+                )))
+            })?;
+            let client = f64::from(account.client().id());
+            balances.write_number(row, 0, client).map_err(xlsx)?;
+            for (column, value) in (1..).zip([*account.available(), *account.held(), total]) {
+                balances
+                    .write_number_with_format(row, column, number(value), &amount)
+                    .map_err(xlsx)?;
+            }
+            balances
+                .write_boolean(row, 4, account.is_locked())
+                .map_err(xlsx)?;
+        }
+
+        let rejections = workbook
+            .add_worksheet()
+            .set_name("Rejections")
+            .map_err(xlsx)?;
+        header(rejections, &["code", "count", "first lines", "summary"])?;
+        for (row, (code, group)) in (1..).zip(self.rejections().groups()) {
+            let lines: Vec<String> = group.lines.iter().map(u64::to_string).collect();
+            rejections.write_string(row, 0, code).map_err(xlsx)?;
+            rejections
+                .write_number(row, 1, group.count as f64)
+                .map_err(xlsx)?;
+            rejections
+                .write_string(row, 2, lines.join(", "))
+                .map_err(xlsx)?;
+            rejections
+                .write_string(row, 3, explain(code).map_or("", |e| e.summary))
+                .map_err(xlsx)?;
+        }
+
+        let buffer = workbook.save_to_buffer().map_err(xlsx)?;
+        writer.write_all(&buffer).map_err(Error::Io)?;
+        writer.flush().map_err(Error::Io)
+    }
+}
+
+fn header(sheet: &mut Worksheet, names: &[&str]) -> Result<(), Error> {
+    for (column, name) in (0..).zip(names) {
+        sheet.write_string(0, column, *name).map_err(xlsx)?;
+    }
+    Ok(())
+}
+
+/// The amount as a spreadsheet number, through its decimal representation so it is the closest
+/// one.
+fn number(amount: Currency) -> f64 {
+    amount.to_string().parse().unwrap_or_default()
+}
+
+fn xlsx(err: XlsxError) -> Error {
+    Error::Io(std::io::Error::other(err))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Transakt;
+    use std::io::{Cursor, Read};
+
+    /// The XML of a part of the workbook.
+    fn part(workbook: &[u8], name: &str) -> String {
+        let mut archive = zip::ZipArchive::new(Cursor::new(workbook)).unwrap();
+        let mut xml = String::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut xml)
+            .unwrap();
+        xml
+    }
+
+    #[test]
+    fn xlsx_workbook() {
+        let input = "type,client,tx,amount\n\
+                     deposit,2,1,2.0\n\
+                     deposit,1,2,1.0\n\
+                     withdrawal,1,3,1.5\n\
+                     withdrawal,2,4,1.5\n\
+                     dispute,2,1,\n\
+                     withdrawal,1,5,2.0\n";
+        let transakt = Transakt::read_from_reader(input.as_bytes()).unwrap();
+        let mut out = vec![];
+        transakt.write_accounts_xlsx(&mut out).unwrap();
+
+        let workbook = part(&out, "xl/workbook.xml");
+        assert!(workbook.contains(r#"<sheet name="Accounts""#));
+        assert!(workbook.contains(r#"<sheet name="Rejections""#));
+        let accounts = part(&out, "xl/worksheets/sheet1.xml");
+        assert!(accounts.contains(r#"<c r="A2"><v>1</v></c>"#));
+        // Negative with decimals, as the dispute held more than was left
+        assert!(accounts.contains(r#"<c r="B3" s="1"><v>-1.5</v></c>"#));
+        assert!(accounts.contains(r#"<c r="C3" s="1"><v>2</v></c>"#));
+        assert!(accounts.contains(r#"<c r="E3" t="b"><v>0</v></c>"#));
+        let rejections = part(&out, "xl/worksheets/sheet2.xml");
+        assert!(rejections.contains(r#"<c r="B2"><v>2</v></c>"#));
+        let strings = part(&out, "xl/sharedStrings.xml");
+        assert!(strings.contains("<t>E_FUNDS</t>"));
+        assert!(strings.contains("<t>4, 7</t>"));
+    }
+}
@@ -0,0 +1,19 @@
+use std::path::PathBuf;
+use transakt::golden::check_golden;
+use transakt::Transakt;
+
+/// Compares the outputs of the inputs in `tests/golden` with their golden files. After an
+/// intended change of behavior, rewrite them with `cargo run -- golden tests/golden --update`.
+#[test]
+pub fn golden() {
+    let mut dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    dir.push("tests/golden");
+    let results = check_golden(&dir, || Transakt::builder().verify(), false).unwrap();
+    assert!(!results.is_empty());
+    let failures: Vec<String> = results
+        .iter()
+        .filter(|result| !result.is_ok())
+        .map(ToString::to_string)
+        .collect();
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
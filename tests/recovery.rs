@@ -0,0 +1,112 @@
+//! Recovery from storage failures, injected with `transakt::fault`. Run with
+//! `cargo test --features fault-injection`.
+
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use transakt::fault::{Faults, FaultyWriter};
+use transakt::journal::{JournalWriter, ReplayLimit};
+use transakt::snapshot::Snapshot;
+use transakt::{Error, Transakt};
+
+const INPUT: &str = "type,client,tx,amount\n\
+                     deposit,1,1,10.0\n\
+                     deposit,2,2,5.0\n\
+                     withdrawal,1,3,4.0\n\
+                     dispute,2,2,\n\
+                     deposit,3,4,1.25\n";
+
+/// Keeps what the journal wrote readable by the test.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn contents(&self) -> Vec<u8> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs the input with a journal behind a faulty writer, returning the engine, the result of the
+/// run, which ends with flushing the journal, and the buffer behind the writer.
+fn run_journaled(faults: Faults) -> (Transakt, Result<(), Error>, SharedBuffer) {
+    let buffer = SharedBuffer::default();
+    let writer = FaultyWriter::new(buffer.clone(), faults);
+    let mut transakt = Transakt::builder()
+        .journal(JournalWriter::new(writer))
+        .build();
+    let res = transakt.load_csv_from("input", INPUT.as_bytes());
+    (transakt, res, buffer)
+}
+
+fn replay(journal: &[u8]) -> Transakt {
+    let mut transakt = Transakt::default();
+    transakt.replay(journal, &ReplayLimit::default()).unwrap();
+    transakt
+}
+
+#[test]
+fn interrupted_writes_are_retried() {
+    let (transakt, res, buffer) = run_journaled(Faults::new().interrupt_every(2));
+    res.unwrap();
+    assert_eq!(replay(&buffer.contents()).snapshot(), transakt.snapshot());
+}
+
+#[test]
+fn failed_flush_can_be_retried() {
+    let (mut transakt, res, buffer) = run_journaled(Faults::new().fail_write(1));
+    assert!(matches!(res, Err(Error::Io(_))));
+    assert!(buffer.contents().is_empty());
+    // Nothing is lost, the entries wait in the buffer for the next flush
+    transakt.flush_journal().unwrap();
+    assert_eq!(replay(&buffer.contents()).snapshot(), transakt.snapshot());
+}
+
+#[test]
+fn torn_journal_replays_up_to_the_tear() {
+    let (complete, _, buffer) = run_journaled(Faults::new());
+    let journal = buffer.contents();
+    let lines: Vec<&[u8]> = journal.split_inclusive(|byte| *byte == b'\n').collect();
+    // Crash halfway through the fourth entry
+    let tear = lines[..3].iter().map(|line| line.len()).sum::<usize>() + lines[3].len() / 2;
+
+    let (_, res, buffer) = run_journaled(Faults::new().crash_after(tear as u64));
+    assert!(res.is_err());
+    let torn = buffer.contents();
+    assert_eq!(torn, &journal[..tear]);
+
+    let mut recovered = Transakt::default();
+    let replayed = recovered
+        .replay(&torn[..], &ReplayLimit::default())
+        .unwrap();
+    assert_eq!(replayed, 3);
+    assert_eq!(recovered.snapshot().applied, 3);
+    // Replaying the rest of a complete journal on top catches up
+    recovered
+        .replay(&journal[..], &ReplayLimit::default())
+        .unwrap();
+    assert_eq!(recovered.snapshot(), complete.snapshot());
+}
+
+#[test]
+fn torn_snapshot_is_rejected() {
+    let (transakt, _, _) = run_journaled(Faults::new());
+    let snapshot = transakt.snapshot();
+    let buffer = SharedBuffer::default();
+    let writer = FaultyWriter::new(buffer.clone(), Faults::new().crash_after(40));
+    let log = writer.log();
+    assert!(matches!(snapshot.write(writer), Err(Error::Io(_))));
+    assert!(log.lock().unwrap().crashed);
+    assert!(matches!(
+        Snapshot::read(&buffer.contents()[..]),
+        Err(Error::InvalidSnapshot(_))
+    ));
+}
@@ -1,77 +1,41 @@
 use std::path::PathBuf;
+use transakt::scenario::Scenario;
 use transakt::Transakt;
-use transakt::transaction::ClientId;
-use transakt::currency::Currency;
-use std::str::FromStr;
 
-#[test]
-pub fn scenario1() {
-    env_logger::builder()
-        .filter_level(log::LevelFilter::Info)
-        .init();
-    let mut filepath = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    filepath.push("tests/scenario1.csv");
-
-    let transakt = Transakt::read_from_csv(&filepath).unwrap();
-    let accounts = transakt.get_accounts_map();
-    let account = accounts.get(&ClientId::new(1)).unwrap();
-    assert_eq!(account.total().unwrap(), Currency::from_str("0.8999").unwrap());
-}
-
-#[test]
-pub fn scenario2() {
-    env_logger::builder()
-        .filter_level(log::LevelFilter::Info)
-        .init();
-    let mut filepath = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    filepath.push("tests/scenario2.csv");
-
-    let transakt = Transakt::read_from_csv(&filepath).unwrap();
-    let accounts = transakt.get_accounts_map();
-    let account = accounts.get(&ClientId::new(1)).unwrap();
-    assert_eq!(account.total().unwrap(), Currency::from_str("-1").unwrap());
-}
-
-#[test]
-pub fn scenario3() {
-    env_logger::builder()
-        .filter_level(log::LevelFilter::Info)
-        .init();
-    let mut filepath = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    filepath.push("tests/scenario3.csv");
-
-    let transakt = Transakt::read_from_csv(&filepath).unwrap();
-    let accounts = transakt.get_accounts_map();
-    let account = accounts.get(&ClientId::new(1)).unwrap();
-    assert_eq!(account.total().unwrap(), Currency::from_str("1").unwrap());
-
-    let account = accounts.get(&ClientId::new(2)).unwrap();
-    assert_eq!(account.total().unwrap(), Currency::from_str("2").unwrap());
-
-    let account = accounts.get(&ClientId::new(3)).unwrap();
-    assert_eq!(account.total().unwrap(), Currency::from_str("3.1415").unwrap());
-
-    let account = accounts.get(&ClientId::new(6)).unwrap();
-    assert_eq!(account.total().unwrap(), Currency::from_str("42").unwrap());
-
-    let account = accounts.get(&ClientId::new(9)).unwrap();
-    assert_eq!(account.total().unwrap(), Currency::from_str("0.123").unwrap());
-
-    let account = accounts.get(&ClientId::new(100));
-    assert!(account.is_none());
+/// Tests run in parallel in the same process, so only the first one gets to install the
+/// subscriber.
+fn init_logging() {
+    let _ = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::INFO)
+        .with_test_writer()
+        .try_init();
 }
 
+/// Runs every `*.scenario` file in `tests/scenarios`.
 #[test]
-pub fn scenario4() {
-    env_logger::builder()
-        .filter_level(log::LevelFilter::Info)
-        .init();
-    let mut filepath = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    filepath.push("tests/scenario4.csv");
-
-    let transakt = Transakt::read_from_csv(&filepath).unwrap();
-    let accounts = transakt.get_accounts_map();
-    let account = accounts.get(&ClientId::new(1)).unwrap();
-    assert_eq!(account.total().unwrap(), Currency::from_str("0").unwrap());
-    assert!(account.is_locked());
+pub fn scenarios() {
+    init_logging();
+    let mut dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    dir.push("tests/scenarios");
+
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "scenario"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+    let mut failures = vec![];
+    for path in paths {
+        let report = Scenario::load(&path).unwrap().run(Transakt::builder());
+        assert!(
+            report.expectations > 0,
+            "{} expects nothing",
+            path.display()
+        );
+        for failure in report.failures {
+            failures.push(format!("{}: {}", path.display(), failure));
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
 }
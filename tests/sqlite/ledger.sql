@@ -0,0 +1,17 @@
+-- The database of the sqlite tests, built with `sqlite3 ledger.db < ledger.sql`. Small pages
+-- spread the rows over several levels of the table, and the long memo over overflow pages.
+PRAGMA page_size = 512;
+CREATE TABLE ledger (
+    id INTEGER PRIMARY KEY,
+    "type" TEXT NOT NULL,
+    client INTEGER NOT NULL,
+    tx INTEGER NOT NULL,
+    amount NUMERIC,
+    memo TEXT
+);
+WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 100)
+INSERT INTO ledger (type, client, tx, amount) SELECT 'deposit', i % 3 + 1, i, i * 0.25 FROM n;
+-- Rows from before this have no timestamp at all
+ALTER TABLE ledger ADD COLUMN timestamp INTEGER;
+INSERT INTO ledger (type, client, tx, amount, timestamp) VALUES ('withdrawal', 1, 101, '1000', 1700000000);
+INSERT INTO ledger (type, client, tx, memo) VALUES ('dispute', 3, 2, replace(hex(zeroblob(1000)), '0', 'x'));